@@ -8,6 +8,12 @@ const CUSTOM_URL: &str = "matthias://";
 /// Define the url to the deocumentation
 const DOCUMENTATION_URL: &str = "https://matthias.gitbook.io/matthiasdocu";
 
+/// The time we wait before retrying after the first failed reconnection attempt, this is doubled after every failed attempt
+const RECONNECT_BACKOFF_MIN: Duration = Duration::from_secs(1);
+
+/// The longest time we wait between two reconnection attempts
+const RECONNECT_BACKOFF_MAX: Duration = Duration::from_secs(30);
+
 use crate::app::lua::ExtensionProperties;
 use anyhow::Error;
 use base64::{engine::general_purpose, Engine};
@@ -17,7 +23,13 @@ use egui::{
 };
 use egui_extras::{Column, TableBuilder};
 use egui_notify::Toast;
-use std::fs::{self};
+use std::{
+    collections::HashMap,
+    fs::{self},
+    path::PathBuf,
+    sync::atomic::Ordering::Relaxed,
+    time::Duration,
+};
 use tap::TapFallible;
 use tokio_util::sync::CancellationToken;
 
@@ -25,12 +37,16 @@ pub mod backend;
 
 mod client;
 mod lua;
-mod server;
 mod ui;
 
-use self::backend::{display_error_message, ClientMessage, UserInformation};
+//The server lives in its own crate, so it can be run without the Ui too
+use matthias_server::server;
+
+use self::backend::{display_error_message, ClientMessage, OutboxRequest, UserInformation};
 
-use self::backend::{ClientConnection, ConnectionState, ServerMaster};
+use self::backend::{
+    ClientConnection, ConnectionState, ServerControlReply, ServerMaster, DEFAULT_CHANNEL_ID,
+};
 
 impl eframe::App for backend::Application
 {
@@ -63,6 +79,11 @@ impl eframe::App for backend::Application
             });
         }
 
+        //The history cache isnt removed, so the messages can be read the next time too
+        if let Err(err) = self.history_cache.save(&self.client_ui.incoming_messages) {
+            tracing::error!("Failed to save the history cache: {err}");
+        }
+
         //clean up after server and client
         match std::env::var("APPDATA") {
             Ok(app_data) => {
@@ -278,30 +299,118 @@ impl eframe::App for backend::Application
         match self.connection_receiver.try_recv() {
             Ok(connection) => {
                 if let Some(connection) = connection {
+                    //The messages we have are kept when reconnecting, the server has only sent the ones we have missed
+                    let reconnected =
+                        matches!(self.client_connection.state, ConnectionState::Reconnecting);
+
                     //Modify client_connection
                     self.client_connection = connection.0;
 
-                    //Modify local message list
-                    let incoming_sync_message: Result<ServerMaster, serde_json::Error> =
-                        serde_json::from_str(&connection.1);
+                    //The reader and sync threads are spawned for the new connection
+                    self.server_sender_thread = None;
 
                     //Modify the base64 encoded string of send on ip, so it can be used in different places without having to re-encode every frame
                     self.client_ui.send_on_ip_base64_encoded =
                         general_purpose::URL_SAFE_NO_PAD.encode(self.client_ui.send_on_ip.clone());
 
-                    if let Ok(incoming_message) = incoming_sync_message {
-                        self.client_ui.incoming_messages = incoming_message;
+                    //Modify local message list, the older messages are loaded when the user scrolls up
+                    self.client_ui.incoming_messages.apply_sync(connection.1);
+                    self.history_cache.mark_changed();
+                    self.client_ui.history_request_pending = false;
+                    self.client_ui.resync_pending = false;
+
+                    //Continue the file transfers which were interrupted by a dropped connection
+                    let file_transfers = self.file_transfers.clone();
+                    let client_connection = self.client_connection.clone();
+                    let server_address = self.client_ui.send_on_ip.clone();
+                    let uuid = self.opened_user_information.uuid.clone();
+
+                    tokio::spawn(async move {
+                        if let Err(err) = file_transfers
+                            .resume(client_connection, server_address, &uuid)
+                            .await
+                        {
+                            tracing::error!("Failed to resume the file transfers: {err}");
+                        }
+                    });
 
-                        //Callback
-                        self.client_ui.extension.event_call_extensions(
-                            crate::app::lua::EventCall::OnConnect,
-                            &self.lua,
-                            Some(self.client_ui.send_on_ip.clone()),
+                    //Send what has been queued while we werent connected, the outbox is delivered by the thread spawned in ```client_recv```
+                    if let Err(err) =
+                        self.opened_user_information
+                            .encryption_key()
+                            .and_then(|encryption_key| {
+                                self.outbox.open(
+                                    &self.client_ui.send_on_ip,
+                                    &self.opened_user_information.uuid,
+                                    encryption_key,
+                                )
+                            })
+                    {
+                        display_error_message(
+                            format!("Failed to open the outbox: {err}"),
+                            self.toasts.clone(),
                         );
                     }
-                    else {
-                        eprintln!("Failed to convert {} to ServerMaster", connection.1)
+
+                    //Restore where we have left off in the channels
+                    *self.client_ui.last_seen_msg_id.lock().unwrap() = self
+                        .client_ui
+                        .incoming_messages
+                        .user_seen_list
+                        .iter()
+                        .filter(|last_seen| last_seen.uuid == self.opened_user_information.uuid)
+                        .map(|last_seen| (last_seen.channel_id.clone(), last_seen.message_id))
+                        .collect();
+
+                    //The channel we have been viewing might not exist on this server
+                    if !self
+                        .client_ui
+                        .incoming_messages
+                        .channels
+                        .iter()
+                        .any(|channel| channel.id == self.client_ui.selected_channel)
+                    {
+                        self.client_ui.selected_channel = DEFAULT_CHANNEL_ID.to_string();
+                    }
+
+                    //The conversation we have been viewing is kept open after reconnecting
+                    if !reconnected {
+                        self.client_ui.selected_direct_conversation = None;
                     }
+
+                    //The private messages received after our last message in a conversation are considered unread
+                    let mut conversation_lengths: HashMap<String, usize> = HashMap::new();
+
+                    self.client_ui.direct_messages_seen.clear();
+
+                    for message in &self.client_ui.incoming_messages.direct_messages {
+                        if let Some(partner) =
+                            message.direct_message_partner(&self.opened_user_information.uuid)
+                        {
+                            let conversation_length =
+                                conversation_lengths.entry(partner.clone()).or_default();
+
+                            *conversation_length += 1;
+
+                            if message.uuid == self.opened_user_information.uuid {
+                                self.client_ui
+                                    .direct_messages_seen
+                                    .insert(partner, *conversation_length);
+                            }
+                        }
+                    }
+
+                    //Rejoin the call we have been in before the connection was lost
+                    if reconnected {
+                        self.rejoin_voip_call();
+                    }
+
+                    //Callback
+                    self.client_ui.extension.event_call_extensions(
+                        crate::app::lua::EventCall::OnConnect,
+                        &self.lua,
+                        Some(self.client_ui.send_on_ip.clone()),
+                    );
                 }
                 else {
                     // A race condition will occur if we connected successfully after getting a connection error (request timed out)
@@ -352,10 +461,78 @@ impl backend::Application
         });
     }
 
-    /// This function resets clientconnection and all of its other attributes (self.client_ui.incoming_msg, self.autosync_should_run)
+    /// Queues the message in the outbox, the message is sent once there is a connection and the requests queued before it have been sent
+    /// This should be used for the requests the user would lose if the connection was down (Messages, reactions and edits)
+    pub fn queue_msg(&self, message: ClientMessage)
+    {
+        self.queue_request(OutboxRequest::Message(message));
+    }
+
+    /// Queues the upload in the outbox, the file is hashed and uploaded in chunks once the requests queued before it have been sent
+    /// The chunks are sent once the server replies with the offset the upload continues from
+    pub fn upload_file(&self, path: PathBuf, temporary: bool)
+    {
+        self.queue_request(OutboxRequest::Upload {
+            path,
+            temporary,
+            uuid: self.opened_user_information.uuid.clone(),
+            replying_to: self.client_ui.messaging_mode.get_reply_id(),
+            channel_id: self.client_ui.selected_channel.clone(),
+        });
+    }
+
+    fn queue_request(&self, request: OutboxRequest)
+    {
+        if let Err(err) = self.outbox.push(request) {
+            display_error_message(
+                format!("Failed to save the outbox: {err}"),
+                self.toasts.clone(),
+            );
+        }
+    }
+
+    /// Opens the history cache of the server, and returns the cached messages (An empty ```ServerMaster``` is returned if the cache cannot be opened)
+    fn open_history_cache(&self, address: &str) -> ServerMaster
+    {
+        match self
+            .opened_user_information
+            .encryption_key()
+            .and_then(|encryption_key| {
+                self.history_cache
+                    .open(address, &self.opened_user_information.uuid, encryption_key)
+            }) {
+            Ok(messages) => messages,
+            Err(err) => {
+                display_error_message(
+                    format!("Failed to open the history cache: {err}"),
+                    self.toasts.clone(),
+                );
+
+                ServerMaster::default()
+            },
+        }
+    }
+
+    /// Saves the messages to the history cache, this is done on a blocking thread since encrypting all of the messages could take a while
+    pub fn save_history_cache(&self)
+    {
+        let history_cache = self.history_cache.clone();
+        let messages = self.client_ui.incoming_messages.clone();
+
+        tokio::task::spawn_blocking(move || {
+            if let Err(err) = history_cache.save(&messages) {
+                tracing::error!("Failed to save the history cache: {err}");
+            }
+        });
+    }
+
+    /// This function resets clientconnection and all of its other attributes (self.autosync_should_run)
+    /// The messages are kept (and saved to the history cache), so that they can be read without a connection
     fn reset_client_connection(&mut self)
     {
-        self.client_ui.incoming_messages = ServerMaster::default();
+        self.save_history_cache();
+
+        *self.client_ui.latency.lock().unwrap() = None;
 
         self.autosync_shutdown_token.cancel();
 
@@ -372,8 +549,12 @@ impl backend::Application
             ui.allocate_ui(vec2(ui.available_width(), 25.), |ui| {
                 ui.with_layout(Layout::left_to_right(Align::Center), |ui| {
                     ui.add_enabled_ui(
-                        matches!(self.client_connection.state, ConnectionState::Disconnected)
-                            || matches!(self.client_connection.state, ConnectionState::Error),
+                        matches!(
+                            self.client_connection.state,
+                            ConnectionState::Disconnected
+                                | ConnectionState::Error
+                                | ConnectionState::Closed(_)
+                        ),
                         |ui| {
                             ui.add(
                                 TextEdit::singleline(&mut self.client_ui.send_on_ip)
@@ -401,7 +582,7 @@ impl backend::Application
                                 );
                             }
                         },
-                        ConnectionState::Connecting => {
+                        ConnectionState::Connecting | ConnectionState::Reconnecting => {
                             if ui
                                 .button(
                                     RichText::from("Cancel connection").color(Color32::LIGHT_GRAY),
@@ -423,7 +604,7 @@ impl backend::Application
                         },
                     }
 
-                    ui.label(match self.client_connection.state {
+                    ui.label(match &self.client_connection.state {
                         ConnectionState::Connected(_) => {
                             RichText::from("Connected").color(Color32::GREEN)
                         },
@@ -433,9 +614,15 @@ impl backend::Application
                         ConnectionState::Connecting => {
                             RichText::from("Connecting").color(Color32::LIGHT_GREEN)
                         },
+                        ConnectionState::Reconnecting => {
+                            RichText::from("Reconnecting").color(Color32::YELLOW)
+                        },
                         ConnectionState::Error => {
                             RichText::from("Error when trying to connect").color(Color32::RED)
                         },
+                        ConnectionState::Closed(reply) => {
+                            RichText::from(reply.to_string()).color(Color32::RED)
+                        },
                     });
 
                     ui.allocate_ui(vec2(25., 25.), |ui| {
@@ -454,8 +641,12 @@ impl backend::Application
             let compare_passwords = self.client_ui.client_password.clone();
 
             ui.add_enabled(
-                matches!(self.client_connection.state, ConnectionState::Disconnected)
-                    || matches!(self.client_connection.state, ConnectionState::Error),
+                matches!(
+                    self.client_connection.state,
+                    ConnectionState::Disconnected
+                        | ConnectionState::Error
+                        | ConnectionState::Closed(_)
+                ),
                 |ui: &mut egui::Ui| {
                     ui.add(
                         TextEdit::singleline(&mut self.client_ui.client_password)
@@ -513,10 +704,11 @@ impl backend::Application
 
         let user_information = self.opened_user_information.clone();
 
-        //Reset all messages and everything else
-        self.client_ui.incoming_messages = ServerMaster::default();
+        //Display the cached messages of the server until it sends us the recent ones
+        //The cached messages are replaced by the sync, since they could have been edited while we werent connected
+        self.client_ui.incoming_messages = self.open_history_cache(&address);
 
-        //Forget all imaes so the cached imges will be deleted
+        //Forget all imaes so the cached imges will be deleted (The images of this server are loaded from the history cache)
         ctx.forget_all_images();
 
         let toasts = self.toasts.clone();
@@ -528,6 +720,9 @@ impl backend::Application
                 password,
                 &user_information.uuid,
                 user_information.profile,
+                None,
+                None,
+                None,
             )
             .await
             {
@@ -555,6 +750,151 @@ impl backend::Application
         self.client_connection.state = ConnectionState::Connecting;
     }
 
+    /// Connects to the server again after the connection has been lost, the attempts are retried with an exponential backoff until one succeeds
+    /// Unlike ```connect_to_server``` this keeps the messages we have, the server only sends the ones we have missed
+    /// The reconnection stops if the server refuses the connection, or if the user cancels it (```reset_client_connection```)
+    pub fn reconnect_to_server(&mut self, ctx: &egui::Context)
+    {
+        let username = self.login_username.clone();
+
+        let sender = self.connection_sender.clone();
+
+        //The threads of the lost connection have been shut down already, the reconnection is cancelled with the new token
+        self.autosync_shutdown_token = CancellationToken::new();
+
+        let cancellation_token = self.autosync_shutdown_token.clone();
+
+        //Clone ctx so we can call request repaint from another thread
+        let ctx = ctx.clone();
+
+        let user_information = self.opened_user_information.clone();
+
+        let address = self.client_ui.send_on_ip.clone();
+
+        let password = self.client_ui.client_password.clone();
+
+        //The server continues from the last message we have, and sends the messages which have changed since our last sync
+        let last_message_id = self.client_ui.incoming_messages.last_message_id();
+        let last_revision = Some(self.client_ui.incoming_messages.revision);
+
+        //The server might not have noticed that our old connection is gone, the token lets us replace it
+        let reconnect_token = self.client_ui.incoming_messages.reconnect_token.clone();
+
+        let toasts = self.toasts.clone();
+
+        tokio::task::spawn(async move {
+            let mut backoff = RECONNECT_BACKOFF_MIN;
+
+            loop {
+                let connection = tokio::select! {
+                    _ = cancellation_token.cancelled() => return,
+
+                    connection = ClientConnection::connect_to_server(
+                        address.clone(),
+                        username.clone(),
+                        Some(password.clone()),
+                        &user_information.uuid,
+                        user_information.profile.clone(),
+                        last_message_id,
+                        last_revision,
+                        reconnect_token.clone(),
+                    ) => connection,
+                };
+
+                match connection {
+                    //The user might have cancelled the reconnection while the connection was being set up
+                    Ok(_) if cancellation_token.is_cancelled() => return,
+                    Ok(ok) => {
+                        ctx.request_repaint();
+                        if let Err(err) = sender.send(Some(ok)) {
+                            tracing::error!("{}", err);
+                        };
+
+                        return;
+                    },
+                    //The server has refused the connection (For example we have been banned meanwhile), retrying wouldnt change that
+                    Err(err) if err.downcast_ref::<ServerControlReply>().is_some() => {
+                        //Avoid panicking when trying to display a Notification
+                        //This is very rare but can still happen
+                        display_error_message(err, toasts);
+
+                        ctx.request_repaint();
+                        if let Err(err) = sender.send(None) {
+                            tracing::error!("{}", err);
+                        };
+
+                        return;
+                    },
+                    Err(err) => {
+                        tracing::warn!(
+                            "Failed to reconnect to the server, retrying in {backoff:?}: {err}"
+                        );
+                    },
+                }
+
+                tokio::select! {
+                    _ = cancellation_token.cancelled() => return,
+
+                    _ = tokio::time::sleep(backoff) => {},
+                };
+
+                backoff = (backoff * 2).min(RECONNECT_BACKOFF_MAX);
+            }
+        });
+
+        //The reader and sync threads are spawned again once we have reconnected
+        self.server_sender_thread = None;
+
+        self.client_connection.state = ConnectionState::Reconnecting;
+    }
+
+    /// Rejoins the call we have been in before the connection was lost, our side of the call is shut down if the call has ended meanwhile
+    fn rejoin_voip_call(&mut self)
+    {
+        let Some(voip) = self.client_ui.voip.clone()
+        else {
+            return;
+        };
+
+        //The call is still running, so the server only has to know where to send it to
+        if self
+            .client_ui
+            .incoming_messages
+            .ongoing_voip_call
+            .connected_clients
+            .is_some()
+        {
+            match voip.socket.local_addr() {
+                Ok(local_addr) => {
+                    self.send_msg(ClientMessage::construct_voip_connect(
+                        &self.opened_user_information.uuid,
+                        local_addr.port(),
+                    ));
+                },
+                Err(err) => {
+                    tracing::error!("Failed to rejoin the call: {err}");
+                },
+            }
+
+            return;
+        }
+
+        //Disable camera if it exists before everything else
+        if voip.camera_handle_is_open.load(Relaxed) {
+            self.disable_camera(voip);
+        }
+
+        //Shutdown listener and recorder thread
+        self.voip_shutdown_token.cancel();
+
+        //Signal the voice recorder function to stop
+        let _ = self.record_audio_interrupter.send(());
+
+        //Reset state
+        self.client_ui.voip = None;
+        self.voip_thread = None;
+    }
+
     fn disconnect_from_server(&mut self)
     {
         let username = self.login_username.clone();
@@ -1,16 +1,11 @@
 use super::{
-    client::{connect_to_server, ServerReply},
+    client::{connect_to_server, send_handshake, ServerEvent, ServerReply},
     lua::{Extension, LuaOutput},
     read_extensions_dir,
     server::SharedFields,
     ui::register::create_dynamic_image_from_bytes,
 };
-use aes_gcm::{
-    aead::{generic_array::GenericArray, Aead, KeyInit},
-    Aes256Gcm, Key,
-};
 use anyhow::{bail, ensure, Error, Result};
-use argon2::Config;
 use base64::{engine::general_purpose, Engine};
 use chrono::{DateTime, NaiveDate, Utc};
 use dashmap::DashMap;
@@ -20,10 +15,9 @@ use egui::{
 };
 use egui_notify::{Toast, Toasts};
 use image::{DynamicImage, ImageOutputFormat};
-use indexmap::IndexMap;
 use mlua::Lua;
 use mlua_proc_macro::ToTable;
-use rand::{rngs::ThreadRng, Rng};
+use rand::rngs::ThreadRng;
 use regex::Regex;
 use rfd::FileDialog;
 use rodio::{OutputStream, OutputStreamHandle, Sink};
@@ -33,19 +27,17 @@ use std::{
     fmt::{Debug, Display},
     fs,
     io::{self, BufWriter, Cursor, Read, Seek, SeekFrom, Write},
-    net::SocketAddr,
-    path::PathBuf,
+    path::{Path, PathBuf},
     sync::{
-        atomic::{AtomicBool, AtomicI64},
+        atomic::{AtomicBool, AtomicI64, Ordering},
         mpsc::{self, Receiver, Sender},
         Arc, Mutex,
     },
-    time::Duration,
+    time::{Duration, Instant},
 };
-use strum::{EnumDiscriminants, EnumMessage};
-use strum_macros::EnumString;
+use strum::EnumDiscriminants;
 use tokio::{
-    io::{AsyncRead, AsyncReadExt, AsyncWriteExt},
+    io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt},
     net::{
         tcp::{OwnedReadHalf, OwnedWriteHalf},
         UdpSocket,
@@ -55,6 +47,26 @@ use tokio_util::sync::CancellationToken;
 use uuid::Uuid;
 use wincam::Webcam;
 
+//The types shared with the server are defined in the server crate
+pub use matthias_server::protocol::*;
+
+use matthias_server::{
+    ban::{BanRecord, Subnet},
+    quota::QuotaConfig,
+    validation::check_hash,
+};
+
+/// The durations a user can be muted, timed out or banned for, the values are in minutes
+pub const MODERATION_DURATIONS: [(&str, u32); 4] = [
+    ("5 minutes", 5),
+    ("1 hour", 60),
+    ("1 day", 60 * 24),
+    ("1 week", 60 * 24 * 7),
+];
+
+/// How often the history cache is saved while the messages are changing, it is also saved when the connection is closed
+pub const HISTORY_CACHE_SAVE_INTERVAL: Duration = Duration::from_secs(5);
+
 #[derive(serde::Deserialize, serde::Serialize, ToTable, Clone)]
 #[serde(default)]
 pub struct Application
@@ -129,6 +141,13 @@ pub struct Application
     ///Which port is the server open on
     pub open_on_port: String,
 
+    ///The ban the user is adding from the server's settings
+    #[serde(skip)]
+    pub server_ban_form: BanForm,
+
+    ///The storage limits the server is started with
+    pub server_quota_config: QuotaConfig,
+
     ///child windows
     #[serde(skip)]
     pub settings_window: bool,
@@ -139,9 +158,9 @@ pub struct Application
 
     ///thread communication for audio ! SAVING !
     #[serde(skip)]
-    pub audio_save_rx: Arc<mpsc::Receiver<(Option<Arc<Sink>>, PlaybackCursor, u64, PathBuf)>>,
+    pub audio_save_rx: Arc<mpsc::Receiver<(Option<Arc<Sink>>, PlaybackCursor, MessageId, PathBuf)>>,
     #[serde(skip)]
-    pub audio_save_tx: Arc<mpsc::Sender<(Option<Arc<Sink>>, PlaybackCursor, u64, PathBuf)>>,
+    pub audio_save_tx: Arc<mpsc::Sender<(Option<Arc<Sink>>, PlaybackCursor, MessageId, PathBuf)>>,
 
     ///Channels for sending recorded, and formatted Wav audio bytes
     #[serde(skip)]
@@ -182,11 +201,11 @@ pub struct Application
     pub dtx: Arc<mpsc::Sender<String>>,
 
     /// Server connection
-    /// This channel hosts a Client connection and the sync message sent by the server in a String format
+    /// This channel hosts a Client connection and the sync message sent by the server
     #[serde(skip)]
-    pub connection_receiver: Arc<mpsc::Receiver<Option<(ClientConnection, String)>>>,
+    pub connection_receiver: Arc<mpsc::Receiver<Option<(ClientConnection, ServerMaster)>>>,
     #[serde(skip)]
-    pub connection_sender: mpsc::Sender<Option<(ClientConnection, String)>>,
+    pub connection_sender: mpsc::Sender<Option<(ClientConnection, ServerMaster)>>,
 
     /// Voip (UdpSocket) maker
     /// When a successful ```Voip``` instance is created it is sent over from the async thread
@@ -209,11 +228,11 @@ pub struct Application
 
     #[serde(skip)]
     /// This is what the main thread uses to receive messages from the sync thread
-    pub server_output_receiver: Arc<Receiver<Option<String>>>,
+    pub server_output_receiver: Arc<Receiver<Option<ServerEvent>>>,
 
     #[serde(skip)]
     /// This is what the sync thread uses to send messages to the main thread
-    pub server_output_sender: Sender<Option<String>>,
+    pub server_output_sender: Sender<Option<ServerEvent>>,
 
     #[serde(skip)]
     /// This is what the main thread uses to send the shutdown message to the sync thread
@@ -224,6 +243,18 @@ pub struct Application
 
     #[serde(skip)]
     pub opened_user_information: UserInformation,
+
+    #[serde(skip)]
+    /// The files which are being uploaded or downloaded in chunks
+    pub file_transfers: FileTransfers,
+
+    #[serde(skip)]
+    /// The messages, reactions, edits and uploads which are sent once there is a connection to the server
+    pub outbox: Outbox,
+
+    #[serde(skip)]
+    /// The messages and images of the opened server cached on the disk, so they can be read without a connection
+    pub history_cache: HistoryCache,
 }
 
 impl Default for Application
@@ -232,14 +263,14 @@ impl Default for Application
     {
         let (dtx, drx) = mpsc::channel::<String>();
         let (audio_save_tx, audio_save_rx) =
-            mpsc::channel::<(Option<Arc<Sink>>, PlaybackCursor, u64, PathBuf)>();
+            mpsc::channel::<(Option<Arc<Sink>>, PlaybackCursor, MessageId, PathBuf)>();
 
         let (audio_bytes_tx, audio_bytes_rx) = mpsc::channel::<Vec<u8>>();
 
         let (connection_sender, connection_receiver) =
-            mpsc::channel::<Option<(ClientConnection, String)>>();
+            mpsc::channel::<Option<(ClientConnection, ServerMaster)>>();
 
-        let (server_output_sender, server_output_receiver) = mpsc::channel::<Option<String>>();
+        let (server_output_sender, server_output_receiver) = mpsc::channel::<Option<ServerEvent>>();
 
         let (voip_connection_sender, voip_connection_receiver) = mpsc::channel::<Voip>();
 
@@ -278,6 +309,8 @@ impl Default for Application
             server_req_password: false,
             server_password: String::default(),
             open_on_port: String::default(),
+            server_ban_form: BanForm::default(),
+            server_quota_config: QuotaConfig::default(),
 
             //child windows
             settings_window: false,
@@ -328,6 +361,9 @@ impl Default for Application
             autosync_shutdown_token: CancellationToken::new(),
             server_connected_clients_profile: Arc::new(DashMap::new()),
             opened_user_information: UserInformation::default(),
+            file_transfers: FileTransfers::default(),
+            outbox: Outbox::default(),
+            history_cache: HistoryCache::default(),
         }
     }
 }
@@ -348,6 +384,19 @@ impl Application
             .set_lua_table_function(&self.lua);
     }
 
+    /// Returns the role of the user (identified by their uuid) on the server we are connected to
+    pub fn role_of(&self, uuid: &str) -> Role
+    {
+        Role::of_user(&self.client_ui.incoming_messages.roles, uuid)
+    }
+
+    /// Returns whether we have the permission on the server we are connected to
+    pub fn has_permission(&self, permission: Permission) -> bool
+    {
+        self.role_of(&self.opened_user_information.uuid)
+            .has_permission(permission)
+    }
+
     pub fn new(cc: &eframe::CreationContext<'_>) -> Self
     {
         if let Some(storage) = cc.storage {
@@ -673,6 +722,51 @@ impl ToString for EmojiTypes
     }
 }
 
+/// The fields of the form the user can add a ban with from the server's settings
+#[derive(Default, Clone)]
+pub struct BanForm
+{
+    /// The uuid of the user to ban, this can be left empty if only an address is banned
+    pub uuid: String,
+
+    /// The address or the subnet (in CIDR notation) to ban, this can be left empty if only a user is banned
+    pub subnet: String,
+
+    /// Why the ban is issued
+    pub reason: String,
+
+    /// How long the ban lasts in minutes, if this is ```None``` the ban is permanent
+    pub minutes: Option<u32>,
+}
+
+impl BanForm
+{
+    /// Creates a ban from the form's fields, returns an error if neither a user nor a valid address has been provided
+    pub fn to_ban_record(&self) -> anyhow::Result<BanRecord>
+    {
+        let uuid = Some(self.uuid.trim().to_string()).filter(|uuid| !uuid.is_empty());
+
+        let subnet = match self.subnet.trim() {
+            "" => None,
+            subnet => Some(subnet.parse::<Subnet>()?),
+        };
+
+        ensure!(
+            uuid.is_some() || subnet.is_some(),
+            "A user or an address has to be provided!"
+        );
+
+        Ok(BanRecord::new(
+            uuid,
+            subnet,
+            self.reason.trim().to_string(),
+            None,
+            self.minutes
+                .map(|minutes| chrono::Duration::minutes(minutes as i64)),
+        ))
+    }
+}
+
 /// Client side variables
 #[derive(serde::Deserialize, serde::Serialize, Clone, ToTable)]
 pub struct Client
@@ -740,10 +834,24 @@ pub struct Client
     #[table(save)]
     pub scroll_to_message: Option<ScrollToMessage>,
 
-    ///index of the reply the user clicked on
+    ///id of the reply the user clicked on
+    #[serde(skip)]
+    #[table(save)]
+    pub scroll_to_message_id: Option<MessageId>,
+
+    ///This is set to true while an older page of the message history is being requested, so that it is only requested once
+    #[serde(skip)]
+    #[table(save)]
+    pub history_request_pending: bool,
+
+    ///This is set to true after a missed message has been detected, until the server has sent the current state (The messages received meanwhile are dropped)
     #[serde(skip)]
     #[table(save)]
-    pub scroll_to_message_index: Option<usize>,
+    pub resync_pending: bool,
+
+    /// The round trip time of the connection to the server, the server measures it with its pings (This is None until the server has measured it)
+    #[serde(skip)]
+    pub latency: Arc<Mutex<Option<Duration>>>,
 
     ///Selected port on sending
     pub send_on_port: String,
@@ -806,9 +914,44 @@ pub struct Client
     #[table(save)]
     pub incoming_messages: ServerMaster,
 
-    /// Last seen message's index, this will get sent
+    /// Last seen message's id in every channel, the key is the channel's id, these will get sent
+    #[serde(skip)]
+    pub last_seen_msg_id: Arc<Mutex<HashMap<String, MessageId>>>,
+
+    /// The id of the channel the user is currently viewing, messages are sent to this channel
+    #[serde(skip, default = "default_channel_id")]
+    #[table(save)]
+    pub selected_channel: String,
+
+    /// The name of the channel the user wants to create
+    #[serde(skip)]
+    #[table(save)]
+    pub new_channel_name: String,
+
+    /// The channel the user is renaming, the first value is the channel's id, the second one is the new name
+    #[serde(skip)]
+    #[table(save)]
+    pub channel_rename: Option<(String, String)>,
+
+    /// The uuid of the user whose private conversation is opened, if this is ```None``` the selected channel is displayed
+    /// Messages are sent to this user while the conversation is opened
+    #[serde(skip)]
+    #[table(save)]
+    pub selected_direct_conversation: Option<String>,
+
+    /// The number of messages the user has seen in each of their private conversations, the key is the other user's uuid
     #[serde(skip)]
-    pub last_seen_msg_index: Arc<Mutex<usize>>,
+    pub direct_messages_seen: HashMap<String, usize>,
+
+    /// The reason of the ban the user is about to issue
+    #[serde(skip)]
+    #[table(save)]
+    pub ban_reason: String,
+
+    /// Whether the address of the user is banned too when the user issues a ban
+    #[serde(skip)]
+    #[table(save)]
+    pub ban_address: bool,
 
     ///emoji fasz
     pub random_emoji: String,
@@ -856,7 +999,10 @@ impl Default for Client
             audio_playback: AudioPlayback::default(),
             scroll_widget_rect: egui::Rect::NAN,
             text_widget_offset: 0.0,
-            scroll_to_message_index: None,
+            scroll_to_message_id: None,
+            history_request_pending: false,
+            resync_pending: false,
+            latency: Arc::new(Mutex::new(None)),
             scroll_to_message: None,
             send_on_port: String::new(),
             send_on_address: String::new(),
@@ -883,7 +1029,14 @@ impl Default for Client
             incoming_messages: ServerMaster::default(),
 
             voice_recording_start: None,
-            last_seen_msg_index: Arc::new(Mutex::new(0)),
+            last_seen_msg_id: Arc::new(Mutex::new(HashMap::new())),
+            selected_channel: default_channel_id(),
+            new_channel_name: String::new(),
+            channel_rename: None,
+            selected_direct_conversation: None,
+            direct_messages_seen: HashMap::new(),
+            ban_reason: String::new(),
+            ban_address: false,
             emoji_selector_index: 0,
             voip: None,
             microphone_volume: Arc::new(AtomicI64::new(100)),
@@ -1010,53 +1163,20 @@ impl Default for ProfileImage
     }
 }
 
-/// The clients profile, this struct should be sent at a server connection
-/// It hold everything which needs to be displayed when viewing someone's profile
-/// This struct might look similar too ```Register```, but that one contains more information, and is only made to control the ui
-/// This struct is sent to the server upon successful connection
-/// If you are searching for the uuid in this struct, please note that most of the times this struct is used in a hashmap where the key is the uuid
-#[derive(serde::Deserialize, serde::Serialize, Default, Clone, Debug, PartialEq)]
-pub struct ClientProfile
-{
-    /// The client's username
-    /// We might not need it in some contexts
-    pub username: String,
-
-    /// The client's full name
-    /// If its empty it means the client did not agree to share it
-    pub full_name: String,
-
-    /// The client's gender
-    /// false: Male
-    /// true: Female
-    /// None: Rather not answer
-    /// Rework this
-    pub gender: Option<bool>,
-
-    /// The client's birthdate
-    pub birth_date: NaiveDate,
-
-    /// This entry hold the profile's 64x64 profile picture
-    pub small_profile_picture: Vec<u8>,
-
-    /// This entry hold the profile's 256x256 profile picture
-    pub normal_profile_picture: Vec<u8>,
-}
-
 #[derive(serde::Deserialize, serde::Serialize, Default, Clone, Debug)]
 pub enum MessagingMode
 {
     #[default]
     Normal,
-    /// The inner value of this enum holds the index which this message is editing
-    Edit(usize),
-    /// The inner value of this enum holds the index which this message is replying to
-    Reply(usize),
+    /// The inner value of this enum holds the id of the message which this message is editing
+    Edit(MessageId),
+    /// The inner value of this enum holds the id of the message which this message is replying to
+    Reply(MessageId),
 }
 
 impl MessagingMode
 {
-    pub fn get_reply_index(&self) -> Option<usize>
+    pub fn get_reply_id(&self) -> Option<MessageId>
     {
         match self {
             MessagingMode::Reply(i) => Some(*i),
@@ -1065,437 +1185,6 @@ impl MessagingMode
     }
 }
 
-///When the client is uploading a file, this packet gets sent
-#[derive(Default, serde::Serialize, serde::Deserialize, Debug, Clone)]
-pub struct ClientFileUpload
-{
-    pub extension: Option<String>,
-    pub name: Option<String>,
-    pub bytes: Vec<u8>,
-}
-
-///Normal message
-#[derive(Default, serde::Serialize, serde::Deserialize, Debug, Clone)]
-pub struct ClientNormalMessage
-{
-    pub message: String,
-}
-
-// Used for syncing or connecting & disconnecting
-#[derive(Default, serde::Serialize, serde::Deserialize, Debug, Clone)]
-pub struct ClientSyncMessage
-{
-    /// If its None its used for syncing, false: disconnecting, true: connecting
-    /// If you have already registered the client with the server then the true value will be ignored
-    pub sync_attribute: Option<ConnectionType>,
-
-    /// This is used to tell the server how many messages it has to send, if its a None it will automatically sync all messages
-    /// This value is ignored if the `sync_attribute` field is Some(_)
-    pub client_message_counter: Option<usize>,
-
-    /// The index of the last seen message by the user, this is sent so we can display which was the last message the user has seen, if its None we ignore the value
-    pub last_seen_message_index: Option<usize>,
-
-    /// Contains password in the sync message, so we will send the password when authenticating
-    pub password: String,
-
-    /// This field is used when connecting, the server will save the uuid and the username pair
-    /// The client will not send their username except here, and the server is expected to pair the name to the message
-    pub username: String,
-}
-
-#[derive(Default, serde::Serialize, serde::Deserialize, Debug, Clone)]
-pub enum ConnectionType
-{
-    #[default]
-    Disconnect,
-    Connect(ClientProfile),
-}
-
-///This is used by the client for requesting file
-#[derive(Default, serde::Serialize, serde::Deserialize, Debug, Clone)]
-pub struct ClientFileRequest
-{
-    /// This is the signature of the file which has been uploaded, this acts like a handle to the file
-    pub signature: String,
-}
-
-///This is used by the client for requesting images
-#[derive(Default, serde::Serialize, serde::Deserialize, Debug, Clone)]
-pub struct ClientImageRequest
-{
-    /// This is the signature of the file which has been uploaded, this acts like a handle to the file
-    pub signature: String,
-}
-
-///Client requests audio file in server
-#[derive(Default, serde::Serialize, serde::Deserialize, Debug, Clone)]
-pub struct ClientAudioRequest
-{
-    /// This is the signature of the file which has been uploaded, this acts like a handle to the file
-    pub signature: String,
-
-    pub index: u64,
-}
-
-///Reaction packet, defines which message its reacting to and with which char
-#[derive(Default, serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
-pub struct ClientReaction
-{
-    pub emoji_name: String,
-    pub message_index: usize,
-    pub uuid: String,
-}
-
-///Lets the client edit their *OWN* message, a client check is implemented TODO: please write a server check for this
-#[derive(Default, serde::Serialize, serde::Deserialize, Debug, Clone)]
-pub struct ClientMessageEdit
-{
-    ///The message which is edited
-    pub index: usize,
-    ///The new message
-    pub new_message: Option<String>,
-}
-
-///These are the types of requests the client can ask
-#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
-pub enum ClientFileRequestType
-{
-    ///this is when you want to display an image and you have to make a request to the server file
-    ImageRequest(ClientImageRequest),
-    FileRequest(ClientFileRequest),
-    AudioRequest(ClientAudioRequest),
-
-    /// This enum is used when the client is requesting another client's information (```ClientProfile``` struct)
-    /// The wrapped value in this enum is an encrypted (aes256: ```fn encrypt_aes256()```) uuid (In string)
-    ClientRequest(String),
-}
-
-///Client outgoing message types
-#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
-pub enum ClientMessageType
-{
-    FileRequestType(ClientFileRequestType),
-
-    FileUpload(ClientFileUpload),
-
-    ///Normal msg
-    NormalMessage(ClientNormalMessage),
-
-    ///Used for syncing with client and server
-    SyncMessage(ClientSyncMessage),
-
-    Reaction(ReactionType),
-
-    MessageEdit(ClientMessageEdit),
-
-    VoipConnection(ClientVoipRequest),
-}
-
-/// The variant of the reaction message
-#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
-pub enum ReactionType
-{
-    Add(ClientReaction),
-    Remove(ClientReaction),
-}
-
-/// This is what gets to be sent out by the client
-#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
-pub struct ClientMessage
-{
-    /// Which message in the message stack its replying to
-    pub replying_to: Option<usize>,
-
-    /// The message type of the message
-    pub message_type: ClientMessageType,
-
-    /// The every uuid takes up 120 bytes
-    pub uuid: String,
-
-    /// When was this message sent
-    pub message_date: String,
-}
-
-impl ClientMessage
-{
-    ///struct into string, it makes sending information easier by putting it all in a string
-    pub fn struct_into_string(&self) -> String
-    {
-        serde_json::to_string(self).unwrap_or_default()
-    }
-
-    pub fn construct_file_msg_from_bytes(
-        bytes: Vec<u8>,
-        file_extension: String,
-        replying_to: Option<usize>,
-        uuid: String,
-    ) -> ClientMessage
-    {
-        ClientMessage {
-            replying_to,
-            message_type: ClientMessageType::FileUpload(ClientFileUpload {
-                extension: Some(file_extension),
-                name: None,
-                bytes,
-            }),
-            uuid,
-            message_date: { Utc::now().format("%Y.%m.%d. %H:%M").to_string() },
-        }
-    }
-
-    ///this is used when sending a normal message
-    pub fn construct_normal_msg(msg: &str, uuid: &str, replying_to: Option<usize>)
-        -> ClientMessage
-    {
-        ClientMessage {
-            replying_to,
-            message_type: ClientMessageType::NormalMessage(ClientNormalMessage {
-                message: msg.trim().to_string(),
-            }),
-            //If the password is set as None (Meaning the user didnt enter any password) just send the message with an empty string
-            uuid: uuid.to_string(),
-            message_date: { Utc::now().format("%Y.%m.%d. %H:%M").to_string() },
-        }
-    }
-
-    ///this is used when you want to send a file, this contains name, bytes
-    pub fn construct_file_msg(
-        file_path: PathBuf,
-        uuid: &str,
-        replying_to: Option<usize>,
-    ) -> ClientMessage
-    {
-        ClientMessage {
-            replying_to,
-            //Dont execute me please :3 |
-            //                          |
-            //                          V
-            message_type: ClientMessageType::FileUpload(ClientFileUpload {
-                extension: Some(file_path.extension().unwrap().to_str().unwrap().to_string()),
-                name: Some(
-                    file_path
-                        .file_name()
-                        .unwrap()
-                        .to_str()
-                        .unwrap()
-                        .to_string()
-                        .split(".")
-                        .next()
-                        .unwrap()
-                        .to_string(),
-                ),
-                bytes: std::fs::read(file_path).unwrap_or_default(),
-            }),
-
-            uuid: uuid.to_string(),
-            message_date: { Utc::now().format("%Y.%m.%d. %H:%M").to_string() },
-        }
-    }
-
-    pub fn construct_reaction_msg(emoji_name: String, index: usize, uuid: &str) -> ClientMessage
-    {
-        ClientMessage {
-            replying_to: None,
-            message_type: ClientMessageType::Reaction(ReactionType::Add(ClientReaction {
-                emoji_name,
-                message_index: index,
-                uuid: uuid.to_string(),
-            })),
-            uuid: uuid.to_string(),
-            message_date: { Utc::now().format("%Y.%m.%d. %H:%M").to_string() },
-        }
-    }
-
-    pub fn construct_reaction_remove_msg(
-        emoji_name: String,
-        index: usize,
-        uuid: &str,
-    ) -> ClientMessage
-    {
-        ClientMessage {
-            replying_to: None,
-            message_type: ClientMessageType::Reaction(ReactionType::Remove(ClientReaction {
-                emoji_name,
-                message_index: index,
-                uuid: uuid.to_string(),
-            })),
-            uuid: uuid.to_string(),
-            message_date: { Utc::now().format("%Y.%m.%d. %H:%M").to_string() },
-        }
-    }
-
-    /// this is used for constructing a sync msg aka sending an empty packet, so server can reply
-    /// If its None its used for syncing, false: disconnecting, true: connecting
-    pub fn construct_sync_msg(
-        password: &str,
-        author: &str,
-        uuid: &str,
-        client_message_counter: usize,
-        last_seen_message_index: Option<usize>,
-    ) -> ClientMessage
-    {
-        ClientMessage {
-            replying_to: None,
-            message_type: ClientMessageType::SyncMessage(ClientSyncMessage {
-                sync_attribute: None,
-                password: password.to_string(),
-                //This value is not ignored in this context
-                client_message_counter: Some(client_message_counter),
-                last_seen_message_index,
-                username: author.to_string(),
-            }),
-            uuid: uuid.to_string(),
-            message_date: { Utc::now().format("%Y.%m.%d. %H:%M").to_string() },
-        }
-    }
-
-    /// If its None its used for syncing, false: disconnecting, true: connecting
-    pub fn construct_connection_msg(
-        password: String,
-        author: String,
-        uuid: &str,
-        last_seen_message_index: Option<usize>,
-        profile: ClientProfile,
-    ) -> ClientMessage
-    {
-        ClientMessage {
-            replying_to: None,
-            message_type: ClientMessageType::SyncMessage(ClientSyncMessage {
-                sync_attribute: Some(ConnectionType::Connect(profile)),
-                password,
-                //If its used for connecting / disconnecting this value is ignored
-                client_message_counter: None,
-                last_seen_message_index,
-                username: author,
-            }),
-            uuid: uuid.to_string(),
-            message_date: { Utc::now().format("%Y.%m.%d. %H:%M").to_string() },
-        }
-    }
-
-    /// If its None its used for syncing, false: disconnecting, true: connecting
-    /// Please note that its doesnt really matter what we pass in the author because the server identifies us based on our ip address
-    pub fn construct_disconnection_msg(
-        password: String,
-        author: String,
-        uuid: String,
-    ) -> ClientMessage
-    {
-        ClientMessage {
-            replying_to: None,
-            message_type: ClientMessageType::SyncMessage(ClientSyncMessage {
-                sync_attribute: Some(ConnectionType::Disconnect),
-                password,
-                //If its used for connecting / disconnecting this value is ignored
-                client_message_counter: None,
-                last_seen_message_index: None,
-                username: author,
-            }),
-            uuid,
-            message_date: { Utc::now().format("%Y.%m.%d. %H:%M").to_string() },
-        }
-    }
-
-    ///this is used for asking for a file
-    pub fn construct_file_request_msg(signature: String, uuid: &str) -> ClientMessage
-    {
-        ClientMessage {
-            replying_to: None,
-            message_type: ClientMessageType::FileRequestType(ClientFileRequestType::FileRequest(
-                ClientFileRequest { signature },
-            )),
-            uuid: uuid.to_string(),
-            message_date: { Utc::now().format("%Y.%m.%d. %H:%M").to_string() },
-        }
-    }
-
-    ///this is used for asking for an image
-    pub fn construct_image_request_msg(signature: String, uuid: &str) -> ClientMessage
-    {
-        ClientMessage {
-            replying_to: None,
-            message_type: ClientMessageType::FileRequestType(ClientFileRequestType::ImageRequest(
-                ClientImageRequest { signature },
-            )),
-            uuid: uuid.to_string(),
-            message_date: { Utc::now().format("%Y.%m.%d. %H:%M").to_string() },
-        }
-    }
-
-    ///this is used for asking for an image
-    pub fn construct_audio_request_msg(signature: String, uuid: &str, index: u64) -> ClientMessage
-    {
-        ClientMessage {
-            replying_to: None,
-            message_type: ClientMessageType::FileRequestType(ClientFileRequestType::AudioRequest(
-                ClientAudioRequest { signature, index },
-            )),
-            uuid: uuid.to_string(),
-            message_date: { Utc::now().format("%Y.%m.%d. %H:%M").to_string() },
-        }
-    }
-
-    pub fn construct_client_request_msg(
-        uuid_of_requested_client: String,
-        uuid: &str,
-    ) -> ClientMessage
-    {
-        ClientMessage {
-            replying_to: None,
-            message_type: ClientMessageType::FileRequestType(ClientFileRequestType::ClientRequest(
-                uuid_of_requested_client,
-            )),
-            uuid: uuid.to_string(),
-            message_date: { Utc::now().format("%Y.%m.%d. %H:%M").to_string() },
-        }
-    }
-
-    pub fn construct_client_message_edit(
-        index: usize,
-        new_message: Option<String>,
-        uuid: &str,
-    ) -> ClientMessage
-    {
-        ClientMessage {
-            replying_to: None,
-            message_type: ClientMessageType::MessageEdit(ClientMessageEdit { index, new_message }),
-            uuid: uuid.to_string(),
-            message_date: { Utc::now().format("%Y.%m.%d. %H:%M").to_string() },
-        }
-    }
-
-    pub fn construct_voip_connect(uuid: &str, port: u16) -> ClientMessage
-    {
-        ClientMessage {
-            replying_to: None,
-            message_type: ClientMessageType::VoipConnection(ClientVoipRequest::Connect(port)),
-            uuid: uuid.to_string(),
-            message_date: { Utc::now().format("%Y.%m.%d. %H:%M").to_string() },
-        }
-    }
-
-    pub fn construct_voip_disconnect(uuid: &str) -> ClientMessage
-    {
-        ClientMessage {
-            replying_to: None,
-            message_type: ClientMessageType::VoipConnection(ClientVoipRequest::Disconnect),
-            uuid: uuid.to_string(),
-            message_date: { Utc::now().format("%Y.%m.%d. %H:%M").to_string() },
-        }
-    }
-
-    pub fn construct_voip_event(uuid: String, event: ClientVoipRequest) -> ClientMessage
-    {
-        ClientMessage {
-            replying_to: None,
-            message_type: ClientMessageType::VoipConnection(event),
-            uuid: uuid.to_string(),
-            message_date: { Utc::now().format("%Y.%m.%d. %H:%M").to_string() },
-        }
-    }
-}
-
 ///This manages all the settings and variables for maintaining a connection with the server (from client)
 #[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Default, ToTable)]
 pub struct ClientConnection
@@ -1513,6 +1202,11 @@ pub struct ClientConnection
     #[serde(skip)]
     //Password which was used to connect (and could connect with, it has been password matched with the server)
     pub password: String,
+
+    #[table(save)]
+    #[serde(skip)]
+    /// The optional features both the client and the server support, these were agreed on in the handshake
+    pub capabilities: Vec<Capability>,
 }
 
 impl ClientConnection
@@ -1544,7 +1238,14 @@ impl ClientConnection
         uuid: &str,
         //Profile
         profile: ClientProfile,
-    ) -> anyhow::Result<(Self, String)>
+        //The id of the last message we have, the server only sends the messages after it
+        //This is None when connecting for the first time, since the most recent page of the history is requested then
+        last_message_id: Option<MessageId>,
+        //The revision of the last sync we have received, the server sends the changes of the messages we have since then
+        last_revision: Option<u64>,
+        //The token we have received when we last connected, the server only lets us replace a connection it still considers open if we send it
+        reconnect_token: Option<String>,
+    ) -> anyhow::Result<(Self, ServerMaster)>
     {
         let hashed_password = encrypt(password.clone().unwrap_or(String::from("")));
         let connection_msg = ClientMessage::construct_connection_msg(
@@ -1552,37 +1253,49 @@ impl ClientConnection
             author.clone(),
             uuid,
             None,
+            last_message_id,
+            last_revision,
+            reconnect_token,
             profile,
         );
 
         //Ping server to receive custom uuid, and to also get if server ip is valid
-        let client_handle = tokio::net::TcpStream::connect(ip).await?;
+        let mut client_handle = tokio::net::TcpStream::connect(ip).await?;
+
+        //Agree on the protocol version and the optional features before connecting
+        let (protocol_version, capabilities) = send_handshake(&mut client_handle).await?;
+
+        tracing::info!("Speaking protocol version {protocol_version} with the server, capabilities: {capabilities:?}");
+
+        //The frames sent after the handshake are compressed if both sides support it
+        let compression = FrameCompression::negotiate(&capabilities);
 
         /*We could return this, this is what the server is supposed to return, when a new user is connected */
         let (server_reply, server_handle) =
-            connect_to_server(client_handle, connection_msg).await?;
+            connect_to_server(client_handle, connection_msg, compression).await?;
 
-        ensure!(server_reply != "Invalid Password!", "Invalid password!");
-        ensure!(
-            server_reply != "Invalid Client!",
-            "Outdated client or connection!"
-        );
-        ensure!(
-            server_reply != "You have been banned!",
-            "You have been banned from this server!"
-        );
+        //The server either accepts the connection and replies with the key, or tells us why it has refused it
+        let server_reply = match serde_json::from_slice::<ServerControlReply>(&server_reply)? {
+            ServerControlReply::Accepted(key) => key,
+            reply => return Err(reply.into()),
+        };
 
         //This the key the server replied, and this is what well need to decrypt the messages, overwrite the client_secret variable
-        let client_secret = hex::decode(server_reply)?;
+        let client_secret = hex::decode(&server_reply)?;
 
         //Create connection pair
         let (reader, writer) = server_handle.into_split();
 
-        let connection_pair = ConnectionPair::new(writer, reader);
+        let connection_pair = ConnectionPair::new(writer, reader, compression);
 
         //Sync with the server
-        let sync_message =
-            ClientMessage::construct_sync_msg(&hashed_password, &author, uuid, 0, None);
+        let sync_message = ClientMessage::construct_sync_msg(
+            &hashed_password,
+            &author,
+            uuid,
+            None,
+            DEFAULT_CHANNEL_ID.to_string(),
+        );
 
         let server_response = connection_pair
             .send_message(sync_message)
@@ -1590,15 +1303,19 @@ impl ClientConnection
             .wait_for_response()
             .await?;
 
-        //This contains the sync string
-        let server_reply = decrypt_aes256(&server_response, &client_secret)
-            .expect("Failed to decrypt server sync packet");
+        //This contains the full sync
+        let ServerFrame::Master(server_reply) =
+            ServerFrame::decrypt(&server_response, &client_secret, compression)?
+        else {
+            bail!("The server has not replied with a full sync!");
+        };
 
         Ok((
             Self {
                 client_secret,
                 state: ConnectionState::Connected(connection_pair),
                 password: hashed_password,
+                capabilities,
             },
             server_reply,
         ))
@@ -1608,13 +1325,26 @@ impl ClientConnection
     {
         self.client_secret = Vec::new();
         self.state = ConnectionState::default();
+        self.capabilities = Vec::new();
     }
 
-    /// This function is used to __DISCONNECT__ from a server, with this the ```ClientConnection``` instance is destroyed (reset to its default values)
-    pub async fn disconnect(
-        &mut self,
-        author: String,
-        password: String,
+    /// Returns whether the capability was agreed on with the server
+    pub fn supports(&self, capability: Capability) -> bool
+    {
+        self.capabilities.contains(&capability)
+    }
+
+    /// Returns the compression of the frames sent by the server
+    pub fn compression(&self) -> FrameCompression
+    {
+        FrameCompression::negotiate(&self.capabilities)
+    }
+
+    /// This function is used to __DISCONNECT__ from a server, with this the ```ClientConnection``` instance is destroyed (reset to its default values)
+    pub async fn disconnect(
+        &mut self,
+        author: String,
+        password: String,
         uuid: String,
     ) -> anyhow::Result<()>
     {
@@ -1645,15 +1375,20 @@ pub struct ConnectionPair
 {
     pub writer: Arc<tokio::sync::Mutex<OwnedWriteHalf>>,
     pub reader: Arc<tokio::sync::Mutex<OwnedReadHalf>>,
+
+    /// The compression of the frames sent to the server, this was agreed on in the handshake
+    pub compression: FrameCompression,
 }
 
 impl ConnectionPair
 {
-    pub fn new(writer: OwnedWriteHalf, reader: OwnedReadHalf) -> Self
+    pub fn new(writer: OwnedWriteHalf, reader: OwnedReadHalf, compression: FrameCompression)
+        -> Self
     {
         Self {
             writer: Arc::new(tokio::sync::Mutex::new(writer)),
             reader: Arc::new(tokio::sync::Mutex::new(reader)),
+            compression,
         }
     }
 
@@ -1661,9 +1396,7 @@ impl ConnectionPair
     {
         let mut writer: tokio::sync::MutexGuard<'_, OwnedWriteHalf> = self.writer.lock().await;
 
-        let message_string = message.struct_into_string();
-
-        let message_bytes = message_string.as_bytes();
+        let message_bytes = self.compression.compress(encode_message(&message)?)?;
 
         //Send message length to server
         writer
@@ -1671,7 +1404,7 @@ impl ConnectionPair
             .await?;
 
         //Send message to server
-        writer.write_all(message_bytes).await?;
+        writer.write_all(&message_bytes).await?;
 
         writer.flush().await?;
 
@@ -1689,7 +1422,11 @@ pub enum ConnectionState
     #[default]
     Disconnected,
     Connecting,
+    /// The connection has been lost, and we are trying to connect again (The messages we have are kept meanwhile)
+    Reconnecting,
     Error,
+    /// The server has closed the connection, this contains the reason it has sent
+    Closed(ServerControlReply),
 }
 
 impl Debug for ConnectionState
@@ -1700,636 +1437,13 @@ impl Debug for ConnectionState
             ConnectionState::Connected(_) => "Connected",
             ConnectionState::Disconnected => "Disconnected",
             ConnectionState::Connecting => "Connecting",
+            ConnectionState::Reconnecting => "Reconnecting",
             ConnectionState::Error => "Error",
+            ConnectionState::Closed(_) => "Closed",
         })
     }
 }
 
-/*
-    Server. . .
-
-    Are used to convert client sent messages into a server message, so it can be sent back;
-    Therefor they're smaller in size
-*/
-
-/*
-        NOTICE:
-
-
-    .... Upload : is always what the server sends back to the client (so the client knows what to ask about)
-
-    .... Reply : is always what the server send to the client after the client asked.
-
-*/
-
-///This is what the server sends back (pushes to message vector), when receiving a file
-#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
-pub struct ServerFileUpload
-{
-    /// The uploaded file's name
-    pub file_name: String,
-    /// The uploaded file's sha256 singnature
-    pub signature: String,
-}
-
-/// This enum holds all the Server reply types so it can be decoded more easily on the client side
-#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
-pub enum ServerReplyType
-{
-    /// Returns the requested file
-    File(ServerFileReply),
-
-    /// Returns the requested image
-    Image(ServerImageReply),
-
-    /// Returns the requested audio file
-    Audio(ServerAudioReply),
-
-    /// The requested client's profile
-    /// The first value is the encrypted uuid
-    Client(ServerClientReply),
-}
-
-/// This struct holds everything important so the client can save and handle client profiles
-#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
-pub struct ServerClientReply
-{
-    /// The uuid of the user's profile we requested
-    pub uuid: String,
-    /// The profile of the user
-    pub profile: ClientProfile,
-}
-
-///When client asks for the image based on the provided index, reply with the image bytes
-#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
-pub struct ServerImageReply
-{
-    /// The requested image's bytes
-    pub bytes: Vec<u8>,
-
-    /// The requested image's sha256 signature
-    pub signature: String,
-}
-
-///This is what the server sends back, when asked for a file (FIleRequest)
-#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
-pub struct ServerFileReply
-{
-    /// The requested file's bytes
-    pub bytes: Vec<u8>,
-
-    /// The requested file's name
-    /// The reason a ```PathBuf``` is used here instead of a String, is because we need to grab the extension of the file easily
-    pub file_name: PathBuf,
-}
-
-///When client asks for the image based on the provided index, reply with the audio bytes, which gets written so it can be opened by a readbuf
-#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
-pub struct ServerAudioReply
-{
-    /// The requested audio file's bytes
-    pub bytes: Vec<u8>,
-    /// The requested audio file's signature
-    pub signature: String,
-    /// The requested audio file's name
-    pub file_name: String,
-
-    /// The index of the audio
-    pub audio_idx: u64,
-}
-
-///This is what the server sends back (pushes to message vector), when receiving a normal message
-#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
-pub struct ServerNormalMessage
-{
-    pub has_been_edited: bool,
-    pub message: String,
-}
-
-///REFER TO -> ServerImageUpload; logic      ||      same thing but with audio files
-#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
-pub struct ServerAudioUpload
-{
-    /// The signature of the uploaded image file
-    pub signature: String,
-    /// The file name of the uploaded audio
-    pub file_name: String,
-}
-
-///This is what gets sent to a client basically, and they have to ask for the file when the ui containing this gets rendered
-#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
-pub struct ServerImageUpload
-{
-    /// The signature of the uploaded image, this is the "handle" the clients asks the file on
-    pub signature: String,
-}
-
-/// This struct contains all the important information for the client to edit / update its own message list
-#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
-pub struct ServerMessageEdit
-{
-    /// The message's index it belongs to
-    pub index: i32,
-
-    /// None indicates a deleted message, rest is self explanatory
-    pub new_message: Option<String>,
-}
-
-/// This struct contains all the necessarily information for the client to update its own message list's reactions
-#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
-pub struct ServerMessageReaction
-{
-    pub reaction_type: ReactionType,
-}
-
-/// This struct is empty as its just a placeholder, because the info is provided in the struct which this message is wrapped in, and is provided directly when sending a message from the server to the client
-#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
-pub struct ServerMessageSync {}
-
-/// These are the possible server replies
-/// Why do we have to implement PartialEq for all of the structs? This is so funny
-#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, EnumDiscriminants, PartialEq)]
-#[strum_discriminants(derive(EnumString, EnumMessage))]
-pub enum ServerMessageType
-{
-    #[strum_discriminants(strum(message = "Upload"))]
-    Upload(ServerFileUpload),
-    #[strum_discriminants(strum(message = "Normal"))]
-    Normal(ServerNormalMessage),
-
-    ///Used to send and index to client so it knows which index to ask for
-    ///The index provided by this enum
-    #[strum_discriminants(strum(message = "Image"))]
-    Image(ServerImageUpload),
-    #[strum_discriminants(strum(message = "Audio"))]
-    Audio(ServerAudioUpload),
-
-    ///When a message is deleted this is what gets displayed
-    #[strum_discriminants(strum(message = "Deleted"))]
-    Deleted,
-
-    ///This message indicates an edit in the server's message list, therefor we need to send a message to the client so that the client will update its own list
-    #[strum_discriminants(strum(message = "Edit"))]
-    Edit(ServerMessageEdit),
-
-    ///This message indicates an edit in the server's message list, therefor we need to send a message to the client so that the client will update its own list
-    #[strum_discriminants(strum(message = "Reaction"))]
-    Reaction(ServerMessageReaction),
-
-    /// This message is used to "sync" with the client, it provides useful information to the client about other clients (like last viewed message)
-    #[strum_discriminants(strum(message = "Sync"))]
-    Sync(ServerMessageSync),
-
-    /// This message type can only be produced by the server, and hold useful information to the user(s)
-    #[strum_discriminants(strum(message = "Server"))]
-    Server(ServerMessage),
-
-    /// This message shows if a user has connected to the voip call
-    #[strum_discriminants(strum(message = "Voip connection"))]
-    VoipEvent(ServerVoipEvent),
-
-    /// This message holds the State of the Voip service
-    #[strum_discriminants(strum(message = "Voip state"))]
-    VoipState(ServerVoipState),
-}
-
-/// The types of message the server can "send"
-#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
-pub enum ServerMessage
-{
-    /// This is sent when a user is connected to the server
-    Connect(ClientProfile),
-    /// This is sent when a user is disconnecting from the server
-    Disconnect(ClientProfile),
-
-    /// This is sent when a user is banned from the server
-    Ban(ClientProfile),
-}
-
-///This is one msg (packet), which gets bundled when sending ServerMain
-#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
-pub struct ServerOutput
-{
-    /// The ```usize``` shows which message its a reply to in the message stack
-    /// The server stores all messages in a vector so this index shows which message its a reply to (if it is)
-    pub replying_to: Option<usize>,
-    /// Inner message which is *wrapped* in the ServerOutput
-    pub message_type: ServerMessageType,
-    /// The account's name who sent the message
-    pub author: String,
-    /// The date when this message was sent
-    pub message_date: String,
-    /// The user who sent this message's uuid
-    pub uuid: String,
-}
-
-impl ServerOutput
-{
-    pub fn _struct_into_string(&self) -> String
-    {
-        serde_json::to_string(self).unwrap_or_default()
-    }
-
-    /// This function converts a client message to a ServerOutput, which gets sent to all the clients (Its basically a simplified client message)
-    pub fn convert_clientmsg_to_servermsg(
-        normal_msg: ClientMessage,
-        // The signature is used to ask bytes from the server, for example in a image message this signature will be used to get the image's byte
-        signature: String,
-        //Automatically generated enum by strum
-        upload_type: ServerMessageTypeDiscriminants,
-        uuid: String,
-        username: String,
-    ) -> ServerOutput
-    {
-        ServerOutput {
-            replying_to: normal_msg.replying_to,
-            message_type:
-                match normal_msg.message_type {
-                    ClientMessageType::FileRequestType(_) => unimplemented!("Converting request packets isnt implemented, because they shouldnt be displayed by the client"),
-                    ClientMessageType::FileUpload(upload) => {
-                        match upload_type {
-                            ServerMessageTypeDiscriminants::Upload => {
-                                ServerMessageType::Upload(
-                                    ServerFileUpload {
-                                        file_name: format!(
-                                            "{}.{}",
-                                            upload.name.unwrap_or_default(),
-                                            upload.extension.unwrap_or_default()
-                                        ),
-                                        signature,
-                                    }
-                                )
-                            },
-                            ServerMessageTypeDiscriminants::Image => {
-                                ServerMessageType::Image(
-                                    ServerImageUpload {
-                                        signature,
-                                    }
-                                )
-                            },
-                            ServerMessageTypeDiscriminants::Audio => {
-                                ServerMessageType::Audio(
-                                    ServerAudioUpload {
-                                        signature,
-                                        file_name: format!(
-                                            "{}.{}",
-                                            upload.name.unwrap_or_default(),
-                                            upload.extension.unwrap_or_default()
-                                        ),
-                                    }
-                                )
-                            },
-                            ServerMessageTypeDiscriminants::VoipState => unreachable!(),
-                            ServerMessageTypeDiscriminants::VoipEvent => unreachable!(),
-                            ServerMessageTypeDiscriminants::Deleted => unreachable!(),
-                            ServerMessageTypeDiscriminants::Sync => unreachable!(),
-                            ServerMessageTypeDiscriminants::Normal => unreachable!(),
-                            ServerMessageTypeDiscriminants::Edit => unreachable!(),
-                            ServerMessageTypeDiscriminants::Reaction => unreachable!(),
-                            ServerMessageTypeDiscriminants::Server => unreachable!(),
-                        }
-                    },
-                    ClientMessageType::NormalMessage(message) => {
-                        ServerMessageType::Normal(
-                            ServerNormalMessage {
-                                message: message.message,
-                                //Set default value for incoming messages
-                                has_been_edited: false,
-                            }
-                        )
-                    },
-                    ClientMessageType::VoipConnection(voip_message_type) => {
-                        let server_message = match voip_message_type {
-                            ClientVoipRequest::Connect(_) => {
-                                ServerVoipEvent {
-                                    event: VoipEvent::Connected,
-                                    uuid: uuid.clone(),
-                                }
-                            },
-                            ClientVoipRequest::Disconnect => {
-                                ServerVoipEvent {
-                                    event: VoipEvent::Disconnected,
-                                    uuid: uuid.clone(),
-                                }
-                            },
-                            ClientVoipRequest::ImageConnected => {
-                                ServerVoipEvent {
-                                    event: VoipEvent::ImageConnected,
-                                    uuid: uuid.clone(),
-                                }
-                            },
-                            ClientVoipRequest::ImageDisconnected => {
-                                ServerVoipEvent {
-                                    event: VoipEvent::ImageDisconnected,
-                                    uuid: uuid.clone(),
-                                }
-                            },
-                        };
-
-                        ServerMessageType::VoipEvent(server_message)
-                    },
-                    ClientMessageType::SyncMessage(_) => {
-                        ServerMessageType::Sync(ServerMessageSync {  })
-                    },
-                    //These messages also have a side effect on the server's list of the messages
-                    //The client will interpret these messages and modify its own message list
-                    ClientMessageType::Reaction(message) => {
-                        ServerMessageType::Reaction(
-                            //Match the incoming ReactionType
-                            match message {
-                                //The client will increment its emoji counter
-                                ReactionType::Add(message) => {
-                                    ServerMessageReaction { reaction_type: ReactionType::Add(ClientReaction { emoji_name: message.emoji_name, uuid: message.uuid, message_index: message.message_index }) }
-                                },
-                                //The client will decrement its emoji counter
-                                //If the index is 0 the client will automatically remove that emoji entry
-                                ReactionType::Remove(message) => {
-                                    ServerMessageReaction { reaction_type: ReactionType::Remove(ClientReaction { emoji_name: message.emoji_name, uuid: message.uuid, message_index: message.message_index }) }
-                                },
-                            }
-                        )
-                    },
-                    ClientMessageType::MessageEdit(message) => {
-                        ServerMessageType::Edit(ServerMessageEdit { index: message.index as i32, new_message: message.new_message })
-                    },
-                },
-            author: username,
-            message_date: normal_msg.message_date,
-            uuid,
-        }
-    }
-}
-
-/// Used to put all the messages into 1 big pack (Bundling All the ServerOutput-s), Main packet, this gets to all the clients
-/// This message type is only used when a client is connecting an has to do a full sync (sending everything to the client all the messages reactions, etc)
-#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Default)]
-pub struct ServerMaster
-{
-    ///All of the messages received from the server
-    pub message_list: Vec<ServerOutput>,
-
-    ///All of the messages' reactions are
-    pub reaction_list: Vec<MessageReaction>,
-
-    ///Users last seen message index
-    pub user_seen_list: Vec<ClientLastSeenMessage>,
-
-    ///This entry holds all the connected user's profile
-    pub connected_clients_profile: HashMap<String, ClientProfile>,
-
-    ///This entry shows all the client connected to the Voip call, if there is a a call
-    pub ongoing_voip_call: ServerVoipState,
-}
-
-impl ServerMaster
-{
-    pub fn struct_into_string(&self) -> String
-    {
-        serde_json::to_string(self).unwrap_or_default()
-    }
-}
-
-///This struct provides all the necessary information to keep the client and the server in sync
-/// Its struct contains ```Vec<ClientLastSeenMessage>``` which is for displaying which message has the user seen
-/// And the message the client has sent
-/// We dont need to provide any other information since, the ```ServerMaster``` struct ensures all the clients have the same field when connecting
-#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
-pub struct ServerSync
-{
-    ///Users last seen message index
-    pub user_seen_list: Vec<ClientLastSeenMessage>,
-    /// The inner message
-    pub message: ServerOutput,
-}
-
-impl ServerSync
-{
-    pub fn struct_into_string(&self) -> String
-    {
-        serde_json::to_string(self).unwrap_or_default()
-    }
-}
-
-//When a client is connected this is where the client gets saved
-#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
-pub struct ConnectedClient
-{
-    /// The reason one gets EOF when disconnecting is because this field is dropped (With this struct)
-    /// This handle wouldnt have to be sent so its all okay, its only present on the server's side
-    #[serde(skip)]
-    pub handle: Option<Arc<tokio::sync::Mutex<OwnedWriteHalf>>>,
-    pub uuid: String,
-    pub username: String,
-}
-
-impl ConnectedClient
-{
-    pub fn new(
-        uuid: String,
-        username: String,
-        handle: Arc<tokio::sync::Mutex<OwnedWriteHalf>>,
-    ) -> Self
-    {
-        Self {
-            uuid,
-            username,
-            handle: Some(handle),
-        }
-    }
-}
-
-//This contains the client's name and their last seen message's index
-#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
-pub struct ClientLastSeenMessage
-{
-    pub index: usize,
-    pub uuid: String,
-}
-
-impl ClientLastSeenMessage
-{
-    pub fn new(index: usize, uuid: String) -> Self
-    {
-        Self { index, uuid }
-    }
-}
-
-/// This enum contains the actions the client can take, these are sent to the server
-#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
-pub enum ClientVoipRequest
-{
-    /// A voip a call will be automatically issued if there is no ongoing call
-    /// The inner value of conncect is the port the Client ```UdpSocket``` is opened on
-    Connect(u16),
-
-    /// The voip call will automatically stop once there are no connected clients
-    Disconnect,
-
-    /// The client has enabled video
-    ImageConnected,
-
-    /// The client has disabled video
-    ImageDisconnected,
-}
-
-/// This enum is used to display if a client has joined or left the Voip call, this is a ```ServerMessageType```
-#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
-pub enum VoipEvent
-{
-    /// Client connected
-    Connected,
-    /// Client disconnected
-    Disconnected,
-
-    /// The client has enabled video
-    ImageConnected,
-
-    /// The client has disabled video
-    ImageDisconnected,
-}
-
-/// This struct holds all important information, when informing clients / servers about a ```VoipEvent```
-#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
-pub struct ServerVoipEvent
-{
-    /// The uuid of the user who has initiated this event
-    pub uuid: String,
-    /// The event the user has initiated
-    pub event: VoipEvent,
-}
-
-///The struct contains all the useful information for displaying an ongoing voip connection.
-#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Default, PartialEq)]
-pub struct ServerVoipState
-{
-    pub connected_clients: Option<Vec<String>>,
-}
-
-/// This num contains the actions the server can take, these are sent to the client
-#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
-pub enum ServerVoipRequest
-{
-    /// This enum acts as a ```packet``` and is handed out to all the clients if a connection is started / established
-    ConnectionStart(ServerVoipStart),
-    /// This enum acts as a ```packet``` and i handed out to all the clients connected to the call
-    ConnectionClosed(ServerVoipClose),
-}
-
-/// This struct contains all the information important for the non connected clients
-#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
-pub struct ServerVoipStart
-{
-    /// The clients connected to the Voip call
-    pub connected_clients: Vec<ClientProfile>,
-}
-
-/// This enum holds the two outcomes of a connection request
-#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
-pub enum ServerVoipReply
-{
-    /// This enum is when the connection request is successful
-    Success,
-    /// This enum is when the connection request is unsuccessful, it also contains the reason
-    Fail(ServerVoipClose),
-}
-
-/// This struct contains the reason for closing the voip connection
-/// This maybe at any point of the Voip call, or the connection
-#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
-pub struct ServerVoipClose
-{
-    /// The reason for closing the Voip connection
-    pub reason: String,
-}
-
-pub type ImageBuffer = Arc<DashMap<String, IndexMap<String, HashMap<String, Option<Vec<u8>>>>>>;
-
-#[derive(Debug, Clone)]
-pub struct ServerVoip
-{
-    /// This field contains all the connected client's ```uuid``` with their ```SocketAddr```
-    pub connected_clients: Arc<DashMap<String, SocketAddr>>,
-
-    /// This field contains a ```HashMap``` which pairs the SocketAddr to the client's listening thread's sender (So that the receiver thread can receive the ```Vec<u8>``` sent by the sender)
-    /// The second part of the tuple is for shutting down the client manager thread, if they disconnect
-    pub connected_client_thread_channels:
-        Arc<DashMap<SocketAddr, (Arc<tokio::sync::mpsc::Sender<Vec<u8>>>, CancellationToken)>>,
-
-    /// This field contains the amount of time the call has been established for
-    pub _established_since: chrono::DateTime<Utc>,
-
-    /// The socket the server is listening on for incoming messages from ipv6 addresses
-    pub socket_v6: Option<Arc<UdpSocket>>,
-
-    /// The socket the server is listening on for incoming messages from ipv4 addresses
-    pub socket_v4: Arc<UdpSocket>,
-
-    /// The cancellation token cancels threads, which are for listening and relaying (Distributing info)
-    pub thread_cancellation_token: CancellationToken,
-
-    /// This entry makes sure the 2 threads are only spawned once
-    pub threads: Option<()>,
-
-    /// This field contains the Video(Image) buffer of the clients.
-    /// If header file's hashes are paired we send / relay the image to all the other clients.
-    /// The ```DashMap``` contains the HeaderMessages (value) paired with the uuid's of the clients (key)
-    /// The ```IndexMap``` contains the MessageParts (value)  paired with the HeaderMessage's uuid (key)
-    /// The ```HashMap``` contains the image bytes (value) paired with the byte hash (key)
-    pub image_buffer: ImageBuffer,
-}
-
-impl ServerVoip
-{
-    /// Add the ```SocketAddr``` to the ```UDP``` server's destinations
-    /// This function can take Self as a clone since we are only accessing entries which implement ```Sync```
-    pub fn connect(&self, uuid: String, socket_addr: SocketAddr) -> anyhow::Result<()>
-    {
-        self.connected_clients.insert(uuid, socket_addr);
-
-        Ok(())
-    }
-
-    /// Remove the ```SocketAddr``` to the ```UDP``` server's destinations
-    pub fn disconnect(&self, uuid: String) -> anyhow::Result<()>
-    {
-        let (_, removed_address) = self
-            .connected_clients
-            .remove(&uuid)
-            .ok_or_else(|| anyhow::Error::msg("Client was not connected"))?;
-
-        self.connected_client_thread_channels
-            .remove(&removed_address);
-
-        Ok(())
-    }
-}
-
-/// This enum holds the variants of a UdpMessage
-#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
-pub enum UdpMessageType
-{
-    /// Voice message
-    Voice = 1,
-    /// Image header message
-    ImageHeader = 3,
-    /// Image message
-    Image = 2,
-}
-
-impl UdpMessageType
-{
-    pub fn from_number(num: u32) -> Self
-    {
-        match num {
-            1 => Self::Voice,
-            2 => Self::Image,
-            3 => Self::ImageHeader,
-
-            _ => unimplemented!("Branch not covered"),
-        }
-    }
-}
-
 /// This struct contains all the information for having a voice and or video call.
 #[derive(Clone)]
 pub struct Voip
@@ -2587,10 +1701,10 @@ pub struct AudioPlayback
     pub _stream: Arc<OutputStream>,
     ///Output stream handle
     pub stream_handle: OutputStreamHandle,
-    ///Audio sinks, these are the audios played
-    pub sink_list: Vec<Option<Arc<Sink>>>,
-    ///Settings list for the sink_list (The audios being played)
-    pub settings_list: Vec<AudioSettings>,
+    ///Audio sinks, these are the audios played, the key is the id of the message the audio was sent in
+    pub sink_list: HashMap<MessageId, Arc<Sink>>,
+    ///Settings list for the sink_list (The audios being played), the key is the id of the message the audio was sent in
+    pub settings_list: HashMap<MessageId, AudioSettings>,
 }
 
 impl Default for AudioPlayback
@@ -2601,8 +1715,8 @@ impl Default for AudioPlayback
         Self {
             _stream: Arc::new(stream),
             stream_handle,
-            sink_list: Vec::new(),
-            settings_list: Vec::new(),
+            sink_list: HashMap::new(),
+            settings_list: HashMap::new(),
         }
     }
 }
@@ -2832,14 +1946,19 @@ impl UserInformation
         pass_hash_match(password, self.password.clone())
     }
 
-    /// This serializer function automatically encrypts the struct with the *encrypt_aes256* fn to string
-    pub fn serialize(&self) -> anyhow::Result<String>
+    /// Returns the key the user's files are encrypted with, this is derived from the (already encrypted) password
+    pub fn encryption_key(&self) -> anyhow::Result<Vec<u8>>
     {
         //Hash password so it can be used to encrypt a file
         let hashed_password = sha256::digest(self.password.clone());
-        let encryption_key = hex::decode(hashed_password)?;
 
-        encrypt_aes256(serde_json::to_string(&self)?, &encryption_key)
+        Ok(hex::decode(hashed_password)?)
+    }
+
+    /// This serializer function automatically encrypts the struct with the *encrypt_aes256* fn to string
+    pub fn serialize(&self) -> anyhow::Result<String>
+    {
+        encrypt_aes256(serde_json::to_string(&self)?, &self.encryption_key()?)
     }
 
     /// This deserializer function automatically decrypts the string the *encrypt_aes256* fn to Self
@@ -2883,182 +2002,1029 @@ impl UserInformation
     }
 }
 
-/// aes256 is decrypted by this function by a fixed key
-pub fn decrypt_aes256(string_to_be_decrypted: &str, key: &[u8]) -> anyhow::Result<String>
+///Check login
+pub fn login(username: String, password: String) -> Result<(UserInformation, PathBuf)>
 {
-    let mut ciphertext = hex::decode(string_to_be_decrypted)?;
+    let app_data = env::var("APPDATA")?;
 
-    let nonce: Vec<u8> = ciphertext.drain(ciphertext.len() - 12..).collect();
+    let path = PathBuf::from(format!("{app_data}\\Matthias\\{username}.szch"));
 
-    let key = Key::<Aes256Gcm>::from_slice(key);
+    let file_contents: UserInformation =
+        UserInformation::deserialize(&fs::read_to_string(&path)?, encrypt(password.clone()))?;
 
-    let cipher = Aes256Gcm::new(key);
+    let user_check = username == file_contents.username;
 
-    let plaintext = cipher
-        .decrypt(&GenericArray::clone_from_slice(&nonce), ciphertext.as_ref())
-        .map_err(|_| Error::msg("Invalid password!"))?;
+    ensure!(user_check, "File corrupted at the username entry");
 
-    Ok(String::from_utf8(plaintext)?)
+    Ok((file_contents, path))
 }
 
-/// This function decrypts a provided ```String```, with the provided key using ```Aes-256```
-pub fn encrypt_aes256(string_to_be_encrypted: String, key: &[u8]) -> anyhow::Result<String>
+///Register a new profile
+pub fn register(register: Register) -> anyhow::Result<UserInformation>
 {
-    ensure!(key.len() == 32);
+    if register.username.contains(' ')
+        || register.username.contains('@')
+        || register.username.contains(' ')
+    {
+        return Err(anyhow::Error::msg("Cant use special characters in name"));
+    }
 
-    let key = Key::<Aes256Gcm>::from_slice(key);
+    let app_data = env::var("APPDATA")?;
 
-    let cipher = Aes256Gcm::new(key);
+    let user_path = PathBuf::from(format!("{app_data}\\Matthias\\{}.szch", register.username));
 
-    let mut nonce = [0; 12];
+    //Check if user already exists
+    if std::fs::metadata(&user_path).is_ok() {
+        return Err(anyhow::Error::msg("User already exists"));
+    }
 
-    rand::thread_rng().fill(&mut nonce);
+    //Construct user info struct then write it to the appdata matthias folder
+    let user_info = UserInformation::new(
+        register.username,
+        register.password,
+        generate_uuid().to_string(),
+        register.full_name,
+        register.gender,
+        register.birth_date,
+        register.normal_profile_picture,
+        register.small_profile_picture,
+        user_path.clone(),
+    );
 
-    let bytes = string_to_be_encrypted.as_bytes().to_vec();
+    user_info.write_file(user_path)?;
 
-    let ciphertext = cipher
-        .encrypt(&nonce.into(), &*bytes)
-        .map_err(|_| Error::msg("Invalid key, couldnt encrypt the specified item."))?;
+    Ok(user_info)
+}
 
-    let ciphertext = hex::encode([ciphertext, nonce.to_vec()].concat());
+/// The files which are being uploaded or downloaded in chunks (```TRANSFER_CHUNK_SIZE```), the key is the sha256 hash (signature) of the file
+/// The transfers are kept until they are finished, so that they can be resumed after reconnecting
+#[derive(Debug, Clone, Default)]
+pub struct FileTransfers
+{
+    /// The uploads which havent been committed yet
+    pub uploads: Arc<DashMap<String, PendingUpload>>,
 
-    Ok(ciphertext)
+    /// The downloads in progress, the value is the path the file is saved to
+    pub downloads: Arc<DashMap<String, PathBuf>>,
+
+    /// The address of the server the files are transferred with
+    pub server_address: Arc<Mutex<String>>,
 }
 
-/// The provided byte array is encrypted with aes256 with the given key
-pub fn encrypt_aes256_bytes(bytes: &[u8], key: &[u8]) -> anyhow::Result<Vec<u8>>
+/// A file which is being uploaded, the upload is committed with the message once all of its chunks have been sent
+#[derive(Debug, Clone)]
+pub struct PendingUpload
 {
-    ensure!(key.len() == 32);
+    /// The path of the file being uploaded
+    pub path: PathBuf,
 
-    let key = Key::<Aes256Gcm>::from_slice(key);
+    /// The sha256 hash of the file, this identifies the upload
+    pub hash: String,
 
-    let cipher = Aes256Gcm::new(key);
+    /// The size of the file in bytes
+    pub size: u64,
 
-    let mut nonce = [0; 12];
+    /// The message which commits the upload
+    pub message: ClientMessage,
 
-    rand::thread_rng().fill(&mut nonce);
+    /// Recorded audio is saved to a temporary file before uploading it, which is removed once the upload has been committed
+    pub temporary: bool,
+}
+
+impl PendingUpload
+{
+    /// Hashes the file, this reads the whole file so it shouldnt be called from the main thread
+    pub fn new(
+        path: PathBuf,
+        temporary: bool,
+        uuid: &str,
+        replying_to: Option<MessageId>,
+        channel_id: String,
+    ) -> Result<Self>
+    {
+        let hash = sha256::try_digest(path.as_path())?;
+        let size = fs::metadata(&path)?.len();
 
-    let encrypted_bytes = cipher
-        .encrypt(&nonce.into(), bytes)
-        .map_err(|_| Error::msg("Invalid key, couldnt encrypt the specified item."))?;
+        Ok(Self {
+            message: ClientMessage::construct_file_msg(
+                &path,
+                hash.clone(),
+                size,
+                uuid,
+                replying_to,
+                channel_id,
+            ),
+            path,
+            hash,
+            size,
+            temporary,
+        })
+    }
 
-    Ok([encrypted_bytes, nonce.to_vec()].concat())
+    /// Returns the message which starts (or resumes) the upload
+    fn start_message(&self) -> ClientMessage
+    {
+        ClientMessage::construct_file_transfer_msg(
+            ClientFileTransfer::StartUpload {
+                hash: self.hash.clone(),
+                size: self.size,
+                extension: self
+                    .path
+                    .extension()
+                    .map(|extension| extension.to_string_lossy().to_string()),
+            },
+            &self.message.uuid,
+        )
+    }
 }
 
-#[inline]
-/// This function decrypts a provided array of ```Bytes```, with the provided key using ```Aes-256```
-pub fn decrypt_aes256_bytes(bytes_to_be_decrypted: &[u8], key: &[u8]) -> anyhow::Result<Vec<u8>>
+impl FileTransfers
 {
-    let mut bytes: Vec<u8> = bytes_to_be_decrypted.to_vec();
+    /// Starts the upload, the chunks are sent once the server replies with the offset the upload continues from (```send_chunks```)
+    pub async fn start_upload(
+        &self,
+        connection: ClientConnection,
+        upload: PendingUpload,
+    ) -> Result<()>
+    {
+        let message = upload.start_message();
+
+        self.uploads.insert(upload.hash.clone(), upload);
 
-    let key = Key::<Aes256Gcm>::from_slice(key);
+        connection.send_message(message).await?;
 
-    let cipher = Aes256Gcm::new(key);
+        Ok(())
+    }
+
+    /// Sends the chunks of the upload from the offset the server has replied with, then commits the upload
+    pub async fn send_chunks(
+        &self,
+        connection: ClientConnection,
+        reply: ServerUploadReply,
+    ) -> Result<()>
+    {
+        let upload = self
+            .uploads
+            .get(&reply.hash)
+            .map(|upload| upload.clone())
+            .ok_or_else(|| Error::msg("The server replied to an unknown upload."))?;
+
+        let mut file = tokio::fs::File::open(&upload.path).await?;
+
+        file.seek(SeekFrom::Start(reply.offset)).await?;
 
-    let nonce: Vec<u8> = bytes.drain(bytes.len() - 12..).collect();
+        let mut offset = reply.offset;
+
+        //Only one chunk is held in memory at a time
+        while offset < upload.size {
+            let mut bytes = Vec::new();
+
+            (&mut file)
+                .take((upload.size - offset).min(TRANSFER_CHUNK_SIZE as u64))
+                .read_to_end(&mut bytes)
+                .await?;
+
+            ensure!(
+                !bytes.is_empty(),
+                "The file has changed since the upload was started."
+            );
+
+            let chunk_size = bytes.len() as u64;
+
+            connection
+                .clone()
+                .send_message(ClientMessage::construct_file_transfer_msg(
+                    ClientFileTransfer::UploadChunk {
+                        hash: upload.hash.clone(),
+                        offset,
+                        bytes,
+                    },
+                    &upload.message.uuid,
+                ))
+                .await?;
+
+            offset += chunk_size;
+        }
+
+        connection.send_message(upload.message.clone()).await?;
+
+        self.uploads.remove(&upload.hash);
+
+        if upload.temporary {
+            fs::remove_file(&upload.path)?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes the downloaded chunk to the download's partial file, the user is asked where to save the file when the first chunk arrives
+    /// Returns the offset of the next chunk which has to be requested, returns ```None``` if there is nothing left to request
+    pub fn receive_chunk(
+        &self,
+        reply: ServerFileReply,
+        toasts: Arc<Mutex<Toasts>>,
+    ) -> Result<Option<u64>>
+    {
+        let download = self
+            .downloads
+            .get(&reply.signature)
+            .map(|path| path.clone());
+
+        let path = match download {
+            Some(path) => path,
+            None => {
+                let Some(path) = save_file_dialog(&reply.file_name)
+                else {
+                    return Ok(None);
+                };
+
+                self.downloads.insert(reply.signature.clone(), path.clone());
+
+                path
+            },
+        };
+
+        let part_path = download_part_path(&path);
+
+        let mut part_file = if reply.offset == 0 {
+            fs::File::create(&part_path)?
+        }
+        else {
+            fs::OpenOptions::new().append(true).open(&part_path)?
+        };
+
+        ensure!(
+            part_file.metadata()?.len() == reply.offset,
+            "Received a chunk of {} from the wrong offset.",
+            path.display()
+        );
 
-    let decrypted_bytes = cipher
-        .decrypt(&GenericArray::clone_from_slice(&nonce), &*bytes)
-        .map_err(|_| Error::msg("Invalid password!"))?;
+        part_file.write_all(&reply.bytes)?;
 
-    Ok(decrypted_bytes)
+        let received = reply.offset + reply.bytes.len() as u64;
+
+        if received < reply.total_size {
+            return Ok(Some(received));
+        }
+
+        self.downloads.remove(&reply.signature);
+
+        //Hashing a large file takes a while, so it shouldnt block the ui
+        tokio::task::spawn_blocking(move || {
+            if let Err(err) = finish_download(&part_path, &path, &reply.signature) {
+                display_error_message(err, toasts);
+            }
+        });
+
+        Ok(None)
+    }
+
+    /// Continues the unfinished transfers after connecting to the server, the transfers of other servers are dropped
+    /// The uploads are started again (the server replies with the offset each upload continues from), the downloads are requested from the end of their partial files
+    pub async fn resume(
+        &self,
+        connection: ClientConnection,
+        server_address: String,
+        uuid: &str,
+    ) -> Result<()>
+    {
+        {
+            let mut transfers_address = self
+                .server_address
+                .lock()
+                .map_err(|err| Error::msg(err.to_string()))?;
+
+            if *transfers_address != server_address {
+                self.uploads.clear();
+                self.downloads.clear();
+
+                *transfers_address = server_address;
+            }
+        }
+
+        let mut messages: Vec<ClientMessage> = self
+            .uploads
+            .iter()
+            .map(|upload| upload.start_message())
+            .collect();
+
+        messages.extend(self.downloads.iter().map(|download| {
+            let offset = fs::metadata(download_part_path(download.value()))
+                .map(|metadata| metadata.len())
+                .unwrap_or_default();
+
+            ClientMessage::construct_file_request_msg(download.key().clone(), offset, uuid)
+        }));
+
+        for message in messages {
+            connection.clone().send_message(message).await?;
+        }
+
+        Ok(())
+    }
 }
 
-#[inline]
-/// Argon is used to encrypt this
-pub fn encrypt(string_to_be_encrypted: String) -> String
+/// The requests which are sent once there is a connection to the server, so that nothing the user does while the connection is down is lost
+/// The requests are delivered in the order they were made, the outbox of every server is saved to the disk (encrypted) so it survives restarts
+#[derive(Debug, Clone, Default)]
+pub struct Outbox
 {
-    let password = string_to_be_encrypted.trim().as_bytes();
-    let salt = b"c1eaa94ec38ab7aa16e9c41d029256d3e423f01defb0a2760b27117ad513ccd2";
-    let config = Config::owasp1();
+    /// The requests which havent been sent yet, in the order they are sent in
+    pub items: Arc<Mutex<Vec<OutboxItem>>>,
+
+    /// The file the outbox of the opened server is saved to, this is ```None``` until we have connected to a server
+    storage: Arc<Mutex<Option<OutboxStorage>>>,
 
-    argon2::hash_encoded(password, salt, &config).unwrap()
+    /// Wakes up the thread which delivers the requests
+    flush_requested: Arc<tokio::sync::Notify>,
 }
 
-#[inline]
-fn pass_hash_match(to_be_verified: String, encoded: String) -> bool
+/// The file the outbox is saved to and the key it is encrypted with
+#[derive(Debug, Clone)]
+struct OutboxStorage
 {
-    argon2::verify_encoded(&encoded, to_be_verified.as_bytes()).unwrap()
+    path: PathBuf,
+    encryption_key: Vec<u8>,
 }
 
-///Check login
-pub fn login(username: String, password: String) -> Result<(UserInformation, PathBuf)>
+/// A request waiting in the outbox
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct OutboxItem
 {
-    let app_data = env::var("APPDATA")?;
+    /// Identifies the item, so it can be cancelled or retried
+    pub id: String,
 
-    let path = PathBuf::from(format!("{app_data}\\Matthias\\{username}.szch"));
+    pub request: OutboxRequest,
 
-    let file_contents: UserInformation =
-        UserInformation::deserialize(&fs::read_to_string(&path)?, encrypt(password.clone()))?;
+    pub state: OutboxState,
+}
 
-    let user_check = username == file_contents.username;
+/// The requests which can be queued in the outbox
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub enum OutboxRequest
+{
+    /// A normal or private message, a reaction or an edit
+    Message(ClientMessage),
 
-    ensure!(user_check, "File corrupted at the username entry");
+    /// The file is only hashed when the upload is started, since it could take a while
+    Upload
+    {
+        path: PathBuf,
+        temporary: bool,
+        uuid: String,
+        replying_to: Option<MessageId>,
+        channel_id: String,
+    },
+}
 
-    Ok((file_contents, path))
+impl OutboxRequest
+{
+    /// Returns the channel the request should be displayed in, ```None``` is returned if the request doesnt belong to a channel (Private messages)
+    /// Reactions and edits are displayed in the channel of the message they modify, if that message is loaded
+    pub fn channel_id(&self, messages: &ServerMaster) -> Option<String>
+    {
+        match self {
+            OutboxRequest::Message(message) => {
+                let modified_message_id = match &message.message_type {
+                    ClientMessageType::DirectMessage(_) => return None,
+                    ClientMessageType::Reaction(
+                        ReactionType::Add(reaction) | ReactionType::Remove(reaction),
+                    ) => Some(reaction.message_id),
+                    ClientMessageType::MessageEdit(edit) => Some(edit.message_id),
+                    _ => None,
+                };
+
+                Some(
+                    modified_message_id
+                        .and_then(|message_id| messages.message(message_id))
+                        .map(|modified_message| modified_message.channel_id.clone())
+                        .unwrap_or_else(|| message.channel_id.clone()),
+                )
+            },
+            OutboxRequest::Upload { channel_id, .. } => Some(channel_id.clone()),
+        }
+    }
+
+    /// Returns the private conversation the request belongs to, if its a private message
+    pub fn direct_message_recipient(&self) -> Option<&str>
+    {
+        match self {
+            OutboxRequest::Message(ClientMessage {
+                message_type: ClientMessageType::DirectMessage(message),
+                ..
+            }) => Some(message.recipient.as_str()),
+            _ => None,
+        }
+    }
+
+    /// A short description of the request which is displayed while its pending
+    pub fn description(&self) -> String
+    {
+        match self {
+            OutboxRequest::Message(message) => {
+                match &message.message_type {
+                    ClientMessageType::NormalMessage(message) => message.message.clone(),
+                    ClientMessageType::DirectMessage(message) => message.message.clone(),
+                    ClientMessageType::Reaction(ReactionType::Add(reaction)) => {
+                        format!("Reacting with :{}:", reaction.emoji_name)
+                    },
+                    ClientMessageType::Reaction(ReactionType::Remove(reaction)) => {
+                        format!("Removing the :{}: reaction", reaction.emoji_name)
+                    },
+                    ClientMessageType::MessageEdit(ClientMessageEdit {
+                        new_message: Some(new_message),
+                        ..
+                    }) => format!("Edit: {new_message}"),
+                    ClientMessageType::MessageEdit(ClientMessageEdit {
+                        new_message: None, ..
+                    }) => "Deleting a message".to_string(),
+                    _ => "Request".to_string(),
+                }
+            },
+            OutboxRequest::Upload { path, .. } => {
+                format!(
+                    "Upload {}",
+                    path.file_name().unwrap_or_default().to_string_lossy()
+                )
+            },
+        }
+    }
 }
 
-///Register a new profile
-pub fn register(register: Register) -> anyhow::Result<UserInformation>
+/// The state of a request in the outbox
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub enum OutboxState
 {
-    if register.username.contains(' ')
-        || register.username.contains('@')
-        || register.username.contains(' ')
+    /// The request is sent once there is a connection
+    Pending,
+
+    /// The request is being sent, or its waiting for the server to acknowledge it (```ServerReplyType::Acknowledged```)
+    /// It cannot be cancelled anymore, if the connection is lost its sent again after reconnecting
+    Sending,
+
+    /// Sending the request has failed, the requests after it wait until its retried or cancelled
+    Failed(String),
+}
+
+impl Outbox
+{
+    /// Opens the outbox of the server, the requests which have failed because the connection was lost are sent again
+    /// The outbox is kept per server and per user, the requests queued before any outbox was opened are kept
+    pub fn open(&self, server_address: &str, uuid: &str, encryption_key: Vec<u8>) -> Result<()>
     {
-        return Err(anyhow::Error::msg("Cant use special characters in name"));
+        let folder_path = format!(
+            "{}\\matthias\\Outbox\\{}",
+            env!("APPDATA"),
+            general_purpose::URL_SAFE_NO_PAD.encode(server_address),
+        );
+
+        let path = PathBuf::from(format!("{folder_path}\\{uuid}"));
+
+        {
+            let mut storage = self
+                .storage
+                .lock()
+                .map_err(|err| Error::msg(err.to_string()))?;
+
+            if storage.as_ref().map(|storage| &storage.path) != Some(&path) {
+                //An unreadable outbox is overwritten, so that the requests queued from now on are still saved
+                let mut items = match fs::read_to_string(&path) {
+                    Ok(contents) => {
+                        decrypt_aes256(&contents, &encryption_key)
+                            .and_then(|items| Ok(serde_json::from_str::<Vec<OutboxItem>>(&items)?))
+                            .unwrap_or_else(|err| {
+                                tracing::error!(
+                                    "Failed to read the outbox ({}): {err}",
+                                    path.display()
+                                );
+
+                                Vec::new()
+                            })
+                    },
+                    Err(err) if err.kind() == io::ErrorKind::NotFound => Vec::new(),
+                    Err(err) => return Err(err.into()),
+                };
+
+                let mut queued_items = self
+                    .items
+                    .lock()
+                    .map_err(|err| Error::msg(err.to_string()))?;
+
+                //The outbox of the previous server has already been saved
+                if storage.is_none() {
+                    items.append(&mut queued_items);
+                }
+
+                *queued_items = items;
+            }
+
+            fs::create_dir_all(&folder_path)?;
+
+            *storage = Some(OutboxStorage {
+                path,
+                encryption_key,
+            });
+        }
+
+        for item in self
+            .items
+            .lock()
+            .map_err(|err| Error::msg(err.to_string()))?
+            .iter_mut()
+        {
+            item.state = OutboxState::Pending;
+        }
+
+        self.save()?;
+
+        self.flush_requested.notify_one();
+
+        Ok(())
     }
 
-    let app_data = env::var("APPDATA")?;
+    /// Queues the request, its sent right away if there is a connection and there arent any requests before it
+    pub fn push(&self, request: OutboxRequest) -> Result<()>
+    {
+        self.items
+            .lock()
+            .map_err(|err| Error::msg(err.to_string()))?
+            .push(OutboxItem {
+                id: generate_uuid().to_string(),
+                request,
+                state: OutboxState::Pending,
+            });
 
-    let user_path = PathBuf::from(format!("{app_data}\\Matthias\\{}.szch", register.username));
+        self.save()?;
 
-    //Check if user already exists
-    if std::fs::metadata(&user_path).is_ok() {
-        return Err(anyhow::Error::msg("User already exists"));
+        self.flush_requested.notify_one();
+
+        Ok(())
     }
 
-    //Construct user info struct then write it to the appdata matthias folder
-    let user_info = UserInformation::new(
-        register.username,
-        register.password,
-        generate_uuid().to_string(),
-        register.full_name,
-        register.gender,
-        register.birth_date,
-        register.normal_profile_picture,
-        register.small_profile_picture,
-        user_path.clone(),
-    );
+    /// Removes the request from the outbox, the temporary files of uploads are deleted
+    /// The requests which are being sent cannot be cancelled
+    pub fn cancel(&self, id: &str) -> Result<()>
+    {
+        let removed_item = {
+            let mut items = self
+                .items
+                .lock()
+                .map_err(|err| Error::msg(err.to_string()))?;
 
-    user_info.write_file(user_path)?;
+            let Some(index) = items.iter().position(|item| item.id == id)
+            else {
+                return Ok(());
+            };
 
-    Ok(user_info)
+            ensure!(
+                items[index].state != OutboxState::Sending,
+                "The request is being sent, it cannot be cancelled!"
+            );
+
+            items.remove(index)
+        };
+
+        //The outbox is saved before the file is deleted, so that the cancelled request doesnt come back even if the file cannot be deleted
+        self.save()?;
+
+        //The requests after the cancelled one can be sent now
+        self.flush_requested.notify_one();
+
+        if let OutboxRequest::Upload {
+            path,
+            temporary: true,
+            ..
+        } = removed_item.request
+        {
+            match fs::remove_file(path) {
+                Ok(_) => {},
+                Err(err) if err.kind() == io::ErrorKind::NotFound => {},
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Tries to send the failed request (and the ones after it) again
+    pub fn retry(&self, id: &str) -> Result<()>
+    {
+        if let Some(item) = self
+            .items
+            .lock()
+            .map_err(|err| Error::msg(err.to_string()))?
+            .iter_mut()
+            .find(|item| item.id == id && matches!(item.state, OutboxState::Failed(_)))
+        {
+            item.state = OutboxState::Pending;
+        }
+
+        self.save()?;
+
+        self.flush_requested.notify_one();
+
+        Ok(())
+    }
+
+    /// Waits until the outbox has to be flushed (A request has been queued, retried or cancelled)
+    pub async fn wait_for_flush(&self)
+    {
+        self.flush_requested.notified().await;
+    }
+
+    /// Removes the request (identified by its id) which the server has acknowledged
+    pub fn acknowledge(&self, id: &str) -> Result<()>
+    {
+        self.items
+            .lock()
+            .map_err(|err| Error::msg(err.to_string()))?
+            .retain(|item| item.id != id);
+
+        self.save()
+    }
+
+    /// Sends the requests in order until every request has been sent, or a request has failed
+    /// The sent messages are kept in the outbox until the server acknowledges them, the uploads are handed over to the file transfers (which resume them after reconnecting)
+    /// A failed request is kept in the outbox, the requests after it are sent once its retried or cancelled
+    pub async fn flush(
+        &self,
+        connection: ClientConnection,
+        file_transfers: FileTransfers,
+    ) -> Result<()>
+    {
+        loop {
+            //The request is marked as being sent while the items are locked, so that it cannot be cancelled anymore
+            let item = {
+                let mut items = self
+                    .items
+                    .lock()
+                    .map_err(|err| Error::msg(err.to_string()))?;
+
+                let Some(item) = items
+                    .iter_mut()
+                    .find(|item| item.state != OutboxState::Sending)
+                else {
+                    return Ok(());
+                };
+
+                if item.state != OutboxState::Pending {
+                    return Ok(());
+                }
+
+                item.state = OutboxState::Sending;
+
+                item.clone()
+            };
+
+            self.save()?;
+
+            let is_message = matches!(item.request, OutboxRequest::Message(_));
+
+            let result = match item.request {
+                OutboxRequest::Message(message) => {
+                    //The id lets the server acknowledge the message, and recognize it if its sent again
+                    connection
+                        .clone()
+                        .send_message(ClientMessage {
+                            request_id: Some(item.id.clone()),
+                            ..message
+                        })
+                        .await
+                        .map(|_| ())
+                },
+                OutboxRequest::Upload {
+                    path,
+                    temporary,
+                    uuid,
+                    replying_to,
+                    channel_id,
+                } => {
+                    match tokio::task::spawn_blocking(move || {
+                        PendingUpload::new(path, temporary, &uuid, replying_to, channel_id)
+                    })
+                    .await
+                    .map_err(Error::from)
+                    .and_then(|upload| upload)
+                    {
+                        //The upload is resumed by the file transfers if the connection is lost while its being uploaded
+                        Ok(upload) => {
+                            file_transfers
+                                .start_upload(connection.clone(), upload)
+                                .await
+                        },
+                        Err(err) => Err(err),
+                    }
+                },
+            };
+
+            {
+                let mut items = self
+                    .items
+                    .lock()
+                    .map_err(|err| Error::msg(err.to_string()))?;
+
+                match &result {
+                    //The messages are removed once the server has acknowledged them
+                    Ok(_) if is_message => {},
+                    Ok(_) => items.retain(|queued_item| queued_item.id != item.id),
+                    Err(err) => {
+                        if let Some(failed_item) = items
+                            .iter_mut()
+                            .find(|queued_item| queued_item.id == item.id)
+                        {
+                            failed_item.state = OutboxState::Failed(err.to_string());
+                        }
+                    },
+                }
+            }
+
+            self.save()?;
+
+            result?;
+        }
+    }
+
+    /// Writes the outbox to the file of the opened server, nothing is written if no outbox has been opened yet
+    fn save(&self) -> Result<()>
+    {
+        let storage = self
+            .storage
+            .lock()
+            .map_err(|err| Error::msg(err.to_string()))?;
+
+        let Some(storage) = storage.as_ref()
+        else {
+            return Ok(());
+        };
+
+        let items = serde_json::to_string(
+            &*self
+                .items
+                .lock()
+                .map_err(|err| Error::msg(err.to_string()))?,
+        )?;
+
+        fs::write(
+            &storage.path,
+            encrypt_aes256(items, &storage.encryption_key)?,
+        )?;
+
+        Ok(())
+    }
+}
+
+/// The messages (with their reactions) and the fetched images of the servers we have connected to are cached on the disk (encrypted)
+/// This lets the messages be displayed as soon as we start connecting, and be read while the server cannot be reached
+/// Every server has its own cache folder (per user), which isnt removed on exit unlike the other files of the client
+#[derive(Debug, Clone, Default)]
+pub struct HistoryCache
+{
+    /// The folder of the opened server's cache, this is ```None``` until a cache has been opened
+    storage: Arc<Mutex<Option<CacheStorage>>>,
+
+    /// Whether the messages have changed since they were last saved
+    changed: Arc<AtomicBool>,
+
+    /// When the messages were last saved, this is ```None``` if they havent been saved since the cache was opened
+    last_saved: Arc<Mutex<Option<Instant>>>,
+}
+
+/// The folder the cache is saved to and the key it is encrypted with
+#[derive(Debug, Clone)]
+struct CacheStorage
+{
+    folder_path: PathBuf,
+    encryption_key: Vec<u8>,
+}
+
+impl CacheStorage
+{
+    fn messages_path(&self) -> PathBuf
+    {
+        self.folder_path.join("Messages")
+    }
+
+    /// Returns the path of the cached image, the signature is sent by the server so it is checked before its used as a file name
+    fn image_path(&self, signature: &str) -> Result<PathBuf>
+    {
+        ensure!(
+            check_hash(signature).is_ok(),
+            "Invalid image signature: {signature}"
+        );
+
+        Ok(self.folder_path.join("Images").join(signature))
+    }
+}
+
+impl HistoryCache
+{
+    /// Opens the cache of the server, and returns the cached messages
+    /// An empty ```ServerMaster``` is returned if there is nothing cached yet, or if the cache cannot be read
+    pub fn open(
+        &self,
+        server_address: &str,
+        uuid: &str,
+        encryption_key: Vec<u8>,
+    ) -> Result<ServerMaster>
+    {
+        //The cache of the previous server shouldnt be overwritten if this one cannot be opened
+        *self
+            .storage
+            .lock()
+            .map_err(|err| Error::msg(err.to_string()))? = None;
+
+        let folder_path = PathBuf::from(format!(
+            "{}\\matthias\\Cache\\{}\\{uuid}",
+            env!("APPDATA"),
+            general_purpose::URL_SAFE_NO_PAD.encode(server_address),
+        ));
+
+        fs::create_dir_all(folder_path.join("Images"))?;
+
+        let storage = CacheStorage {
+            folder_path,
+            encryption_key,
+        };
+
+        //An unreadable cache is overwritten the next time the messages are saved
+        let mut messages = match fs::read_to_string(storage.messages_path()) {
+            Ok(contents) => {
+                decrypt_aes256(&contents, &storage.encryption_key)
+                    .and_then(|messages| Ok(serde_json::from_str::<ServerMaster>(&messages)?))
+                    .unwrap_or_else(|err| {
+                        tracing::error!("Failed to read the history cache: {err}");
+
+                        ServerMaster::default()
+                    })
+            },
+            Err(err) if err.kind() == io::ErrorKind::NotFound => ServerMaster::default(),
+            Err(err) => return Err(err.into()),
+        };
+
+        //The call could have ended since the messages were cached
+        messages.ongoing_voip_call = ServerVoipState::default();
+
+        *self
+            .storage
+            .lock()
+            .map_err(|err| Error::msg(err.to_string()))? = Some(storage);
+
+        *self
+            .last_saved
+            .lock()
+            .map_err(|err| Error::msg(err.to_string()))? = None;
+
+        self.changed.store(false, Ordering::Relaxed);
+
+        Ok(messages)
+    }
+
+    /// Lets the cache know that the messages have changed, they are saved the next time ```save_due``` returns true
+    pub fn mark_changed(&self)
+    {
+        self.changed.store(true, Ordering::Relaxed);
+    }
+
+    /// Returns whether the messages should be saved now (They have changed, and ```HISTORY_CACHE_SAVE_INTERVAL``` has passed since they were last saved)
+    /// The changes are considered saved once this has returned true, so the caller has to save them
+    pub fn save_due(&self) -> bool
+    {
+        let Ok(mut last_saved) = self.last_saved.lock()
+        else {
+            return false;
+        };
+
+        if last_saved.is_some_and(|last_saved| last_saved.elapsed() < HISTORY_CACHE_SAVE_INTERVAL)
+            || !self.changed.swap(false, Ordering::Relaxed)
+        {
+            return false;
+        }
+
+        *last_saved = Some(Instant::now());
+
+        true
+    }
+
+    /// Writes the messages to the cache of the opened server, nothing is written if no cache has been opened yet
+    /// This encrypts all of the messages so it shouldnt be called from the main thread
+    pub fn save(&self, messages: &ServerMaster) -> Result<()>
+    {
+        //The lock is held while writing, so that an older save cannot overwrite a newer one
+        let storage = self
+            .storage
+            .lock()
+            .map_err(|err| Error::msg(err.to_string()))?;
+
+        let Some(storage) = storage.as_ref()
+        else {
+            return Ok(());
+        };
+
+        let messages_path = storage.messages_path();
+
+        //The messages are written to a temporary file first, so that the cache isnt lost if the client exits while writing
+        let mut temporary_path = messages_path.as_os_str().to_owned();
+
+        temporary_path.push(".tmp");
+
+        fs::write(
+            &temporary_path,
+            encrypt_aes256(serde_json::to_string(messages)?, &storage.encryption_key)?,
+        )?;
+
+        fs::rename(temporary_path, messages_path)?;
+
+        Ok(())
+    }
+
+    /// Caches the image fetched from the server (identified by its signature)
+    pub fn save_image(&self, signature: &str, bytes: &[u8]) -> Result<()>
+    {
+        let Some(storage) = self
+            .storage
+            .lock()
+            .map_err(|err| Error::msg(err.to_string()))?
+            .clone()
+        else {
+            return Ok(());
+        };
+
+        fs::write(
+            storage.image_path(signature)?,
+            encrypt_aes256_bytes(bytes, &storage.encryption_key)?,
+        )?;
+
+        Ok(())
+    }
+
+    /// Returns the cached image (identified by its signature), ```None``` is returned if it hasnt been cached
+    pub fn load_image(&self, signature: &str) -> Result<Option<Vec<u8>>>
+    {
+        let Some(storage) = self
+            .storage
+            .lock()
+            .map_err(|err| Error::msg(err.to_string()))?
+            .clone()
+        else {
+            return Ok(None);
+        };
+
+        match fs::read(storage.image_path(signature)?) {
+            Ok(bytes) => Ok(Some(decrypt_aes256_bytes(&bytes, &storage.encryption_key)?)),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
 }
 
-///Write general file, this function takes in a custom pathsrc/app/backend.rs
-pub fn write_file(file_response: ServerFileReply) -> Result<()>
+/// Returns the path the chunks of a download are written to until the whole file has been downloaded
+fn download_part_path(path: &Path) -> PathBuf
 {
-    let files = FileDialog::new()
+    let mut part_path = path.as_os_str().to_owned();
+
+    part_path.push(".part");
+
+    PathBuf::from(part_path)
+}
+
+/// Checks if the downloaded file matches its signature, then moves it to the path the user has chosen
+fn finish_download(part_path: &Path, path: &Path, signature: &str) -> Result<()>
+{
+    if sha256::try_digest(part_path)? != signature {
+        fs::remove_file(part_path)?;
+
+        bail!(
+            "The downloaded file ({}) is corrupted, please download it again!",
+            path.display()
+        );
+    }
+
+    fs::rename(part_path, path)?;
+
+    Ok(())
+}
+
+///Ask the user where to save a file, the dialog only lists the files with the same extension
+pub fn save_file_dialog(file_name: &Path) -> Option<PathBuf>
+{
+    let extension = file_name
+        .extension()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+
+    FileDialog::new()
         .set_title("Save to")
         .set_directory("/")
-        .add_filter(
-            file_response
-                .file_name
-                .extension()
-                .unwrap()
-                .to_string_lossy()
-                .to_string(),
-            &[file_response
-                .file_name
-                .extension()
-                .unwrap()
-                .to_string_lossy()
-                .to_string()],
-        )
-        .save_file();
+        .add_filter(&extension, &[&extension])
+        .save_file()
+}
 
-    if let Some(file) = files {
-        fs::write(file, file_response.bytes)?;
+///Write general file, the user is asked where to save it
+pub fn write_file(file_name: &Path, bytes: &[u8]) -> Result<()>
+{
+    if let Some(file) = save_file_dialog(file_name) {
+        fs::write(file, bytes)?;
     }
 
     Ok(())
@@ -3113,8 +3079,6 @@ where
     }
 }
 
-
-/// This function fetches the incoming full message's length (it reads the 4 bytes and creates an u32 number from them, which it returns)
 ///Display Error message with a messagebox
 pub fn display_info_message<T>(display: T, toasts: Arc<Mutex<Toasts>>)
 where
@@ -3135,20 +3099,6 @@ where
     }
 }
 
-/// This function fetches the incoming full message's lenght (it reads the 4 bytes and creates an u32 number from them, which it returns)
-
-/// afaik this function blocks until it can read the first 4 bytes out of the ```reader```
-pub async fn fetch_incoming_message_length<T>(reader: &mut T) -> anyhow::Result<u32>
-where
-    T: AsyncReadExt + Unpin + AsyncRead,
-{
-    let mut buf: Vec<u8> = vec![0; 4];
-
-    reader.read_exact(&mut buf).await?;
-
-    Ok(u32::from_be_bytes(buf[..4].try_into()?))
-}
-
 pub struct Message
 {
     pub inner_message: MessageDisplay,
@@ -3525,84 +3475,3 @@ pub struct HyperLink
     /// This is the part of the hyperlink which it redirects to
     pub destination: String,
 }
-
-///This struct contains all the reactions of one message
-#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Default)]
-pub struct MessageReaction
-{
-    /// The list of reactions added to a message
-    pub message_reactions: Vec<Reaction>,
-}
-
-/// This struct contains the what the people have reacted with.
-/// This struct contains the one emoji's name and the users' uuid who have sent this.
-#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
-pub struct Reaction
-{
-    /// The emoji send by the ```authors```
-    pub emoji_name: String,
-
-    /// Author's uuid list
-    pub authors: Vec<String>,
-}
-
-/// This header struct contains the uuid of the author who has sent this header and the image parts the server would receive
-#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
-pub struct ImageHeader
-{
-    /// The author of this header aka who constructed it
-    pub uuid: String,
-
-    /// This entry contains the image parts in a list.
-    /// The keys are the bytes hashsed with ```Sha256```.
-    pub image_parts_hash: Vec<String>,
-
-    /// Custom identifier of the ```ImageHeader``` the server uses this to know where the image part goes
-    pub identificator: String,
-}
-
-impl ImageHeader
-{
-    /// Construct a new ```ImageHeader``` instance
-    pub fn new(uuid: String, image_parts_hash: Vec<String>, identificator: String) -> Self
-    {
-        Self {
-            uuid,
-            image_parts_hash,
-            identificator,
-        }
-    }
-}
-
-/// This function fetches the image header from the decrypted bytes.
-/// It inserts the image header into the ```ImageBuffer``` provided in the arguments.
-pub fn get_image_header(
-    decrypted_bytes: &Vec<u8>,
-    image_buffer: &Arc<DashMap<String, IndexMap<String, HashMap<String, Option<Vec<u8>>>>>>,
-) -> anyhow::Result<()>
-{
-    //Get actual message, we ignore the message type
-    let message_bytes = decrypted_bytes.to_vec();
-
-    //Get string from bytes
-    let message_as_string = String::from_utf8(message_bytes)?;
-
-    //```Deserialize``` string into ```ImageHeader``` struct
-    let image_header = serde_json::from_str::<ImageHeader>(&message_as_string)?;
-
-    //Try getting the uuid's ImageHeaders
-    //Insert IndexMap into the ```image_buffer```
-    if let Some(mut image_headers) = image_buffer.get_mut(&image_header.uuid) {
-        image_headers.value_mut().insert(
-            image_header.identificator.clone(),
-            HashMap::from_iter(
-                image_header
-                    .image_parts_hash
-                    .iter()
-                    .map(|hash| (hash.clone(), None)),
-            ),
-        );
-    }
-
-    Ok(())
-}
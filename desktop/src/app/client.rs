@@ -6,33 +6,21 @@ use tokio::{
 };
 
 use rodio::Sink;
-use std::{fs, path::PathBuf, sync::Arc, time::Duration};
+use std::{collections::HashMap, fs, path::PathBuf, sync::Arc, time::Duration};
 use tokio::select;
 
 use crate::app::backend::{
-    decrypt_aes256, display_error_message, write_audio, write_file, ClientMessage,
-    ClientMessageType, ConnectionState, MessageReaction, PlaybackCursor, Reaction, ServerReplyType,
-    ServerSync, ServerVoipReply,
+    display_error_message, encode_message, write_audio, Capability, ClientHandshake, ClientMessage,
+    ConnectionState, FrameCompression, MessageId, PlaybackCursor, SequenceCheck,
+    ServerControlReply, ServerFrame, ServerReplyType, ServerVoipReply, DEFAULT_CHANNEL_ID,
 };
 
 use crate::app::backend::{Application, ServerMessageType};
 
-/// Image byte offset
-/// This value is the end of the hash bytes, the start is ```UUID_BYTE_OFFSET```
-pub const HASH_BYTE_OFFSET: usize = 64 + 64 + 36;
-
-/// Hash byte offset
-/// This value is the end of the uuid string bytes, the start is ```IDENTIFICATOR_BYTE_OFFSET```
-pub const UUID_BYTE_OFFSET: usize = 64 + 36;
-
-/// Identificator byte offset
-/// This value is the start of the identificator bytes, the end is the end of the message itself
-pub const IDENTIFICATOR_BYTE_OFFSET: usize = 64;
-
-/// This is the byte length of the uuid's text representation (utf8)
-pub const UUID_STRING_BYTE_LENGTH: usize = 36;
-
-use super::backend::{fetch_incoming_message_length, get_image_header};
+use super::backend::{
+    fetch_incoming_message_length, get_image_header, HASH_BYTE_OFFSET, IDENTIFICATOR_BYTE_OFFSET,
+    UUID_BYTE_OFFSET, UUID_STRING_BYTE_LENGTH,
+};
 pub const VOIP_PACKET_BUFFER_LENGTH_MS: usize = 35;
 
 use image::ImageOutputFormat;
@@ -48,16 +36,47 @@ use crate::app::ui::client_ui::client_actions::audio_recording::{
     create_wav_file, record_audio_with_interrupt,
 };
 
+/// Sends the handshake to the specified server handle, returns the protocol version and the capabilities both sides support
+/// This has to be the first frame sent to the server, an error is returned if the server refuses to talk to the client
+pub async fn send_handshake(connection: &mut TcpStream) -> anyhow::Result<(u32, Vec<Capability>)>
+{
+    let server_reply = send_frame_and_wait(
+        connection,
+        ClientHandshake::default().struct_into_string().as_bytes(),
+    )
+    .await?;
+
+    match serde_json::from_slice::<ServerControlReply>(&server_reply)? {
+        ServerControlReply::Handshake {
+            protocol_version,
+            capabilities,
+        } => Ok((protocol_version, capabilities)),
+        reply => Err(reply.into()),
+    }
+}
+
 /// Sends connection request to the specified server handle, returns the server's response, this function does not create a new thread, and may block
 pub async fn connect_to_server(
     mut connection: TcpStream,
     message: ClientMessage,
-) -> anyhow::Result<(String, TcpStream)>
+    compression: FrameCompression,
+) -> anyhow::Result<(Vec<u8>, TcpStream)>
 {
-    let message_as_string = message.struct_into_string();
+    let server_reply = send_frame_and_wait(
+        &mut connection,
+        &compression.compress(encode_message(&message)?)?,
+    )
+    .await?;
 
-    let message_bytes = message_as_string.as_bytes();
+    Ok((server_reply, connection))
+}
 
+/// Sends a frame to the server and waits for its reply, this is only used before the connection is established (Before the reader thread is spawned)
+async fn send_frame_and_wait(
+    connection: &mut TcpStream,
+    message_bytes: &[u8],
+) -> anyhow::Result<Vec<u8>>
+{
     //Send message length to server
     connection
         .write_all(&(message_bytes.len() as u32).to_be_bytes())
@@ -68,7 +87,7 @@ pub async fn connect_to_server(
 
     //Read the server reply length
     //blocks here for unknown reason
-    let msg_len = fetch_incoming_message_length(&mut connection).await?;
+    let msg_len = fetch_incoming_message_length(&mut *connection).await?;
 
     //Create buffer with said length
     let mut msg_buffer = vec![0; msg_len as usize];
@@ -76,7 +95,7 @@ pub async fn connect_to_server(
     //Read the server reply
     connection.read_exact(&mut msg_buffer).await?;
 
-    Ok((String::from_utf8(msg_buffer)?, connection))
+    Ok(msg_buffer)
 }
 
 pub struct ServerReply
@@ -86,7 +105,7 @@ pub struct ServerReply
 
 impl ServerReply
 {
-    pub async fn wait_for_response(&self) -> anyhow::Result<String>
+    pub async fn wait_for_response(&self) -> anyhow::Result<Vec<u8>>
     {
         let reader = &mut *self.reader.lock().await;
 
@@ -99,7 +118,7 @@ impl ServerReply
         //Read the server reply
         reader.read_exact(&mut msg_buffer).await?;
 
-        Ok(String::from_utf8(msg_buffer)?)
+        Ok(msg_buffer)
     }
 
     pub fn new(reader: Arc<Mutex<OwnedReadHalf>>) -> Self
@@ -108,6 +127,16 @@ impl ServerReply
     }
 }
 
+/// What the reader thread passes on to the main thread
+pub enum ServerEvent
+{
+    /// The server has closed the connection, this is the reason it has sent
+    Closed(ServerControlReply),
+
+    /// A frame sent by the server, it has already been decrypted and decoded by the reader thread
+    Frame(anyhow::Result<ServerFrame>),
+}
+
 impl Application
 {
     ///This function is used to send voice recording in a voip connection, this function spawns a thread which record 35ms of your voice then sends it to the linked voip destination
@@ -288,6 +317,16 @@ impl Application
 
                 let toasts = self.toasts.clone();
 
+                //The frames are decrypted by the reader thread, so that the pings can be answered even if the Ui isnt being drawn
+                let client_secret = self.client_connection.client_secret.clone();
+                let compression = self.client_connection.compression();
+
+                //The pings are answered through the same connection
+                let pong_connection = connection_pair.clone();
+                let pong_uuid = self.opened_user_information.uuid.clone();
+
+                let latency = self.client_ui.latency.clone();
+
                 //Spawn server reader thread
                 tokio::spawn(async move {
                     loop {
@@ -305,15 +344,44 @@ impl Application
                                 match reply {
                                     //If we have a response from the server
                                     Ok(response) => {
-                                        //Check for special cases like server disconnecting
-                                        if response == "Server disconnecting from client." {
-                                            break;
+                                        //Check for special cases like the server closing the connection, these replies are not encrypted
+                                        if let Ok(reply) = serde_json::from_slice::<ServerControlReply>(&response) {
+                                            if reply.closes_connection() {
+                                                context_clone.request_repaint();
+
+                                                //Let the main thread know why the connection was closed
+                                                let _ = sender_clone.send(Some(ServerEvent::Closed(reply)));
+
+                                                break;
+                                            }
+
+                                            continue;
+                                        }
+
+                                        let frame = ServerFrame::decrypt(&response, &client_secret, compression);
+
+                                        //The server disconnects us if we dont answer its pings
+                                        if let Ok(ServerFrame::Ping(ping)) = &frame {
+                                            *latency.lock().unwrap() = ping.latency;
+
+                                            if let Err(err) = pong_connection.send_message(ClientMessage::construct_pong_msg(ping.sent_at, &pong_uuid)).await {
+                                                tracing::error!("Failed to answer the server's ping: {err}");
+                                            }
+
+                                            //The latency is displayed by the Ui
+                                            context_clone.request_repaint();
+
+                                            continue;
                                         }
 
                                         //Request repaint
                                         context_clone.request_repaint();
-                                        //Send to receiver
-                                        sender_clone.send(Some(response)).expect("Error occurred when trying to send message, after receiving message from client");
+                                        //Send to receiver, if the main thread has dropped its receiver there is no one left to read the frames
+                                        if sender_clone.send(Some(ServerEvent::Frame(frame))).is_err() {
+                                            tracing::error!("The main thread has stopped receiving the server's messages");
+
+                                            break;
+                                        }
                                     },
                                     Err(err) => {
                                         tracing::error!("{}", err);
@@ -335,72 +403,166 @@ impl Application
                     }
                 });
 
-                //Init sync message
-                let mut message = ClientMessage::construct_sync_msg(
-                    &self.client_connection.password,
-                    &self.login_username,
-                    &self.opened_user_information.uuid,
-                    //Send how many messages we have, the server will compare it to its list, and then send the missing messages, reducing traffic
-                    self.client_ui.incoming_messages.message_list.len(),
-                    Some(*self.client_ui.last_seen_msg_index.lock().unwrap()),
-                );
+                //Sync message information
+                let password = self.client_connection.password.clone();
+                let username = self.login_username.clone();
+                let uuid = self.opened_user_information.uuid.clone();
 
-                let last_seen_message_index = self.client_ui.last_seen_msg_index.clone();
+                let last_seen_message_id = self.client_ui.last_seen_msg_id.clone();
 
                 //Spawn server syncer thread
                 tokio::spawn(async move {
-                    loop {
-                        //This patter match will always return true, the message were trying to pattern match is constructed above 
-                        //We should update the message for syncing, so we will provide the latest info to the server
-                        if let ClientMessageType::SyncMessage(inner) = &mut message.message_type {
-                            select! {
-                                _ = tokio::time::sleep(Duration::from_secs(2)) => {
-                                    let index = *last_seen_message_index.lock().unwrap();
-
-                                    if inner.last_seen_message_index < Some(index) {
-                                        inner.last_seen_message_index = Some(index);
-
-                                        //We only send a sync packet if we need to
-                                        //We only have to send the sync message, since in the other thread we are receiving every message sent to us
-                                        match connection_pair.send_message(message.clone()).await {
-                                            Ok(_) => {},
-                                            Err(err) => {
-                                                tracing::error!("{}", err);
-
-                                                //Error appeared, after this the tread quits, so there arent an inf amount of threads running
-                                                sender.send(None).expect("Failed to signal thread error");
-                                                break;
-                                            }
-                                        };
+                    //The last seen ids we have already sent to the server, the key is the channel's id
+                    let mut sent_last_seen_ids: HashMap<String, MessageId> = HashMap::new();
+
+                    'sync: loop {
+                        select! {
+                            _ = tokio::time::sleep(Duration::from_secs(2)) => {
+                                let last_seen_ids = last_seen_message_id.lock().unwrap().clone();
+
+                                for (channel_id, message_id) in last_seen_ids {
+                                    //We only send a sync packet if we need to
+                                    if sent_last_seen_ids.get(&channel_id).is_some_and(|sent_id| *sent_id >= message_id) {
+                                        continue;
                                     }
-                                }
 
-                                _ = shutdown_token_clone.cancelled() => {
-                                    break;
+                                    let message = ClientMessage::construct_sync_msg(
+                                        &password,
+                                        &username,
+                                        &uuid,
+                                        Some(message_id),
+                                        channel_id.clone(),
+                                    );
+
+                                    //We only have to send the sync message, since in the other thread we are receiving every message sent to us
+                                    match connection_pair.send_message(message).await {
+                                        Ok(_) => {
+                                            sent_last_seen_ids.insert(channel_id, message_id);
+                                        },
+                                        Err(err) => {
+                                            tracing::error!("{}", err);
+
+                                            //Error appeared, after this the tread quits, so there arent an inf amount of threads running
+                                            let _ = sender.send(None);
+                                            break 'sync;
+                                        }
+                                    };
                                 }
                             }
+
+                            _ = shutdown_token_clone.cancelled() => {
+                                break;
+                            }
                         }
-                        else
-                        {
-                            panic!("The message watning to be sent isnt a clientsyncmessage (as required), check what youve modified");
+                    }
+                });
+
+                //The outbox is delivered in order by its own thread, so that a large upload being hashed doesnt hold up the other threads
+                let outbox = self.outbox.clone();
+                let outbox_connection = self.client_connection.clone();
+                let file_transfers = self.file_transfers.clone();
+                let outbox_shutdown_token = self.autosync_shutdown_token.child_token();
+
+                //Spawn outbox thread
+                tokio::spawn(async move {
+                    loop {
+                        select! {
+                            _ = outbox.wait_for_flush() => {
+                                //If the connection has been lost the requests are kept in the outbox, and sent again after reconnecting
+                                if let Err(err) = outbox.flush(outbox_connection.clone(), file_transfers.clone()).await {
+                                    tracing::error!("Failed to send a request from the outbox: {err}");
+                                }
+                            }
+
+                            _ = outbox_shutdown_token.cancelled() => {
+                                break;
+                            }
                         }
                     }
                 });
             });
 
+            //Save the changed messages to the history cache every once in a while
+            if self.history_cache.save_due() {
+                self.save_history_cache();
+            }
+
             //Try to receive the threads messages
             //Get sent to the channel to be displayed, if the connections errors out, do nothing lol cuz its prolly cuz the sender hadnt done anything
             match self.server_output_receiver.try_recv() {
                 Ok(msg) => {
                     //show messages
-                    if let Some(message) = msg {
-                        //Decrypt the server's reply
-                        match decrypt_aes256(&message, &self.client_connection.client_secret) {
-                            Ok(decrypted_message) => {
-                                let incoming_struct: Result<ServerSync, serde_json::Error> =
-                                    serde_json::from_str(&decrypted_message);
-                                match incoming_struct {
-                                    Ok(msg) => {
+                    if let Some(event) = msg {
+                        let frame = match event {
+                            //The server has closed the connection
+                            ServerEvent::Closed(reply) => {
+                                self.handle_control_reply(reply);
+
+                                return;
+                            },
+                            ServerEvent::Frame(frame) => frame,
+                        };
+
+                        match frame {
+                            Ok(frame) => {
+                                match frame {
+                                    ServerFrame::Sync(msg) => {
+                                        //The messages sent to every client are dropped until the server has sent the messages we have missed
+                                        //The replies sent only to us (Request errors and private messages) are still handled, their sequence isnt checked as we are behind anyway
+                                        if self.client_ui.resync_pending {
+                                            if msg.message.is_broadcast() {
+                                                return;
+                                            }
+                                        }
+                                        else {
+                                            match self
+                                                .client_ui
+                                                .incoming_messages
+                                                .check_sequence(&msg.message)
+                                            {
+                                                SequenceCheck::InOrder => {},
+                                                //We have already received this message
+                                                SequenceCheck::Duplicate => return,
+                                                //We have missed some messages, so we ask the server to send us the current state
+                                                SequenceCheck::Gap => {
+                                                    tracing::warn!(
+                                                    "Missed messages before sequence {}, resyncing",
+                                                    msg.message.sequence
+                                                );
+
+                                                    self.client_ui.resync_pending = true;
+
+                                                    let resync_msg =
+                                                        ClientMessage::construct_resync_msg(
+                                                            &self.client_connection.password,
+                                                            &self.login_username,
+                                                            &self.opened_user_information.uuid,
+                                                            None,
+                                                            None,
+                                                        );
+
+                                                    let connection = self.client_connection.clone();
+                                                    let sender = self.server_output_sender.clone();
+
+                                                    //If the resync cannot be sent the connection is faulty, so we reconnect (The server sends the full sync after reconnecting, which clears the pending resync)
+                                                    tokio::spawn(async move {
+                                                        if let Err(err) = connection
+                                                            .send_message(resync_msg)
+                                                            .await
+                                                        {
+                                                            tracing::error!(
+                                                                "Failed to request a resync: {err}"
+                                                            );
+
+                                                            let _ = sender.send(None);
+                                                        }
+                                                    });
+
+                                                    return;
+                                                },
+                                            }
+                                        }
+
                                         //Always make sure to store the latest user_seen list
                                         self.client_ui.incoming_messages.user_seen_list =
                                             msg.user_seen_list;
@@ -415,122 +577,119 @@ impl Application
 
                                         match &msg.message.message_type {
                                             ServerMessageType::Edit(message) => {
-                                                if let Some(new_message) =
-                                                    message.new_message.clone()
+                                                //The edited message may not be loaded, if its in an older page of the history
+                                                if let Some(edited_message) = self
+                                                    .client_ui
+                                                    .incoming_messages
+                                                    .message_mut(message.message_id)
                                                 {
-                                                    if let ServerMessageType::Normal(inner) =
-                                                        &mut self
-                                                            .client_ui
-                                                            .incoming_messages
-                                                            .message_list
-                                                            [message.index as usize]
-                                                            .message_type
+                                                    if let Some(new_message) =
+                                                        message.new_message.clone()
                                                     {
-                                                        inner.message = new_message;
-                                                        inner.has_been_edited = true;
+                                                        if let ServerMessageType::Normal(inner) =
+                                                            &mut edited_message.message_type
+                                                        {
+                                                            inner.message = new_message;
+                                                            inner.has_been_edited = true;
+                                                        }
                                                     }
-                                                }
-                                                else {
-                                                    self.client_ui.incoming_messages.message_list
-                                                        [message.index as usize]
-                                                        .message_type = ServerMessageType::Deleted;
+                                                    else {
+                                                        edited_message.message_type =
+                                                            ServerMessageType::Deleted;
+                                                    }
+
+                                                    self.history_cache.mark_changed();
                                                 }
                                             },
                                             ServerMessageType::Reaction(message) => {
-                                                //Search if there has already been a reaction added
                                                 match &message.reaction_type {
                                                     crate::app::backend::ReactionType::Add(
                                                         reaction,
                                                     ) => {
-                                                        if let Some(index) = self
+                                                        if let Some(reacted_message) = self
                                                             .client_ui
                                                             .incoming_messages
-                                                            .reaction_list[reaction.message_index]
-                                                            .message_reactions
-                                                            .iter()
-                                                            .position(|item| {
-                                                                item.emoji_name
-                                                                    == reaction.emoji_name
-                                                            })
+                                                            .message_mut(reaction.message_id)
                                                         {
-                                                            //If yes, increment the reaction counter
-                                                            self.client_ui
-                                                                .incoming_messages
-                                                                .reaction_list
-                                                                [reaction.message_index]
-                                                                .message_reactions[index]
-                                                                .authors
-                                                                .push(reaction.uuid.clone());
-                                                        }
-                                                        else {
-                                                            //If no, add a new reaction counter
-                                                            self.client_ui
-                                                                .incoming_messages
-                                                                .reaction_list
-                                                                [reaction.message_index]
-                                                                .message_reactions
-                                                                .push(Reaction {
-                                                                    emoji_name: reaction
-                                                                        .emoji_name
-                                                                        .clone(),
-                                                                    authors: vec![reaction
-                                                                        .uuid
-                                                                        .clone()],
-                                                                })
+                                                            reacted_message.reactions.add(
+                                                                &reaction.emoji_name,
+                                                                &reaction.uuid,
+                                                            );
+
+                                                            self.history_cache.mark_changed();
                                                         }
                                                     },
                                                     crate::app::backend::ReactionType::Remove(
                                                         reaction,
                                                     ) => {
-                                                        //Search for emoji in the emoji list
-                                                        //If its not found, it a serious issue, or just internet inconsistency
-                                                        if let Some(index) = self
+                                                        if let Some(reacted_message) = self
                                                             .client_ui
                                                             .incoming_messages
-                                                            .reaction_list[reaction.message_index]
-                                                            .message_reactions
-                                                            .iter()
-                                                            .position(|item| {
-                                                                item.emoji_name
-                                                                    == reaction.emoji_name
-                                                            })
+                                                            .message_mut(reaction.message_id)
                                                         {
-                                                            //Borrow authors list as mutable
-                                                            let emoji_authors = &mut self
-                                                                .client_ui
-                                                                .incoming_messages
-                                                                .reaction_list
-                                                                [reaction.message_index]
-                                                                .message_reactions[index]
-                                                                .authors;
-
-                                                            //Remove the user who has sent this message from the authors list
-                                                            match emoji_authors.iter().position(
-                                                                |uuid| *uuid == reaction.uuid,
-                                                            ) {
-                                                                Some(idx) => {
-                                                                    emoji_authors.remove(idx);
-                                                                },
-                                                                None => {
-                                                                    tracing::error!("Tried to remove a non-author from the authors list.");
-                                                                },
-                                                            }
-                                                            //If the emoji is reacted with 0 times, it means it has been fully deleted from the list
-                                                            if emoji_authors.is_empty() {
-                                                                self.client_ui
-                                                                    .incoming_messages
-                                                                    .reaction_list
-                                                                    [reaction.message_index]
-                                                                    .message_reactions
-                                                                    .remove(index);
-                                                            }
-                                                        }
-                                                        else {
-                                                            tracing::error!("Emoji was already deleted before requesting removal");
+                                                            reacted_message.reactions.remove(
+                                                                &reaction.emoji_name,
+                                                                &reaction.uuid,
+                                                            );
+
+                                                            self.history_cache.mark_changed();
                                                         }
                                                     },
                                                 }
                                             },
+                                            ServerMessageType::ChannelUpdate(update) => {
+                                                self.client_ui.incoming_messages.channels =
+                                                    update.channels.clone();
+
+                                                //If the channel we were viewing got deleted, go back to the default one
+                                                if !update.channels.iter().any(|channel| {
+                                                    channel.id == self.client_ui.selected_channel
+                                                }) {
+                                                    self.client_ui.selected_channel =
+                                                        DEFAULT_CHANNEL_ID.to_string();
+                                                }
+
+                                                //Forget the last seen ids of the deleted channels
+                                                self.client_ui
+                                                    .last_seen_msg_id
+                                                    .lock()
+                                                    .unwrap()
+                                                    .retain(|channel_id, _| {
+                                                        update.channels.iter().any(|channel| {
+                                                            channel.id == *channel_id
+                                                        })
+                                                    });
+
+                                                self.history_cache.mark_changed();
+                                            },
+                                            ServerMessageType::PinUpdate(update) => {
+                                                self.client_ui.incoming_messages.pinned_messages =
+                                                    update.pinned_messages.clone();
+
+                                                self.history_cache.mark_changed();
+                                            },
+                                            ServerMessageType::RoleUpdate(update) => {
+                                                self.client_ui.incoming_messages.roles =
+                                                    update.roles.clone();
+
+                                                self.history_cache.mark_changed();
+                                            },
+                                            ServerMessageType::RequestError(request_error) => {
+                                                //Our request has been rejected, let the user know why
+                                                display_error_message(
+                                                    request_error.clone(),
+                                                    self.toasts.clone(),
+                                                );
+                                            },
+                                            ServerMessageType::DirectMessage(_) => {
+                                                //Private messages are stored separately, so they dont interfere with the indexes of the public messages
+                                                self.client_ui
+                                                    .incoming_messages
+                                                    .direct_messages
+                                                    .push(msg.message.clone());
+
+                                                self.history_cache.mark_changed();
+                                            },
                                             ServerMessageType::VoipState(state) => {
                                                 //Check if the call was alive before the state update
                                                 let was_call_alive = self
@@ -598,137 +757,191 @@ impl Application
                                             },
                                         }
                                     },
-                                    //If converting the message to a ServerSync then it was probably a ServerReplyType enum
-                                    Err(_err) => {
-                                        let incoming_reply: Result<
-                                            ServerReplyType,
-                                            serde_json::Error,
-                                        > = serde_json::from_str(&decrypted_message);
-
-                                        match incoming_reply {
-                                            Ok(inner) => {
-                                                match inner {
-                                                    ServerReplyType::File(file) => {
-                                                        let _ = write_file(file);
-                                                    },
-                                                    ServerReplyType::Image(image) => {
-                                                        //Forget image so itll be able to get displayed
-                                                        ctx.forget_image(&format!(
-                                                            "bytes://{}",
-                                                            image.signature
-                                                        ));
-
-                                                        //load image to the said URI
-                                                        ctx.include_bytes(
-                                                            format!("bytes://{}", image.signature),
-                                                            image.bytes,
+                                    ServerFrame::Reply(inner) => {
+                                        match inner {
+                                            ServerReplyType::File(file) => {
+                                                let signature = file.signature.clone();
+
+                                                match self
+                                                    .file_transfers
+                                                    .receive_chunk(file, self.toasts.clone())
+                                                {
+                                                    //Request the next chunk of the file
+                                                    Ok(Some(offset)) => {
+                                                        self.send_msg(
+                                                            ClientMessage::construct_file_request_msg(
+                                                                signature,
+                                                                offset,
+                                                                &self.opened_user_information.uuid,
+                                                            ),
                                                         );
                                                     },
-                                                    ServerReplyType::Audio(audio) => {
-                                                        let stream_handle = self
-                                                            .client_ui
-                                                            .audio_playback
-                                                            .stream_handle
-                                                            .clone();
-
-                                                        let sender = self.audio_save_tx.clone();
-
-                                                        let path_to_audio = PathBuf::from(format!(
-                                                            "{}\\Matthias\\Client\\{}\\Audios\\{}",
-                                                            env!("APPDATA"),
-                                                            self.client_ui
-                                                                .send_on_ip_base64_encoded,
-                                                            audio.signature
-                                                        ));
-                                                        let ip = self.client_ui.send_on_ip.clone();
-
-                                                        //Spawn writer thread
-                                                        std::thread::spawn(move || {
-                                                            let _ = write_audio(audio.clone(), ip);
-
-                                                            while !path_to_audio.exists() {
-                                                                //Block until it exists, we can do this because we are in a different thread then main
-                                                            }
-
-                                                            let file_stream_to_be_read =
-                                                                fs::read(&path_to_audio)
-                                                                    .unwrap_or_default();
-
-                                                            let cursor = PlaybackCursor::new(
-                                                                file_stream_to_be_read,
-                                                            );
-
-                                                            let sink = Some(Arc::new(
-                                                                Sink::try_new(&stream_handle)
-                                                                    .unwrap(),
-                                                            ));
-
-                                                            sender
-                                                                .send((
-                                                                    sink,
-                                                                    cursor,
-                                                                    //Is this needed
-                                                                    audio.audio_idx,
-                                                                    path_to_audio,
-                                                                ))
-                                                                .unwrap();
-                                                        });
-                                                    },
-                                                    ServerReplyType::Client(client_reply) => {
-                                                        self.client_ui
-                                                            .incoming_messages
-                                                            .connected_clients_profile
-                                                            .insert(
-                                                                client_reply.uuid.clone(),
-                                                                client_reply.profile.clone(),
-                                                            );
-
-                                                        //Forget old placeholder bytes
-                                                        ctx.forget_image(&format!(
-                                                            "bytes://{}",
-                                                            client_reply.uuid
-                                                        ));
-
-                                                        //Pair URI with profile image
-                                                        ctx.include_bytes(
-                                                            format!(
-                                                                "bytes://{}",
-                                                                client_reply.uuid
-                                                            ),
-                                                            client_reply
-                                                                .profile
-                                                                .small_profile_picture,
+                                                    Ok(None) => {},
+                                                    Err(err) => {
+                                                        self.file_transfers
+                                                            .downloads
+                                                            .remove(&signature);
+
+                                                        display_error_message(
+                                                            err,
+                                                            self.toasts.clone(),
                                                         );
                                                     },
                                                 }
                                             },
-                                            Err(_err) => {
-                                                let incoming_reply: Result<
-                                                    ServerVoipReply,
-                                                    serde_json::Error,
-                                                > = serde_json::from_str(&decrypted_message);
-
-                                                match incoming_reply {
-                                                    Ok(voip_connection) => {
-                                                        match voip_connection {
-                                                            ServerVoipReply::Success => {},
-                                                            ServerVoipReply::Fail(err) => {
-                                                                //Avoid panicking when trying to display a Notification
-                                                                //This is very rare but can still happen
-                                                                display_error_message(
-                                                                    err.reason,
-                                                                    self.toasts.clone(),
-                                                                );
-                                                            },
-                                                        }
-                                                    },
-                                                    Err(_err) => {
-                                                        tracing::error!("{_err}");
-                                                    },
+                                            ServerReplyType::Upload(upload) => {
+                                                let file_transfers = self.file_transfers.clone();
+                                                let connection = self.client_connection.clone();
+
+                                                tokio::spawn(async move {
+                                                    if let Err(err) = file_transfers
+                                                        .send_chunks(connection, upload)
+                                                        .await
+                                                    {
+                                                        tracing::error!(
+                                                            "Failed to upload the file: {err}"
+                                                        );
+                                                    }
+                                                });
+                                            },
+                                            ServerReplyType::Image(image) => {
+                                                //The image is cached so it can be displayed without fetching it again
+                                                let history_cache = self.history_cache.clone();
+                                                let signature = image.signature.clone();
+                                                let bytes = image.bytes.clone();
+
+                                                tokio::task::spawn_blocking(move || {
+                                                    if let Err(err) =
+                                                        history_cache.save_image(&signature, &bytes)
+                                                    {
+                                                        tracing::error!(
+                                                            "Failed to cache the image: {err}"
+                                                        );
+                                                    }
+                                                });
+
+                                                //Forget image so itll be able to get displayed
+                                                ctx.forget_image(&format!(
+                                                    "bytes://{}",
+                                                    image.signature
+                                                ));
+
+                                                //load image to the said URI
+                                                ctx.include_bytes(
+                                                    format!("bytes://{}", image.signature),
+                                                    image.bytes,
+                                                );
+                                            },
+                                            ServerReplyType::Audio(audio) => {
+                                                let stream_handle = self
+                                                    .client_ui
+                                                    .audio_playback
+                                                    .stream_handle
+                                                    .clone();
+
+                                                let sender = self.audio_save_tx.clone();
+
+                                                let path_to_audio = PathBuf::from(format!(
+                                                    "{}\\Matthias\\Client\\{}\\Audios\\{}",
+                                                    env!("APPDATA"),
+                                                    self.client_ui.send_on_ip_base64_encoded,
+                                                    audio.signature
+                                                ));
+                                                let ip = self.client_ui.send_on_ip.clone();
+
+                                                //Spawn writer thread
+                                                std::thread::spawn(move || {
+                                                    let _ = write_audio(audio.clone(), ip);
+
+                                                    while !path_to_audio.exists() {
+                                                        //Block until it exists, we can do this because we are in a different thread then main
+                                                    }
+
+                                                    let file_stream_to_be_read =
+                                                        fs::read(&path_to_audio)
+                                                            .unwrap_or_default();
+
+                                                    let cursor =
+                                                        PlaybackCursor::new(file_stream_to_be_read);
+
+                                                    let sink = Some(Arc::new(
+                                                        Sink::try_new(&stream_handle).unwrap(),
+                                                    ));
+
+                                                    sender
+                                                        .send((
+                                                            sink,
+                                                            cursor,
+                                                            audio.message_id,
+                                                            path_to_audio,
+                                                        ))
+                                                        .unwrap();
+                                                });
+                                            },
+                                            ServerReplyType::Client(client_reply) => {
+                                                self.client_ui
+                                                    .incoming_messages
+                                                    .connected_clients_profile
+                                                    .insert(
+                                                        client_reply.uuid.clone(),
+                                                        client_reply.profile.clone(),
+                                                    );
+
+                                                //Forget old placeholder bytes
+                                                ctx.forget_image(&format!(
+                                                    "bytes://{}",
+                                                    client_reply.uuid
+                                                ));
+
+                                                //Pair URI with profile image
+                                                ctx.include_bytes(
+                                                    format!("bytes://{}", client_reply.uuid),
+                                                    client_reply.profile.small_profile_picture,
+                                                );
+                                            },
+                                            ServerReplyType::History(history_page) => {
+                                                self.client_ui
+                                                    .incoming_messages
+                                                    .insert_history_page(history_page);
+                                                self.history_cache.mark_changed();
+
+                                                self.client_ui.history_request_pending = false;
+                                            },
+                                            //The request has been handled by the server, so it can be removed from the outbox
+                                            ServerReplyType::Acknowledged(request_id) => {
+                                                if let Err(err) =
+                                                    self.outbox.acknowledge(&request_id)
+                                                {
+                                                    tracing::error!(
+                                                        "Failed to save the outbox: {err}"
+                                                    );
                                                 }
                                             },
                                         }
                                     },
+                                    ServerFrame::Voip(voip_connection) => {
+                                        match voip_connection {
+                                            ServerVoipReply::Success => {},
+                                            ServerVoipReply::Fail(err) => {
+                                                //Avoid panicking when trying to display a Notification
+                                                //This is very rare but can still happen
+                                                display_error_message(
+                                                    err.reason,
+                                                    self.toasts.clone(),
+                                                );
+                                            },
+                                        }
+                                    },
+                                    //The full sync is sent after connecting, or after we have missed some messages
+                                    ServerFrame::Master(master) => {
+                                        self.client_ui.incoming_messages.apply_sync(master);
+                                        self.history_cache.mark_changed();
+
+                                        self.client_ui.resync_pending = false;
+                                        self.client_ui.history_request_pending = false;
+                                    },
+                                    //The pings are answered by the reader thread
+                                    ServerFrame::Ping(_) => {},
                                 }
                             },
                             Err(err) => {
@@ -752,6 +965,11 @@ impl Application
 
                         //Then the thread got an error, we should reset the state
                         tracing::error!("Client receiver or sync thread panicked");
+
+                        //The connection has been lost, the other thread's error is ignored since we are reconnecting already
+                        if matches!(self.client_connection.state, ConnectionState::Connected(_)) {
+                            self.reconnect_to_server(ctx);
+                        }
                     }
                 },
                 Err(_err) => {
@@ -761,19 +979,43 @@ impl Application
         }
     }
 
+    /// Handles the unencrypted control replies of the server, these replies are sent when the server closes the connection
+    fn handle_control_reply(&mut self, reply: ServerControlReply)
+    {
+        if !reply.closes_connection() {
+            return;
+        }
+
+        //Let the user know why the server has closed the connection
+        display_error_message(reply.clone(), self.toasts.clone());
+
+        //The server has already closed the connection so we only have to shut down the threads
+        self.reset_client_connection();
+
+        self.voip_shutdown_token.cancel();
+        self.voip_video_shutdown_token.cancel();
+
+        self.client_connection.reset_state();
+
+        self.client_connection.state = ConnectionState::Closed(reply);
+
+        //Callback
+        self.client_ui.extension.event_call_extensions(
+            crate::app::lua::EventCall::OnDisconnect,
+            &self.lua,
+            None,
+        );
+    }
+
     fn add_message(&mut self, message: super::backend::ServerOutput)
     {
-        //Allocate Message vec for the new message
+        //We can insert the missing messages sent from the server, to the self.client_ui.incoming_msg.struct_list vector
         self.client_ui
             .incoming_messages
-            .reaction_list
-            .push(MessageReaction::default());
+            .insert_message(message.clone());
 
-        //We can append the missing messages sent from the server, to the self.client_ui.incoming_msg.struct_list vector
-        self.client_ui
-            .incoming_messages
-            .message_list
-            .push(message.clone());
+        //The history cache is saved the next time its due
+        self.history_cache.mark_changed();
 
         //Callback
         self.client_ui.extension.event_call_extensions(
@@ -3,10 +3,12 @@ use egui::{
     ImageButton, Layout, Pos2, RichText, Sense, Stroke,
 };
 use rodio::Decoder;
-use std::{net::SocketAddr, sync::atomic::Ordering::Relaxed};
+use std::{fs, net::SocketAddr, path::PathBuf, sync::atomic::Ordering::Relaxed};
 use tokio_util::sync::CancellationToken;
 
-use crate::app::backend::{display_error_message, ClientMessage, ConnectionState, Voip};
+use crate::app::backend::{
+    display_error_message, Capability, ClientMessage, ConnectionState, Permission, Voip,
+};
 
 use crate::app::backend::{Application, SearchType, ServerMessageType};
 
@@ -72,6 +74,12 @@ impl Application
                     ui.separator();
                 });
 
+                //The messages stay visible while we are reconnecting, so the user has to be told that they might be out of date
+                if matches!(self.client_connection.state, ConnectionState::Reconnecting) {
+                    ui.label(RichText::from("Reconnecting...").color(Color32::YELLOW).size(20.))
+                        .on_hover_text("The connection to the server has been lost, the new messages will appear once we have reconnected");
+                }
+
                 if matches!(self.client_connection.state, ConnectionState::Connected(_)) {
                     let port = self
                         .client_ui
@@ -132,7 +140,20 @@ impl Application
                             }
                         }
                         else {
-                            ui.add_enabled_ui(self.atx.is_none(), |ui| {
+                            //Starting a call requires a permission, but anyone can join an ongoing call
+                            let can_join_call = self
+                                .client_ui
+                                .incoming_messages
+                                .ongoing_voip_call
+                                .connected_clients
+                                .is_some()
+                                || self.has_permission(Permission::StartCalls);
+
+                            //Calls can only be used if the server supports them
+                            let can_join_call =
+                                can_join_call && self.client_connection.supports(Capability::Voip);
+
+                            ui.add_enabled_ui(self.atx.is_none() && can_join_call, |ui| {
                                 let call_button = ui.add(ImageButton::new(Image::new(
                                     egui::include_image!("../../../../assets/icons/call.png"),
                                 )));
@@ -200,6 +221,12 @@ impl Application
                             });
                         }
                     });
+
+                    //The latency is measured by the server, its only known if the server supports the heartbeat
+                    if let Some(latency) = *self.client_ui.latency.lock().unwrap() {
+                        ui.label(RichText::from(format!("{} ms", latency.as_millis())).weak())
+                            .on_hover_text("Latency");
+                    }
                 }
             });
 
@@ -360,8 +387,12 @@ impl Application
         let usr_panel = egui::TopBottomPanel::bottom("usr_input")
             .max_height(ctx.used_size().y / 2.)
             .show_animated(ctx, self.client_ui.usr_msg_expanded, |ui| {
+                //Messages can be written while reconnecting, they are queued in the outbox
                 ui.add_enabled_ui(
-                    matches!(self.client_connection.state, ConnectionState::Connected(_)),
+                    matches!(
+                        self.client_connection.state,
+                        ConnectionState::Connected(_) | ConnectionState::Reconnecting
+                    ),
                     |ui| {
                         let msg_tray = self.message_tray(ui, ctx);
 
@@ -372,6 +403,13 @@ impl Application
                 );
             });
 
+        //Channel list, this is displayed when we are connected to a server, or have its messages cached
+        if matches!(self.client_connection.state, ConnectionState::Connected(_))
+            || !self.client_ui.incoming_messages.channels.is_empty()
+        {
+            self.channel_list(ctx);
+        }
+
         //We have to render the message area after everything else, because then we will be using the area whats left of the ui
         //msg_area
         egui::CentralPanel::default().show(ctx, |ui| {
@@ -458,11 +496,22 @@ impl Application
                 self.client_ui.files_to_send.push(dropped_file_path);
             }
 
-            //Messages go here, check if there is a connection
+            //Messages go here, check if there is a connection (The messages stay readable while reconnecting)
             ui.add_enabled_ui(
-                matches!(self.client_connection.state, ConnectionState::Connected(_)),
+                matches!(
+                    self.client_connection.state,
+                    ConnectionState::Connected(_) | ConnectionState::Reconnecting
+                ),
                 |ui| {
-                    self.client_ui_message_main(ui, ctx);
+                    //Display the private conversation if one is opened
+                    match self.client_ui.selected_direct_conversation.clone() {
+                        Some(partner_uuid) => {
+                            self.client_ui_direct_message_main(ui, ctx, &partner_uuid);
+                        },
+                        None => {
+                            self.client_ui_message_main(ui, ctx);
+                        },
+                    }
                 },
             );
         });
@@ -499,7 +548,7 @@ impl Application
                 egui::ScrollArea::new([true, true]).auto_shrink([false, true]).show(ui, |ui|{
                     ui.allocate_ui(ui.available_size(), |ui|{
                         let mut has_search = false;
-                        for (index, message) in self.client_ui.incoming_messages.message_list.iter().enumerate() {
+                        for message in self.client_ui.incoming_messages.message_list.iter() {
                             match self.client_ui.search_parameter {
                                 SearchType::Name => {
                                     if let ServerMessageType::Normal(inner_message) = &message.message_type {
@@ -511,7 +560,7 @@ impl Application
                                             });
 
                                             if group.response.interact(Sense::click()).clicked() {
-                                                self.client_ui.scroll_to_message_index = Some(index)
+                                                self.client_ui.scroll_to_message_id = Some(message.id)
                                             };
 
                                             group.response.on_hover_text("Click to jump to message");
@@ -530,7 +579,7 @@ impl Application
                                             });
 
                                             if group.response.interact(Sense::click()).clicked() {
-                                                self.client_ui.scroll_to_message_index = Some(index)
+                                                self.client_ui.scroll_to_message_id = Some(message.id)
                                             };
 
                                             group.response.on_hover_text("Click to jump to message");
@@ -549,7 +598,7 @@ impl Application
                                             });
 
                                             if group.response.interact(Sense::click()).clicked() {
-                                                self.client_ui.scroll_to_message_index = Some(index)
+                                                self.client_ui.scroll_to_message_id = Some(message.id)
                                             };
 
                                             group.response.on_hover_text("Click to jump to message");
@@ -568,7 +617,7 @@ impl Application
                                             });
 
                                             if group.response.interact(Sense::click()).clicked() {
-                                                self.client_ui.scroll_to_message_index = Some(index)
+                                                self.client_ui.scroll_to_message_id = Some(message.id)
                                             };
 
                                             group.response.on_hover_text("Click to jump to message");
@@ -584,13 +633,13 @@ impl Application
 
                                             //This button shouldnt actually do anything because when this message group gets clicked it throws you to the message
                                             if ui.small_button(inner_message.file_name.to_string()).clicked() {
-                                                self.client_ui.scroll_to_message_index = Some(index)
+                                                self.client_ui.scroll_to_message_id = Some(message.id)
                                             };
                                             ui.small(&message.message_date);
                                         });
 
                                         if group.response.interact(Sense::click()).clicked() {
-                                            self.client_ui.scroll_to_message_index = Some(index)
+                                            self.client_ui.scroll_to_message_id = Some(message.id)
                                         };
 
                                         group.response.on_hover_text("Click to jump to message");
@@ -604,13 +653,13 @@ impl Application
 
                                             //This button shouldnt actually do anything because when this message group gets clicked it throws you to the message
                                             if ui.small_button("Image").clicked() {
-                                                self.client_ui.scroll_to_message_index = Some(index)
+                                                self.client_ui.scroll_to_message_id = Some(message.id)
                                             };
                                             ui.small(&message.message_date);
                                         });
 
                                         if group.response.interact(Sense::click()).clicked() {
-                                            self.client_ui.scroll_to_message_index = Some(index)
+                                            self.client_ui.scroll_to_message_id = Some(message.id)
                                         };
 
                                         group.response.on_hover_text("Click to jump to message");
@@ -623,12 +672,12 @@ impl Application
 
                                             //This button shouldnt actually do anything because when this message group gets clicked it throws you to the message
                                             if ui.small_button("Audio").clicked() {
-                                                self.client_ui.scroll_to_message_index = Some(index)
+                                                self.client_ui.scroll_to_message_id = Some(message.id)
                                             };
                                             ui.small(&message.message_date);
                                         });
                                         if group.response.interact(Sense::click()).clicked() {
-                                            self.client_ui.scroll_to_message_index = Some(index)
+                                            self.client_ui.scroll_to_message_id = Some(message.id)
                                         };
 
                                         group.response.on_hover_text("Click to jump to message");
@@ -688,13 +737,20 @@ impl Application
 
         match self.audio_bytes_rx.try_recv() {
             Ok(bytes) => {
-                //Send audio file
-                self.send_msg(ClientMessage::construct_file_msg_from_bytes(
-                    bytes,
-                    "wav".to_string(),
-                    self.client_ui.messaging_mode.get_reply_index(),
-                    self.opened_user_information.uuid.clone(),
+                //The recording is saved to a temporary file, so that it can be uploaded in chunks like any other file
+                let path = PathBuf::from(format!(
+                    "{}\\Matthias\\Client\\Uploads\\{}.wav",
+                    env!("APPDATA"),
+                    sha256::digest(&bytes)
                 ));
+
+                match fs::create_dir_all(path.parent().unwrap_or(&path))
+                    .and_then(|_| fs::write(&path, bytes))
+                {
+                    //Send audio file
+                    Ok(_) => self.upload_file(path, true),
+                    Err(err) => tracing::error!("Failed to save the voice recording: {err}"),
+                }
             },
             Err(_err) => {
                 // dbg!(_err);
@@ -702,26 +758,30 @@ impl Application
         }
 
         match self.audio_save_rx.try_recv() {
-            Ok((sink, cursor, index, path_to_audio)) => {
-                //Convert idx to usize
-                let index = index as usize;
+            Ok((sink, cursor, message_id, path_to_audio)) => {
+                let settings = self
+                    .client_ui
+                    .audio_playback
+                    .settings_list
+                    .entry(message_id)
+                    .or_default();
 
                 //Check if the request was unsuccessful, so we can reset the states
-                if sink.is_none() {
+                let Some(sink) = sink
+                else {
                     //Reset state
-                    self.client_ui.audio_playback.settings_list[index].is_loading = false;
+                    settings.is_loading = false;
                     return;
-                }
-
-                //Modify audio player
-                self.client_ui.audio_playback.sink_list[index] = sink;
+                };
 
                 //Set path
-                self.client_ui.audio_playback.settings_list[index].path_to_audio = path_to_audio;
+                settings.path_to_audio = path_to_audio;
 
-                let sink = self.client_ui.audio_playback.sink_list[index]
-                    .as_mut()
-                    .unwrap();
+                //Modify audio player
+                self.client_ui
+                    .audio_playback
+                    .sink_list
+                    .insert(message_id, sink.clone());
 
                 let source = Decoder::new(
                     cursor.clone(), /*We can assume its always Some because we just set it to some above (lol)*/
@@ -740,15 +800,15 @@ impl Application
                     },
                 }
 
-                self.client_ui.audio_playback.settings_list[index].cursor = cursor;
+                settings.cursor = cursor;
                 //Reset button state so it can be used again
-                self.client_ui.audio_playback.settings_list[index].is_loading = false;
+                settings.is_loading = false;
             },
             Err(_err) => {},
         }
     }
 
-    fn disable_camera(&mut self, voip: Voip)
+    pub fn disable_camera(&mut self, voip: Voip)
     {
         let uuid = self.opened_user_information.uuid.clone();
         //Drop camera handle
@@ -0,0 +1,131 @@
+use crate::app::backend::{Application, OutboxItem};
+use egui::{vec2, Color32, Layout, RichText};
+
+impl Application
+{
+    /// This function draws the private conversation with the user (identified by their uuid)
+    /// Private messages cannot be replied to, edited or reacted to, so they are drawn without any of those
+    pub fn client_ui_direct_message_main(
+        &mut self,
+        ui: &mut egui::Ui,
+        ctx: &egui::Context,
+        partner_uuid: &str,
+    ) -> egui::InnerResponse<()>
+    {
+        let conversation: Vec<crate::app::backend::ServerOutput> = self
+            .client_ui
+            .incoming_messages
+            .direct_messages
+            .iter()
+            .filter(|message| {
+                message
+                    .direct_message_partner(&self.opened_user_information.uuid)
+                    .is_some_and(|partner| partner == partner_uuid)
+            })
+            .cloned()
+            .collect();
+
+        //The private messages which havent been sent yet
+        let pending_items: Vec<OutboxItem> = self
+            .outbox
+            .items
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|item| item.request.direct_message_recipient() == Some(partner_uuid))
+            .cloned()
+            .collect();
+
+        ui.allocate_ui(vec2(ui.available_width(), ui.available_height()), |ui| {
+            egui::ScrollArea::vertical()
+                .id_source("direct_msg_area")
+                .stick_to_bottom(true)
+                .auto_shrink([false, true])
+                .show(ui, |ui| {
+                    if conversation.is_empty() && pending_items.is_empty() {
+                        ui.with_layout(
+                            Layout::centered_and_justified(egui::Direction::TopDown),
+                            |ui| {
+                                ui.label(
+                                    RichText::from(
+                                        "This is the beginning of your private conversation.",
+                                    )
+                                    .size(self.font_size)
+                                    .color(Color32::LIGHT_BLUE),
+                                );
+                            },
+                        );
+                    }
+
+                    for (iter_index, item) in conversation.iter().enumerate() {
+                        let message_group = ui.group(|ui| {
+                            //Display author
+                            ui.horizontal(|ui| {
+                                //Profile picture
+                                self.display_icon_from_server(ctx, item.uuid.clone(), ui);
+                                //Client name
+                                ui.label(
+                                    RichText::from(item.author.to_string())
+                                        .size(self.font_size / 1.3)
+                                        .color(Color32::WHITE),
+                                );
+                            });
+
+                            ui.push_id(iter_index, |ui| {
+                                self.message_display(item, ui, ctx);
+                            });
+
+                            //Display Message date
+                            ui.label(
+                                RichText::from(item.local_date())
+                                    .size(self.font_size / 1.5)
+                                    .color(Color32::DARK_GRAY),
+                            );
+                        });
+
+                        //Mark the message as seen if its visible
+                        if ui.is_rect_visible(message_group.response.rect) {
+                            let seen_messages = self
+                                .client_ui
+                                .direct_messages_seen
+                                .entry(partner_uuid.to_string())
+                                .or_default();
+
+                            if *seen_messages < iter_index + 1 {
+                                *seen_messages = iter_index + 1;
+                            }
+                        }
+                    }
+
+                    self.draw_outbox_items(ui, pending_items);
+
+                    if self.client_ui.usr_msg_expanded {
+                        ui.allocate_space(vec2(ui.available_width(), 25.));
+                    }
+                });
+        })
+    }
+
+    /// Returns the number of private messages the user hasnt seen yet in the conversation with the user (identified by their uuid)
+    pub fn unread_direct_message_count(&self, partner_uuid: &str) -> usize
+    {
+        let seen_messages = self
+            .client_ui
+            .direct_messages_seen
+            .get(partner_uuid)
+            .copied()
+            .unwrap_or_default();
+
+        self.client_ui
+            .incoming_messages
+            .direct_messages
+            .iter()
+            .filter(|message| {
+                message
+                    .direct_message_partner(&self.opened_user_information.uuid)
+                    .is_some_and(|partner| partner == partner_uuid)
+            })
+            .skip(seen_messages)
+            .count()
+    }
+}
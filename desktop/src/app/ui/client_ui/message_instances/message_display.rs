@@ -6,7 +6,7 @@ use egui::{
 
 use crate::app::backend::{
     parse_incoming_message, Application, ClientMessage, ClientProfile, MessageDisplay,
-    ServerFileReply, ServerMessageType,
+    ServerDirectMessage, ServerMessageType, ServerNormalMessage,
 };
 use rodio::Decoder;
 
@@ -20,7 +20,6 @@ impl Application
         message: &crate::app::backend::ServerOutput,
         ui: &mut Ui,
         ctx: &egui::Context,
-        current_index_in_message_list: usize,
     ) -> Response
     {
         match &message.message_type {
@@ -30,9 +29,12 @@ impl Application
                     ui.button(RichText::from(inner.file_name.to_string()).size(self.font_size));
                 button.paint_debug_info();
                 //If we want to download the file included in the message
-                if button.clicked() {
+                //The file is downloaded in chunks, the next chunk is requested when the previous one arrives
+                if button.clicked() && !self.file_transfers.downloads.contains_key(&inner.signature)
+                {
                     let message = ClientMessage::construct_file_request_msg(
                         inner.signature.clone(),
+                        0,
                         &self.opened_user_information.uuid,
                     );
 
@@ -45,8 +47,12 @@ impl Application
 
                 button
             },
-            crate::app::backend::ServerMessageType::Normal(message) => {
-                let messages = parse_incoming_message(message.message.clone());
+            //Private messages are displayed the same way as normal messages
+            crate::app::backend::ServerMessageType::Normal(ServerNormalMessage {
+                message, ..
+            })
+            | ServerMessageType::DirectMessage(ServerDirectMessage { message, .. }) => {
+                let messages = parse_incoming_message(message.clone());
                 let mut messages_iter = messages.iter();
 
                 'mainloop: loop {
@@ -146,11 +152,10 @@ impl Application
                                                 _ => todo!(),
                                             };
 
-                                            let image_save = ServerFileReply {
-                                                bytes: bytes.to_vec(),
-                                                file_name: PathBuf::from(format!("image.{}",type_name.to_lowercase())),
-                                            };
-                                            let _ = crate::app::backend::write_file(image_save);
+                                            let _ = crate::app::backend::write_file(
+                                                &PathBuf::from(format!("image.{}",type_name.to_lowercase())),
+                                                &bytes,
+                                            );
                                         }
                                     }
                                 });
@@ -169,14 +174,39 @@ impl Application
                                     }
                                     //Load an empty byte to the said URI
                                     ctx.include_bytes(format!("bytes://{}", picture.signature), vec![0]);
-                                    //We dont have file on our local system so we have to ask the server to provide it
+                                    //If the image isnt in the history cache we have to ask the server to provide it
                                     let uuid = &self.opened_user_information.uuid;
                                     let message =
                                         ClientMessage::construct_image_request_msg(picture.signature.clone(), uuid);
                                     let connection = self.client_connection.clone();
+                                    let history_cache = self.history_cache.clone();
+                                    let signature = picture.signature.clone();
+                                    let context_clone = ctx.clone();
                                     tokio::spawn(async move {
+                                        let cached_image = {
+                                            let signature = signature.clone();
+
+                                            tokio::task::spawn_blocking(move || history_cache.load_image(&signature)).await
+                                        };
+
+                                        match cached_image {
+                                            Ok(Ok(Some(bytes))) => {
+                                                //Forget the placeholder so the image will get displayed
+                                                context_clone.forget_image(&format!("bytes://{signature}"));
+                                                context_clone.include_bytes(format!("bytes://{signature}"), bytes);
+                                                context_clone.request_repaint();
+
+                                                return;
+                                            },
+                                            Ok(Ok(None)) => {},
+                                            Ok(Err(err)) => tracing::error!("Failed to read the cached image: {err}"),
+                                            Err(err) => tracing::error!("{err}"),
+                                        }
+
                                         //We only have to send the message it will get received in a diff place
-                                        connection.clone().send_message(message).await.unwrap();
+                                        if let Err(err) = connection.send_message(message).await {
+                                            tracing::error!("{err}");
+                                        }
                                     });
                                 }
                                 else {
@@ -208,9 +238,7 @@ impl Application
 
                 ui.allocate_ui(vec2(300., 150.), |ui| {
                     ui.with_layout(Layout::top_down(Align::Center), |ui| {
-                        match self.client_ui.audio_playback.sink_list[current_index_in_message_list]
-                            .as_mut()
-                        {
+                        match self.client_ui.audio_playback.sink_list.get(&message.id) {
                             Some(sink) => {
                                 match sink.is_paused() || sink.empty() {
                                     //Audio is stopped
@@ -268,8 +296,12 @@ impl Application
                                 }
                             },
                             None => {
-                                let is_loading = self.client_ui.audio_playback.settings_list
-                                    [current_index_in_message_list]
+                                let is_loading = self
+                                    .client_ui
+                                    .audio_playback
+                                    .settings_list
+                                    .entry(message.id)
+                                    .or_default()
                                     .is_loading;
 
                                 //If its loading display a spinner
@@ -286,7 +318,7 @@ impl Application
                                                 ClientMessage::construct_audio_request_msg(
                                                     audio.signature.clone(),
                                                     &self.opened_user_information.uuid,
-                                                    current_index_in_message_list as u64,
+                                                    message.id,
                                                 );
 
                                             let connection = self.client_connection.clone();
@@ -296,8 +328,11 @@ impl Application
                                             });
 
                                             //Set button to be disabled
-                                            self.client_ui.audio_playback.settings_list
-                                                [current_index_in_message_list]
+                                            self.client_ui
+                                                .audio_playback
+                                                .settings_list
+                                                .entry(message.id)
+                                                .or_default()
                                                 .is_loading = true;
                                         }
                                     };
@@ -307,20 +342,23 @@ impl Application
                     });
 
                     //Set properties of audio stream, when there is one
-                    if let Some(sink) = self.client_ui.audio_playback.sink_list
-                        [current_index_in_message_list]
-                        .as_mut()
-                    {
+                    if let Some(sink) = self.client_ui.audio_playback.sink_list.get(&message.id) {
                         //Set volume
                         sink.set_volume(
-                            self.client_ui.audio_playback.settings_list
-                                [current_index_in_message_list]
+                            self.client_ui
+                                .audio_playback
+                                .settings_list
+                                .entry(message.id)
+                                .or_default()
                                 .volume,
                         );
 
                         sink.set_speed(
-                            self.client_ui.audio_playback.settings_list
-                                [current_index_in_message_list]
+                            self.client_ui
+                                .audio_playback
+                                .settings_list
+                                .entry(message.id)
+                                .or_default()
                                 .speed,
                         );
                     }
@@ -329,8 +367,12 @@ impl Application
                     //Audio volume
                     ui.add(
                         egui::Slider::new(
-                            &mut self.client_ui.audio_playback.settings_list
-                                [current_index_in_message_list]
+                            &mut self
+                                .client_ui
+                                .audio_playback
+                                .settings_list
+                                .entry(message.id)
+                                .or_default()
                                 .volume,
                             0.01..=5.,
                         )
@@ -340,8 +382,12 @@ impl Application
                     //Audio speed
                     ui.add(
                         egui::Slider::new(
-                            &mut self.client_ui.audio_playback.settings_list
-                                [current_index_in_message_list]
+                            &mut self
+                                .client_ui
+                                .audio_playback
+                                .settings_list
+                                .entry(message.id)
+                                .or_default()
                                 .speed,
                             0.01..=5.,
                         )
@@ -369,6 +415,23 @@ impl Application
                     crate::app::backend::ServerMessage::Ban(profile) => {
                         format!("@{} has been banned from the server.", profile.username)
                     },
+                    crate::app::backend::ServerMessage::Kick(profile) => {
+                        format!("@{} has been kicked from the server.", profile.username)
+                    },
+                    crate::app::backend::ServerMessage::Mute(profile, until) => {
+                        format!(
+                            "@{} has been muted until {}.",
+                            profile.username,
+                            until.format("%Y.%m.%d. %H:%M")
+                        )
+                    },
+                    crate::app::backend::ServerMessage::Timeout(profile, until) => {
+                        format!(
+                            "@{} has been timed out until {}.",
+                            profile.username,
+                            until.format("%Y.%m.%d. %H:%M")
+                        )
+                    },
                 };
 
                 //We can safely unwrap here since the message is defined above
@@ -455,6 +518,10 @@ impl Application
             },
             crate::app::backend::ServerMessageType::Edit(_)
             | ServerMessageType::VoipState(_)
+            | ServerMessageType::ChannelUpdate(_)
+            | ServerMessageType::PinUpdate(_)
+            | ServerMessageType::RoleUpdate(_)
+            | ServerMessageType::RequestError(_)
             | crate::app::backend::ServerMessageType::Reaction(_)
             | crate::app::backend::ServerMessageType::Sync(_) => {
                 unimplemented!("Message type should not be displayed")
@@ -1,11 +1,14 @@
 use crate::app::{
     backend::{
-        Application, AudioSettings, ClientMessage, MessagingMode, ScrollToMessage,
-        ServerMessageType,
+        display_error_message, Application, ClientMessage, ClientModerationRequest, MessageId,
+        MessagingMode, OutboxItem, OutboxState, Permission, Role, ScrollToMessage,
+        ServerMessageType, MODERATION_DURATIONS,
     },
     server::SERVER_UUID,
     ui::client_ui::widgets::emoji_tray::emoji::display_emoji,
 };
+use strum::IntoEnumIterator;
+
 use egui::{
     load::{BytesPoll, LoadError},
     vec2, Align, Button, Color32, Image, Layout, Response, RichText, Sense,
@@ -20,7 +23,7 @@ impl Application
     ) -> egui::InnerResponse<()>
     {
         ui.allocate_ui(vec2(ui.available_width(), ui.available_height()), |ui|{
-            egui::ScrollArea::vertical()
+            let scroll_area = egui::ScrollArea::vertical()
                     .id_source("msg_area")
                     .stick_to_bottom(self.client_ui.scroll_to_message.is_none())
                     .auto_shrink([false, true])
@@ -33,29 +36,58 @@ impl Application
                                 });
                             }
 
-                            //Check if sink_list is bigger than messages, to avoid crashing
-                            if self.client_ui.audio_playback.sink_list.len() > self.client_ui.incoming_messages.message_list.len() {
-                                for _ in 0..(self.client_ui.audio_playback.sink_list.len() as i32 - self.client_ui.incoming_messages.message_list.len() as i32).abs() {
-                                    self.client_ui.audio_playback.sink_list.remove(self.client_ui.audio_playback.sink_list.len() - 1);
-                                }
-                            }
+                            let mut message_instances: Vec<Response> = Vec::new();
 
-                            //Allocate places manually for the audio playback (sink_list), but only allocate what we need
-                            for _ in 0..(self.client_ui.incoming_messages.message_list.len() - self.client_ui.audio_playback.sink_list.len()) {
-                                self.client_ui.audio_playback.sink_list.push(None);
+                            //The ids of the displayed messages, so we know which instance belongs to which message
+                            let mut displayed_message_ids: Vec<MessageId> = Vec::new();
 
-                                //Define defaults, for speed and volume based on the same logic as above ^
-                                self.client_ui.audio_playback.settings_list.push(AudioSettings::default());
-                            }
+                            for item in self.client_ui.incoming_messages.clone().message_list.iter() {
+                                //Only display the messages of the selected channel
+                                if item.channel_id != self.client_ui.selected_channel {
+                                    continue;
+                                }
 
-                            let mut message_instances: Vec<Response> = Vec::new();
+                                self.draw_message(ui, item, ctx, &mut message_instances);
 
-                            for (iter_index, item) in self.client_ui.incoming_messages.clone().message_list.iter().enumerate() {
-                                self.draw_message(ui, item, ctx, iter_index, &mut message_instances);
+                                displayed_message_ids.push(item.id);
                             };
 
-                            if let Some(scroll_to_reply) = self.client_ui.scroll_to_message_index {
-                                self.client_ui.scroll_to_message = Some(ScrollToMessage::new(message_instances, scroll_to_reply));
+                            //The requests which havent been sent yet are displayed after the messages
+                            let pending_items: Vec<OutboxItem> = self
+                                .outbox
+                                .items
+                                .lock()
+                                .unwrap()
+                                .iter()
+                                .filter(|item| {
+                                    item.request
+                                        .channel_id(&self.client_ui.incoming_messages)
+                                        .is_some_and(|channel_id| channel_id == self.client_ui.selected_channel)
+                                })
+                                .cloned()
+                                .collect();
+
+                            self.draw_outbox_items(ui, pending_items);
+
+                            if let Some(scroll_to_reply) = self.client_ui.scroll_to_message_id {
+                                match displayed_message_ids.iter().position(|id| *id == scroll_to_reply) {
+                                    Some(instance_index) => {
+                                        self.client_ui.scroll_to_message = Some(ScrollToMessage::new(message_instances, instance_index));
+                                    },
+                                    //If the message is in another channel, switch to that channel, the message will be displayed in the next frame
+                                    None => {
+                                        //If the message hasnt been loaded yet, load the older pages until we reach it
+                                        if self.client_ui.incoming_messages.first_message_id(&self.client_ui.selected_channel).is_some_and(|first_message_id| scroll_to_reply < first_message_id) {
+                                            self.request_older_messages();
+                                        }
+                                        else if let Some(message) = self.client_ui.incoming_messages.message(scroll_to_reply) {
+                                            self.client_ui.selected_channel = message.channel_id.clone();
+                                        }
+                                        else {
+                                            self.client_ui.scroll_to_message_id = None;
+                                        }
+                                    },
+                                }
                             }
 
                         //Scroll to reply logic
@@ -68,7 +100,7 @@ impl Application
                             //Only destroy instance if the message is visible, thanks egui!!
                             if ui.is_rect_visible(message_rect.rect) {
                                 self.client_ui.scroll_to_message = None;
-                                self.client_ui.scroll_to_message_index = None;
+                                self.client_ui.scroll_to_message_id = None;
                             }
                         }
                         });
@@ -77,9 +109,98 @@ impl Application
                             ui.allocate_space(vec2(ui.available_width(), 25.));
                         }
                     });
+
+            //Load the older messages when the user has scrolled to the top of the message list (This also fills the message list if it isnt scrollable yet)
+            if scroll_area.state.offset.y <= 0. {
+                self.request_older_messages();
+            }
         })
     }
 
+    /// Draws the requests waiting in the outbox, they can be cancelled until they are being sent, the failed ones can be retried too
+    pub fn draw_outbox_items(&mut self, ui: &mut egui::Ui, items: Vec<OutboxItem>)
+    {
+        for item in items {
+            ui.group(|ui| {
+                ui.label(
+                    RichText::from(item.request.description())
+                        .size(self.font_size)
+                        .color(Color32::GRAY),
+                );
+
+                ui.horizontal(|ui| {
+                    match &item.state {
+                        OutboxState::Pending => {
+                            ui.label(
+                                RichText::from("Pending")
+                                    .size(self.font_size / 1.5)
+                                    .color(Color32::YELLOW),
+                            );
+                        },
+                        OutboxState::Sending => {
+                            ui.label(
+                                RichText::from("Sending")
+                                    .size(self.font_size / 1.5)
+                                    .color(Color32::LIGHT_BLUE),
+                            );
+                        },
+                        OutboxState::Failed(err) => {
+                            ui.label(
+                                RichText::from(format!("Failed: {err}"))
+                                    .size(self.font_size / 1.5)
+                                    .color(Color32::RED),
+                            );
+
+                            if ui.button("Retry").clicked() {
+                                if let Err(err) = self.outbox.retry(&item.id) {
+                                    display_error_message(err, self.toasts.clone());
+                                }
+                            }
+                        },
+                    }
+
+                    if ui
+                        .add_enabled(item.state != OutboxState::Sending, Button::new("Cancel"))
+                        .clicked()
+                    {
+                        if let Err(err) = self.outbox.cancel(&item.id) {
+                            display_error_message(err, self.toasts.clone());
+                        }
+                    }
+                });
+            });
+        }
+    }
+
+    /// Requests the page of the selected channel's history before its oldest loaded message, if there is one and it hasnt been requested yet
+    fn request_older_messages(&mut self)
+    {
+        if self.client_ui.history_request_pending
+            || !self
+                .client_ui
+                .incoming_messages
+                .has_older_messages(&self.client_ui.selected_channel)
+        {
+            return;
+        }
+
+        let Some(first_message_id) = self
+            .client_ui
+            .incoming_messages
+            .first_message_id(&self.client_ui.selected_channel)
+        else {
+            return;
+        };
+
+        self.client_ui.history_request_pending = true;
+
+        self.send_msg(ClientMessage::construct_history_request_msg(
+            first_message_id,
+            self.client_ui.selected_channel.clone(),
+            &self.opened_user_information.uuid,
+        ));
+    }
+
     /// This function draws the message at the ui passted in as an argument.
     /// The message instance created by this function has a context_menu called upon it.
     /// This function handles all the message types because this function is to display the whole message.
@@ -88,7 +209,6 @@ impl Application
         ui: &mut egui::Ui,
         item: &crate::app::backend::ServerOutput,
         ctx: &egui::Context,
-        iter_index: usize,
         message_instances: &mut Vec<Response>,
     )
     {
@@ -100,28 +220,34 @@ impl Application
         //Emoji tray pops up when right clicking on a message
         let message_group = ui.group(|ui| {
             if let Some(replied_to) = item.replying_to {
+                //The replied message may not be loaded, if its in an older page of the history
+                let replied_message = self
+                    .client_ui
+                    .incoming_messages
+                    .message(replied_to)
+                    .cloned();
+
                 ui.allocate_ui(vec2(ui.available_width(), self.font_size), |ui| {
                     //Avoid reply area ID match, explicitly push id of this area
                     let reply_button_area = ui.push_id(ui.next_auto_id(), |ui| {
                         ui.horizontal(|ui| {
-                            self.display_icon_from_server(
-                                ctx,
-                                self.client_ui.incoming_messages.message_list[replied_to]
-                                    .uuid
-                                    .clone(),
-                                ui,
-                            );
+                            let Some(replied_message) = &replied_message
+                            else {
+                                ui.label(
+                                    RichText::from("Older message").size(self.font_size / 1.5),
+                                );
+
+                                return;
+                            };
+
+                            self.display_icon_from_server(ctx, replied_message.uuid.clone(), ui);
 
                             ui.add(
                                 egui::widgets::Button::new(
                                     RichText::from(format!(
                                         "{}: {}",
-                                        self.client_ui.incoming_messages.message_list[replied_to]
-                                            .author,
-                                        match &self.client_ui.incoming_messages.message_list
-                                            [replied_to]
-                                            .message_type
-                                        {
+                                        replied_message.author,
+                                        match &replied_message.message_type {
                                             ServerMessageType::Deleted =>
                                                 "Deleted message".to_string(),
                                             ServerMessageType::Audio(audio) =>
@@ -156,12 +282,37 @@ impl Application
                                                         profile.username
                                                     )
                                                 },
+                                                crate::app::backend::ServerMessage::Kick(
+                                                    profile,
+                                                ) => {
+                                                    format!("{} has been kicked", profile.username)
+                                                },
+                                                crate::app::backend::ServerMessage::Mute(
+                                                    profile,
+                                                    _,
+                                                ) => {
+                                                    format!("{} has been muted", profile.username)
+                                                },
+                                                crate::app::backend::ServerMessage::Timeout(
+                                                    profile,
+                                                    _,
+                                                ) => {
+                                                    format!(
+                                                        "{} has been timed out",
+                                                        profile.username
+                                                    )
+                                                },
                                             },
                                             ServerMessageType::Edit(_) => unreachable!(),
                                             ServerMessageType::Reaction(_) => unreachable!(),
                                             ServerMessageType::Sync(_) => unreachable!(),
                                             ServerMessageType::VoipEvent(_) => unreachable!(),
                                             ServerMessageType::VoipState(_) => unreachable!(),
+                                            ServerMessageType::ChannelUpdate(_) => unreachable!(),
+                                            ServerMessageType::DirectMessage(_) => unreachable!(),
+                                            ServerMessageType::PinUpdate(_) => unreachable!(),
+                                            ServerMessageType::RoleUpdate(_) => unreachable!(),
+                                            ServerMessageType::RequestError(_) => unreachable!(),
                                         }
                                     ))
                                     .size(self.font_size / 1.5),
@@ -182,7 +333,7 @@ impl Application
                         .clicked()
                     {
                         //implement scrolling to message
-                        self.client_ui.scroll_to_message_index = Some(replied_to);
+                        self.client_ui.scroll_to_message_id = Some(replied_to);
                     }
                 });
             }
@@ -201,15 +352,13 @@ impl Application
 
             //IMPORTANT: Each of these functions have logic inside them for displaying
             message_response = Some(
-                ui.push_id(iter_index, |ui| {
-                    self.message_display(item, ui, ctx, iter_index)
-                })
-                .inner,
+                ui.push_id(item.id, |ui| self.message_display(item, ui, ctx))
+                    .inner,
             );
 
             //Display Message date
             ui.label(
-                RichText::from(item.message_date.to_string())
+                RichText::from(item.local_date())
                     .size(self.font_size / 1.5)
                     .color(Color32::DARK_GRAY),
             );
@@ -220,6 +369,15 @@ impl Application
                 }
             }
 
+            if self
+                .client_ui
+                .incoming_messages
+                .pinned_messages
+                .contains(&item.id)
+            {
+                ui.label(RichText::from("(Pinned)").strong());
+            }
+
             egui::ScrollArea::horizontal()
                 .id_source(
                     /* Autoassign id's to integrated scroll widgets */ ui.next_auto_id(),
@@ -227,61 +385,49 @@ impl Application
                 .max_height(self.font_size)
                 .show(ui, |ui| {
                     ui.horizontal(|ui| {
-                        //Check if there is a reaction list vector already allocated non the index of the specific message
-                        match self
-                            .client_ui
-                            .incoming_messages
-                            .reaction_list
-                            .clone()
-                            .get(iter_index)
-                        {
-                            Some(reactions) => {
-                                for (idx, item) in reactions.message_reactions.iter().enumerate() {
-                                    ui.push_id(idx, |ui| {
-                                        let group = ui.group(|ui| {
-                                            ui.allocate_ui(vec2(20., 20.), |ui| {
-                                                display_emoji(ctx, &item.emoji_name, ui);
-                                            });
-
-                                            ui.label(
-                                                RichText::from(item.authors.len().to_string())
-                                                    .size(self.font_size / 1.3),
-                                            );
-                                        });
-
-                                        let emoji_group_rect = ui.interact(
-                                            group.response.rect,
-                                            ui.next_auto_id(),
-                                            Sense::click(),
-                                        );
-
-                                        if emoji_group_rect.clicked() {
-                                            self.change_send_emoji(
-                                                iter_index,
-                                                item.emoji_name.clone(),
-                                            );
-                                        };
+                        for (idx, reaction) in item.reactions.message_reactions.iter().enumerate() {
+                            ui.push_id(idx, |ui| {
+                                let group = ui.group(|ui| {
+                                    ui.allocate_ui(vec2(20., 20.), |ui| {
+                                        display_emoji(ctx, &reaction.emoji_name, ui);
                                     });
-                                }
-                            },
-                            None => {
-                                // eprintln!("message_main.rs: No reaction list allocated for message {}", iter_index);
-                            },
+
+                                    ui.label(
+                                        RichText::from(reaction.authors.len().to_string())
+                                            .size(self.font_size / 1.3),
+                                    );
+                                });
+
+                                let emoji_group_rect = ui.interact(
+                                    group.response.rect,
+                                    ui.next_auto_id(),
+                                    Sense::click(),
+                                );
+
+                                if emoji_group_rect.clicked() {
+                                    self.change_send_emoji(item.id, reaction.emoji_name.clone());
+                                };
+                            });
                         }
                     });
                 });
 
-            if ui.is_rect_visible(ui.min_rect())
-                && *self.client_ui.last_seen_msg_index.lock().unwrap() < iter_index
-            {
-                *self.client_ui.last_seen_msg_index.lock().unwrap() = iter_index;
+            if ui.is_rect_visible(ui.min_rect()) {
+                let mut last_seen_msg_id = self.client_ui.last_seen_msg_id.lock().unwrap();
+
+                //Update the last seen message of the channel this message belongs to
+                let last_seen_id = last_seen_msg_id.entry(item.channel_id.clone()).or_default();
+
+                if *last_seen_id < item.id {
+                    *last_seen_id = item.id;
+                }
             }
         });
 
         //Display where the users seen their last message
         ui.horizontal(|ui| {
             for client in self.client_ui.incoming_messages.user_seen_list.clone() {
-                if iter_index == client.index {
+                if item.id == client.message_id && item.channel_id == client.channel_id {
                     //Make it more visible
                     ui.group(|ui| {
                         //Profile picture
@@ -390,6 +536,127 @@ impl Application
                                 }
                             ));
                         }
+
+                        let user_role = self.role_of(&item.uuid);
+
+                        let own_role = self.role_of(&self.opened_user_information.uuid);
+
+                        ui.label(format!("Role: {user_role}"));
+
+                        //We can only manage the users who have a lower role than us, the server checks this too
+                        if user_role < own_role {
+                            if self.has_permission(Permission::ManageRoles) {
+                                ui.menu_button("Set role", |ui| {
+                                    //We cannot assign a role higher than our own
+                                    for role in Role::iter().filter(|role| *role <= own_role) {
+                                        if ui
+                                            .selectable_label(role == user_role, role.to_string())
+                                            .clicked()
+                                        {
+                                            self.send_msg(ClientMessage::construct_role_request(
+                                                item.uuid.clone(),
+                                                role,
+                                                &self.opened_user_information.uuid,
+                                            ));
+
+                                            ui.close_menu();
+                                        }
+                                    }
+                                });
+                            }
+
+                            if self.has_permission(Permission::Mute) {
+                                ui.menu_button("Mute", |ui| {
+                                    for (duration_name, minutes) in MODERATION_DURATIONS {
+                                        if ui.button(duration_name).clicked() {
+                                            self.send_msg(
+                                                ClientMessage::construct_moderation_request(
+                                                    ClientModerationRequest::Mute {
+                                                        uuid: item.uuid.clone(),
+                                                        minutes,
+                                                    },
+                                                    &self.opened_user_information.uuid,
+                                                ),
+                                            );
+
+                                            ui.close_menu();
+                                        }
+                                    }
+                                });
+                            }
+
+                            if self.has_permission(Permission::Kick) {
+                                ui.menu_button("Timeout", |ui| {
+                                    for (duration_name, minutes) in MODERATION_DURATIONS {
+                                        if ui.button(duration_name).clicked() {
+                                            self.send_msg(
+                                                ClientMessage::construct_moderation_request(
+                                                    ClientModerationRequest::Timeout {
+                                                        uuid: item.uuid.clone(),
+                                                        minutes,
+                                                    },
+                                                    &self.opened_user_information.uuid,
+                                                ),
+                                            );
+
+                                            ui.close_menu();
+                                        }
+                                    }
+                                });
+
+                                if ui.button("Kick").clicked() {
+                                    self.send_msg(ClientMessage::construct_moderation_request(
+                                        ClientModerationRequest::Kick(item.uuid.clone()),
+                                        &self.opened_user_information.uuid,
+                                    ));
+
+                                    ui.close_menu();
+                                }
+                            }
+
+                            if self.has_permission(Permission::Ban) {
+                                ui.menu_button(RichText::from("Ban").color(Color32::RED), |ui| {
+                                    ui.add(
+                                        egui::TextEdit::singleline(&mut self.client_ui.ban_reason)
+                                            .hint_text("Reason"),
+                                    );
+
+                                    ui.checkbox(
+                                        &mut self.client_ui.ban_address,
+                                        "Ban their address too",
+                                    );
+
+                                    ui.separator();
+
+                                    for (duration_name, minutes) in MODERATION_DURATIONS
+                                        .into_iter()
+                                        .map(|(duration_name, minutes)| {
+                                            (duration_name, Some(minutes))
+                                        })
+                                        .chain([("Permanently", None)])
+                                    {
+                                        if ui.button(duration_name).clicked() {
+                                            let reason =
+                                                std::mem::take(&mut self.client_ui.ban_reason);
+
+                                            self.send_msg(
+                                                ClientMessage::construct_moderation_request(
+                                                    ClientModerationRequest::Ban {
+                                                        uuid: item.uuid.clone(),
+                                                        reason,
+                                                        minutes,
+                                                        include_address: self.client_ui.ban_address,
+                                                    },
+                                                    &self.opened_user_information.uuid,
+                                                ),
+                                            );
+
+                                            ui.close_menu();
+                                        }
+                                    }
+                                });
+                            }
+                        }
                     }
                 });
 
@@ -407,27 +674,31 @@ impl Application
                     ))
                     .clicked()
                 {
-                    self.client_ui.messaging_mode = MessagingMode::Reply(iter_index);
+                    self.client_ui.messaging_mode = MessagingMode::Reply(item.id);
                     ui.close_menu();
                 }
                 ui.separator();
 
                 //Client-side uuid check, there is a check in the server file
-                if item.uuid == self.opened_user_information.uuid
+                //Users with the permission can delete other users' messages too
+                if (item.uuid == self.opened_user_information.uuid
+                    || self.has_permission(Permission::DeleteOthersMessages))
                     && item.message_type != ServerMessageType::Deleted
                 {
-                    //We should only display the `edit` button if its a normal message thus its editable
-                    if let ServerMessageType::Normal(inner) = &item.message_type {
-                        if ui
-                            .add(Button::image_and_text(
-                                egui::include_image!("../../../../../../assets/icons/edit.png"),
-                                "Edit",
-                            ))
-                            .clicked()
-                        {
-                            self.client_ui.messaging_mode = MessagingMode::Edit(iter_index);
-                            self.client_ui.message_buffer = inner.message.to_string();
-                            ui.close_menu();
+                    //We should only display the `edit` button if its our normal message thus its editable
+                    if item.uuid == self.opened_user_information.uuid {
+                        if let ServerMessageType::Normal(inner) = &item.message_type {
+                            if ui
+                                .add(Button::image_and_text(
+                                    egui::include_image!("../../../../../../assets/icons/edit.png"),
+                                    "Edit",
+                                ))
+                                .clicked()
+                            {
+                                self.client_ui.messaging_mode = MessagingMode::Edit(item.id);
+                                self.client_ui.message_buffer = inner.message.to_string();
+                                ui.close_menu();
+                            }
                         }
                     }
 
@@ -438,8 +709,8 @@ impl Application
                         ))
                         .clicked()
                     {
-                        self.send_msg(ClientMessage::construct_client_message_edit(
-                            iter_index,
+                        self.queue_msg(ClientMessage::construct_client_message_edit(
+                            item.id,
                             None,
                             &self.opened_user_information.uuid,
                         ));
@@ -449,9 +720,34 @@ impl Application
                     ui.separator();
                 }
 
+                if self.has_permission(Permission::PinMessages) {
+                    let is_pinned = self
+                        .client_ui
+                        .incoming_messages
+                        .pinned_messages
+                        .contains(&item.id);
+
+                    if ui
+                        .button(match is_pinned {
+                            true => "Unpin",
+                            false => "Pin",
+                        })
+                        .clicked()
+                    {
+                        self.send_msg(ClientMessage::construct_message_pin(
+                            item.id,
+                            !is_pinned,
+                            &self.opened_user_information.uuid,
+                        ));
+                        ui.close_menu();
+                    }
+
+                    ui.separator();
+                }
+
                 ui.menu_button("React", |ui| {
                     if let Some(selected_emoji_name) = self.draw_emoji_selector(ui, ctx) {
-                        self.change_send_emoji(iter_index, selected_emoji_name);
+                        self.change_send_emoji(item.id, selected_emoji_name);
                     }
                 });
 
@@ -471,17 +767,24 @@ impl Application
         }
     }
 
-    /// ```iter_index```: Which message does this emoji change belong to
+    /// ```message_id```: Which message does this emoji change belong to
     /// ```selected_emoji_name```: Which message the user has selected
     /// This function tries to send / delete the emoji based on the passed arg
     /// NOTE: This function will send an emoji deletion message if you have already sent this specific emojio
-    fn change_send_emoji(&mut self, iter_index: usize, selected_emoji_name: String)
+    fn change_send_emoji(&mut self, message_id: MessageId, selected_emoji_name: String)
     {
         //Check if there is an emoji already added
-        if let Some(reaction) = self.client_ui.incoming_messages.reaction_list[iter_index]
-            .message_reactions
-            .iter()
-            .find(|reaction| reaction.emoji_name == selected_emoji_name)
+        if let Some(reaction) = self
+            .client_ui
+            .incoming_messages
+            .message(message_id)
+            .and_then(|message| {
+                message
+                    .reactions
+                    .message_reactions
+                    .iter()
+                    .find(|reaction| reaction.emoji_name == selected_emoji_name)
+            })
         {
             //Check if we have already sent this message, if yes we delete it
             if reaction
@@ -489,26 +792,26 @@ impl Application
                 .iter()
                 .any(|uuid| *uuid == self.opened_user_information.uuid)
             {
-                self.send_msg(ClientMessage::construct_reaction_remove_msg(
+                self.queue_msg(ClientMessage::construct_reaction_remove_msg(
                     selected_emoji_name,
-                    iter_index,
+                    message_id,
                     &self.opened_user_information.uuid,
                 ));
             }
             //If no, we can send it
             else {
-                self.send_msg(ClientMessage::construct_reaction_msg(
+                self.queue_msg(ClientMessage::construct_reaction_msg(
                     selected_emoji_name,
-                    iter_index,
+                    message_id,
                     &self.opened_user_information.uuid,
                 ));
             }
         }
         //If no that means we can add the emoji
         else {
-            self.send_msg(ClientMessage::construct_reaction_msg(
+            self.queue_msg(ClientMessage::construct_reaction_msg(
                 selected_emoji_name,
-                iter_index,
+                message_id,
                 &self.opened_user_information.uuid,
             ));
         }
@@ -1,2 +1,3 @@
+mod direct_message_main;
 mod message_display;
 mod message_main;
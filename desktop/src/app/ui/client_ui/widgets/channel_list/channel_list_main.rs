@@ -0,0 +1,265 @@
+use egui::{Button, Color32, Key, RichText};
+
+use crate::app::backend::{
+    Application, ClientChannelRequest, ClientMessage, ConnectionState, MessagingMode, Permission,
+    ServerMessageType, DEFAULT_CHANNEL_ID,
+};
+
+impl Application
+{
+    /// This function draws the channel list (on the left side), the user can switch between the server's channels and their private conversations here
+    /// Channels can also be created, renamed and deleted from this panel
+    pub fn channel_list(&mut self, ctx: &egui::Context)
+    {
+        egui::SidePanel::left("channel_list")
+            .resizable(false)
+            .show(ctx, |ui| {
+                ui.label(RichText::from("Channels").size(self.font_size).strong());
+
+                ui.separator();
+
+                egui::ScrollArea::vertical()
+                    .id_source("channel_list_area")
+                    .auto_shrink([false, true])
+                    .max_height(ui.available_height() - self.font_size * 3.)
+                    .show(ui, |ui| {
+                        for channel in self.client_ui.incoming_messages.channels.clone() {
+                            //If the user is renaming this channel, display a text editor instead of the channel's name
+                            if let Some((_, new_name)) = self
+                                .client_ui
+                                .channel_rename
+                                .as_mut()
+                                .filter(|(channel_id, _)| *channel_id == channel.id)
+                            {
+                                let text_edit = ui.text_edit_singleline(new_name);
+
+                                if text_edit.lost_focus() {
+                                    //Only send the request if the user pressed enter
+                                    if let Some((channel_id, new_name)) =
+                                        self.client_ui.channel_rename.take()
+                                    {
+                                        if ctx.input(|reader| reader.key_pressed(Key::Enter))
+                                            && !new_name.trim().is_empty()
+                                        {
+                                            self.send_msg(
+                                                ClientMessage::construct_channel_request(
+                                                    ClientChannelRequest::Rename {
+                                                        channel_id,
+                                                        name: new_name,
+                                                    },
+                                                    &self.opened_user_information.uuid,
+                                                ),
+                                            );
+                                        }
+                                    }
+                                }
+                                else {
+                                    text_edit.request_focus();
+                                }
+
+                                continue;
+                            }
+
+                            let unread_messages = self.unread_message_count(&channel.id);
+
+                            let channel_button = ui.add(
+                                Button::new(
+                                    RichText::from(match unread_messages {
+                                        0 => format!("# {}", channel.name),
+                                        _ => format!("# {} ({unread_messages})", channel.name),
+                                    })
+                                    .size(self.font_size / 1.3)
+                                    .color(
+                                        match unread_messages {
+                                            0 => Color32::GRAY,
+                                            _ => Color32::WHITE,
+                                        },
+                                    ),
+                                )
+                                .selected(
+                                    self.client_ui.selected_channel == channel.id
+                                        && self.client_ui.selected_direct_conversation.is_none(),
+                                )
+                                .frame(false),
+                            );
+
+                            if channel_button.clicked() {
+                                self.client_ui.selected_channel = channel.id.clone();
+                                self.client_ui.selected_direct_conversation = None;
+
+                                //Replies and edits are bound to the messages of the channel we have left
+                                self.client_ui.messaging_mode = MessagingMode::Normal;
+                            }
+
+                            //Only the users with the permission can manage the channels (While connected)
+                            if !self.can_manage_channels() {
+                                continue;
+                            }
+
+                            channel_button.context_menu(|ui| {
+                                if ui.button("Rename").clicked() {
+                                    self.client_ui.channel_rename =
+                                        Some((channel.id.clone(), channel.name.clone()));
+
+                                    ui.close_menu();
+                                }
+
+                                //The default channel cannot be deleted
+                                if ui
+                                    .add_enabled(
+                                        channel.id != DEFAULT_CHANNEL_ID,
+                                        Button::new("Delete"),
+                                    )
+                                    .clicked()
+                                {
+                                    self.send_msg(ClientMessage::construct_channel_request(
+                                        ClientChannelRequest::Delete(channel.id.clone()),
+                                        &self.opened_user_information.uuid,
+                                    ));
+
+                                    ui.close_menu();
+                                }
+                            });
+                        }
+
+                        ui.separator();
+
+                        ui.label(
+                            RichText::from("Direct messages")
+                                .size(self.font_size / 1.3)
+                                .strong(),
+                        );
+
+                        for (partner_uuid, username) in self.direct_conversation_partners() {
+                            let unread_messages = self.unread_direct_message_count(&partner_uuid);
+
+                            let conversation_button = ui.add(
+                                Button::new(
+                                    RichText::from(match unread_messages {
+                                        0 => format!("@ {username}"),
+                                        _ => format!("@ {username} ({unread_messages})"),
+                                    })
+                                    .size(self.font_size / 1.3)
+                                    .color(
+                                        match unread_messages {
+                                            0 => Color32::GRAY,
+                                            _ => Color32::WHITE,
+                                        },
+                                    ),
+                                )
+                                .selected(
+                                    self.client_ui.selected_direct_conversation.as_ref()
+                                        == Some(&partner_uuid),
+                                )
+                                .frame(false),
+                            );
+
+                            if conversation_button.clicked() {
+                                self.client_ui.selected_direct_conversation = Some(partner_uuid);
+
+                                //Private messages cannot be replied to or edited
+                                self.client_ui.messaging_mode = MessagingMode::Normal;
+                            }
+                        }
+                    });
+
+                ui.separator();
+
+                //Create a new channel
+                ui.add_enabled_ui(self.can_manage_channels(), |ui| {
+                    ui.horizontal(|ui| {
+                        ui.add(
+                            egui::TextEdit::singleline(&mut self.client_ui.new_channel_name)
+                                .hint_text("New channel")
+                                .desired_width(ui.available_width() / 1.5),
+                        );
+
+                        if ui
+                            .add_enabled(
+                                !self.client_ui.new_channel_name.trim().is_empty(),
+                                Button::new("Create"),
+                            )
+                            .clicked()
+                        {
+                            self.send_msg(ClientMessage::construct_channel_request(
+                                ClientChannelRequest::Create(
+                                    self.client_ui.new_channel_name.clone(),
+                                ),
+                                &self.opened_user_information.uuid,
+                            ));
+
+                            self.client_ui.new_channel_name.clear();
+                        }
+                    });
+                });
+            });
+    }
+
+    /// Returns whether the user can create, rename or delete channels, the cached channels cannot be managed until we have connected
+    fn can_manage_channels(&self) -> bool
+    {
+        self.has_permission(Permission::ManageChannels)
+            && matches!(self.client_connection.state, ConnectionState::Connected(_))
+    }
+
+    /// Returns the uuids and the names of the users the user can have a private conversation with
+    /// These are the users who have connected to the server, and the users the user already has private messages with
+    fn direct_conversation_partners(&self) -> Vec<(String, String)>
+    {
+        let own_uuid = &self.opened_user_information.uuid;
+
+        let mut partners: Vec<(String, String)> = self
+            .client_ui
+            .incoming_messages
+            .connected_clients_profile
+            .iter()
+            .filter(|(uuid, _)| *uuid != own_uuid)
+            .map(|(uuid, profile)| (uuid.clone(), profile.username.clone()))
+            .collect();
+
+        for message in &self.client_ui.incoming_messages.direct_messages {
+            if let Some(partner_uuid) = message.direct_message_partner(own_uuid) {
+                if partners.iter().any(|(uuid, _)| *uuid == partner_uuid) {
+                    continue;
+                }
+
+                //If the user's profile is unknown, we can only display their name if they have sent us a message
+                let username = if message.uuid == partner_uuid {
+                    message.author.clone()
+                }
+                else {
+                    partner_uuid.clone()
+                };
+
+                partners.push((partner_uuid, username));
+            }
+        }
+
+        partners.sort_by(|(_, username), (_, other_username)| username.cmp(other_username));
+
+        partners
+    }
+
+    /// Returns the number of messages the user hasnt seen yet in the channel
+    fn unread_message_count(&self, channel_id: &str) -> usize
+    {
+        let last_seen_id = self
+            .client_ui
+            .last_seen_msg_id
+            .lock()
+            .unwrap()
+            .get(channel_id)
+            .copied();
+
+        self.client_ui
+            .incoming_messages
+            .message_list
+            .iter()
+            .filter(|message| {
+                message.channel_id == channel_id
+                    && message.message_type != ServerMessageType::Deleted
+                    && last_seen_id.map_or(true, |last_seen_id| message.id > last_seen_id)
+            })
+            .count()
+    }
+}
@@ -0,0 +1 @@
+pub mod channel_list_main;
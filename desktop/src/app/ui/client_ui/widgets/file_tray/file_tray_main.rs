@@ -75,7 +75,7 @@ impl Application
                             });
                 });
                 match self.client_ui.messaging_mode {
-                    MessagingMode::Edit(edit_index) => {
+                    MessagingMode::Edit(edit_id) => {
                         if !self.client_ui.files_to_send.is_empty() {
                             ui.separator();
                         }
@@ -85,7 +85,9 @@ impl Application
                                 ui.allocate_ui(vec2(ui.available_width(), self.font_size), |ui|{
                                     //place them in one line
                                     //Selected message
-                                    let selected_message = &self.client_ui.incoming_messages.message_list[edit_index];
+                                    let Some(selected_message) = self.client_ui.incoming_messages.message(edit_id) else {
+                                        return;
+                                    };
                                     ui.horizontal(|ui| {
                                         //Editing: {msg}
                                         ui.label(RichText::from(match &selected_message.message_type {
@@ -125,7 +127,9 @@ impl Application
                                 ui.allocate_ui(vec2(ui.available_width(), self.font_size), |ui|{
                                     //place them in one line
                                     //Selected message
-                                    let selected_message = &self.client_ui.incoming_messages.message_list[replying_to];
+                                    let Some(selected_message) = self.client_ui.incoming_messages.message(replying_to) else {
+                                        return;
+                                    };
                                     ui.horizontal(|ui| {
                                         //Replying to "{author}:"
                                         ui.label(RichText::from(format!("{}:", selected_message.author)).size(self.font_size).weak().color(Color32::LIGHT_GRAY));
@@ -145,12 +149,26 @@ impl Application
                                                 crate::app::backend::ServerMessage::Ban(profile) => {
                                                     format!("{} has been banned", profile.username)
                                                 },
+                                                crate::app::backend::ServerMessage::Kick(profile) => {
+                                                    format!("{} has been kicked", profile.username)
+                                                },
+                                                crate::app::backend::ServerMessage::Mute(profile, _) => {
+                                                    format!("{} has been muted", profile.username)
+                                                },
+                                                crate::app::backend::ServerMessage::Timeout(profile, _) => {
+                                                    format!("{} has been timed out", profile.username)
+                                                },
                                             },
                                             ServerMessageType::VoipEvent(_) => unreachable!(),
                                             ServerMessageType::Edit(_) => unreachable!(),
                                             ServerMessageType::Reaction(_) => unreachable!(),
                                             ServerMessageType::Sync(_) => unreachable!(),
                                                                 ServerMessageType::VoipState(_) => unreachable!(),
+                                                                ServerMessageType::ChannelUpdate(_) => unreachable!(),
+                                                                ServerMessageType::DirectMessage(_) => unreachable!(),
+                                                                ServerMessageType::PinUpdate(_) => unreachable!(),
+                                                                ServerMessageType::RoleUpdate(_) => unreachable!(),
+                                                                ServerMessageType::RequestError(_) => unreachable!(),
                                                             }).size(self.font_size).strong());
                                     });
                                 });
@@ -11,6 +11,7 @@ use egui::{
     vec2, Align, Align2, Area, Color32, FontFamily, FontId, Image, Key, KeyboardShortcut, Layout,
     Modifiers, RichText, Rounding, ScrollArea, Stroke,
 };
+use matthias_server::validation::MAX_MESSAGE_LENGTH;
 use rand::Rng;
 use rfd::FileDialog;
 use std::sync::mpsc;
@@ -52,13 +53,7 @@ impl Application
                 //We will not consume this key since its not sure we can actually edit the message
                 if reader.key_pressed(Key::ArrowUp) {
                     //Iter over all the messages so we will get the latest message sent by us
-                    for (idx, message) in self
-                        .client_ui
-                        .incoming_messages
-                        .message_list
-                        .iter()
-                        .enumerate()
-                    {
+                    for message in self.client_ui.incoming_messages.message_list.iter() {
                         //Validate editable message
                         if let ServerMessageType::Normal(inner) = &message.message_type {
                             if message.uuid == self.opened_user_information.uuid
@@ -67,7 +62,7 @@ impl Application
                                 //If we can edit said message we can safely consume the key
                                 reader.consume_key(Modifiers::NONE, Key::ArrowUp);
 
-                                self.client_ui.messaging_mode = MessagingMode::Edit(idx);
+                                self.client_ui.messaging_mode = MessagingMode::Edit(message.id);
                                 self.client_ui.message_buffer = inner.message.to_string();
                             }
                         }
@@ -85,6 +80,8 @@ impl Application
             .hint_text(format!("Message to: {}", self.client_ui.send_on_ip))
             .desired_width(ui.available_width() - self.client_ui.text_widget_offset * 1.3)
             .desired_rows(0)
+            //The server rejects longer messages
+            .char_limit(MAX_MESSAGE_LENGTH)
             .return_key(KeyboardShortcut::new(Modifiers::SHIFT, Key::Enter))
             .frame(false);
 
@@ -137,7 +134,10 @@ impl Application
                 self.buttons(
                     ui,
                     ctx,
-                    matches!(self.client_connection.state, ConnectionState::Connected(_)),
+                    matches!(
+                        self.client_connection.state,
+                        ConnectionState::Connected(_) | ConnectionState::Reconnecting
+                    ),
                 );
             })
     }
@@ -300,22 +300,37 @@ impl Application
                                 .trim_end_matches('\n')
                                 .is_empty())
                         {
-                            match self.client_ui.messaging_mode {
-                                MessagingMode::Edit(index) => {
-                                    self.send_msg(ClientMessage::construct_client_message_edit(
-                                        index,
-                                        Some(self.client_ui.message_buffer.clone()),
-                                        &self.opened_user_information.uuid,
-                                    ))
-                                },
-                                //If its reply or normal mode we can just send the message and call get_reply_index on it
-                                _ => {
-                                    self.send_msg(ClientMessage::construct_normal_msg(
-                                        &self.client_ui.message_buffer,
-                                        &self.opened_user_information.uuid,
-                                        self.client_ui.messaging_mode.get_reply_index(),
-                                    ))
-                                },
+                            //Private messages cannot be edited or replied to
+                            if let Some(recipient) =
+                                self.client_ui.selected_direct_conversation.clone()
+                            {
+                                self.queue_msg(ClientMessage::construct_direct_msg(
+                                    &self.client_ui.message_buffer,
+                                    &self.opened_user_information.uuid,
+                                    recipient,
+                                ))
+                            }
+                            else {
+                                match self.client_ui.messaging_mode {
+                                    MessagingMode::Edit(message_id) => {
+                                        self.queue_msg(
+                                            ClientMessage::construct_client_message_edit(
+                                                message_id,
+                                                Some(self.client_ui.message_buffer.clone()),
+                                                &self.opened_user_information.uuid,
+                                            ),
+                                        )
+                                    },
+                                    //If its reply or normal mode we can just send the message and call get_reply_id on it
+                                    _ => {
+                                        self.queue_msg(ClientMessage::construct_normal_msg(
+                                            &self.client_ui.message_buffer,
+                                            &self.opened_user_information.uuid,
+                                            self.client_ui.messaging_mode.get_reply_id(),
+                                            self.client_ui.selected_channel.clone(),
+                                        ))
+                                    },
+                                }
                             }
 
                             //Callback
@@ -326,19 +341,19 @@ impl Application
                             );
                         }
 
-                        for file_path in &self.client_ui.files_to_send {
-                            //Check for no user fuckery
-                            if file_path.exists() {
-                                self.send_msg(ClientMessage::construct_file_msg(
-                                    file_path.clone(),
-                                    &self.opened_user_information.uuid,
-                                    self.client_ui.messaging_mode.get_reply_index(),
-                                ));
+                        //Files cannot be sent in private conversations, they are kept until the user goes back to a channel
+                        if self.client_ui.selected_direct_conversation.is_none() {
+                            for file_path in &self.client_ui.files_to_send {
+                                //Check for no user fuckery
+                                if file_path.exists() {
+                                    self.upload_file(file_path.clone(), false);
+                                }
                             }
+
+                            self.client_ui.files_to_send.clear();
                         }
 
                         //clear vectors
-                        self.client_ui.files_to_send.clear();
                         self.client_ui.messaging_mode = MessagingMode::Normal;
                         self.client_ui.message_buffer.clear();
 
@@ -1,3 +1,4 @@
+pub mod channel_list;
 pub mod emoji_tray;
 pub mod file_tray;
 pub mod message_tray;
@@ -1,12 +1,32 @@
 use crate::app::{
-    backend::{display_error_message, ipv4_get, ipv6_get, Application, ClientProfile},
+    backend::{
+        display_error_message, ipv4_get, ipv6_get, Application, ClientProfile, MODERATION_DURATIONS,
+    },
     server,
 };
 use dashmap::DashMap;
 use egui::{vec2, Align, Color32, Context, Image, Layout, RichText};
 use egui_extras::{Column, TableBuilder};
+use matthias_server::{
+    ban::BanRecord,
+    blob_store::format_size,
+    client_writer::OutboundQueueConfig,
+    heartbeat::HeartbeatConfig,
+    quota::{QuotaConfig, RetentionPolicy, RetentionTarget},
+    rate_limit::RateLimitConfig,
+    validation::MAX_UPLOAD_SIZE,
+};
 use tokio_util::sync::CancellationToken;
 
+/// The sizes of the storage limits are set in megabytes
+const MEGABYTE: u64 = 1024 * 1024;
+
+/// The quota a user or the whole server gets when the limit is enabled, in bytes
+const DEFAULT_QUOTA: u64 = 1024 * MEGABYTE;
+
+/// The age of the messages after which they are removed when the retention is enabled, in days
+const DEFAULT_RETENTION_DAYS: u32 = 30;
+
 impl Application
 {
     pub fn server_setup_ui(&mut self, ui: &mut egui::Ui, ctx: &Context)
@@ -46,23 +66,48 @@ impl Application
                         //Move context so we can request_repaint
                         let ctx = ctx.clone();
 
+                        //The server stores the uploaded files here
+                        let storage_path = std::path::PathBuf::from(format!(
+                            "{}\\Matthias\\Server",
+                            env!("APPDATA")
+                        ));
+
+                        //The user hosting the server owns it
+                        let owner_uuid = self.opened_user_information.uuid.clone();
+
+                        let quota_config = self.server_quota_config;
+
                         self.server_has_started = match temp_open_on_port.parse::<i32>() {
                             Ok(port) => {
                                 tokio::spawn(async move {
                                     match server::server_main(
                                         port.to_string(),
                                         server_pw,
+                                        storage_path,
+                                        Some(owner_uuid),
+                                        RateLimitConfig::default(),
+                                        quota_config,
+                                        OutboundQueueConfig::default(),
+                                        HeartbeatConfig::default(),
                                         token,
-                                        connected_clients,
-                                        ctx,
+                                        Some(server::ServerUiSync {
+                                            connected_clients_profile: connected_clients,
+                                            request_repaint: std::sync::Arc::new(move || {
+                                                ctx.request_repaint()
+                                            }),
+                                        }),
                                     )
                                     .await
                                     {
-                                        Ok(shared_fields) => {
+                                        Ok(server_handle) => {
                                             //Assign shared fields
                                             tokio::spawn(async move {
                                                 *shared_fields_clone.lock().unwrap() =
-                                                    shared_fields.lock().await.clone();
+                                                    server_handle
+                                                        .shared_fields
+                                                        .lock()
+                                                        .await
+                                                        .clone();
                                             });
                                         },
                                         Err(err) => {
@@ -87,6 +132,10 @@ impl Application
                     if self.server_req_password {
                         ui.text_edit_singleline(&mut self.server_password);
                     }
+
+                    ui.collapsing("Storage limits", |ui| {
+                        quota_settings_ui(ui, &mut self.server_quota_config);
+                    });
                 }
                 else {
                     ui.label("Server settings");
@@ -217,24 +266,33 @@ impl Application
                                         row.col(|ui| {
                                             ui.centered_and_justified(|ui| {
                                                 if ui.button("Ban").clicked() {
-                                                    let shared_files = self
+                                                    let bans = self
                                                         .client_ui
                                                         .shared_fields
                                                         .lock()
-                                                        .unwrap();
-
-                                                    let mut banned_uuids = shared_files
-                                                        .banned_uuids
-                                                        .try_lock()
-                                                        .unwrap();
-
-                                                    if !banned_uuids
-                                                        .clone()
-                                                        .iter()
-                                                        .any(|item| *item == key)
-                                                    {
-                                                        banned_uuids.push(key.clone());
-                                                    };
+                                                        .unwrap()
+                                                        .bans
+                                                        .clone();
+
+                                                    let uuid = key.clone();
+
+                                                    //The bans are also locked by the server, so they are modified on a thread of their own instead of blocking the Ui
+                                                    tokio::spawn(async move {
+                                                        let mut bans = bans.lock().await;
+
+                                                        if !bans.iter().any(|ban| {
+                                                            ban.uuid.as_ref() == Some(&uuid)
+                                                        }) {
+                                                            //The reason can be edited in the list of the bans
+                                                            bans.push(BanRecord::new(
+                                                                Some(uuid),
+                                                                None,
+                                                                String::new(),
+                                                                None,
+                                                                None,
+                                                            ));
+                                                        };
+                                                    });
                                                 }
                                             });
                                         });
@@ -245,30 +303,342 @@ impl Application
 
                     ui.separator();
 
-                    ui.label("Banneds uuids");
+                    ui.label("Bans");
 
                     let shared_fields = self.client_ui.shared_fields.lock().unwrap();
 
-                    match shared_fields.banned_uuids.try_lock() {
-                        Ok(mut banned_uuids) => {
-                            for (index, uuid) in banned_uuids.clone().iter().enumerate() {
-                                ui.horizontal(|ui| {
-                                    ui.label(uuid);
-                                    if ui
-                                        .button(RichText::from("Unban").color(Color32::RED))
-                                        .clicked()
-                                    {
-                                        banned_uuids.remove(index);
+                    match shared_fields.bans.try_lock() {
+                        Ok(mut bans) => {
+                            //Add a new ban
+                            ui.horizontal(|ui| {
+                                let ban_form = &mut self.server_ban_form;
+
+                                ui.add(
+                                    egui::TextEdit::singleline(&mut ban_form.uuid)
+                                        .hint_text("Uuid")
+                                        .desired_width(ui.available_width() / 5.),
+                                );
+
+                                ui.add(
+                                    egui::TextEdit::singleline(&mut ban_form.subnet)
+                                        .hint_text("Address or subnet")
+                                        .desired_width(ui.available_width() / 5.),
+                                );
+
+                                ui.add(
+                                    egui::TextEdit::singleline(&mut ban_form.reason)
+                                        .hint_text("Reason")
+                                        .desired_width(ui.available_width() / 4.),
+                                );
+
+                                egui::ComboBox::from_id_source("ban_duration")
+                                    .selected_text(
+                                        MODERATION_DURATIONS
+                                            .iter()
+                                            .find(|(_, minutes)| Some(*minutes) == ban_form.minutes)
+                                            .map_or("Permanently", |(duration_name, _)| {
+                                                *duration_name
+                                            }),
+                                    )
+                                    .show_ui(ui, |ui| {
+                                        ui.selectable_value(
+                                            &mut ban_form.minutes,
+                                            None,
+                                            "Permanently",
+                                        );
+
+                                        for (duration_name, minutes) in MODERATION_DURATIONS {
+                                            ui.selectable_value(
+                                                &mut ban_form.minutes,
+                                                Some(minutes),
+                                                duration_name,
+                                            );
+                                        }
+                                    });
+
+                                if ui.button("Ban").clicked() {
+                                    match ban_form.to_ban_record() {
+                                        Ok(ban) => {
+                                            bans.push(ban);
+
+                                            *ban_form = Default::default();
+                                        },
+                                        Err(err) => {
+                                            display_error_message(err, self.toasts.clone());
+                                        },
                                     }
+                                }
+                            });
+
+                            //The table of the connected clients is in the same Ui, so we need to give this table a different id
+                            ui.push_id("ban_list", |ui| {
+                                ui.allocate_ui(vec2(ui.available_width(), 200.), |ui| {
+                                    TableBuilder::new(ui)
+                                        .resizable(true)
+                                        .auto_shrink([false, false])
+                                        .striped(true)
+                                        .columns(
+                                            Column::remainder()
+                                                .at_most(ctx.available_rect().width()),
+                                            6,
+                                        )
+                                        .header(25., |mut row| {
+                                            for title in [
+                                                "Banned",
+                                                "Reason",
+                                                "Issued by",
+                                                "Issued",
+                                                "Expires",
+                                                "Actions",
+                                            ] {
+                                                row.col(|ui| {
+                                                    ui.label(title);
+                                                });
+                                            }
+                                        })
+                                        .body(|mut body| {
+                                            let mut unbanned_index = None;
+
+                                            for (index, ban) in bans.iter_mut().enumerate() {
+                                                body.row(25., |mut row| {
+                                                    //The banned user and address
+                                                    row.col(|ui| {
+                                                        ui.label(
+                                                            [
+                                                                ban.uuid.clone(),
+                                                                ban.subnet.map(|subnet| {
+                                                                    subnet.to_string()
+                                                                }),
+                                                            ]
+                                                            .into_iter()
+                                                            .flatten()
+                                                            .collect::<Vec<String>>()
+                                                            .join(", "),
+                                                        );
+                                                    });
+                                                    //The reason can be edited
+                                                    row.col(|ui| {
+                                                        ui.text_edit_singleline(&mut ban.reason);
+                                                    });
+                                                    row.col(|ui| {
+                                                        ui.label(
+                                                            ban.issued_by.clone().unwrap_or_else(
+                                                                || "Server".to_string(),
+                                                            ),
+                                                        );
+                                                    });
+                                                    row.col(|ui| {
+                                                        ui.label(
+                                                            ban.created_at
+                                                                .format("%Y.%m.%d. %H:%M")
+                                                                .to_string(),
+                                                        );
+                                                    });
+                                                    row.col(|ui| {
+                                                        ui.label(ban.expires_at.map_or(
+                                                            "Never".to_string(),
+                                                            |expires_at| {
+                                                                expires_at
+                                                                    .format("%Y.%m.%d. %H:%M")
+                                                                    .to_string()
+                                                            },
+                                                        ));
+                                                    });
+                                                    row.col(|ui| {
+                                                        if ui
+                                                            .button(
+                                                                RichText::from("Unban")
+                                                                    .color(Color32::RED),
+                                                            )
+                                                            .clicked()
+                                                        {
+                                                            unbanned_index = Some(index);
+                                                        }
+                                                    });
+                                                });
+                                            }
+
+                                            if let Some(index) = unbanned_index {
+                                                bans.remove(index);
+                                            }
+                                        });
                                 });
-                            }
+                            });
                         },
                         Err(err) => {
                             tracing::error!("{}", err);
                         },
                     };
+
+                    ui.separator();
+
+                    ui.label("Storage");
+
+                    //The report is updated by the collector, which may be holding the lock right now
+                    if let Ok(storage_report) = shared_fields.storage_report.try_lock() {
+                        ui.label(format!(
+                            "Stored files: {} ({}{})",
+                            storage_report.blob_count,
+                            format_size(storage_report.stored_bytes),
+                            self.server_quota_config
+                                .total_quota
+                                .map_or(String::new(), |quota| {
+                                    format!(" of {}", format_size(quota))
+                                })
+                        ));
+
+                        //List the users who take up the most space first
+                        let mut usage_by_user: Vec<(&String, &u64)> =
+                            storage_report.usage_by_user.iter().collect();
+
+                        usage_by_user.sort_by(|(_, a), (_, b)| b.cmp(a));
+
+                        for (uuid, used) in usage_by_user {
+                            //Display the username if the user is connected
+                            let username = self
+                                .server_connected_clients_profile
+                                .get(uuid)
+                                .map_or(uuid.clone(), |profile| profile.username.clone());
+
+                            ui.label(format!(
+                                "{username}: {}{}",
+                                format_size(*used),
+                                self.server_quota_config
+                                    .user_quota
+                                    .map_or(String::new(), |quota| {
+                                        format!(" of {}", format_size(quota))
+                                    })
+                            ));
+                        }
+
+                        ui.label(format!(
+                            "Reclaimed space: {} ({} files collected)",
+                            format_size(storage_report.reclaimed_bytes),
+                            storage_report.collected_count
+                        ));
+
+                        if let Some(retention) = self.server_quota_config.retention {
+                            ui.label(format!(
+                                "Expired messages: {} (older than {} days)",
+                                storage_report.expired_count, retention.max_age_days
+                            ));
+                        }
+
+                        ui.label(format!(
+                            "Last collection: {}",
+                            storage_report.last_collection.map_or(
+                                "Never".to_string(),
+                                |last_collection| {
+                                    last_collection.format("%Y.%m.%d. %H:%M").to_string()
+                                }
+                            )
+                        ));
+                    }
                 }
             });
         });
     }
 }
+
+/// Shows the settings of the storage limits, the sizes are set in megabytes
+fn quota_settings_ui(ui: &mut egui::Ui, quota_config: &mut QuotaConfig)
+{
+    optional_size_setting(ui, "Quota per user", &mut quota_config.user_quota);
+    optional_size_setting(ui, "Total quota", &mut quota_config.total_quota);
+
+    size_setting(ui, "Maximum image size", &mut quota_config.max_image_size);
+    size_setting(ui, "Maximum audio size", &mut quota_config.max_audio_size);
+    size_setting(ui, "Maximum file size", &mut quota_config.max_file_size);
+
+    let mut retention_enabled = quota_config.retention.is_some();
+
+    if ui
+        .checkbox(&mut retention_enabled, "Remove old messages")
+        .changed()
+    {
+        quota_config.retention = retention_enabled.then_some(RetentionPolicy {
+            max_age_days: DEFAULT_RETENTION_DAYS,
+            target: RetentionTarget::Messages,
+        });
+    }
+
+    if let Some(retention) = &mut quota_config.retention {
+        ui.horizontal(|ui| {
+            ui.label("Remove");
+
+            egui::ComboBox::from_id_source("retention_target")
+                .selected_text(match retention.target {
+                    RetentionTarget::Messages => "every message",
+                    RetentionTarget::Attachments => "the attachments",
+                })
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(
+                        &mut retention.target,
+                        RetentionTarget::Messages,
+                        "every message",
+                    );
+                    ui.selectable_value(
+                        &mut retention.target,
+                        RetentionTarget::Attachments,
+                        "the attachments",
+                    );
+                });
+
+            ui.label("after");
+
+            ui.add(
+                egui::DragValue::new(&mut retention.max_age_days)
+                    .clamp_range(1..=3650)
+                    .suffix(" days"),
+            );
+        });
+    }
+}
+
+/// Shows a size setting in megabytes, the size is stored in bytes
+fn size_setting(ui: &mut egui::Ui, label: &str, size: &mut u64)
+{
+    ui.horizontal(|ui| {
+        ui.label(label);
+
+        let mut megabytes = *size / MEGABYTE;
+
+        if ui
+            .add(
+                egui::DragValue::new(&mut megabytes)
+                    .clamp_range(1..=MAX_UPLOAD_SIZE as u64 / MEGABYTE)
+                    .suffix(" MB"),
+            )
+            .changed()
+        {
+            *size = megabytes * MEGABYTE;
+        }
+    });
+}
+
+/// Shows a size setting which can be turned off, the size is ```None``` if its turned off
+fn optional_size_setting(ui: &mut egui::Ui, label: &str, size: &mut Option<u64>)
+{
+    ui.horizontal(|ui| {
+        let mut enabled = size.is_some();
+
+        if ui.checkbox(&mut enabled, label).changed() {
+            *size = enabled.then_some(DEFAULT_QUOTA);
+        }
+
+        if let Some(size) = size {
+            let mut megabytes = *size / MEGABYTE;
+
+            if ui
+                .add(
+                    egui::DragValue::new(&mut megabytes)
+                        .clamp_range(1..=u32::MAX as u64)
+                        .suffix(" MB"),
+                )
+                .changed()
+            {
+                *size = megabytes * MEGABYTE;
+            }
+        }
+    });
+}
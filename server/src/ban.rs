@@ -0,0 +1,380 @@
+use std::{
+    fmt::Display,
+    net::{IpAddr, SocketAddr},
+    str::FromStr,
+};
+
+use anyhow::{ensure, Error};
+use chrono::{DateTime, Duration, Utc};
+
+/// A range of addresses a ban can apply to, a single address is stored as a subnet with the full prefix length
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct Subnet
+{
+    /// The address of the network
+    pub address: IpAddr,
+
+    /// The number of leading bits an address has to share with the network's address to be inside the subnet
+    pub prefix_length: u8,
+}
+
+impl Subnet
+{
+    /// Creates a subnet which only contains the provided address
+    pub fn single(address: IpAddr) -> Self
+    {
+        let address = canonical_address(address);
+
+        Self {
+            address,
+            prefix_length: max_prefix_length(address),
+        }
+    }
+
+    /// Returns whether the address is inside the subnet
+    /// IPv4 addresses mapped to IPv6 (```::ffff:a.b.c.d```) are treated as IPv4 addresses
+    pub fn contains(&self, address: IpAddr) -> bool
+    {
+        match (canonical_address(self.address), canonical_address(address)) {
+            (IpAddr::V4(network), IpAddr::V4(address)) => {
+                prefix_matches(
+                    u32::from(network) as u128,
+                    u32::from(address) as u128,
+                    self.prefix_length,
+                    32,
+                )
+            },
+            (IpAddr::V6(network), IpAddr::V6(address)) => {
+                prefix_matches(
+                    u128::from(network),
+                    u128::from(address),
+                    self.prefix_length,
+                    128,
+                )
+            },
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for Subnet
+{
+    type Err = Error;
+
+    /// Parses a single address (```192.168.0.1```) or a subnet in CIDR notation (```192.168.0.0/24```)
+    fn from_str(s: &str) -> Result<Self, Self::Err>
+    {
+        match s.trim().split_once('/') {
+            Some((address, prefix_length)) => {
+                let address = canonical_address(address.trim().parse()?);
+                let prefix_length: u8 = prefix_length.trim().parse()?;
+
+                ensure!(
+                    prefix_length <= max_prefix_length(address),
+                    "Invalid prefix length: {prefix_length}"
+                );
+
+                Ok(Self {
+                    address,
+                    prefix_length,
+                })
+            },
+            None => Ok(Self::single(s.trim().parse()?)),
+        }
+    }
+}
+
+impl Display for Subnet
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        if self.prefix_length == max_prefix_length(self.address) {
+            write!(f, "{}", self.address)
+        }
+        else {
+            write!(f, "{}/{}", self.address, self.prefix_length)
+        }
+    }
+}
+
+/// Returns the IPv4 address if the address is an IPv4 address mapped to IPv6, so that the same client is always matched the same way
+fn canonical_address(address: IpAddr) -> IpAddr
+{
+    match address {
+        IpAddr::V6(address_v6) => {
+            address_v6
+                .to_ipv4_mapped()
+                .map(IpAddr::V4)
+                .unwrap_or(address)
+        },
+        IpAddr::V4(_) => address,
+    }
+}
+
+/// Returns the length of the address in bits
+fn max_prefix_length(address: IpAddr) -> u8
+{
+    match address {
+        IpAddr::V4(_) => 32,
+        IpAddr::V6(_) => 128,
+    }
+}
+
+/// Returns whether the first ```prefix_length``` bits of the two addresses (which are ```address_length``` bits long) match
+fn prefix_matches(network: u128, address: u128, prefix_length: u8, address_length: u8) -> bool
+{
+    let prefix_length = prefix_length.min(address_length);
+
+    //Every address is inside a subnet with a prefix length of 0, this also avoids shifting by the whole width of the integer
+    if prefix_length == 0 {
+        return true;
+    }
+
+    let shift = address_length - prefix_length;
+
+    (network >> shift) == (address >> shift)
+}
+
+/// A ban issued from the server's settings or by a user with the permission
+/// A ban can target a user (by their uuid), an address or a subnet, or both of them
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub struct BanRecord
+{
+    /// The uuid of the banned user, this is ```None``` if only an address is banned
+    pub uuid: Option<String>,
+
+    /// The banned address or subnet, this is ```None``` if only the user is banned
+    pub subnet: Option<Subnet>,
+
+    /// Why the ban has been issued
+    pub reason: String,
+
+    /// The uuid of the user who has issued the ban, this is ```None``` if it was issued from the server's settings
+    pub issued_by: Option<String>,
+
+    /// The date when the ban was issued
+    pub created_at: DateTime<Utc>,
+
+    /// The date when the ban expires, this is ```None``` if the ban is permanent
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+impl BanRecord
+{
+    /// Creates a ban which is issued now, if a duration is provided the ban expires after it
+    pub fn new(
+        uuid: Option<String>,
+        subnet: Option<Subnet>,
+        reason: String,
+        issued_by: Option<String>,
+        duration: Option<Duration>,
+    ) -> Self
+    {
+        let created_at = Utc::now();
+
+        Self {
+            uuid,
+            subnet,
+            reason,
+            issued_by,
+            created_at,
+            expires_at: duration.map(|duration| created_at + duration),
+        }
+    }
+
+    /// Returns whether the ban has expired
+    pub fn is_expired(&self) -> bool
+    {
+        self.expires_at
+            .is_some_and(|expires_at| expires_at <= Utc::now())
+    }
+
+    /// Returns whether the ban applies to the user (identified by their uuid) connecting from the address
+    pub fn applies_to(&self, uuid: &str, address: Option<SocketAddr>) -> bool
+    {
+        if self.is_expired() {
+            return false;
+        }
+
+        let uuid_matches = self.uuid.as_deref() == Some(uuid);
+
+        let address_matches = self
+            .subnet
+            .zip(address)
+            .is_some_and(|(subnet, address)| subnet.contains(address.ip()));
+
+        uuid_matches || address_matches
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use std::net::{Ipv4Addr, Ipv6Addr};
+
+    use super::*;
+
+    fn address(address: &str) -> IpAddr
+    {
+        address.parse().unwrap()
+    }
+
+    fn socket_address(address: &str) -> Option<SocketAddr>
+    {
+        Some(SocketAddr::new(address.parse().unwrap(), 8080))
+    }
+
+    #[test]
+    fn parses_addresses_and_subnets()
+    {
+        assert_eq!(
+            "192.168.0.1".parse::<Subnet>().unwrap(),
+            Subnet {
+                address: address("192.168.0.1"),
+                prefix_length: 32,
+            }
+        );
+        assert_eq!(
+            " 192.168.0.0 / 24 ".parse::<Subnet>().unwrap(),
+            Subnet {
+                address: address("192.168.0.0"),
+                prefix_length: 24,
+            }
+        );
+        assert_eq!(
+            "fe80::/10".parse::<Subnet>().unwrap(),
+            Subnet {
+                address: address("fe80::"),
+                prefix_length: 10,
+            }
+        );
+
+        //The IPv4 addresses mapped to IPv6 are stored as IPv4 addresses
+        assert_eq!(
+            "::ffff:10.0.0.1".parse::<Subnet>().unwrap(),
+            Subnet::single(IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)))
+        );
+
+        //The prefix cannot be longer than the address
+        assert!("10.0.0.0/33".parse::<Subnet>().is_err());
+        assert!("::/129".parse::<Subnet>().is_err());
+        assert!("10.0.0.0/-1".parse::<Subnet>().is_err());
+        assert!("10.0.0/8".parse::<Subnet>().is_err());
+        assert!("".parse::<Subnet>().is_err());
+
+        //The subnets are displayed in the same format they are parsed from
+        for subnet in ["192.168.0.1", "192.168.0.0/24", "fe80::/10", "::1"] {
+            assert_eq!(subnet.parse::<Subnet>().unwrap().to_string(), subnet);
+        }
+    }
+
+    #[test]
+    fn subnet_contains_the_addresses_sharing_its_prefix()
+    {
+        let subnet: Subnet = "192.168.0.0/24".parse().unwrap();
+
+        assert!(subnet.contains(address("192.168.0.0")));
+        assert!(subnet.contains(address("192.168.0.255")));
+        assert!(!subnet.contains(address("192.168.1.0")));
+
+        let single = Subnet::single(address("10.0.0.1"));
+
+        assert!(single.contains(address("10.0.0.1")));
+        assert!(!single.contains(address("10.0.0.2")));
+
+        let subnet: Subnet = "2001:db8::/32".parse().unwrap();
+
+        assert!(subnet.contains(address("2001:db8:ffff::1")));
+        assert!(!subnet.contains(address("2001:db9::1")));
+
+        //The address families never match each other
+        assert!(!subnet.contains(address("32.1.13.184")));
+        assert!(!Subnet::single(IpAddr::V6(Ipv6Addr::LOCALHOST))
+            .contains(IpAddr::V4(Ipv4Addr::LOCALHOST)));
+    }
+
+    #[test]
+    fn zero_prefix_contains_every_address_of_its_family()
+    {
+        let every_v4: Subnet = "0.0.0.0/0".parse().unwrap();
+
+        assert!(every_v4.contains(address("0.0.0.0")));
+        assert!(every_v4.contains(address("255.255.255.255")));
+        assert!(!every_v4.contains(address("::1")));
+
+        let every_v6: Subnet = "::/0".parse().unwrap();
+
+        assert!(every_v6.contains(address("::")));
+        assert!(every_v6.contains(address("ffff:ffff:ffff:ffff:ffff:ffff:ffff:ffff")));
+    }
+
+    #[test]
+    fn mapped_addresses_are_matched_as_ipv4()
+    {
+        let subnet: Subnet = "10.0.0.0/8".parse().unwrap();
+
+        assert!(subnet.contains(address("::ffff:10.1.2.3")));
+        assert!(!subnet.contains(address("::ffff:11.1.2.3")));
+
+        //A subnet which has been constructed with a mapped address matches the IPv4 clients too
+        let mapped = Subnet {
+            address: address("::ffff:10.0.0.1"),
+            prefix_length: 32,
+        };
+
+        assert!(mapped.contains(address("10.0.0.1")));
+        assert!(!mapped.contains(address("10.0.0.2")));
+    }
+
+    #[test]
+    fn prefix_is_clamped_to_the_address_length()
+    {
+        assert!(prefix_matches(0, u128::MAX, 0, 32));
+        assert!(prefix_matches(0b1010, 0b1011, 31, 32));
+        assert!(!prefix_matches(0b1010, 0b1011, 32, 32));
+
+        //A prefix longer than the address compares the whole address, instead of overflowing the shift
+        assert!(prefix_matches(0b1010, 0b1010, 40, 32));
+        assert!(!prefix_matches(0b1010, 0b1011, 200, 128));
+        assert!(prefix_matches(u128::MAX, u128::MAX, 200, 128));
+    }
+
+    #[test]
+    fn expired_bans_dont_apply()
+    {
+        let mut ban = BanRecord::new(
+            Some("user".to_string()),
+            Some("10.0.0.0/8".parse().unwrap()),
+            String::new(),
+            None,
+            Some(Duration::minutes(10)),
+        );
+
+        assert!(!ban.is_expired());
+        assert!(ban.applies_to("user", None));
+        assert!(ban.applies_to("other user", socket_address("10.1.2.3")));
+        assert!(ban.applies_to("other user", socket_address("::ffff:10.1.2.3")));
+        assert!(!ban.applies_to("other user", socket_address("11.1.2.3")));
+        assert!(!ban.applies_to("other user", None));
+
+        ban.expires_at = Some(Utc::now() - Duration::seconds(1));
+
+        assert!(ban.is_expired());
+        assert!(!ban.applies_to("user", socket_address("10.1.2.3")));
+
+        //The permanent bans never expire
+        ban.expires_at = None;
+
+        assert!(!ban.is_expired());
+        assert!(ban.applies_to("user", None));
+    }
+
+    #[test]
+    fn user_bans_dont_apply_to_the_address()
+    {
+        let ban = BanRecord::new(Some("user".to_string()), None, String::new(), None, None);
+
+        assert!(ban.applies_to("user", socket_address("10.0.0.1")));
+        assert!(!ban.applies_to("other user", socket_address("10.0.0.1")));
+    }
+}
@@ -0,0 +1,704 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs, io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+
+use crate::protocol::{
+    ServerAudioUpload, ServerFileUpload, ServerImageUpload, ServerMessageType, ServerOutput,
+};
+
+/// The name of the folder the blobs are stored in (Inside the server's storage folder)
+pub const BLOBS_FOLDER_NAME: &str = "blobs";
+
+/// How long a blob is kept after its last reference has been removed, so that the clients who are still downloading it can finish
+pub const BLOB_GRACE_PERIOD: Duration = Duration::from_secs(10 * 60);
+
+/// How often the collector looks for the blobs which arent referred to anymore
+pub const BLOB_COLLECTION_INTERVAL: Duration = Duration::from_secs(60);
+
+/// A stored file, the file itself is named after its hash in the blobs folder
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Default)]
+pub struct Blob
+{
+    /// The name of the file when it was first uploaded
+    pub name: Option<String>,
+
+    /// The extension of the file when it was first uploaded
+    pub extension: Option<String>,
+
+    /// The size of the file in bytes
+    pub size: u64,
+
+    /// The uuid of the user who has first uploaded the file, the blob counts towards their quota
+    pub uploader: Option<String>,
+
+    /// The number of messages which refer to the blob
+    pub references: usize,
+
+    /// The date when the last message referring to the blob was deleted, the blob is collected once ```BLOB_GRACE_PERIOD``` has passed
+    pub unreferenced_since: Option<DateTime<Utc>>,
+}
+
+impl Blob
+{
+    /// Returns the name the file was uploaded with, the extension of the name is used by the clients when saving the file
+    pub fn file_name(&self) -> PathBuf
+    {
+        PathBuf::from(format!(
+            "{}.{}",
+            self.name.clone().unwrap_or_default(),
+            self.extension.clone().unwrap_or_default()
+        ))
+    }
+}
+
+/// The uploaded files (files, images and audio) are stored by their hash, so that a file uploaded multiple times is only stored once
+/// Every blob counts the messages which refer to it, the blobs nobody refers to are removed by the collector
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct BlobStore
+{
+    /// The stored blobs, the key is the hash of the blob (which is the signature of the uploaded file)
+    pub blobs: HashMap<String, Blob>,
+
+    /// The hashes of the blobs which have changed since the store was last saved (```BlobStore::take_changes```)
+    #[serde(skip)]
+    changed: HashSet<String>,
+
+    /// The space reserved by the uploads which have been verified but havent been stored yet, the key is the uploader's uuid and the hash of the file
+    /// The reserved space counts towards the quotas, so that the uploads committed at the same time cannot exceed them together
+    #[serde(skip)]
+    reservations: HashMap<(String, String), u64>,
+
+    /// The number of uploads whose files are being moved into the store, the key is the hash of the file (```BlobStore::begin_insert```)
+    /// The collector leaves these blobs alone, so that it doesnt delete a file which has just been uploaded again
+    #[serde(skip)]
+    inserting: HashMap<String, usize>,
+
+    /// The hashes of the blobs whose files are being deleted by the collector (```BlobStore::take_collectable```)
+    #[serde(skip)]
+    collecting: HashSet<String>,
+}
+
+impl BlobStore
+{
+    /// Returns the path of the blob in the storage folder
+    pub fn path(storage_path: &Path, hash: &str) -> PathBuf
+    {
+        storage_path.join(BLOBS_FOLDER_NAME).join(hash)
+    }
+
+    /// Returns the blob, if it is stored
+    pub fn get(&self, hash: &str) -> Option<&Blob>
+    {
+        self.blobs.get(hash)
+    }
+
+    /// Marks the blob as being inserted, so that the collector leaves its file alone while the upload is moved into the store (```BlobStore::store_file```)
+    /// Returns false if the blob's file is being deleted by the collector, the upload can only be stored once it is gone
+    pub fn begin_insert(&mut self, hash: &str) -> bool
+    {
+        if self.collecting.contains(hash) {
+            return false;
+        }
+
+        *self.inserting.entry(hash.to_string()).or_default() += 1;
+
+        true
+    }
+
+    /// Moves the uploaded file into the store, if the same file is already stored the upload is removed instead
+    /// Returns the size of the stored file, this only touches the files so the store doesnt have to be locked while its running
+    pub fn store_file(storage_path: &Path, hash: &str, uploaded_path: &Path) -> io::Result<u64>
+    {
+        let blob_path = Self::path(storage_path, hash);
+
+        if blob_path.exists() {
+            if uploaded_path.exists() {
+                fs::remove_file(uploaded_path)?;
+            }
+        }
+        else {
+            fs::create_dir_all(storage_path.join(BLOBS_FOLDER_NAME))?;
+            fs::rename(uploaded_path, &blob_path)?;
+        }
+
+        Ok(fs::metadata(&blob_path)?.len())
+    }
+
+    /// Adds the blob whose file has been stored (```BlobStore::store_file```) and refers to it, if it is already stored it is only referred to again
+    /// This ends the insert started by ```BlobStore::begin_insert```, and releases the space reserved for the upload
+    pub fn insert(
+        &mut self,
+        hash: &str,
+        size: u64,
+        uploader: &str,
+        name: Option<String>,
+        extension: Option<String>,
+    )
+    {
+        self.end_insert(uploader, hash);
+
+        self.blobs.entry(hash.to_string()).or_insert_with(|| {
+            Blob {
+                name,
+                extension,
+                size,
+                uploader: Some(uploader.to_string()),
+                ..Default::default()
+            }
+        });
+
+        //Adding the reference marks the blob as changed
+        self.add_reference(hash);
+    }
+
+    /// Ends the insert started by ```BlobStore::begin_insert``` without storing the blob, because its file couldnt be stored
+    /// This releases the space reserved for the upload too
+    pub fn end_insert(&mut self, uploader: &str, hash: &str)
+    {
+        self.release(uploader, hash);
+
+        if let Some(count) = self.inserting.get_mut(hash) {
+            *count -= 1;
+
+            if *count == 0 {
+                self.inserting.remove(hash);
+            }
+        }
+    }
+
+    /// Adds a reference to the blob, this also saves it from being collected
+    pub fn add_reference(&mut self, hash: &str)
+    {
+        if let Some(blob) = self.blobs.get_mut(hash) {
+            blob.references += 1;
+            blob.unreferenced_since = None;
+
+            self.changed.insert(hash.to_string());
+        }
+    }
+
+    /// Removes a reference from the blob, once there are no references left the blob is collected after the grace period
+    pub fn remove_reference(&mut self, hash: &str)
+    {
+        if let Some(blob) = self.blobs.get_mut(hash) {
+            blob.references = blob.references.saturating_sub(1);
+
+            if blob.references == 0 {
+                blob.unreferenced_since = Some(Utc::now());
+            }
+
+            self.changed.insert(hash.to_string());
+        }
+    }
+
+    /// Counts the references of the blobs from the messages, this is done when loading the store so that the counts always match the messages
+    /// The blobs whose uploader is unknown (They were stored before the uploaders were saved) are assigned to the author of the first message referring to them
+    pub fn recount<'a>(&mut self, messages: impl IntoIterator<Item = &'a ServerOutput>)
+    {
+        let mut references: HashMap<&str, (usize, &str)> = HashMap::new();
+
+        for message in messages {
+            if let Some(hash) = blob_reference(&message.message_type) {
+                references.entry(hash).or_insert((0, &message.uuid)).0 += 1;
+            }
+        }
+
+        for (hash, blob) in self.blobs.iter_mut() {
+            let (count, first_author) = references.get(hash.as_str()).copied().unwrap_or_default();
+
+            blob.references = count;
+
+            if blob.uploader.is_none() && count > 0 {
+                blob.uploader = Some(first_author.to_string());
+            }
+
+            if blob.references == 0 {
+                blob.unreferenced_since.get_or_insert_with(Utc::now);
+            }
+            else {
+                blob.unreferenced_since = None;
+            }
+        }
+    }
+
+    /// Removes the blobs which havent been referred to for longer than the grace period, their files have to be deleted afterwards (```BlobStore::delete_files```)
+    /// The removed blobs cannot be inserted again until the collection is finished (```BlobStore::finish_collection```)
+    pub fn take_collectable(&mut self) -> Vec<(String, Blob)>
+    {
+        let collectable: Vec<String> = self
+            .blobs
+            .iter()
+            .filter(|(hash, blob)| {
+                //The blobs which are being uploaded again are kept, they will be referred to once they are inserted
+                !self.inserting.contains_key(*hash)
+                    && blob.unreferenced_since.is_some_and(|unreferenced_since| {
+                        (Utc::now() - unreferenced_since)
+                            .to_std()
+                            .is_ok_and(|elapsed| elapsed >= BLOB_GRACE_PERIOD)
+                    })
+            })
+            .map(|(hash, _)| hash.clone())
+            .collect();
+
+        collectable
+            .into_iter()
+            .filter_map(|hash| {
+                let blob = self.blobs.remove(&hash)?;
+
+                self.changed.insert(hash.clone());
+                self.collecting.insert(hash.clone());
+
+                Some((hash, blob))
+            })
+            .collect()
+    }
+
+    /// Deletes the files of the collected blobs, returns the hashes of the blobs whose files couldnt be deleted
+    /// This only touches the files, so the store doesnt have to be locked while its running
+    pub fn delete_files(storage_path: &Path, hashes: &[String]) -> HashSet<String>
+    {
+        let mut failed = HashSet::new();
+
+        for hash in hashes {
+            let blob_path = Self::path(storage_path, hash);
+
+            //The blob is forgotten even if its file is already missing, since there is nothing to serve anymore
+            if blob_path.exists() {
+                if let Err(err) = fs::remove_file(&blob_path) {
+                    tracing::error!("Failed to delete blob {hash}: {err}");
+
+                    failed.insert(hash.clone());
+                }
+            }
+        }
+
+        failed
+    }
+
+    /// Finishes the collection started by ```BlobStore::take_collectable```, the blobs whose files couldnt be deleted are put back into the store
+    /// Returns the number of deleted blobs and the reclaimed space in bytes
+    pub fn finish_collection(
+        &mut self,
+        collected: Vec<(String, Blob)>,
+        failed: &HashSet<String>,
+    ) -> (usize, u64)
+    {
+        let mut collected_count = 0;
+        let mut reclaimed_bytes = 0;
+
+        for (hash, blob) in collected {
+            self.collecting.remove(&hash);
+
+            if failed.contains(&hash) {
+                self.changed.insert(hash.clone());
+                self.blobs.insert(hash, blob);
+            }
+            else {
+                collected_count += 1;
+                reclaimed_bytes += blob.size;
+            }
+        }
+
+        (collected_count, reclaimed_bytes)
+    }
+
+    /// Returns the blobs which have changed since this was last called, the removed blobs are ```None```
+    pub fn take_changes(&mut self) -> Vec<(String, Option<Blob>)>
+    {
+        self.changed
+            .drain()
+            .map(|hash| {
+                let blob = self.blobs.get(&hash).cloned();
+
+                (hash, blob)
+            })
+            .collect()
+    }
+
+    /// Reserves space for the upload of the user (identified by their uuid) until it is stored or released
+    pub fn reserve(&mut self, uploader: &str, hash: &str, size: u64)
+    {
+        self.reservations
+            .insert((uploader.to_string(), hash.to_string()), size);
+    }
+
+    /// Releases the space reserved for the upload of the user (identified by their uuid)
+    pub fn release(&mut self, uploader: &str, hash: &str)
+    {
+        self.reservations
+            .remove(&(uploader.to_string(), hash.to_string()));
+    }
+
+    /// Returns the space reserved for the uploads of the user in bytes
+    pub fn user_reserved(&self, uuid: &str) -> u64
+    {
+        self.reservations
+            .iter()
+            .filter(|((uploader, _), _)| uploader == uuid)
+            .map(|(_, size)| size)
+            .sum()
+    }
+
+    /// Returns the space reserved for the uploads of every user in bytes
+    pub fn reserved(&self) -> u64
+    {
+        self.reservations.values().sum()
+    }
+
+    /// Returns the size of the blobs uploaded by the user in bytes
+    pub fn user_usage(&self, uuid: &str) -> u64
+    {
+        self.blobs
+            .values()
+            .filter(|blob| blob.uploader.as_deref() == Some(uuid))
+            .map(|blob| blob.size)
+            .sum()
+    }
+
+    /// Returns the size of the blobs uploaded by every user in bytes, the key is the uuid of the uploader
+    pub fn usage_by_user(&self) -> HashMap<String, u64>
+    {
+        let mut usage_by_user: HashMap<String, u64> = HashMap::new();
+
+        for blob in self.blobs.values() {
+            if let Some(uploader) = &blob.uploader {
+                *usage_by_user.entry(uploader.clone()).or_default() += blob.size;
+            }
+        }
+
+        usage_by_user
+    }
+
+    /// Returns the number of stored blobs and their size in bytes
+    pub fn usage(&self) -> (usize, u64)
+    {
+        (
+            self.blobs.len(),
+            self.blobs.values().map(|blob| blob.size).sum(),
+        )
+    }
+}
+
+/// The state of the blob store, this is shown in the server's Ui
+#[derive(Debug, Clone, Default)]
+pub struct StorageReport
+{
+    /// The number of stored blobs
+    pub blob_count: usize,
+
+    /// The size of the stored blobs in bytes
+    pub stored_bytes: u64,
+
+    /// The size of the blobs uploaded by every user in bytes, the key is the uuid of the uploader
+    pub usage_by_user: HashMap<String, u64>,
+
+    /// The number of messages the retention policy has removed since the server has started
+    pub expired_count: usize,
+
+    /// The number of blobs the collector has deleted since the server has started
+    pub collected_count: usize,
+
+    /// The space the collector has reclaimed since the server has started, in bytes
+    pub reclaimed_bytes: u64,
+
+    /// The date of the last collection
+    pub last_collection: Option<DateTime<Utc>>,
+}
+
+/// Returns the hash of the blob the message refers to, returns ```None``` if the message isnt an upload
+pub fn blob_reference(message_type: &ServerMessageType) -> Option<&str>
+{
+    match message_type {
+        ServerMessageType::Upload(ServerFileUpload { signature, .. })
+        | ServerMessageType::Image(ServerImageUpload { signature })
+        | ServerMessageType::Audio(ServerAudioUpload { signature, .. }) => Some(signature),
+        _ => None,
+    }
+}
+
+/// Formats a size in bytes to a human readable string (```1.5 MB```)
+pub fn format_size(bytes: u64) -> String
+{
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+
+    let mut size = bytes as f64;
+    let mut unit = 0;
+
+    while size >= 1024. && unit < UNITS.len() - 1 {
+        size /= 1024.;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{bytes} {}", UNITS[unit])
+    }
+    else {
+        format!("{size:.1} {}", UNITS[unit])
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::{protocol::DEFAULT_CHANNEL_ID, server::server_output, test_util::TestStorage};
+
+    impl TestStorage
+    {
+        /// Writes an uploaded file, returns its path
+        fn upload(&self, name: &str, contents: &[u8]) -> PathBuf
+        {
+            let path = self.0.join(name);
+
+            fs::write(&path, contents).unwrap();
+
+            path
+        }
+
+        /// Uploads the file and moves it into the store, the way the server stores the uploads
+        fn insert(&self, store: &mut BlobStore, hash: &str, contents: &[u8], uploader: &str)
+        {
+            let upload = self.upload(&format!("{hash}-{uploader}.part"), contents);
+
+            assert!(store.begin_insert(hash));
+
+            let size = BlobStore::store_file(&self.0, hash, &upload).unwrap();
+
+            store.insert(hash, size, uploader, None, None);
+        }
+
+        /// Collects the expired blobs, the way the server's collector does
+        fn collect(&self, store: &mut BlobStore) -> (usize, u64)
+        {
+            let collected = store.take_collectable();
+
+            let hashes: Vec<String> = collected.iter().map(|(hash, _)| hash.clone()).collect();
+
+            let failed = BlobStore::delete_files(&self.0, &hashes);
+
+            store.finish_collection(collected, &failed)
+        }
+    }
+
+    fn expire(store: &mut BlobStore, hash: &str)
+    {
+        let blob = store.blobs.get_mut(hash).unwrap();
+
+        blob.unreferenced_since =
+            Some(Utc::now() - chrono::Duration::from_std(BLOB_GRACE_PERIOD).unwrap());
+    }
+
+    #[test]
+    fn insert_deduplicates_and_counts_references()
+    {
+        let storage = TestStorage::new();
+        let mut store = BlobStore::default();
+
+        let first_upload = storage.upload("first.part", b"hello");
+
+        assert!(store.begin_insert("hash"));
+        assert_eq!(
+            BlobStore::store_file(&storage.0, "hash", &first_upload).unwrap(),
+            5
+        );
+        store.insert("hash", 5, "uploader", None, None);
+
+        //The same file is only stored once, the second upload is discarded
+        let second_upload = storage.upload("second.part", b"hello");
+
+        assert!(store.begin_insert("hash"));
+        assert_eq!(
+            BlobStore::store_file(&storage.0, "hash", &second_upload).unwrap(),
+            5
+        );
+        store.insert("hash", 5, "other", None, None);
+
+        let blob = store.get("hash").unwrap();
+
+        assert_eq!(blob.references, 2);
+        assert_eq!(blob.size, 5);
+        assert_eq!(blob.uploader.as_deref(), Some("uploader"));
+        assert!(BlobStore::path(&storage.0, "hash").exists());
+        assert!(!first_upload.exists());
+        assert!(!second_upload.exists());
+
+        assert_eq!(store.user_usage("uploader"), 5);
+        assert_eq!(store.user_usage("other"), 0);
+        assert_eq!(store.usage(), (1, 5));
+    }
+
+    #[test]
+    fn references_are_removed_until_the_blob_is_unreferenced()
+    {
+        let storage = TestStorage::new();
+        let mut store = BlobStore::default();
+
+        storage.insert(&mut store, "hash", b"a", "uploader");
+        store.add_reference("hash");
+
+        store.remove_reference("hash");
+
+        assert_eq!(store.get("hash").unwrap().references, 1);
+        assert!(store.get("hash").unwrap().unreferenced_since.is_none());
+
+        store.remove_reference("hash");
+        store.remove_reference("hash");
+
+        assert_eq!(store.get("hash").unwrap().references, 0);
+        assert!(store.get("hash").unwrap().unreferenced_since.is_some());
+
+        //A new reference saves the blob from being collected
+        store.add_reference("hash");
+
+        assert!(store.get("hash").unwrap().unreferenced_since.is_none());
+    }
+
+    #[test]
+    fn collects_only_after_the_grace_period()
+    {
+        let storage = TestStorage::new();
+        let mut store = BlobStore::default();
+
+        storage.insert(&mut store, "kept", b"kept", "uploader");
+        storage.insert(&mut store, "recent", b"recent", "uploader");
+        storage.insert(&mut store, "expired", b"expired", "uploader");
+
+        store.remove_reference("recent");
+        store.remove_reference("expired");
+
+        expire(&mut store, "expired");
+
+        assert_eq!(storage.collect(&mut store), (1, 7));
+
+        assert!(store.get("kept").is_some());
+        assert!(store.get("recent").is_some());
+        assert!(store.get("expired").is_none());
+        assert!(!BlobStore::path(&storage.0, "expired").exists());
+        assert!(BlobStore::path(&storage.0, "recent").exists());
+
+        //A referenced blob is never collected, even if it was unreferenced for a long time before
+        expire(&mut store, "recent");
+        store.add_reference("recent");
+
+        assert_eq!(storage.collect(&mut store), (0, 0));
+    }
+
+    #[test]
+    fn blobs_being_inserted_are_not_collected()
+    {
+        let storage = TestStorage::new();
+        let mut store = BlobStore::default();
+
+        storage.insert(&mut store, "hash", b"hello", "uploader");
+
+        store.remove_reference("hash");
+
+        expire(&mut store, "hash");
+
+        //The same file is being uploaded again, so its file has to stay
+        assert!(store.begin_insert("hash"));
+
+        assert_eq!(storage.collect(&mut store), (0, 0));
+
+        store.insert("hash", 5, "other", None, None);
+
+        assert_eq!(store.get("hash").unwrap().references, 1);
+        assert!(BlobStore::path(&storage.0, "hash").exists());
+    }
+
+    #[test]
+    fn blobs_being_collected_cannot_be_inserted()
+    {
+        let storage = TestStorage::new();
+        let mut store = BlobStore::default();
+
+        storage.insert(&mut store, "hash", b"hello", "uploader");
+
+        store.remove_reference("hash");
+
+        expire(&mut store, "hash");
+
+        let collected = store.take_collectable();
+
+        assert_eq!(collected.len(), 1);
+        assert!(store.get("hash").is_none());
+
+        //The file is being deleted, so it cannot be referred to until the collection is finished
+        assert!(!store.begin_insert("hash"));
+
+        let failed = BlobStore::delete_files(&storage.0, &["hash".to_string()]);
+
+        assert_eq!(store.finish_collection(collected, &failed), (1, 5));
+        assert!(!BlobStore::path(&storage.0, "hash").exists());
+
+        assert!(store.begin_insert("hash"));
+    }
+
+    #[test]
+    fn recount_matches_the_messages()
+    {
+        let mut store = BlobStore::default();
+
+        store.blobs.insert(
+            "hash".to_string(),
+            Blob {
+                references: 5,
+                ..Default::default()
+            },
+        );
+        store.blobs.insert(
+            "orphan".to_string(),
+            Blob {
+                references: 1,
+                ..Default::default()
+            },
+        );
+
+        let message = ServerOutput {
+            uuid: "author".to_string(),
+            ..server_output(
+                1,
+                ServerMessageType::Image(ServerImageUpload {
+                    signature: "hash".to_string(),
+                }),
+                DEFAULT_CHANNEL_ID.to_string(),
+            )
+        };
+
+        store.recount([&message, &message]);
+
+        let blob = store.get("hash").unwrap();
+
+        assert_eq!(blob.references, 2);
+        assert_eq!(blob.uploader.as_deref(), Some("author"));
+        assert!(blob.unreferenced_since.is_none());
+
+        let orphan = store.get("orphan").unwrap();
+
+        assert_eq!(orphan.references, 0);
+        assert!(orphan.unreferenced_since.is_some());
+    }
+
+    #[test]
+    fn tracks_the_changed_blobs()
+    {
+        let mut store = BlobStore::default();
+
+        store.blobs.insert("hash".to_string(), Blob::default());
+        store.add_reference("hash");
+        store.remove_reference("hash");
+
+        let changes = store.take_changes();
+
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].0, "hash");
+        assert!(changes[0].1.is_some());
+        assert!(store.take_changes().is_empty());
+    }
+}
@@ -0,0 +1,253 @@
+use std::{fmt, net::SocketAddr, sync::Arc};
+
+use anyhow::bail;
+use tokio::{
+    io::AsyncWriteExt,
+    net::tcp::OwnedWriteHalf,
+    select,
+    sync::mpsc::{self, error::TrySendError, Receiver},
+};
+use tokio_util::sync::CancellationToken;
+
+use crate::protocol::ServerControlReply;
+
+/// The number of frames which can be waiting to be sent to a client, if the user doesnt specify it
+pub const DEFAULT_OUTBOUND_QUEUE_SIZE: usize = 256;
+
+/// What happens when a client's queue is full, because the client isnt reading the frames fast enough
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OverflowPolicy
+{
+    /// The messages sent to every client which dont fit in the queue are dropped, the client notices the missing messages by their sequences and asks for a resync (```ClientWriter::send_broadcast```)
+    /// The rest of the frames (replies, private messages, etc.) cannot be recovered by a resync, so the client is disconnected if they dont fit
+    Drop,
+
+    /// The client is disconnected, so that it doesnt fall behind the rest of the clients
+    #[default]
+    Disconnect,
+}
+
+/// The settings of the queues the frames sent to the clients wait in
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OutboundQueueConfig
+{
+    /// The number of frames which can be waiting to be sent to a client
+    pub capacity: usize,
+
+    /// What happens when a client's queue is full
+    pub overflow_policy: OverflowPolicy,
+}
+
+impl Default for OutboundQueueConfig
+{
+    fn default() -> Self
+    {
+        Self {
+            capacity: DEFAULT_OUTBOUND_QUEUE_SIZE,
+            overflow_policy: OverflowPolicy::default(),
+        }
+    }
+}
+
+/// The requests the writer task of a client receives
+enum WriterCommand
+{
+    /// Writes the frame prefixed by its length
+    Frame(Arc<[u8]>),
+
+    /// Closes the connection, after the frames queued before this have been written
+    Shutdown,
+}
+
+/// The handle of the task which writes the frames to a client's connection
+/// Sending a frame only puts it in the client's queue, so that a slow client cannot hold up the server
+#[derive(Clone)]
+pub struct ClientWriter
+{
+    sender: mpsc::Sender<WriterCommand>,
+
+    overflow_policy: OverflowPolicy,
+
+    /// This is cancelled when the writer task stops, either because the connection has been closed or because the client has been disconnected
+    closed: CancellationToken,
+
+    socket_addr: SocketAddr,
+}
+
+impl fmt::Debug for ClientWriter
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        f.debug_struct("ClientWriter")
+            .field("socket_addr", &self.socket_addr)
+            .field("closed", &self.is_closed())
+            .finish()
+    }
+}
+
+impl ClientWriter
+{
+    /// Spawns the task which writes the frames to the connection, the task stops when every handle has been dropped or the connection has been closed
+    pub fn spawn(
+        writer: OwnedWriteHalf,
+        socket_addr: SocketAddr,
+        config: OutboundQueueConfig,
+    ) -> Self
+    {
+        let (sender, receiver) = mpsc::channel(config.capacity.max(1));
+
+        let closed = CancellationToken::new();
+
+        tokio::spawn(write_frames(writer, receiver, closed.clone(), socket_addr));
+
+        Self {
+            sender,
+            overflow_policy: config.overflow_policy,
+            closed,
+            socket_addr,
+        }
+    }
+
+    /// Queues the frame to be sent to the client, this never waits for the client
+    /// Returns an error if the connection has been closed, or if the queue is full and the client has been disconnected because of it
+    /// The frame is never dropped, since the client couldnt notice it missing
+    pub fn send(&self, frame: impl Into<Arc<[u8]>>) -> anyhow::Result<()>
+    {
+        self.queue(frame.into(), OverflowPolicy::Disconnect)
+    }
+
+    /// Queues the message sent to every client, this never waits for the client
+    /// If the queue is full the overflow policy decides whether the frame is dropped, the client notices the dropped message by its sequence (```ServerOutput::sequence```)
+    pub fn send_broadcast(&self, frame: impl Into<Arc<[u8]>>) -> anyhow::Result<()>
+    {
+        self.queue(frame.into(), self.overflow_policy)
+    }
+
+    /// Queues the frame, the overflow policy decides what happens if the queue is full
+    fn queue(&self, frame: Arc<[u8]>, overflow_policy: OverflowPolicy) -> anyhow::Result<()>
+    {
+        match self.sender.try_send(WriterCommand::Frame(frame)) {
+            Ok(_) => Ok(()),
+            Err(TrySendError::Full(_)) => {
+                match overflow_policy {
+                    OverflowPolicy::Drop => {
+                        tracing::warn!(
+                            "The queue of {} is full, dropping a frame",
+                            self.socket_addr
+                        );
+
+                        Ok(())
+                    },
+                    OverflowPolicy::Disconnect => {
+                        self.closed.cancel();
+
+                        bail!(
+                            "The queue of {} is full, disconnecting the client",
+                            self.socket_addr
+                        )
+                    },
+                }
+            },
+            Err(TrySendError::Closed(_)) => {
+                bail!("The connection to {} has been closed", self.socket_addr)
+            },
+        }
+    }
+
+    /// Queues the control reply to be sent to the client, these are not encrypted since the client might not have received the key yet
+    pub fn send_control_reply(&self, reply: ServerControlReply) -> anyhow::Result<()>
+    {
+        self.send(reply.struct_into_string().into_bytes())
+    }
+
+    /// Closes the connection after the frames already in the queue have been sent
+    /// If the queue is full the connection is closed right away
+    pub fn shutdown(&self)
+    {
+        if self.sender.try_send(WriterCommand::Shutdown).is_err() {
+            self.closed.cancel();
+        }
+    }
+
+    /// Closes the connection right away, the frames still in the queue are dropped
+    /// This is used when the client is not reachable anymore, so there is no point in waiting for the queue to be sent
+    pub fn close(&self)
+    {
+        self.closed.cancel();
+    }
+
+    /// Returns whether the writer task has stopped
+    pub fn is_closed(&self) -> bool
+    {
+        self.closed.is_cancelled()
+    }
+
+    /// Waits until the writer task has stopped
+    pub async fn closed(&self)
+    {
+        self.closed.cancelled().await
+    }
+
+    /// Returns whether the two handles belong to the same connection
+    pub fn same_connection(&self, other: &Self) -> bool
+    {
+        self.sender.same_channel(&other.sender)
+    }
+}
+
+/// Writes the queued frames to the connection until the connection is closed
+async fn write_frames(
+    mut writer: OwnedWriteHalf,
+    mut receiver: Receiver<WriterCommand>,
+    closed: CancellationToken,
+    socket_addr: SocketAddr,
+)
+{
+    loop {
+        let command = select! {
+            _ = closed.cancelled() => break,
+
+            command = receiver.recv() => command,
+        };
+
+        match command {
+            Some(WriterCommand::Frame(frame)) => {
+                //A client which has stopped reading can only be disconnected while the frame is being written
+                let result = select! {
+                    _ = closed.cancelled() => break,
+
+                    result = write_frame(&mut writer, &frame) => result,
+                };
+
+                if let Err(err) = result {
+                    tracing::warn!("Failed to send a frame to {socket_addr}: {err}");
+
+                    break;
+                }
+            },
+            //Every handle has been dropped, or the connection should be closed
+            Some(WriterCommand::Shutdown) | None => break,
+        }
+    }
+
+    //The rest of the queued frames are dropped
+    closed.cancel();
+
+    if let Err(err) = writer.shutdown().await {
+        tracing::debug!("Failed to close the connection to {socket_addr}: {err}");
+    }
+}
+
+/// Writes the frame prefixed by its length
+async fn write_frame(writer: &mut OwnedWriteHalf, frame: &[u8]) -> anyhow::Result<()>
+{
+    let frame_length = TryInto::<u32>::try_into(frame.len())?;
+
+    writer.write_all(&frame_length.to_be_bytes()).await?;
+
+    writer.write_all(frame).await?;
+
+    writer.flush().await?;
+
+    Ok(())
+}
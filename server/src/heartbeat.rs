@@ -0,0 +1,31 @@
+use std::time::Duration;
+
+/// How often the clients are pinged, if the user doesnt specify it
+pub const DEFAULT_HEARTBEAT_INTERVAL: Duration = Duration::from_secs(10);
+
+/// How long a client can stay silent before it gets disconnected, if the user doesnt specify it
+pub const DEFAULT_HEARTBEAT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The settings of the heartbeat, which detects the clients whose connection has been lost without being closed
+/// Only the clients which support ```Capability::Heartbeat``` are pinged and disconnected, the rest are only disconnected when their connection closes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HeartbeatConfig
+{
+    /// How often the clients are pinged
+    pub interval: Duration,
+
+    /// How long a client can stay silent before it gets disconnected, any frame the client sends counts (not only the pongs)
+    /// This should be a few times longer than the interval, so that a single late pong doesnt disconnect the client
+    pub timeout: Duration,
+}
+
+impl Default for HeartbeatConfig
+{
+    fn default() -> Self
+    {
+        Self {
+            interval: DEFAULT_HEARTBEAT_INTERVAL,
+            timeout: DEFAULT_HEARTBEAT_TIMEOUT,
+        }
+    }
+}
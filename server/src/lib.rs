@@ -0,0 +1,33 @@
+/// The server's ban list, bans can target a user, an address or a whole subnet
+pub mod ban;
+
+/// Stores the uploaded files by their hash, and removes the files which arent referred to by any message anymore
+pub mod blob_store;
+
+/// Writes the frames sent to the clients from a queue, so that a slow client cannot hold up the server
+pub mod client_writer;
+
+/// Pings the clients periodically, so that the clients whose connection has been lost can be disconnected
+pub mod heartbeat;
+
+/// Contains all the types which are sent between the server and the clients, and the functions used to (de)serialize and (de)encrypt them
+pub mod protocol;
+
+/// Limits how much space the uploads can take up, and how long the messages are kept for
+pub mod quota;
+
+/// Limits how fast the clients can send requests, and how many connections can be open from the same address
+pub mod rate_limit;
+
+/// The server itself, this can be run from the desktop client's Ui or from the headless ```matthias-server``` binary
+pub mod server;
+
+/// Saves and loads the server's state (messages, reactions, uploads, etc.), so that the chat history survives a restart
+pub mod storage;
+
+/// The fixtures shared by the tests of the modules
+#[cfg(test)]
+mod test_util;
+
+/// Checks the clients' requests before they are applied, so that an invalid request cannot take down the server
+pub mod validation;
@@ -0,0 +1,291 @@
+#![warn(clippy::all, rust_2018_idioms)]
+
+use std::{env::args, path::PathBuf, time::Duration};
+
+use anyhow::bail;
+use matthias_server::{
+    client_writer::{OutboundQueueConfig, OverflowPolicy},
+    heartbeat::HeartbeatConfig,
+    quota::{QuotaConfig, RetentionPolicy, RetentionTarget},
+    rate_limit::{ActionLimits, RateLimit, RateLimitConfig},
+    server::server_main,
+};
+use tokio_util::sync::CancellationToken;
+use tracing::Level;
+use tracing_subscriber::{filter, layer::SubscriberExt, util::SubscriberInitExt};
+
+/// The port the server is going to be opened on, if the user doesnt specify one
+const DEFAULT_PORT: &str = "3004";
+
+/// The folder the server is going to store the uploaded files in, if the user doesnt specify one
+const DEFAULT_STORAGE_PATH: &str = "matthias_server";
+
+/// The sizes of the storage limits are provided in megabytes
+const MEGABYTE: u64 = 1024 * 1024;
+
+const HELP_MESSAGE: &str = "Usage: matthias-server [OPTIONS]
+
+Options:
+    --port <PORT>                   The port the server will listen on [default: 3004]
+    --password <PASSWORD>           The password the clients will have to provide when connecting [default: none]
+    --storage <PATH>                The folder the server will store the uploaded files in [default: ./matthias_server]
+    --owner <UUID>                  The uuid of the user who will be assigned the owner role [default: none]
+    --messages-per-minute <COUNT>   The number of messages a user can send per minute [default: 60]
+    --uploads-per-minute <COUNT>    The number of files a user can upload per minute [default: 10]
+    --reactions-per-minute <COUNT>  The number of reactions a user can add or remove per minute [default: 120]
+    --max-connections <COUNT>       The number of connections which can be open from the same address [default: 8]
+    --transfer-rate <MB>            The number of megabytes a user can upload and download per second [default: 8]
+    --user-quota <MB>               The space the files uploaded by a single user can take up [default: unlimited]
+    --total-quota <MB>              The space all of the uploaded files can take up [default: unlimited]
+    --max-image-size <MB>           The maximum size of an uploaded image, at most 64 [default: 20]
+    --max-audio-size <MB>           The maximum size of an uploaded audio file, at most 64 [default: 50]
+    --max-file-size <MB>            The maximum size of every other uploaded file [default: 476]
+    --retention-days <DAYS>         The age after which the messages are removed [default: never]
+    --retention-target <TARGET>     Which messages are removed, messages or attachments [default: messages]
+    --outbound-queue-size <FRAMES>  The number of frames which can be waiting to be sent to a client [default: 256]
+    --overflow-policy <POLICY>      What happens when a client cannot keep up with the messages sent to every client, drop (the client resyncs them) or disconnect [default: disconnect]
+    --heartbeat-interval <SECS>     How often the clients are pinged [default: 10]
+    --heartbeat-timeout <SECS>      How long a client can stay silent before it gets disconnected [default: 30]
+    --help                          Print this message";
+
+/// The settings the headless server is started with
+#[derive(Debug)]
+struct ServerArgs
+{
+    port: String,
+    password: String,
+    storage_path: PathBuf,
+    owner_uuid: Option<String>,
+    rate_limit_config: RateLimitConfig,
+    quota_config: QuotaConfig,
+    outbound_queue_config: OutboundQueueConfig,
+    heartbeat_config: HeartbeatConfig,
+}
+
+impl Default for ServerArgs
+{
+    fn default() -> Self
+    {
+        Self {
+            port: DEFAULT_PORT.to_string(),
+            password: String::new(),
+            storage_path: PathBuf::from(DEFAULT_STORAGE_PATH),
+            owner_uuid: None,
+            rate_limit_config: RateLimitConfig::default(),
+            quota_config: QuotaConfig::default(),
+            outbound_queue_config: OutboundQueueConfig::default(),
+            heartbeat_config: HeartbeatConfig::default(),
+        }
+    }
+}
+
+/// Parses the arguments the binary was started with, returns ```None``` if the help message was requested
+fn parse_args(args: Vec<String>) -> anyhow::Result<Option<ServerArgs>>
+{
+    let mut server_args = ServerArgs::default();
+
+    let mut limits = ActionLimits::default();
+    let mut max_connections = server_args.rate_limit_config.max_connections_per_address;
+
+    let mut retention_days = None;
+    let mut retention_target = RetentionTarget::Messages;
+
+    //Skip the first argument since its the path of the executable
+    let mut args = args.into_iter().skip(1);
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--help" | "-h" => return Ok(None),
+            "--port" | "--password" | "--storage" | "--owner" => {
+                let Some(value) = args.next()
+                else {
+                    bail!("Missing value for argument: {arg}");
+                };
+
+                match arg.as_str() {
+                    "--port" => {
+                        //Check if the port is valid
+                        if value.parse::<u16>().is_err() {
+                            bail!("Invalid port: {value}");
+                        }
+
+                        server_args.port = value;
+                    },
+                    "--password" => server_args.password = value,
+                    "--owner" => server_args.owner_uuid = Some(value),
+                    _ => server_args.storage_path = PathBuf::from(value),
+                }
+            },
+            "--messages-per-minute"
+            | "--uploads-per-minute"
+            | "--reactions-per-minute"
+            | "--max-connections" => {
+                let Some(value) = args.next()
+                else {
+                    bail!("Missing value for argument: {arg}");
+                };
+
+                //Check if the count is valid, a limit of zero would make the server unusable
+                let count = match value.parse::<u32>() {
+                    Ok(count) if count > 0 => count,
+                    _ => bail!("Invalid count: {value}"),
+                };
+
+                match arg.as_str() {
+                    "--messages-per-minute" => limits.messages = RateLimit::per_minute(count),
+                    "--uploads-per-minute" => limits.uploads = RateLimit::per_minute(count),
+                    "--reactions-per-minute" => limits.reactions = RateLimit::per_minute(count),
+                    _ => max_connections = count as usize,
+                }
+            },
+            "--user-quota" | "--total-quota" | "--max-image-size" | "--max-audio-size"
+            | "--max-file-size" | "--retention-days" | "--transfer-rate" => {
+                let Some(value) = args.next()
+                else {
+                    bail!("Missing value for argument: {arg}");
+                };
+
+                //Check if the size is valid, a limit of zero would make uploading impossible
+                let amount = match value.parse::<u64>() {
+                    Ok(amount) if amount > 0 => amount,
+                    _ => bail!("Invalid amount: {value}"),
+                };
+
+                let quota_config = &mut server_args.quota_config;
+
+                match arg.as_str() {
+                    "--user-quota" => quota_config.user_quota = Some(amount * MEGABYTE),
+                    "--total-quota" => quota_config.total_quota = Some(amount * MEGABYTE),
+                    "--max-image-size" => quota_config.max_image_size = amount * MEGABYTE,
+                    "--max-audio-size" => quota_config.max_audio_size = amount * MEGABYTE,
+                    "--max-file-size" => quota_config.max_file_size = amount * MEGABYTE,
+                    "--transfer-rate" => limits.transfer_per_second = amount * MEGABYTE,
+                    _ => retention_days = Some(u32::try_from(amount)?),
+                }
+            },
+            "--retention-target" => {
+                let Some(value) = args.next()
+                else {
+                    bail!("Missing value for argument: {arg}");
+                };
+
+                retention_target = match value.as_str() {
+                    "messages" => RetentionTarget::Messages,
+                    "attachments" => RetentionTarget::Attachments,
+                    _ => bail!("Invalid retention target: {value}"),
+                };
+            },
+            "--outbound-queue-size" => {
+                let Some(value) = args.next()
+                else {
+                    bail!("Missing value for argument: {arg}");
+                };
+
+                //A queue which cannot hold any frames couldnt send anything
+                server_args.outbound_queue_config.capacity = match value.parse::<usize>() {
+                    Ok(capacity) if capacity > 0 => capacity,
+                    _ => bail!("Invalid queue size: {value}"),
+                };
+            },
+            "--overflow-policy" => {
+                let Some(value) = args.next()
+                else {
+                    bail!("Missing value for argument: {arg}");
+                };
+
+                server_args.outbound_queue_config.overflow_policy = match value.as_str() {
+                    "drop" => OverflowPolicy::Drop,
+                    "disconnect" => OverflowPolicy::Disconnect,
+                    _ => bail!("Invalid overflow policy: {value}"),
+                };
+            },
+            "--heartbeat-interval" | "--heartbeat-timeout" => {
+                let Some(value) = args.next()
+                else {
+                    bail!("Missing value for argument: {arg}");
+                };
+
+                let duration = match value.parse::<u64>() {
+                    Ok(secs) if secs > 0 => Duration::from_secs(secs),
+                    _ => bail!("Invalid duration: {value}"),
+                };
+
+                match arg.as_str() {
+                    "--heartbeat-interval" => server_args.heartbeat_config.interval = duration,
+                    _ => server_args.heartbeat_config.timeout = duration,
+                }
+            },
+            _ => bail!("Unknown argument: {arg}\n\n{HELP_MESSAGE}"),
+        }
+    }
+
+    //The clients would be disconnected before they could answer a single ping
+    if server_args.heartbeat_config.timeout <= server_args.heartbeat_config.interval {
+        bail!("The heartbeat timeout has to be longer than the heartbeat interval");
+    }
+
+    server_args.rate_limit_config = RateLimitConfig::new(limits, max_connections);
+
+    server_args.quota_config.retention = retention_days.map(|max_age_days| {
+        RetentionPolicy {
+            max_age_days,
+            target: retention_target,
+        }
+    });
+
+    Ok(Some(server_args))
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()>
+{
+    let filter = filter::Targets::new().with_default(Level::INFO);
+
+    tracing_subscriber::registry()
+        .with(tracing_subscriber::fmt::layer())
+        .with(filter)
+        .init();
+
+    let Some(server_args) = parse_args(args().collect())?
+    else {
+        println!("{HELP_MESSAGE}");
+
+        return Ok(());
+    };
+
+    //This is used to shut down the server
+    let cancellation_token = CancellationToken::new();
+
+    //There is no Ui attached to the server, so we dont need to keep anything in sync
+    let server_handle = server_main(
+        server_args.port.clone(),
+        server_args.password,
+        server_args.storage_path.clone(),
+        server_args.owner_uuid,
+        server_args.rate_limit_config,
+        server_args.quota_config,
+        server_args.outbound_queue_config,
+        server_args.heartbeat_config,
+        cancellation_token.clone(),
+        None,
+    )
+    .await?;
+
+    tracing::info!(
+        "Server started on port {}, storing files in {}",
+        server_args.port,
+        server_args.storage_path.display()
+    );
+
+    //Wait until the user shuts down the server
+    tokio::signal::ctrl_c().await?;
+
+    tracing::info!("Shutting down server.");
+
+    cancellation_token.cancel();
+
+    //Wait until the last changes have been saved
+    server_handle.saver.await?;
+
+    Ok(())
+}
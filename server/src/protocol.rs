@@ -0,0 +1,3273 @@
+use aes_gcm::{
+    aead::{generic_array::GenericArray, Aead, KeyInit},
+    Aes256Gcm, Key,
+};
+use anyhow::{ensure, Error};
+use argon2::Config;
+use chrono::{DateTime, Local, NaiveDate, Utc};
+use dashmap::DashMap;
+use indexmap::IndexMap;
+use rand::Rng;
+use std::{
+    collections::{HashMap, HashSet},
+    io::Read,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+use strum::{EnumDiscriminants, EnumMessage};
+use strum_macros::EnumString;
+use tokio::{
+    io::{AsyncRead, AsyncReadExt},
+    net::UdpSocket,
+};
+use tokio_util::sync::CancellationToken;
+
+use crate::{blob_store::format_size, client_writer::ClientWriter};
+
+/// Image byte offset
+/// This value is the end of the hash bytes, the start is ```UUID_BYTE_OFFSET```
+pub const HASH_BYTE_OFFSET: usize = 64 + 64 + 36;
+
+/// Hash byte offset
+/// This value is the end of the uuid string bytes, the start is ```IDENTIFICATOR_BYTE_OFFSET```
+pub const UUID_BYTE_OFFSET: usize = 64 + 36;
+
+/// Identificator byte offset
+/// This value is the start of the identificator bytes, the end is the end of the message itself
+pub const IDENTIFICATOR_BYTE_OFFSET: usize = 64;
+
+/// This is the byte length of the uuid's text representation (utf8)
+pub const UUID_STRING_BYTE_LENGTH: usize = 36;
+
+/// The clients profile, this struct should be sent at a server connection
+/// The id of the channel every server has by default, this channel cannot be deleted
+/// Messages which do not specify a channel (Or were sent before channels were introduced) belong to this channel
+pub const DEFAULT_CHANNEL_ID: &str = "general";
+
+/// The name of the default channel
+pub const DEFAULT_CHANNEL_NAME: &str = "general";
+
+/// The revision of the protocol this build speaks, this has to be increased every time the protocol changes in an incompatible way
+pub const PROTOCOL_VERSION: u32 = 2;
+
+/// The oldest revision of the protocol this build can still talk to
+/// Revision 2 refers to the messages by their ids instead of their indexes, so older clients cannot be understood
+pub const MIN_PROTOCOL_VERSION: u32 = 2;
+
+/// The size of the chunks files are uploaded and downloaded in, only the last chunk of a file can be smaller than this
+pub const TRANSFER_CHUNK_SIZE: usize = 1024 * 1024;
+
+/// The number of messages sent in a page of the message history, the clients receive the most recent page of every channel when connecting and request the older ones as they scroll up
+pub const HISTORY_PAGE_SIZE: usize = 100;
+
+/// The unique identifier of a message, this is assigned by the server when the message is sent
+/// The ids are increasing, so the message list is always ordered by them
+pub type MessageId = u64;
+
+/// The format of the messages' dates (```ServerOutput::message_date```), the dates are in UTC
+pub const MESSAGE_DATE_FORMAT: &str = "%Y.%m.%d. %H:%M";
+
+/// Used by serde when deserializing a message which doesnt have a channel id
+pub fn default_channel_id() -> String
+{
+    DEFAULT_CHANNEL_ID.to_string()
+}
+
+/// A text channel of the server, every message belongs to exactly one channel
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub struct Channel
+{
+    /// The id of the channel, this is what the messages refer to
+    pub id: String,
+
+    /// The name of the channel, this can be changed
+    pub name: String,
+}
+
+impl Channel
+{
+    /// Creates a new channel with a randomly generated id
+    pub fn new(name: String) -> Self
+    {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            name,
+        }
+    }
+}
+
+impl Default for Channel
+{
+    fn default() -> Self
+    {
+        Self {
+            id: DEFAULT_CHANNEL_ID.to_string(),
+            name: DEFAULT_CHANNEL_NAME.to_string(),
+        }
+    }
+}
+
+/// The roles a user can have on a server, the roles are ordered by their rank (```Owner``` being the highest)
+/// Users who havent been assigned a role are ```Member```-s
+#[derive(
+    serde::Serialize,
+    serde::Deserialize,
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Default,
+    strum_macros::Display,
+    strum_macros::EnumIter,
+)]
+pub enum Role
+{
+    Guest,
+    #[default]
+    Member,
+    Moderator,
+    Owner,
+}
+
+/// The actions which require a permission, every role has a set of these
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Permission
+{
+    /// Send normal and private messages
+    SendMessages,
+
+    /// Upload files, images and audio
+    UploadFiles,
+
+    /// Add reactions to messages
+    React,
+
+    /// Start a voip call, anyone can join an ongoing call
+    StartCalls,
+
+    /// Pin and unpin messages
+    PinMessages,
+
+    /// Delete messages sent by other users
+    DeleteOthersMessages,
+
+    /// Create, rename and delete channels
+    ManageChannels,
+
+    /// Kick and time out users who have a lower role
+    Kick,
+
+    /// Mute users who have a lower role
+    Mute,
+
+    /// Ban users who have a lower role
+    Ban,
+
+    /// Assign roles to users who have a lower role
+    ManageRoles,
+}
+
+impl Role
+{
+    /// Returns the permissions of the role
+    pub fn permissions(&self) -> &'static [Permission]
+    {
+        match self {
+            Role::Guest => &[Permission::SendMessages, Permission::React],
+            Role::Member => {
+                &[
+                    Permission::SendMessages,
+                    Permission::UploadFiles,
+                    Permission::React,
+                    Permission::StartCalls,
+                ]
+            },
+            Role::Moderator => {
+                &[
+                    Permission::SendMessages,
+                    Permission::UploadFiles,
+                    Permission::React,
+                    Permission::StartCalls,
+                    Permission::PinMessages,
+                    Permission::DeleteOthersMessages,
+                    Permission::ManageChannels,
+                    Permission::Kick,
+                    Permission::Mute,
+                    Permission::Ban,
+                ]
+            },
+            Role::Owner => {
+                &[
+                    Permission::SendMessages,
+                    Permission::UploadFiles,
+                    Permission::React,
+                    Permission::StartCalls,
+                    Permission::PinMessages,
+                    Permission::DeleteOthersMessages,
+                    Permission::ManageChannels,
+                    Permission::Kick,
+                    Permission::Mute,
+                    Permission::Ban,
+                    Permission::ManageRoles,
+                ]
+            },
+        }
+    }
+
+    /// Returns whether the role has the permission
+    pub fn has_permission(&self, permission: Permission) -> bool
+    {
+        self.permissions().contains(&permission)
+    }
+
+    /// Returns the role of the user (identified by their uuid), if the user hasnt been assigned a role the default role is returned
+    pub fn of_user(roles: &HashMap<String, Role>, uuid: &str) -> Role
+    {
+        roles.get(uuid).copied().unwrap_or_default()
+    }
+}
+
+/// It hold everything which needs to be displayed when viewing someone's profile
+/// This struct might look similar too ```Register```, but that one contains more information, and is only made to control the ui
+/// This struct is sent to the server upon successful connection
+/// If you are searching for the uuid in this struct, please note that most of the times this struct is used in a hashmap where the key is the uuid
+#[derive(serde::Deserialize, serde::Serialize, Default, Clone, Debug, PartialEq)]
+pub struct ClientProfile
+{
+    /// The client's username
+    /// We might not need it in some contexts
+    pub username: String,
+
+    /// The client's full name
+    /// If its empty it means the client did not agree to share it
+    pub full_name: String,
+
+    /// The client's gender
+    /// false: Male
+    /// true: Female
+    /// None: Rather not answer
+    /// Rework this
+    pub gender: Option<bool>,
+
+    /// The client's birthdate
+    pub birth_date: NaiveDate,
+
+    /// This entry hold the profile's 64x64 profile picture
+    pub small_profile_picture: Vec<u8>,
+
+    /// This entry hold the profile's 256x256 profile picture
+    pub normal_profile_picture: Vec<u8>,
+}
+
+///When the client has uploaded all the chunks of a file (```ClientFileTransfer```), this packet gets sent to commit the upload
+#[derive(Default, serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct ClientFileUpload
+{
+    pub extension: Option<String>,
+    pub name: Option<String>,
+
+    /// The sha256 hash of the whole file, this identifies the upload and becomes the signature of the file
+    pub hash: String,
+
+    /// The size of the whole file in bytes
+    pub size: u64,
+}
+
+/// Files are uploaded in chunks, so that neither side has to hold the whole file in memory
+/// An upload is identified by the hash of the whole file, if the connection drops it can be resumed by starting it again
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub enum ClientFileTransfer
+{
+    /// Starts or resumes an upload, the server replies with the offset the next chunk has to be sent from (```ServerUploadReply```)
+    /// The extension of the file is sent too, so that the server can check the limits of the file's media type before any chunks are sent
+    StartUpload
+    {
+        hash: String,
+        size: u64,
+        extension: Option<String>,
+    },
+
+    /// A chunk of the file, which is at most ```TRANSFER_CHUNK_SIZE``` bytes long
+    /// The offset has to match the number of bytes the server has already received
+    UploadChunk
+    {
+        hash: String,
+        offset: u64,
+        bytes: Vec<u8>,
+    },
+
+    /// Discards the chunks the server has received
+    CancelUpload
+    {
+        hash: String
+    },
+}
+
+///Normal message
+#[derive(Default, serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct ClientNormalMessage
+{
+    pub message: String,
+}
+
+/// A private message, which is only delivered to the sender and the recipient
+#[derive(Default, serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct ClientDirectMessage
+{
+    /// The uuid of the user this message is addressed to
+    pub recipient: String,
+
+    pub message: String,
+}
+
+// Used for syncing or connecting & disconnecting
+#[derive(Default, serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct ClientSyncMessage
+{
+    /// If its None its used for syncing, false: disconnecting, true: connecting
+    /// If you have already registered the client with the server then the true value will be ignored
+    pub sync_attribute: Option<ConnectionType>,
+
+    /// This is used to tell the server which is the newest message the client already has when connecting, so that the server only sends the messages after it
+    /// If its None (or the client is missing more than a page of messages) the server sends the most recent page of the history
+    /// This value is ignored if the client is only syncing (the `sync_attribute` field is None), unless it requests a resync
+    pub last_message_id: Option<MessageId>,
+
+    /// The id of the last seen message by the user, this is sent so we can display which was the last message the user has seen, if its None we ignore the value
+    pub last_seen_message_id: Option<MessageId>,
+
+    /// This is set when the client has missed some of the messages sent to every client (```ServerOutput::sequence```)
+    /// The server replies with a full sync (```ServerMaster```), which continues from ```last_message_id```
+    #[serde(default)]
+    pub resync: bool,
+
+    /// The revision of the last sync the client has received (```ServerMaster::revision```)
+    /// The messages the client already has which have been changed since are sent again (```ServerMaster::changed_messages```)
+    #[serde(default)]
+    pub last_revision: Option<u64>,
+
+    /// The token the server has sent when the client last connected (```ServerMaster::reconnect_token```)
+    /// If the server still considers the client connected, the new connection only replaces the old one if the token matches
+    #[serde(default)]
+    pub reconnect_token: Option<String>,
+
+    /// Contains password in the sync message, so we will send the password when authenticating
+    pub password: String,
+
+    /// This field is used when connecting, the server will save the uuid and the username pair
+    /// The client will not send their username except here, and the server is expected to pair the name to the message
+    pub username: String,
+}
+
+#[derive(Default, serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub enum ConnectionType
+{
+    #[default]
+    Disconnect,
+    Connect(ClientProfile),
+}
+
+///This is used by the client for requesting file
+#[derive(Default, serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct ClientFileRequest
+{
+    /// This is the signature of the file which has been uploaded, this acts like a handle to the file
+    pub signature: String,
+
+    /// The offset of the requested chunk, files are downloaded in chunks of ```TRANSFER_CHUNK_SIZE``` bytes
+    pub offset: u64,
+}
+
+/// This is used by the client for requesting an older page of the message history (```ServerHistoryPage```)
+/// The history is paged per channel, the page contains the messages of the channel the request is sent in (```ClientMessage::channel_id```)
+#[derive(Default, serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct ClientHistoryRequest
+{
+    /// The id of the oldest message the client has in the channel, the page contains the messages before it
+    pub before_id: MessageId,
+}
+
+/// This is what the client sends back when the server pings it (```ServerPing```), the server measures the latency of the connection with it
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct ClientPong
+{
+    /// The date the ping was sent at, echoed back as is
+    pub sent_at: DateTime<Utc>,
+}
+
+///This is used by the client for requesting images
+#[derive(Default, serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct ClientImageRequest
+{
+    /// This is the signature of the file which has been uploaded, this acts like a handle to the file
+    pub signature: String,
+}
+
+///Client requests audio file in server
+#[derive(Default, serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct ClientAudioRequest
+{
+    /// This is the signature of the file which has been uploaded, this acts like a handle to the file
+    pub signature: String,
+
+    /// The id of the message the audio file belongs to, this is sent back in the reply
+    pub message_id: MessageId,
+}
+
+///Reaction packet, defines which message its reacting to and with which char
+#[derive(Default, serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub struct ClientReaction
+{
+    pub emoji_name: String,
+    pub message_id: MessageId,
+    pub uuid: String,
+}
+
+///Lets the client edit their *OWN* message, a client check is implemented TODO: please write a server check for this
+#[derive(Default, serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct ClientMessageEdit
+{
+    ///The message which is edited
+    pub message_id: MessageId,
+    ///The new message
+    pub new_message: Option<String>,
+}
+
+/// The channel operations a client can request, after any of these the server sends the updated channel list to all the clients
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub enum ClientChannelRequest
+{
+    /// Create a new channel with the provided name
+    Create(String),
+
+    /// Rename the channel with the provided id
+    Rename
+    {
+        channel_id: String, name: String
+    },
+
+    /// Delete the channel with the provided id, the messages of the channel are deleted too
+    /// The default channel cannot be deleted
+    Delete(String),
+}
+
+/// Used to pin or unpin a message
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct ClientMessagePin
+{
+    /// The message which is pinned or unpinned
+    pub message_id: MessageId,
+
+    /// Whether the message should be pinned or unpinned
+    pub pinned: bool,
+}
+
+/// Used to assign a role to a user
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct ClientRoleRequest
+{
+    /// The uuid of the user the role is assigned to
+    pub uuid: String,
+
+    pub role: Role,
+}
+
+/// The actions a user can take against other users (if they have the permission to)
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub enum ClientModerationRequest
+{
+    /// Ban the user with the provided uuid, if ```minutes``` is ```None``` the ban is permanent
+    /// If ```include_address``` is true, the address the user is connected from gets banned too
+    Ban
+    {
+        uuid: String,
+        reason: String,
+        minutes: Option<u32>,
+        include_address: bool,
+    },
+
+    /// Disconnect the user with the provided uuid, they can reconnect right away
+    Kick(String),
+
+    /// Prevent the user from sending messages (private ones too), uploading files, reacting and editing their messages for the provided amount of minutes
+    Mute
+    {
+        uuid: String, minutes: u32
+    },
+
+    /// Disconnect the user and prevent them from reconnecting for the provided amount of minutes
+    Timeout
+    {
+        uuid: String, minutes: u32
+    },
+}
+
+///These are the types of requests the client can ask
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub enum ClientFileRequestType
+{
+    ///this is when you want to display an image and you have to make a request to the server file
+    ImageRequest(ClientImageRequest),
+    FileRequest(ClientFileRequest),
+    AudioRequest(ClientAudioRequest),
+
+    /// This enum is used when the client is requesting another client's information (```ClientProfile``` struct)
+    /// The wrapped value in this enum is an encrypted (aes256: ```fn encrypt_aes256()```) uuid (In string)
+    ClientRequest(String),
+}
+
+///Client outgoing message types
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub enum ClientMessageType
+{
+    FileRequestType(ClientFileRequestType),
+
+    FileUpload(ClientFileUpload),
+
+    /// Used to upload the chunks of a file before committing it with ```FileUpload```
+    FileTransfer(ClientFileTransfer),
+
+    ///Normal msg
+    NormalMessage(ClientNormalMessage),
+
+    ///Used for syncing with client and server
+    SyncMessage(ClientSyncMessage),
+
+    Reaction(ReactionType),
+
+    MessageEdit(ClientMessageEdit),
+
+    VoipConnection(ClientVoipRequest),
+
+    /// Used to create, rename or delete channels
+    ChannelRequest(ClientChannelRequest),
+
+    /// Private message sent to a single user
+    DirectMessage(ClientDirectMessage),
+
+    /// Used to pin or unpin a message
+    MessagePin(ClientMessagePin),
+
+    /// Used to assign a role to a user
+    RoleRequest(ClientRoleRequest),
+
+    /// Used to take actions against other users
+    ModerationRequest(ClientModerationRequest),
+
+    /// Used to request an older page of the message history
+    HistoryRequest(ClientHistoryRequest),
+
+    /// The reply to the server's ping (```ServerPing```)
+    Pong(ClientPong),
+}
+
+/// The variant of the reaction message
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub enum ReactionType
+{
+    Add(ClientReaction),
+    Remove(ClientReaction),
+}
+
+/// This is what gets to be sent out by the client
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct ClientMessage
+{
+    /// Which message (identified by its id) its replying to
+    pub replying_to: Option<MessageId>,
+
+    /// The message type of the message
+    pub message_type: ClientMessageType,
+
+    /// The every uuid takes up 120 bytes
+    pub uuid: String,
+
+    /// When was this message sent
+    pub message_date: String,
+
+    /// The channel this message was sent in
+    #[serde(default = "default_channel_id")]
+    pub channel_id: String,
+
+    /// Identifies the request if it was sent from the client's outbox, the server acknowledges it once its been handled (```ServerReplyType::Acknowledged```)
+    /// The server doesnt handle the same request twice, so the client can send it again if the connection was lost before the acknowledgement arrived
+    #[serde(default)]
+    pub request_id: Option<String>,
+}
+
+impl ClientMessage
+{
+    ///struct into string, it makes sending information easier by putting it all in a string
+    pub fn struct_into_string(&self) -> String
+    {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+
+    /// Returns the optional feature the request uses, if it uses any
+    pub fn required_capability(&self) -> Option<Capability>
+    {
+        match &self.message_type {
+            ClientMessageType::ChannelRequest(_) => Some(Capability::Channels),
+            ClientMessageType::NormalMessage(_) | ClientMessageType::FileUpload(_)
+                if self.channel_id != DEFAULT_CHANNEL_ID =>
+            {
+                Some(Capability::Channels)
+            },
+            ClientMessageType::DirectMessage(_) => Some(Capability::DirectMessages),
+            ClientMessageType::MessagePin(_)
+            | ClientMessageType::RoleRequest(_)
+            | ClientMessageType::ModerationRequest(_) => Some(Capability::Moderation),
+            ClientMessageType::VoipConnection(_) => Some(Capability::Voip),
+            ClientMessageType::Pong(_) => Some(Capability::Heartbeat),
+            _ => None,
+        }
+    }
+
+    /// This is used when uploading the chunks of a file, or starting and cancelling an upload
+    pub fn construct_file_transfer_msg(transfer: ClientFileTransfer, uuid: &str) -> ClientMessage
+    {
+        ClientMessage {
+            replying_to: None,
+            message_type: ClientMessageType::FileTransfer(transfer),
+            uuid: uuid.to_string(),
+            message_date: { Utc::now().format("%Y.%m.%d. %H:%M").to_string() },
+            channel_id: DEFAULT_CHANNEL_ID.to_string(),
+            request_id: None,
+        }
+    }
+
+    ///this is used when sending a normal message
+    pub fn construct_normal_msg(
+        msg: &str,
+        uuid: &str,
+        replying_to: Option<MessageId>,
+        channel_id: String,
+    ) -> ClientMessage
+    {
+        ClientMessage {
+            replying_to,
+            message_type: ClientMessageType::NormalMessage(ClientNormalMessage {
+                message: msg.trim().to_string(),
+            }),
+            //If the password is set as None (Meaning the user didnt enter any password) just send the message with an empty string
+            uuid: uuid.to_string(),
+            message_date: { Utc::now().format("%Y.%m.%d. %H:%M").to_string() },
+            channel_id,
+            request_id: None,
+        }
+    }
+
+    /// This is used when sending a private message to another user (Identified by their uuid)
+    pub fn construct_direct_msg(msg: &str, uuid: &str, recipient: String) -> ClientMessage
+    {
+        ClientMessage {
+            replying_to: None,
+            message_type: ClientMessageType::DirectMessage(ClientDirectMessage {
+                recipient,
+                message: msg.trim().to_string(),
+            }),
+            uuid: uuid.to_string(),
+            message_date: { Utc::now().format("%Y.%m.%d. %H:%M").to_string() },
+            channel_id: DEFAULT_CHANNEL_ID.to_string(),
+            request_id: None,
+        }
+    }
+
+    ///this is used when you want to send a file, this contains name, hash and size
+    ///The file has to be uploaded in chunks (```ClientFileTransfer```) before this message commits it
+    pub fn construct_file_msg(
+        file_path: &Path,
+        hash: String,
+        size: u64,
+        uuid: &str,
+        replying_to: Option<MessageId>,
+        channel_id: String,
+    ) -> ClientMessage
+    {
+        ClientMessage {
+            replying_to,
+            message_type: ClientMessageType::FileUpload(ClientFileUpload {
+                extension: file_path
+                    .extension()
+                    .map(|extension| extension.to_string_lossy().to_string()),
+                name: file_path
+                    .file_stem()
+                    .map(|name| name.to_string_lossy().to_string()),
+                hash,
+                size,
+            }),
+
+            uuid: uuid.to_string(),
+            message_date: { Utc::now().format("%Y.%m.%d. %H:%M").to_string() },
+            channel_id,
+            request_id: None,
+        }
+    }
+
+    pub fn construct_reaction_msg(
+        emoji_name: String,
+        message_id: MessageId,
+        uuid: &str,
+    ) -> ClientMessage
+    {
+        ClientMessage {
+            replying_to: None,
+            message_type: ClientMessageType::Reaction(ReactionType::Add(ClientReaction {
+                emoji_name,
+                message_id,
+                uuid: uuid.to_string(),
+            })),
+            uuid: uuid.to_string(),
+            message_date: { Utc::now().format("%Y.%m.%d. %H:%M").to_string() },
+            channel_id: DEFAULT_CHANNEL_ID.to_string(),
+            request_id: None,
+        }
+    }
+
+    pub fn construct_reaction_remove_msg(
+        emoji_name: String,
+        message_id: MessageId,
+        uuid: &str,
+    ) -> ClientMessage
+    {
+        ClientMessage {
+            replying_to: None,
+            message_type: ClientMessageType::Reaction(ReactionType::Remove(ClientReaction {
+                emoji_name,
+                message_id,
+                uuid: uuid.to_string(),
+            })),
+            uuid: uuid.to_string(),
+            message_date: { Utc::now().format("%Y.%m.%d. %H:%M").to_string() },
+            channel_id: DEFAULT_CHANNEL_ID.to_string(),
+            request_id: None,
+        }
+    }
+
+    /// this is used for constructing a sync msg aka sending an empty packet, so server can reply
+    /// If its None its used for syncing, false: disconnecting, true: connecting
+    /// The ```channel_id``` is the channel the ```last_seen_message_id``` belongs to
+    pub fn construct_sync_msg(
+        password: &str,
+        author: &str,
+        uuid: &str,
+        last_seen_message_id: Option<MessageId>,
+        channel_id: String,
+    ) -> ClientMessage
+    {
+        ClientMessage {
+            replying_to: None,
+            message_type: ClientMessageType::SyncMessage(ClientSyncMessage {
+                sync_attribute: None,
+                password: password.to_string(),
+                //This value is only used when connecting
+                last_message_id: None,
+                last_seen_message_id,
+                resync: false,
+                last_revision: None,
+                reconnect_token: None,
+                username: author.to_string(),
+            }),
+            uuid: uuid.to_string(),
+            message_date: { Utc::now().format("%Y.%m.%d. %H:%M").to_string() },
+            channel_id,
+            request_id: None,
+        }
+    }
+
+    /// If its None its used for syncing, false: disconnecting, true: connecting
+    /// The ```last_message_id``` is the id of the newest message the client already has, so that only the missing messages are sent (```None``` if the client doesnt have any)
+    /// The ```last_revision``` is the revision of the last sync the client has received, so that the changes of the messages it already has are sent too
+    /// The ```reconnect_token``` is the token received when the client last connected, it proves that the client owns the connection the server might still consider open
+    #[allow(clippy::too_many_arguments)]
+    pub fn construct_connection_msg(
+        password: String,
+        author: String,
+        uuid: &str,
+        last_seen_message_id: Option<MessageId>,
+        last_message_id: Option<MessageId>,
+        last_revision: Option<u64>,
+        reconnect_token: Option<String>,
+        profile: ClientProfile,
+    ) -> ClientMessage
+    {
+        ClientMessage {
+            replying_to: None,
+            message_type: ClientMessageType::SyncMessage(ClientSyncMessage {
+                sync_attribute: Some(ConnectionType::Connect(profile)),
+                password,
+                last_message_id,
+                last_seen_message_id,
+                resync: false,
+                last_revision,
+                reconnect_token,
+                username: author,
+            }),
+            uuid: uuid.to_string(),
+            message_date: { Utc::now().format("%Y.%m.%d. %H:%M").to_string() },
+            channel_id: DEFAULT_CHANNEL_ID.to_string(),
+            request_id: None,
+        }
+    }
+
+    /// This is used for requesting a full sync, when the client has missed some of the messages
+    /// The ```last_message_id``` is the id of the newest message the client already has, if its ```None``` the messages the client has are replaced with the most recent pages
+    /// The ```last_revision``` is the revision of the last sync the client has received, so that the changes of the messages it already has are sent too
+    pub fn construct_resync_msg(
+        password: &str,
+        author: &str,
+        uuid: &str,
+        last_message_id: Option<MessageId>,
+        last_revision: Option<u64>,
+    ) -> ClientMessage
+    {
+        ClientMessage {
+            replying_to: None,
+            message_type: ClientMessageType::SyncMessage(ClientSyncMessage {
+                sync_attribute: None,
+                password: password.to_string(),
+                last_message_id,
+                last_seen_message_id: None,
+                resync: true,
+                last_revision,
+                reconnect_token: None,
+                username: author.to_string(),
+            }),
+            uuid: uuid.to_string(),
+            message_date: { Utc::now().format("%Y.%m.%d. %H:%M").to_string() },
+            channel_id: DEFAULT_CHANNEL_ID.to_string(),
+            request_id: None,
+        }
+    }
+
+    /// If its None its used for syncing, false: disconnecting, true: connecting
+    /// Please note that its doesnt really matter what we pass in the author because the server identifies us based on our ip address
+    pub fn construct_disconnection_msg(
+        password: String,
+        author: String,
+        uuid: String,
+    ) -> ClientMessage
+    {
+        ClientMessage {
+            replying_to: None,
+            message_type: ClientMessageType::SyncMessage(ClientSyncMessage {
+                sync_attribute: Some(ConnectionType::Disconnect),
+                password,
+                //If its used for disconnecting this value is ignored
+                last_message_id: None,
+                last_seen_message_id: None,
+                resync: false,
+                last_revision: None,
+                reconnect_token: None,
+                username: author,
+            }),
+            uuid,
+            message_date: { Utc::now().format("%Y.%m.%d. %H:%M").to_string() },
+            channel_id: DEFAULT_CHANNEL_ID.to_string(),
+            request_id: None,
+        }
+    }
+
+    ///this is used for asking for a chunk of a file, starting at the offset
+    pub fn construct_file_request_msg(signature: String, offset: u64, uuid: &str) -> ClientMessage
+    {
+        ClientMessage {
+            replying_to: None,
+            message_type: ClientMessageType::FileRequestType(ClientFileRequestType::FileRequest(
+                ClientFileRequest { signature, offset },
+            )),
+            uuid: uuid.to_string(),
+            message_date: { Utc::now().format("%Y.%m.%d. %H:%M").to_string() },
+            channel_id: DEFAULT_CHANNEL_ID.to_string(),
+            request_id: None,
+        }
+    }
+
+    ///this is used for asking for the page of the channel's message history before the message (identified by its id)
+    pub fn construct_history_request_msg(
+        before_id: MessageId,
+        channel_id: String,
+        uuid: &str,
+    ) -> ClientMessage
+    {
+        ClientMessage {
+            replying_to: None,
+            message_type: ClientMessageType::HistoryRequest(ClientHistoryRequest { before_id }),
+            uuid: uuid.to_string(),
+            message_date: { Utc::now().format("%Y.%m.%d. %H:%M").to_string() },
+            channel_id,
+            request_id: None,
+        }
+    }
+
+    ///this is used for answering the server's ping, the date the ping was sent at is echoed back
+    pub fn construct_pong_msg(sent_at: DateTime<Utc>, uuid: &str) -> ClientMessage
+    {
+        ClientMessage {
+            replying_to: None,
+            message_type: ClientMessageType::Pong(ClientPong { sent_at }),
+            uuid: uuid.to_string(),
+            message_date: { Utc::now().format("%Y.%m.%d. %H:%M").to_string() },
+            channel_id: DEFAULT_CHANNEL_ID.to_string(),
+            request_id: None,
+        }
+    }
+
+    ///this is used for asking for an image
+    pub fn construct_image_request_msg(signature: String, uuid: &str) -> ClientMessage
+    {
+        ClientMessage {
+            replying_to: None,
+            message_type: ClientMessageType::FileRequestType(ClientFileRequestType::ImageRequest(
+                ClientImageRequest { signature },
+            )),
+            uuid: uuid.to_string(),
+            message_date: { Utc::now().format("%Y.%m.%d. %H:%M").to_string() },
+            channel_id: DEFAULT_CHANNEL_ID.to_string(),
+            request_id: None,
+        }
+    }
+
+    ///this is used for asking for an image
+    pub fn construct_audio_request_msg(
+        signature: String,
+        uuid: &str,
+        message_id: MessageId,
+    ) -> ClientMessage
+    {
+        ClientMessage {
+            replying_to: None,
+            message_type: ClientMessageType::FileRequestType(ClientFileRequestType::AudioRequest(
+                ClientAudioRequest {
+                    signature,
+                    message_id,
+                },
+            )),
+            uuid: uuid.to_string(),
+            message_date: { Utc::now().format("%Y.%m.%d. %H:%M").to_string() },
+            channel_id: DEFAULT_CHANNEL_ID.to_string(),
+            request_id: None,
+        }
+    }
+
+    pub fn construct_client_request_msg(
+        uuid_of_requested_client: String,
+        uuid: &str,
+    ) -> ClientMessage
+    {
+        ClientMessage {
+            replying_to: None,
+            message_type: ClientMessageType::FileRequestType(ClientFileRequestType::ClientRequest(
+                uuid_of_requested_client,
+            )),
+            uuid: uuid.to_string(),
+            message_date: { Utc::now().format("%Y.%m.%d. %H:%M").to_string() },
+            channel_id: DEFAULT_CHANNEL_ID.to_string(),
+            request_id: None,
+        }
+    }
+
+    pub fn construct_client_message_edit(
+        message_id: MessageId,
+        new_message: Option<String>,
+        uuid: &str,
+    ) -> ClientMessage
+    {
+        ClientMessage {
+            replying_to: None,
+            message_type: ClientMessageType::MessageEdit(ClientMessageEdit {
+                message_id,
+                new_message,
+            }),
+            uuid: uuid.to_string(),
+            message_date: { Utc::now().format("%Y.%m.%d. %H:%M").to_string() },
+            channel_id: DEFAULT_CHANNEL_ID.to_string(),
+            request_id: None,
+        }
+    }
+
+    pub fn construct_voip_connect(uuid: &str, port: u16) -> ClientMessage
+    {
+        ClientMessage {
+            replying_to: None,
+            message_type: ClientMessageType::VoipConnection(ClientVoipRequest::Connect(port)),
+            uuid: uuid.to_string(),
+            message_date: { Utc::now().format("%Y.%m.%d. %H:%M").to_string() },
+            channel_id: DEFAULT_CHANNEL_ID.to_string(),
+            request_id: None,
+        }
+    }
+
+    pub fn construct_voip_disconnect(uuid: &str) -> ClientMessage
+    {
+        ClientMessage {
+            replying_to: None,
+            message_type: ClientMessageType::VoipConnection(ClientVoipRequest::Disconnect),
+            uuid: uuid.to_string(),
+            message_date: { Utc::now().format("%Y.%m.%d. %H:%M").to_string() },
+            channel_id: DEFAULT_CHANNEL_ID.to_string(),
+            request_id: None,
+        }
+    }
+
+    /// This is used for creating, renaming or deleting a channel
+    pub fn construct_channel_request(request: ClientChannelRequest, uuid: &str) -> ClientMessage
+    {
+        ClientMessage {
+            replying_to: None,
+            message_type: ClientMessageType::ChannelRequest(request),
+            uuid: uuid.to_string(),
+            message_date: { Utc::now().format("%Y.%m.%d. %H:%M").to_string() },
+            channel_id: DEFAULT_CHANNEL_ID.to_string(),
+            request_id: None,
+        }
+    }
+
+    /// This is used for pinning or unpinning a message
+    pub fn construct_message_pin(message_id: MessageId, pinned: bool, uuid: &str) -> ClientMessage
+    {
+        ClientMessage {
+            replying_to: None,
+            message_type: ClientMessageType::MessagePin(ClientMessagePin { message_id, pinned }),
+            uuid: uuid.to_string(),
+            message_date: { Utc::now().format("%Y.%m.%d. %H:%M").to_string() },
+            channel_id: DEFAULT_CHANNEL_ID.to_string(),
+            request_id: None,
+        }
+    }
+
+    /// This is used for assigning a role to a user (identified by ```target_uuid```)
+    pub fn construct_role_request(target_uuid: String, role: Role, uuid: &str) -> ClientMessage
+    {
+        ClientMessage {
+            replying_to: None,
+            message_type: ClientMessageType::RoleRequest(ClientRoleRequest {
+                uuid: target_uuid,
+                role,
+            }),
+            uuid: uuid.to_string(),
+            message_date: { Utc::now().format("%Y.%m.%d. %H:%M").to_string() },
+            channel_id: DEFAULT_CHANNEL_ID.to_string(),
+            request_id: None,
+        }
+    }
+
+    /// This is used for taking an action against another user (for example banning them)
+    pub fn construct_moderation_request(
+        request: ClientModerationRequest,
+        uuid: &str,
+    ) -> ClientMessage
+    {
+        ClientMessage {
+            replying_to: None,
+            message_type: ClientMessageType::ModerationRequest(request),
+            uuid: uuid.to_string(),
+            message_date: { Utc::now().format("%Y.%m.%d. %H:%M").to_string() },
+            channel_id: DEFAULT_CHANNEL_ID.to_string(),
+            request_id: None,
+        }
+    }
+
+    pub fn construct_voip_event(uuid: String, event: ClientVoipRequest) -> ClientMessage
+    {
+        ClientMessage {
+            replying_to: None,
+            message_type: ClientMessageType::VoipConnection(event),
+            uuid: uuid.to_string(),
+            message_date: { Utc::now().format("%Y.%m.%d. %H:%M").to_string() },
+            channel_id: DEFAULT_CHANNEL_ID.to_string(),
+            request_id: None,
+        }
+    }
+}
+
+/*
+    Server. . .
+
+    Are used to convert client sent messages into a server message, so it can be sent back;
+    Therefor they're smaller in size
+*/
+
+/*
+        NOTICE:
+
+
+    .... Upload : is always what the server sends back to the client (so the client knows what to ask about)
+
+    .... Reply : is always what the server send to the client after the client asked.
+
+*/
+
+///This is what the server sends back (pushes to message vector), when receiving a file
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub struct ServerFileUpload
+{
+    /// The uploaded file's name
+    pub file_name: String,
+    /// The uploaded file's sha256 singnature
+    pub signature: String,
+}
+
+/// This enum holds all the Server reply types so it can be decoded more easily on the client side
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub enum ServerReplyType
+{
+    /// Returns the requested file
+    File(ServerFileReply),
+
+    /// Returns the requested image
+    Image(ServerImageReply),
+
+    /// Returns the requested audio file
+    Audio(ServerAudioReply),
+
+    /// Returns the offset a started upload continues from
+    Upload(ServerUploadReply),
+
+    /// The requested client's profile
+    /// The first value is the encrypted uuid
+    Client(ServerClientReply),
+
+    /// Returns the requested page of the message history
+    History(ServerHistoryPage),
+
+    /// The request (identified by its ```request_id```) has been handled, it has either been applied or rejected with a ```RequestError```
+    Acknowledged(String),
+}
+
+/// An older page of the message history, this is what the server sends back when a client requests it (```ClientHistoryRequest```)
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Default)]
+pub struct ServerHistoryPage
+{
+    /// The channel the page belongs to
+    pub channel_id: String,
+
+    /// The messages of the page
+    pub message_list: Vec<ServerOutput>,
+
+    /// Whether there are even older messages in the channel before the page
+    pub has_older_messages: bool,
+}
+
+/// This struct holds everything important so the client can save and handle client profiles
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct ServerClientReply
+{
+    /// The uuid of the user's profile we requested
+    pub uuid: String,
+    /// The profile of the user
+    pub profile: ClientProfile,
+}
+
+///When client asks for the image based on the provided index, reply with the image bytes
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct ServerImageReply
+{
+    /// The requested image's bytes
+    pub bytes: Vec<u8>,
+
+    /// The requested image's sha256 signature
+    pub signature: String,
+}
+
+///This is what the server sends back, when asked for a file (FIleRequest)
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct ServerFileReply
+{
+    /// The bytes of the requested chunk
+    pub bytes: Vec<u8>,
+
+    /// The requested file's name
+    /// The reason a ```PathBuf``` is used here instead of a String, is because we need to grab the extension of the file easily
+    pub file_name: PathBuf,
+
+    /// The signature of the requested file, this is the sha256 hash of the whole file
+    pub signature: String,
+
+    /// The offset of the chunk in the file
+    pub offset: u64,
+
+    /// The size of the whole file in bytes, the file has been downloaded once ```offset + bytes.len()``` reaches this
+    pub total_size: u64,
+}
+
+/// This is what the server sends back, when an upload is started (```ClientFileTransfer::StartUpload```)
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct ServerUploadReply
+{
+    /// The hash of the file being uploaded
+    pub hash: String,
+
+    /// The number of bytes the server has already received, the next chunk has to be sent from this offset
+    pub offset: u64,
+}
+
+///When client asks for the image based on the provided index, reply with the audio bytes, which gets written so it can be opened by a readbuf
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct ServerAudioReply
+{
+    /// The requested audio file's bytes
+    pub bytes: Vec<u8>,
+    /// The requested audio file's signature
+    pub signature: String,
+    /// The requested audio file's name
+    pub file_name: String,
+
+    /// The id of the message the audio file belongs to
+    pub message_id: MessageId,
+}
+
+///This is what the server sends back (pushes to message vector), when receiving a normal message
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub struct ServerNormalMessage
+{
+    pub has_been_edited: bool,
+    pub message: String,
+}
+
+/// This is what the server sends back to the sender and the recipient of a private message, these messages are stored separately from the public ones
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub struct ServerDirectMessage
+{
+    /// The uuid of the user the message was addressed to, the sender's uuid is found in the ```ServerOutput``` wrapping this message
+    pub recipient: String,
+
+    pub message: String,
+}
+
+///REFER TO -> ServerImageUpload; logic      ||      same thing but with audio files
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub struct ServerAudioUpload
+{
+    /// The signature of the uploaded image file
+    pub signature: String,
+    /// The file name of the uploaded audio
+    pub file_name: String,
+}
+
+///This is what gets sent to a client basically, and they have to ask for the file when the ui containing this gets rendered
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub struct ServerImageUpload
+{
+    /// The signature of the uploaded image, this is the "handle" the clients asks the file on
+    pub signature: String,
+}
+
+/// This struct contains all the important information for the client to edit / update its own message list
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub struct ServerMessageEdit
+{
+    /// The id of the message it belongs to
+    pub message_id: MessageId,
+
+    /// None indicates a deleted message, rest is self explanatory
+    pub new_message: Option<String>,
+}
+
+/// This struct contains all the necessarily information for the client to update its own message list's reactions
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub struct ServerMessageReaction
+{
+    pub reaction_type: ReactionType,
+}
+
+/// This struct contains the server's updated channel list
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub struct ServerChannelUpdate
+{
+    /// All the channels of the server
+    pub channels: Vec<Channel>,
+}
+
+/// This struct contains the server's updated list of pinned messages
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub struct ServerPinUpdate
+{
+    /// The ids of the pinned messages
+    pub pinned_messages: Vec<MessageId>,
+}
+
+/// This struct contains the server's updated list of the users' roles
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub struct ServerRoleUpdate
+{
+    /// The roles of the users, the key is the user's uuid
+    pub roles: HashMap<String, Role>,
+}
+
+/// This struct is empty as its just a placeholder, because the info is provided in the struct which this message is wrapped in, and is provided directly when sending a message from the server to the client
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub struct ServerMessageSync {}
+
+/// These are the possible server replies
+/// Why do we have to implement PartialEq for all of the structs? This is so funny
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, EnumDiscriminants, PartialEq)]
+#[strum_discriminants(derive(EnumString, EnumMessage))]
+pub enum ServerMessageType
+{
+    #[strum_discriminants(strum(message = "Upload"))]
+    Upload(ServerFileUpload),
+    #[strum_discriminants(strum(message = "Normal"))]
+    Normal(ServerNormalMessage),
+
+    ///Used to send and index to client so it knows which index to ask for
+    ///The index provided by this enum
+    #[strum_discriminants(strum(message = "Image"))]
+    Image(ServerImageUpload),
+    #[strum_discriminants(strum(message = "Audio"))]
+    Audio(ServerAudioUpload),
+
+    ///When a message is deleted this is what gets displayed
+    #[strum_discriminants(strum(message = "Deleted"))]
+    Deleted,
+
+    ///This message indicates an edit in the server's message list, therefor we need to send a message to the client so that the client will update its own list
+    #[strum_discriminants(strum(message = "Edit"))]
+    Edit(ServerMessageEdit),
+
+    ///This message indicates an edit in the server's message list, therefor we need to send a message to the client so that the client will update its own list
+    #[strum_discriminants(strum(message = "Reaction"))]
+    Reaction(ServerMessageReaction),
+
+    /// This message is used to "sync" with the client, it provides useful information to the client about other clients (like last viewed message)
+    #[strum_discriminants(strum(message = "Sync"))]
+    Sync(ServerMessageSync),
+
+    /// This message type can only be produced by the server, and hold useful information to the user(s)
+    #[strum_discriminants(strum(message = "Server"))]
+    Server(ServerMessage),
+
+    /// This message shows if a user has connected to the voip call
+    #[strum_discriminants(strum(message = "Voip connection"))]
+    VoipEvent(ServerVoipEvent),
+
+    /// This message holds the State of the Voip service
+    #[strum_discriminants(strum(message = "Voip state"))]
+    VoipState(ServerVoipState),
+
+    /// This message holds the updated list of the channels, its sent after a channel has been created, renamed or deleted
+    #[strum_discriminants(strum(message = "Channel update"))]
+    ChannelUpdate(ServerChannelUpdate),
+
+    /// A private message between two users, this is never added to the public message list
+    #[strum_discriminants(strum(message = "Direct message"))]
+    DirectMessage(ServerDirectMessage),
+
+    /// This message holds the updated list of the pinned messages, its sent after a message has been pinned or unpinned
+    #[strum_discriminants(strum(message = "Pin update"))]
+    PinUpdate(ServerPinUpdate),
+
+    /// This message holds the updated roles of the users, its sent after a role has been assigned
+    #[strum_discriminants(strum(message = "Role update"))]
+    RoleUpdate(ServerRoleUpdate),
+
+    /// This message is sent to the client whose request has been rejected, its never added to the message list
+    #[strum_discriminants(strum(message = "Request error"))]
+    RequestError(RequestError),
+}
+
+/// The types of message the server can "send"
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub enum ServerMessage
+{
+    /// This is sent when a user is connected to the server
+    Connect(ClientProfile),
+    /// This is sent when a user is disconnecting from the server
+    Disconnect(ClientProfile),
+
+    /// This is sent when a user is banned from the server
+    Ban(ClientProfile),
+
+    /// This is sent when a user is kicked from the server
+    Kick(ClientProfile),
+
+    /// This is sent when a user is muted, the date shows when the mute expires
+    Mute(ClientProfile, DateTime<Utc>),
+
+    /// This is sent when a user is timed out, the date shows when the timeout expires
+    Timeout(ClientProfile, DateTime<Utc>),
+}
+
+/// The reasons the server can reject a request for, this is only sent to the client whose request was rejected
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub enum RequestError
+{
+    /// The user is muted, they cannot send messages, upload files, react or edit their messages until the mute expires
+    Muted(DateTime<Utc>),
+
+    /// The user (or their address) is sending this kind of request too fast
+    RateLimited(RateLimitedAction),
+
+    /// The request is invalid, it exceeds a limit or refers to something which doesnt exist
+    Invalid(ValidationError),
+
+    /// The upload would exceed one of the server's storage limits
+    QuotaExceeded(QuotaExceeded),
+
+    /// The server couldnt store the upload (identified by its hash), it has to be uploaded again
+    UploadFailed(String),
+
+    /// The user's role doesnt have the permission the request requires
+    PermissionDenied(Permission),
+}
+
+impl std::fmt::Display for RequestError
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        match self {
+            RequestError::Muted(until) => {
+                write!(
+                    f,
+                    "You are muted until {}!",
+                    until.format("%Y.%m.%d. %H:%M")
+                )
+            },
+            RequestError::RateLimited(action) => {
+                write!(
+                    f,
+                    "You are sending {} too fast, slow down!",
+                    match action {
+                        RateLimitedAction::Message => "messages",
+                        RateLimitedAction::Upload => "files",
+                        RateLimitedAction::Reaction => "reactions",
+                    }
+                )
+            },
+            RequestError::Invalid(validation_error) => write!(f, "{validation_error}"),
+            RequestError::QuotaExceeded(quota_exceeded) => write!(f, "{quota_exceeded}"),
+            RequestError::UploadFailed(hash) => {
+                write!(f, "The upload ({hash}) could not be stored, try again!")
+            },
+            RequestError::PermissionDenied(permission) => {
+                write!(
+                    f,
+                    "You do not have the permission to {}!",
+                    match permission {
+                        Permission::SendMessages => "send messages",
+                        Permission::UploadFiles => "upload files",
+                        Permission::React => "react to messages",
+                        Permission::StartCalls => "start calls",
+                        Permission::PinMessages => "pin messages",
+                        Permission::DeleteOthersMessages => "delete other users' messages",
+                        Permission::ManageChannels => "manage the channels",
+                        Permission::Kick => "kick users",
+                        Permission::Mute => "mute users",
+                        Permission::Ban => "ban users",
+                        Permission::ManageRoles => "manage the roles",
+                    }
+                )
+            },
+        }
+    }
+}
+
+impl std::error::Error for RequestError {}
+
+/// The storage limits an upload can exceed, the sizes are in bytes
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub enum QuotaExceeded
+{
+    /// The file is larger than the maximum size of its media type
+    FileSize
+    {
+        media_type: MediaType, max: u64
+    },
+
+    /// The user's uploads would take up more space than the per user quota
+    UserQuota
+    {
+        used: u64, quota: u64
+    },
+
+    /// The server's uploads would take up more space than the total quota
+    TotalQuota
+    {
+        quota: u64
+    },
+}
+
+impl std::fmt::Display for QuotaExceeded
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        match self {
+            QuotaExceeded::FileSize { media_type, max } => {
+                write!(
+                    f,
+                    "The {media_type} is too large, the maximum size is {}!",
+                    format_size(*max)
+                )
+            },
+            QuotaExceeded::UserQuota { used, quota } => {
+                write!(
+                    f,
+                    "You have run out of storage, you have used {} of {}!",
+                    format_size(*used),
+                    format_size(*quota)
+                )
+            },
+            QuotaExceeded::TotalQuota { quota } => {
+                write!(
+                    f,
+                    "The server has run out of storage, it can store {} of uploads!",
+                    format_size(*quota)
+                )
+            },
+        }
+    }
+}
+
+impl std::error::Error for QuotaExceeded {}
+
+/// The kinds of uploads, every kind has its own maximum size
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MediaType
+{
+    /// Images, these are displayed in the chat
+    Image,
+
+    /// Audio files, these can be played from the chat
+    Audio,
+
+    /// Every other file
+    File,
+}
+
+impl MediaType
+{
+    /// Returns the media type of the file based on its extension, NOTICE: ENSURE CLIENT COMPATIBILITY
+    pub fn from_extension(extension: Option<&str>) -> Self
+    {
+        match extension.unwrap_or_default() {
+            "png" | "jpeg" | "bmp" | "tiff" | "webp" | "gif" | "jpg" => MediaType::Image,
+            "wav" | "mp3" | "m4a" => MediaType::Audio,
+            _ => MediaType::File,
+        }
+    }
+}
+
+impl std::fmt::Display for MediaType
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        f.write_str(match self {
+            MediaType::Image => "image",
+            MediaType::Audio => "audio file",
+            MediaType::File => "file",
+        })
+    }
+}
+
+/// The reasons a request can be invalid for, the server checks every request before applying it
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub enum ValidationError
+{
+    /// The frame the request was sent in is larger than the maximum size
+    FrameTooLarge
+    {
+        size: u32, max: u32
+    },
+
+    /// The request could not be parsed
+    MalformedRequest,
+
+    /// The request was sent in the name of a user who isnt connected through the connection the request was sent on
+    UuidMismatch,
+
+    /// A text of the request (message, name, reason) is longer than the maximum length in characters
+    TextTooLong
+    {
+        max: usize
+    },
+
+    /// The profile picture is larger than the maximum size in bytes
+    ProfilePictureTooLarge
+    {
+        max: usize
+    },
+
+    /// The uploaded file is larger than the maximum size in bytes
+    FileTooLarge
+    {
+        max: usize
+    },
+
+    /// The message (identified by its id) the request refers to doesnt exist
+    MessageNotFound(MessageId),
+
+    /// The channel (identified by its id) the message was sent to doesnt exist (anymore)
+    ChannelNotFound(String),
+
+    /// The user tried to edit or delete a message (identified by its id) they havent sent
+    NotMessageAuthor(MessageId),
+
+    /// The file (identified by its signature) the request refers to doesnt exist
+    FileNotFound(String),
+
+    /// The chunk is larger than the maximum size in bytes, or it would exceed the declared size of the file
+    ChunkTooLarge
+    {
+        max: usize
+    },
+
+    /// The upload (identified by its hash) the request refers to hasnt been started
+    UploadNotStarted(String),
+
+    /// The chunk was sent from a different offset than where the upload continues from
+    UploadOffsetMismatch
+    {
+        offset: u64, expected: u64
+    },
+
+    /// The upload was committed before all of its chunks had been sent
+    UploadIncomplete
+    {
+        received: u64, size: u64
+    },
+
+    /// The hash of the uploaded file doesnt match the hash the upload was started with, the received chunks are discarded
+    UploadHashMismatch(String),
+
+    /// The requested chunk starts after the end of the file
+    OffsetOutOfRange
+    {
+        offset: u64, size: u64
+    },
+
+    /// The user (identified by their uuid) the request refers to doesnt exist
+    UserNotFound(String),
+
+    /// The user (identified by their uuid) the request refers to isnt connected
+    UserNotConnected(String),
+
+    /// The request uses an optional feature which wasnt agreed on in the handshake
+    UnsupportedCapability(Capability),
+}
+
+impl std::fmt::Display for ValidationError
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        match self {
+            ValidationError::FrameTooLarge { size, max } => {
+                write!(
+                    f,
+                    "The request is too large ({size} bytes), it can be at most {max} bytes!"
+                )
+            },
+            ValidationError::MalformedRequest => write!(f, "The request could not be parsed!"),
+            ValidationError::UuidMismatch => {
+                write!(f, "The request was sent in the name of another user!")
+            },
+            ValidationError::TextTooLong { max } => {
+                write!(
+                    f,
+                    "The text is too long, it can be at most {max} characters long!"
+                )
+            },
+            ValidationError::ProfilePictureTooLarge { max } => {
+                write!(
+                    f,
+                    "The profile picture is too large, it can be at most {max} bytes!"
+                )
+            },
+            ValidationError::FileTooLarge { max } => {
+                write!(f, "The file is too large, it can be at most {max} bytes!")
+            },
+            ValidationError::MessageNotFound(message_id) => {
+                write!(f, "The message ({message_id}) does not exist!")
+            },
+            ValidationError::ChannelNotFound(channel_id) => {
+                write!(f, "The channel ({channel_id}) does not exist!")
+            },
+            ValidationError::NotMessageAuthor(message_id) => {
+                write!(f, "The message ({message_id}) was sent by another user!")
+            },
+            ValidationError::FileNotFound(signature) => {
+                write!(f, "The file ({signature}) does not exist!")
+            },
+            ValidationError::ChunkTooLarge { max } => {
+                write!(f, "The chunk is too large, it can be at most {max} bytes!")
+            },
+            ValidationError::UploadNotStarted(hash) => {
+                write!(f, "The upload ({hash}) has not been started!")
+            },
+            ValidationError::UploadOffsetMismatch { offset, expected } => {
+                write!(
+                    f,
+                    "The chunk was sent from offset {offset}, but the upload continues from {expected}!"
+                )
+            },
+            ValidationError::UploadIncomplete { received, size } => {
+                write!(
+                    f,
+                    "The upload is incomplete, only {received} of {size} bytes have been received!"
+                )
+            },
+            ValidationError::UploadHashMismatch(hash) => {
+                write!(
+                    f,
+                    "The uploaded file does not match its hash ({hash}), the upload has to be restarted!"
+                )
+            },
+            ValidationError::OffsetOutOfRange { offset, size } => {
+                write!(
+                    f,
+                    "The offset ({offset}) is past the end of the file ({size} bytes)!"
+                )
+            },
+            ValidationError::UserNotFound(uuid) => write!(f, "The user ({uuid}) does not exist!"),
+            ValidationError::UserNotConnected(uuid) => {
+                write!(f, "The user ({uuid}) is not connected!")
+            },
+            ValidationError::UnsupportedCapability(capability) => {
+                write!(
+                    f,
+                    "The request uses a feature ({capability:?}) which is not supported by both sides!"
+                )
+            },
+        }
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+/// The optional features of the protocol, a feature is only used if both the client and the server support it
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Capability
+{
+    /// Text channels, the clients without this can only use the default channel
+    Channels,
+
+    /// Private messages between two users
+    DirectMessages,
+
+    /// Roles, pinned messages and the moderation requests
+    Moderation,
+
+    /// Voice calls
+    Voip,
+
+    /// The binary frames are compressed with zstd, see ```FrameCompression```
+    Compression,
+
+    /// The server pings the client periodically (```ServerPing```), and disconnects it if it stops answering
+    Heartbeat,
+
+    /// A feature of a newer build, which this build doesnt know about
+    #[serde(other)]
+    Unknown,
+}
+
+impl Capability
+{
+    /// The capabilities this build supports
+    pub const SUPPORTED: [Capability; 6] = [
+        Capability::Channels,
+        Capability::DirectMessages,
+        Capability::Moderation,
+        Capability::Voip,
+        Capability::Compression,
+        Capability::Heartbeat,
+    ];
+}
+
+/// This is the first frame the client sends, the server replies with ```ServerControlReply::Handshake``` if it can talk to the client
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub struct ClientHandshake
+{
+    /// The revision of the protocol the client speaks
+    pub protocol_version: u32,
+
+    /// The optional features the client supports
+    pub capabilities: Vec<Capability>,
+}
+
+impl Default for ClientHandshake
+{
+    fn default() -> Self
+    {
+        Self {
+            protocol_version: PROTOCOL_VERSION,
+            capabilities: Capability::SUPPORTED.to_vec(),
+        }
+    }
+}
+
+impl ClientHandshake
+{
+    pub fn struct_into_string(&self) -> String
+    {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+
+    /// Returns whether this build can talk to the client
+    pub fn is_supported(&self) -> bool
+    {
+        self.protocol_version >= MIN_PROTOCOL_VERSION
+    }
+
+    /// Returns the revision of the protocol both sides speak, a newer client has to talk to an older server in the server's revision
+    pub fn negotiate_version(&self) -> u32
+    {
+        self.protocol_version.min(PROTOCOL_VERSION)
+    }
+
+    /// Returns the capabilities both the client and this build support
+    pub fn negotiate_capabilities(&self) -> Vec<Capability>
+    {
+        Capability::SUPPORTED
+            .into_iter()
+            .filter(|capability| self.capabilities.contains(capability))
+            .collect()
+    }
+}
+
+/// The replies the server sends outside of the encrypted messages, these are sent as json
+/// The connection request is always answered with one of these, and one of these is sent before the server closes a connection
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub enum ServerControlReply
+{
+    /// The reply to the client's handshake, this contains the revision of the protocol and the capabilities both sides support
+    Handshake
+    {
+        protocol_version: u32,
+        capabilities: Vec<Capability>,
+    },
+
+    /// The server cannot talk to the client, if ```client_version``` is ```None``` the client hasnt sent a handshake (It is older than the handshake itself)
+    UnsupportedProtocol
+    {
+        client_version: Option<u32>,
+        min_version: u32,
+        max_version: u32,
+    },
+
+    /// The connection has been accepted, the rest of the messages are going to be encrypted with this key (hex encoded)
+    Accepted(String),
+
+    /// The password the client has connected with doesnt match the server's password
+    InvalidPassword,
+
+    /// The user (or their address) is banned, if ```expires_at``` is ```None``` the ban is permanent
+    Banned
+    {
+        reason: String,
+        expires_at: Option<DateTime<Utc>>,
+    },
+
+    /// The user is timed out until the date, they cannot reconnect until then
+    TimedOut(DateTime<Utc>),
+
+    /// The user has been kicked, they can reconnect right away
+    Kicked,
+
+    /// There are too many connections open from the user's address
+    TooManyConnections,
+
+    /// The server has disconnected the client, because the client has requested it
+    Disconnected,
+
+    /// The server is shutting down
+    Shutdown,
+
+    /// The user is already connected, and the new connection hasnt proven that it belongs to them (```ClientSyncMessage::reconnect_token```)
+    AlreadyConnected,
+
+    /// The client has sent an invalid request, which cannot be answered with a ```RequestError```
+    ProtocolError(ValidationError),
+}
+
+impl ServerControlReply
+{
+    pub fn struct_into_string(&self) -> String
+    {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+
+    /// Returns whether the server is going to close the connection after sending this reply
+    pub fn closes_connection(&self) -> bool
+    {
+        !matches!(
+            self,
+            ServerControlReply::Handshake { .. } | ServerControlReply::Accepted(_)
+        )
+    }
+}
+
+impl std::fmt::Display for ServerControlReply
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        match self {
+            ServerControlReply::Handshake {
+                protocol_version, ..
+            } => {
+                write!(
+                    f,
+                    "Speaking protocol version {protocol_version} with the server."
+                )
+            },
+            ServerControlReply::UnsupportedProtocol {
+                client_version,
+                min_version,
+                max_version,
+            } => {
+                match client_version {
+                    Some(client_version) => {
+                        write!(
+                            f,
+                            "The server speaks protocol versions {min_version}-{max_version}, your client speaks version {client_version}, please update your client!"
+                        )
+                    },
+                    None => {
+                        write!(
+                            f,
+                            "Your client is too old to connect to this server, please update your client!"
+                        )
+                    },
+                }
+            },
+            ServerControlReply::Accepted(_) => write!(f, "Connected to the server!"),
+            ServerControlReply::InvalidPassword => write!(f, "Invalid password!"),
+            ServerControlReply::Banned { reason, expires_at } => {
+                match expires_at {
+                    Some(expires_at) => {
+                        write!(
+                            f,
+                            "You have been banned from this server until {}: {reason}",
+                            expires_at.format("%Y.%m.%d. %H:%M")
+                        )
+                    },
+                    None => write!(f, "You have been banned from this server: {reason}"),
+                }
+            },
+            ServerControlReply::TimedOut(until) => {
+                write!(
+                    f,
+                    "You have been timed out from this server until {}!",
+                    until.format("%Y.%m.%d. %H:%M")
+                )
+            },
+            ServerControlReply::Kicked => write!(f, "You have been kicked from this server!"),
+            ServerControlReply::TooManyConnections => {
+                write!(
+                    f,
+                    "Too many connections from your address, try again later!"
+                )
+            },
+            ServerControlReply::Disconnected => write!(f, "Disconnected from the server."),
+            ServerControlReply::Shutdown => write!(f, "The server has shut down."),
+            ServerControlReply::AlreadyConnected => {
+                write!(
+                    f,
+                    "You are already connected to this server from somewhere else!"
+                )
+            },
+            ServerControlReply::ProtocolError(validation_error) => {
+                write!(f, "Outdated client or connection: {validation_error}")
+            },
+        }
+    }
+}
+
+impl std::error::Error for ServerControlReply {}
+
+/// The kinds of requests which are rate limited, every kind has its own limit
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RateLimitedAction
+{
+    /// Normal messages, private messages and edits
+    Message,
+
+    /// File, image and audio uploads
+    Upload,
+
+    /// Adding and removing reactions
+    Reaction,
+}
+
+impl RateLimitedAction
+{
+    /// Returns the kind of the request, returns ```None``` if the request isnt rate limited
+    pub fn of_request(message_type: &ClientMessageType) -> Option<Self>
+    {
+        match message_type {
+            ClientMessageType::NormalMessage(_)
+            | ClientMessageType::DirectMessage(_)
+            | ClientMessageType::MessageEdit(_) => Some(Self::Message),
+            ClientMessageType::FileUpload(_) => Some(Self::Upload),
+            ClientMessageType::Reaction(_) => Some(Self::Reaction),
+            _ => None,
+        }
+    }
+}
+
+///This is one msg (packet), which gets bundled when sending ServerMain
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct ServerOutput
+{
+    /// The unique id of the message, the server assigns it when the message is sent
+    /// Messages which are not stored (for example edits and reactions) have an id too, but nothing refers to them
+    #[serde(default)]
+    pub id: MessageId,
+    /// The id of the message its a reply to (if it is)
+    pub replying_to: Option<MessageId>,
+    /// Inner message which is *wrapped* in the ServerOutput
+    pub message_type: ServerMessageType,
+    /// The account's name who sent the message
+    pub author: String,
+    /// The date when this message was sent, formatted with ```MESSAGE_DATE_FORMAT```
+    /// This is only kept for the older clients, ```timestamp``` should be used instead
+    pub message_date: String,
+    /// The time when the server has received the message, this is set by the server so the clients' clocks dont matter
+    #[serde(default)]
+    pub timestamp: DateTime<Utc>,
+    /// The position of the message in the stream of the messages sent to every client, the clients use it to detect the messages they have missed or received twice
+    /// This is assigned when the message is sent, the messages sent only to some of the clients (```ServerOutput::is_broadcast```) carry the sequence of the last message sent to every client
+    /// The stored messages keep the sequence they have been sent with, it is only 0 in the history saved before the sequences were stored
+    #[serde(default)]
+    pub sequence: u64,
+    /// The revision of the server's state when the message was last stored or changed (edited, deleted, reacted to), every change increases the revision
+    /// The server uses it to find the messages which have changed since they were last saved
+    #[serde(default)]
+    pub revision: u64,
+    /// The user who sent this message's uuid
+    pub uuid: String,
+    /// The channel this message belongs to
+    #[serde(default = "default_channel_id")]
+    pub channel_id: String,
+    /// The reactions added to the message
+    #[serde(default)]
+    pub reactions: MessageReaction,
+}
+
+impl ServerOutput
+{
+    pub fn _struct_into_string(&self) -> String
+    {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+
+    /// Returns whether the message is sent to every client, only these messages advance the sequence (```ServerOutput::sequence```)
+    /// Private messages and request errors are only sent to the clients they concern
+    pub fn is_broadcast(&self) -> bool
+    {
+        !matches!(
+            self.message_type,
+            ServerMessageType::DirectMessage(_) | ServerMessageType::RequestError(_)
+        )
+    }
+
+    /// Returns the date of the message in the local time, the date sent by the server is returned if the message doesnt have a timestamp
+    pub fn local_date(&self) -> String
+    {
+        match self.timestamp == DateTime::<Utc>::default() {
+            true => self.message_date.clone(),
+            false => {
+                self.timestamp
+                    .with_timezone(&Local)
+                    .format(MESSAGE_DATE_FORMAT)
+                    .to_string()
+            },
+        }
+    }
+
+    /// Returns the uuid of the other participant of a private message, from the point of view of the user (identified by the uuid)
+    /// Returns ```None``` if this isnt a private message or the user isnt a participant of it
+    pub fn direct_message_partner(&self, uuid: &str) -> Option<String>
+    {
+        let ServerMessageType::DirectMessage(direct_message) = &self.message_type
+        else {
+            return None;
+        };
+
+        if self.uuid == uuid {
+            Some(direct_message.recipient.clone())
+        }
+        else if direct_message.recipient == uuid {
+            Some(self.uuid.clone())
+        }
+        else {
+            None
+        }
+    }
+
+    /// This function converts a client message to a ServerOutput, which gets sent to all the clients (Its basically a simplified client message)
+    /// The id of the converted message is 0, the server has to assign the id before sending it
+    /// The requests which dont create a message (file requests, channel requests, pongs, etc.) cannot be converted, they are answered by the server directly
+    pub fn convert_clientmsg_to_servermsg(
+        normal_msg: ClientMessage,
+        uuid: String,
+        username: String,
+    ) -> Result<ServerOutput, ValidationError>
+    {
+        let timestamp = Utc::now();
+
+        Ok(ServerOutput {
+            id: 0,
+            replying_to: normal_msg.replying_to,
+            message_type: match normal_msg.message_type {
+                ClientMessageType::FileRequestType(_)
+                | ClientMessageType::FileTransfer(_)
+                | ClientMessageType::HistoryRequest(_)
+                | ClientMessageType::Pong(_)
+                | ClientMessageType::ChannelRequest(_)
+                | ClientMessageType::MessagePin(_)
+                | ClientMessageType::RoleRequest(_)
+                | ClientMessageType::ModerationRequest(_) => {
+                    return Err(ValidationError::MalformedRequest)
+                },
+                ClientMessageType::FileUpload(upload) => {
+                    //The hash of the uploaded file is used as a handle for the clients to ask the file from the server
+                    let signature = upload.hash;
+
+                    let file_name = format!(
+                        "{}.{}",
+                        upload.name.unwrap_or_default(),
+                        upload.extension.clone().unwrap_or_default()
+                    );
+
+                    //The media type decides how the clients display the file
+                    match MediaType::from_extension(upload.extension.as_deref()) {
+                        MediaType::Image => {
+                            ServerMessageType::Image(ServerImageUpload { signature })
+                        },
+                        MediaType::Audio => {
+                            ServerMessageType::Audio(ServerAudioUpload {
+                                signature,
+                                file_name,
+                            })
+                        },
+                        MediaType::File => {
+                            ServerMessageType::Upload(ServerFileUpload {
+                                file_name,
+                                signature,
+                            })
+                        },
+                    }
+                },
+                ClientMessageType::NormalMessage(message) => {
+                    ServerMessageType::Normal(ServerNormalMessage {
+                        message: message.message,
+                        //Set default value for incoming messages
+                        has_been_edited: false,
+                    })
+                },
+                ClientMessageType::VoipConnection(voip_message_type) => {
+                    let server_message = match voip_message_type {
+                        ClientVoipRequest::Connect(_) => {
+                            ServerVoipEvent {
+                                event: VoipEvent::Connected,
+                                uuid: uuid.clone(),
+                            }
+                        },
+                        ClientVoipRequest::Disconnect => {
+                            ServerVoipEvent {
+                                event: VoipEvent::Disconnected,
+                                uuid: uuid.clone(),
+                            }
+                        },
+                        ClientVoipRequest::ImageConnected => {
+                            ServerVoipEvent {
+                                event: VoipEvent::ImageConnected,
+                                uuid: uuid.clone(),
+                            }
+                        },
+                        ClientVoipRequest::ImageDisconnected => {
+                            ServerVoipEvent {
+                                event: VoipEvent::ImageDisconnected,
+                                uuid: uuid.clone(),
+                            }
+                        },
+                    };
+
+                    ServerMessageType::VoipEvent(server_message)
+                },
+                ClientMessageType::SyncMessage(_) => ServerMessageType::Sync(ServerMessageSync {}),
+                //These messages also have a side effect on the server's list of the messages
+                //The client will interpret these messages and modify its own message list
+                ClientMessageType::Reaction(message) => {
+                    ServerMessageType::Reaction(
+                        //Match the incoming ReactionType
+                        match message {
+                            //The client will increment its emoji counter
+                            ReactionType::Add(message) => {
+                                ServerMessageReaction {
+                                    reaction_type: ReactionType::Add(ClientReaction {
+                                        emoji_name: message.emoji_name,
+                                        uuid: message.uuid,
+                                        message_id: message.message_id,
+                                    }),
+                                }
+                            },
+                            //The client will decrement its emoji counter
+                            //If the index is 0 the client will automatically remove that emoji entry
+                            ReactionType::Remove(message) => {
+                                ServerMessageReaction {
+                                    reaction_type: ReactionType::Remove(ClientReaction {
+                                        emoji_name: message.emoji_name,
+                                        uuid: message.uuid,
+                                        message_id: message.message_id,
+                                    }),
+                                }
+                            },
+                        },
+                    )
+                },
+                ClientMessageType::MessageEdit(message) => {
+                    ServerMessageType::Edit(ServerMessageEdit {
+                        message_id: message.message_id,
+                        new_message: message.new_message,
+                    })
+                },
+                ClientMessageType::DirectMessage(message) => {
+                    ServerMessageType::DirectMessage(ServerDirectMessage {
+                        recipient: message.recipient,
+                        message: message.message,
+                    })
+                },
+            },
+            author: username,
+            //The date sent by the client is ignored, so that the messages are dated by the server's clock
+            message_date: timestamp.format(MESSAGE_DATE_FORMAT).to_string(),
+            timestamp,
+            sequence: 0,
+            revision: 0,
+            uuid,
+            channel_id: normal_msg.channel_id,
+            reactions: MessageReaction::default(),
+        })
+    }
+}
+
+/// Used to put all the messages into 1 big pack (Bundling All the ServerOutput-s), Main packet, this gets to all the clients
+/// This message type is only used when a client is connecting an has to do a full sync (sending everything to the client all the messages reactions, etc)
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Default)]
+pub struct ServerMaster
+{
+    ///All of the messages received from the server, ordered by their ids
+    ///When connecting the server only sends the most recent messages of every channel, the older messages are loaded in pages (```ServerHistoryPage```)
+    pub message_list: Vec<ServerOutput>,
+
+    ///The id of the message ```message_list``` continues from, this is ```None``` if the server has sent the most recent pages of the history instead
+    pub continues_from: Option<MessageId>,
+
+    ///The ids of the channels which have older messages that havent been loaded yet
+    pub channels_with_older_messages: HashSet<String>,
+
+    ///The revision of the server's messages when the sync was sent (```ServerOutput::revision```), the client sends it back when it reconnects
+    #[serde(default)]
+    pub revision: u64,
+
+    ///The messages the client already had which have been changed (edited, deleted, reacted to) since the revision the client has sent
+    ///This is only sent if ```message_list``` continues the client's messages, the changed messages replace the client's copies
+    #[serde(default)]
+    pub changed_messages: Vec<ServerOutput>,
+
+    ///The sequence of the last message sent to every client before the sync, the messages sent afterwards continue from it (```ServerOutput::sequence```)
+    ///The client advances it as it receives the messages
+    pub sequence: u64,
+
+    ///Users last seen message id
+    pub user_seen_list: Vec<ClientLastSeenMessage>,
+
+    ///This entry holds all the connected user's profile
+    pub connected_clients_profile: HashMap<String, ClientProfile>,
+
+    ///This entry shows all the client connected to the Voip call, if there is a a call
+    pub ongoing_voip_call: ServerVoipState,
+
+    ///All the channels of the server
+    pub channels: Vec<Channel>,
+
+    ///The private messages the client has sent or received, other users' private messages are never included
+    pub direct_messages: Vec<ServerOutput>,
+
+    ///The ids of the pinned messages
+    pub pinned_messages: Vec<MessageId>,
+
+    ///The roles of the users, the key is the user's uuid (Users who arent in this list have the default role)
+    pub roles: HashMap<String, Role>,
+
+    ///The token the client has to send when it reconnects (```ClientSyncMessage::reconnect_token```), this is only sent when the client connects
+    ///While the server still considers the old connection open, it only lets a new connection take over if the token matches
+    #[serde(default)]
+    pub reconnect_token: Option<String>,
+}
+
+impl ServerMaster
+{
+    pub fn struct_into_string(&self) -> String
+    {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+
+    /// Applies the sync the server has sent when connecting
+    /// If the sync continues the messages the client already has only the new messages are appended and the changed ones are replaced, otherwise the messages are replaced
+    pub fn apply_sync(&mut self, sync: ServerMaster)
+    {
+        let ServerMaster {
+            message_list,
+            continues_from,
+            channels_with_older_messages,
+            revision,
+            changed_messages,
+            sequence,
+            user_seen_list,
+            connected_clients_profile,
+            ongoing_voip_call,
+            channels,
+            direct_messages,
+            pinned_messages,
+            roles,
+            reconnect_token,
+        } = sync;
+
+        if continues_from.is_some() && continues_from == self.last_message_id() {
+            self.message_list.extend(message_list);
+
+            for changed_message in changed_messages {
+                if let Some(message) = self.message_mut(changed_message.id) {
+                    *message = changed_message;
+                }
+            }
+        }
+        else {
+            self.message_list = message_list;
+            self.channels_with_older_messages = channels_with_older_messages;
+        }
+
+        self.revision = revision;
+        self.sequence = sequence;
+        self.user_seen_list = user_seen_list;
+        self.connected_clients_profile = connected_clients_profile;
+        self.ongoing_voip_call = ongoing_voip_call;
+        self.channels = channels;
+        self.direct_messages = direct_messages;
+        self.pinned_messages = pinned_messages;
+        self.roles = roles;
+
+        //The resyncs dont carry the token, the one received when connecting is kept
+        if reconnect_token.is_some() {
+            self.reconnect_token = reconnect_token;
+        }
+    }
+
+    /// Checks the sequence of the message received from the server (```ServerOutput::sequence```), and advances the sequence if the message follows it
+    pub fn check_sequence(&mut self, message: &ServerOutput) -> SequenceCheck
+    {
+        //The messages which arent sent to every client carry the sequence of the last message sent to every client
+        if !message.is_broadcast() {
+            return match message.sequence > self.sequence {
+                true => SequenceCheck::Gap,
+                false => SequenceCheck::InOrder,
+            };
+        }
+
+        if message.sequence <= self.sequence {
+            SequenceCheck::Duplicate
+        }
+        else if message.sequence == self.sequence + 1 {
+            self.sequence = message.sequence;
+
+            SequenceCheck::InOrder
+        }
+        else {
+            SequenceCheck::Gap
+        }
+    }
+
+    /// Inserts the received message into the message list, the list is kept ordered by the ids
+    /// The messages are sent in the order of their sequences, which can differ from the order of their ids if they have been sent at the same time
+    pub fn insert_message(&mut self, message: ServerOutput)
+    {
+        let position = self
+            .message_list
+            .partition_point(|loaded_message| loaded_message.id < message.id);
+
+        //The message has already been received
+        if self
+            .message_list
+            .get(position)
+            .is_some_and(|loaded_message| loaded_message.id == message.id)
+        {
+            return;
+        }
+
+        self.message_list.insert(position, message);
+    }
+
+    /// Inserts the messages of the older page before the loaded messages of its channel
+    /// The messages of the other channels can be older than the page, so the messages are inserted by their ids
+    pub fn insert_history_page(&mut self, page: ServerHistoryPage)
+    {
+        //The page has to end before the loaded messages of the channel start, otherwise it has already been inserted
+        if page
+            .message_list
+            .last()
+            .zip(self.first_message_id(&page.channel_id))
+            .is_some_and(|(last_of_page, first_loaded_id)| last_of_page.id >= first_loaded_id)
+        {
+            return;
+        }
+
+        for message in page.message_list {
+            self.insert_message(message);
+        }
+
+        if page.has_older_messages {
+            self.channels_with_older_messages.insert(page.channel_id);
+        }
+        else {
+            self.channels_with_older_messages.remove(&page.channel_id);
+        }
+    }
+
+    /// Returns whether the channel has older messages which havent been loaded yet
+    pub fn has_older_messages(&self, channel_id: &str) -> bool
+    {
+        self.channels_with_older_messages.contains(channel_id)
+    }
+
+    /// Returns the id of the oldest loaded message of the channel
+    pub fn first_message_id(&self, channel_id: &str) -> Option<MessageId>
+    {
+        self.message_list
+            .iter()
+            .find(|message| message.channel_id == channel_id)
+            .map(|message| message.id)
+    }
+
+    /// Returns the id of the newest loaded message
+    pub fn last_message_id(&self) -> Option<MessageId>
+    {
+        self.message_list.last().map(|message| message.id)
+    }
+
+    /// Returns the loaded message (identified by its id)
+    pub fn message(&self, id: MessageId) -> Option<&ServerOutput>
+    {
+        message_position(&self.message_list, id).map(|position| &self.message_list[position])
+    }
+
+    /// Returns the loaded message (identified by its id) as mutable
+    pub fn message_mut(&mut self, id: MessageId) -> Option<&mut ServerOutput>
+    {
+        message_position(&self.message_list, id).map(|position| &mut self.message_list[position])
+    }
+}
+
+/// The result of checking the sequence of a message received from the server (```ServerMaster::check_sequence```)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SequenceCheck
+{
+    /// The message follows the messages received before it
+    InOrder,
+    /// The message has already been received, it should be ignored
+    Duplicate,
+    /// Some of the messages sent before this one have been missed, the client should request a resync
+    Gap,
+}
+
+///This struct provides all the necessary information to keep the client and the server in sync
+/// Its struct contains ```Vec<ClientLastSeenMessage>``` which is for displaying which message has the user seen
+/// And the message the client has sent
+/// We dont need to provide any other information since, the ```ServerMaster``` struct ensures all the clients have the same field when connecting
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct ServerSync
+{
+    ///Users last seen message id
+    pub user_seen_list: Vec<ClientLastSeenMessage>,
+    /// The inner message
+    pub message: ServerOutput,
+}
+
+impl ServerSync
+{
+    pub fn struct_into_string(&self) -> String
+    {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+}
+
+/// Every encrypted frame the server sends to the client is one of these, the binary format cannot tell the different kinds of frames apart on its own
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub enum ServerFrame
+{
+    /// The full sync, this is sent when the client connects
+    Master(ServerMaster),
+
+    /// A single message
+    Sync(ServerSync),
+
+    /// The reply to a ```ClientRequestTypeStruct``` request
+    Reply(ServerReplyType),
+
+    /// The reply to a voip connection request
+    Voip(ServerVoipReply),
+
+    /// Sent periodically to check whether the client is still connected, the client has to answer it with a ```ClientPong```
+    Ping(ServerPing),
+}
+
+/// The server's heartbeat, it also lets the client know how fast its connection is
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct ServerPing
+{
+    /// The date the ping was sent at, the client has to echo it back in its ```ClientPong```
+    pub sent_at: DateTime<Utc>,
+
+    /// The round trip time of the previous ping, this is None until the client has answered one
+    pub latency: Option<Duration>,
+}
+
+impl ServerFrame
+{
+    /// Encodes the frame, compresses it (if the connection uses compression) and encrypts it with the key, the ciphertext is sent as is
+    pub fn encrypt(&self, key: &[u8], compression: FrameCompression) -> anyhow::Result<Vec<u8>>
+    {
+        encrypt_aes256_bytes(&compression.compress(encode_message(self)?)?, key)
+    }
+
+    /// Decrypts the frame with the key, decompresses it (if the connection uses compression) and decodes it
+    /// The client trusts the server, so the size of the decompressed frame isnt limited
+    pub fn decrypt(bytes: &[u8], key: &[u8], compression: FrameCompression)
+        -> anyhow::Result<Self>
+    {
+        decode_message(&compression.decompress(&decrypt_aes256_bytes(bytes, key)?, usize::MAX)?)
+    }
+}
+
+//When a client is connected this is where the client gets saved
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct ConnectedClient
+{
+    /// The reason one gets EOF when disconnecting is because this field is dropped (With this struct)
+    /// This handle wouldnt have to be sent so its all okay, its only present on the server's side
+    #[serde(skip)]
+    pub handle: Option<ClientWriter>,
+    pub uuid: String,
+    pub username: String,
+
+    /// The address the client is connected from, this is used to enforce the address bans
+    #[serde(skip)]
+    pub address: Option<SocketAddr>,
+
+    /// The optional features both the client and the server support, these were agreed on in the handshake
+    #[serde(skip)]
+    pub capabilities: Vec<Capability>,
+
+    /// The round trip time of the last ping the client has answered
+    #[serde(skip)]
+    pub latency: Option<Duration>,
+
+    /// The token the client has received when connecting, a new connection can only take over this one if it sends the token (```ClientSyncMessage::reconnect_token```)
+    #[serde(skip)]
+    pub reconnect_token: String,
+}
+
+impl ConnectedClient
+{
+    pub fn new(
+        uuid: String,
+        username: String,
+        handle: ClientWriter,
+        address: SocketAddr,
+        capabilities: Vec<Capability>,
+        reconnect_token: String,
+    ) -> Self
+    {
+        Self {
+            uuid,
+            username,
+            handle: Some(handle),
+            address: Some(address),
+            capabilities,
+            latency: None,
+            reconnect_token,
+        }
+    }
+
+    /// Returns whether the capability was agreed on with the client
+    pub fn supports(&self, capability: Capability) -> bool
+    {
+        self.capabilities.contains(&capability)
+    }
+
+    /// Returns the compression of the frames sent to the client
+    pub fn compression(&self) -> FrameCompression
+    {
+        FrameCompression::negotiate(&self.capabilities)
+    }
+
+    /// Returns whether the client is connected through the connection the handle belongs to
+    pub fn is_connected_through(&self, handle: &ClientWriter) -> bool
+    {
+        self.handle
+            .as_ref()
+            .is_some_and(|client_handle| client_handle.same_connection(handle))
+    }
+}
+
+//This contains the client's name and their last seen message's id
+//Every client has a last seen message in every channel
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct ClientLastSeenMessage
+{
+    #[serde(alias = "index")]
+    pub message_id: MessageId,
+    pub uuid: String,
+    #[serde(default = "default_channel_id")]
+    pub channel_id: String,
+}
+
+impl ClientLastSeenMessage
+{
+    pub fn new(message_id: MessageId, uuid: String, channel_id: String) -> Self
+    {
+        Self {
+            message_id,
+            uuid,
+            channel_id,
+        }
+    }
+}
+
+/// This enum contains the actions the client can take, these are sent to the server
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub enum ClientVoipRequest
+{
+    /// A voip a call will be automatically issued if there is no ongoing call
+    /// The inner value of conncect is the port the Client ```UdpSocket``` is opened on
+    Connect(u16),
+
+    /// The voip call will automatically stop once there are no connected clients
+    Disconnect,
+
+    /// The client has enabled video
+    ImageConnected,
+
+    /// The client has disabled video
+    ImageDisconnected,
+}
+
+/// This enum is used to display if a client has joined or left the Voip call, this is a ```ServerMessageType```
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub enum VoipEvent
+{
+    /// Client connected
+    Connected,
+    /// Client disconnected
+    Disconnected,
+
+    /// The client has enabled video
+    ImageConnected,
+
+    /// The client has disabled video
+    ImageDisconnected,
+}
+
+/// This struct holds all important information, when informing clients / servers about a ```VoipEvent```
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub struct ServerVoipEvent
+{
+    /// The uuid of the user who has initiated this event
+    pub uuid: String,
+    /// The event the user has initiated
+    pub event: VoipEvent,
+}
+
+///The struct contains all the useful information for displaying an ongoing voip connection.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct ServerVoipState
+{
+    pub connected_clients: Option<Vec<String>>,
+}
+
+/// This num contains the actions the server can take, these are sent to the client
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub enum ServerVoipRequest
+{
+    /// This enum acts as a ```packet``` and is handed out to all the clients if a connection is started / established
+    ConnectionStart(ServerVoipStart),
+    /// This enum acts as a ```packet``` and i handed out to all the clients connected to the call
+    ConnectionClosed(ServerVoipClose),
+}
+
+/// This struct contains all the information important for the non connected clients
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub struct ServerVoipStart
+{
+    /// The clients connected to the Voip call
+    pub connected_clients: Vec<ClientProfile>,
+}
+
+/// This enum holds the two outcomes of a connection request
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub enum ServerVoipReply
+{
+    /// This enum is when the connection request is successful
+    Success,
+    /// This enum is when the connection request is unsuccessful, it also contains the reason
+    Fail(ServerVoipClose),
+}
+
+/// This struct contains the reason for closing the voip connection
+/// This maybe at any point of the Voip call, or the connection
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub struct ServerVoipClose
+{
+    /// The reason for closing the Voip connection
+    pub reason: String,
+}
+
+pub type ImageBuffer = Arc<DashMap<String, IndexMap<String, HashMap<String, Option<Vec<u8>>>>>>;
+
+/// Pairs the ```SocketAddr``` of the clients in the call with the sender of their thread, and the token shutting down the thread
+pub type ClientThreadChannels =
+    Arc<DashMap<SocketAddr, (Arc<tokio::sync::mpsc::Sender<Vec<u8>>>, CancellationToken)>>;
+
+#[derive(Debug, Clone)]
+pub struct ServerVoip
+{
+    /// This field contains all the connected client's ```uuid``` with their ```SocketAddr```
+    pub connected_clients: Arc<DashMap<String, SocketAddr>>,
+
+    /// This field contains a ```HashMap``` which pairs the SocketAddr to the client's listening thread's sender (So that the receiver thread can receive the ```Vec<u8>``` sent by the sender)
+    /// The second part of the tuple is for shutting down the client manager thread, if they disconnect
+    pub connected_client_thread_channels: ClientThreadChannels,
+
+    /// This field contains the amount of time the call has been established for
+    pub _established_since: chrono::DateTime<Utc>,
+
+    /// The socket the server is listening on for incoming messages from ipv6 addresses
+    pub socket_v6: Option<Arc<UdpSocket>>,
+
+    /// The socket the server is listening on for incoming messages from ipv4 addresses
+    pub socket_v4: Arc<UdpSocket>,
+
+    /// The cancellation token cancels threads, which are for listening and relaying (Distributing info)
+    pub thread_cancellation_token: CancellationToken,
+
+    /// This entry makes sure the 2 threads are only spawned once
+    pub threads: Option<()>,
+
+    /// This field contains the Video(Image) buffer of the clients.
+    /// If header file's hashes are paired we send / relay the image to all the other clients.
+    /// The ```DashMap``` contains the HeaderMessages (value) paired with the uuid's of the clients (key)
+    /// The ```IndexMap``` contains the MessageParts (value)  paired with the HeaderMessage's uuid (key)
+    /// The ```HashMap``` contains the image bytes (value) paired with the byte hash (key)
+    pub image_buffer: ImageBuffer,
+}
+
+impl ServerVoip
+{
+    /// Add the ```SocketAddr``` to the ```UDP``` server's destinations
+    /// This function can take Self as a clone since we are only accessing entries which implement ```Sync```
+    pub fn connect(&self, uuid: String, socket_addr: SocketAddr) -> anyhow::Result<()>
+    {
+        self.connected_clients.insert(uuid, socket_addr);
+
+        Ok(())
+    }
+
+    /// Remove the ```SocketAddr``` to the ```UDP``` server's destinations
+    pub fn disconnect(&self, uuid: String) -> anyhow::Result<()>
+    {
+        let (_, removed_address) = self
+            .connected_clients
+            .remove(&uuid)
+            .ok_or_else(|| anyhow::Error::msg("Client was not connected"))?;
+
+        self.connected_client_thread_channels
+            .remove(&removed_address);
+
+        Ok(())
+    }
+}
+
+/// This enum holds the variants of a UdpMessage
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub enum UdpMessageType
+{
+    /// Voice message
+    Voice = 1,
+    /// Image header message
+    ImageHeader = 3,
+    /// Image message
+    Image = 2,
+}
+
+impl UdpMessageType
+{
+    pub fn from_number(num: u32) -> Self
+    {
+        Self::try_from_number(num).unwrap_or_else(|| unimplemented!("Branch not covered"))
+    }
+
+    /// Returns ```None``` if the number doesnt belong to any of the variants, this should be used when the number was received from a peer
+    pub fn try_from_number(num: u32) -> Option<Self>
+    {
+        match num {
+            1 => Some(Self::Voice),
+            2 => Some(Self::Image),
+            3 => Some(Self::ImageHeader),
+
+            _ => None,
+        }
+    }
+}
+
+/// Encodes the message into the binary format every frame is sent in (except for the handshake and the control replies, which are json)
+pub fn encode_message<T>(message: &T) -> anyhow::Result<Vec<u8>>
+where
+    T: serde::Serialize,
+{
+    Ok(bincode::serialize(message)?)
+}
+
+/// Decodes a message sent in the binary format
+pub fn decode_message<T>(bytes: &[u8]) -> anyhow::Result<T>
+where
+    T: serde::de::DeserializeOwned,
+{
+    Ok(bincode::deserialize(bytes)?)
+}
+
+/// Frames smaller than this (in bytes) are not compressed, since compressing them wouldnt save much
+pub const COMPRESSION_THRESHOLD: usize = 1024;
+
+/// The zstd compression level of the frames, higher levels are too slow for the larger frames (like the full sync)
+const COMPRESSION_LEVEL: i32 = 3;
+
+/// Whether the binary frames of a connection are compressed, this is agreed on in the handshake (```Capability::Compression```)
+/// If compression is enabled every frame starts with a flag byte, which tells whether the rest of the frame is compressed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum FrameCompression
+{
+    #[default]
+    Disabled,
+    Zstd,
+}
+
+impl FrameCompression
+{
+    /// The flag of the frames which arent compressed (because they are smaller than ```COMPRESSION_THRESHOLD```)
+    const UNCOMPRESSED_FLAG: u8 = 0;
+
+    /// The flag of the zstd compressed frames
+    const ZSTD_FLAG: u8 = 1;
+
+    /// Returns the compression of the connection from the capabilities agreed on in the handshake
+    pub fn negotiate(capabilities: &[Capability]) -> Self
+    {
+        if capabilities.contains(&Capability::Compression) {
+            FrameCompression::Zstd
+        }
+        else {
+            FrameCompression::Disabled
+        }
+    }
+
+    /// Compresses the frame if compression is enabled and the frame is larger than ```COMPRESSION_THRESHOLD```
+    pub fn compress(self, bytes: Vec<u8>) -> anyhow::Result<Vec<u8>>
+    {
+        match self {
+            FrameCompression::Disabled => Ok(bytes),
+            FrameCompression::Zstd => {
+                if bytes.len() < COMPRESSION_THRESHOLD {
+                    return Ok([vec![Self::UNCOMPRESSED_FLAG], bytes].concat());
+                }
+
+                let compressed_bytes = zstd::bulk::compress(&bytes, COMPRESSION_LEVEL)?;
+
+                Ok([vec![Self::ZSTD_FLAG], compressed_bytes].concat())
+            },
+        }
+    }
+
+    /// Decompresses the frame if compression is enabled, returns an error if the decompressed frame would be larger than ```max_size```
+    pub fn decompress(self, bytes: &[u8], max_size: usize) -> anyhow::Result<Vec<u8>>
+    {
+        match self {
+            FrameCompression::Disabled => Ok(bytes.to_vec()),
+            FrameCompression::Zstd => {
+                let (flag, bytes) = bytes
+                    .split_first()
+                    .ok_or_else(|| Error::msg("The frame is empty!"))?;
+
+                match *flag {
+                    Self::UNCOMPRESSED_FLAG => Ok(bytes.to_vec()),
+                    Self::ZSTD_FLAG => {
+                        let mut decompressed_bytes = Vec::new();
+
+                        //Only read one byte more than the maximum size, so that a small frame cannot make us allocate any amount of memory
+                        zstd::stream::read::Decoder::new(bytes)?
+                            .take(max_size.saturating_add(1) as u64)
+                            .read_to_end(&mut decompressed_bytes)?;
+
+                        ensure!(
+                            decompressed_bytes.len() <= max_size,
+                            "The decompressed frame is larger than {max_size} bytes!"
+                        );
+
+                        Ok(decompressed_bytes)
+                    },
+                    flag => Err(Error::msg(format!("Unknown compression flag: {flag}"))),
+                }
+            },
+        }
+    }
+}
+
+/// aes256 is decrypted by this function by a fixed key
+pub fn decrypt_aes256(string_to_be_decrypted: &str, key: &[u8]) -> anyhow::Result<String>
+{
+    let mut ciphertext = hex::decode(string_to_be_decrypted)?;
+
+    let nonce: Vec<u8> = ciphertext.drain(ciphertext.len() - 12..).collect();
+
+    let key = Key::<Aes256Gcm>::from_slice(key);
+
+    let cipher = Aes256Gcm::new(key);
+
+    let plaintext = cipher
+        .decrypt(&GenericArray::clone_from_slice(&nonce), ciphertext.as_ref())
+        .map_err(|_| Error::msg("Invalid password!"))?;
+
+    Ok(String::from_utf8(plaintext)?)
+}
+
+/// This function decrypts a provided ```String```, with the provided key using ```Aes-256```
+pub fn encrypt_aes256(string_to_be_encrypted: String, key: &[u8]) -> anyhow::Result<String>
+{
+    ensure!(key.len() == 32);
+
+    let key = Key::<Aes256Gcm>::from_slice(key);
+
+    let cipher = Aes256Gcm::new(key);
+
+    let mut nonce = [0; 12];
+
+    rand::thread_rng().fill(&mut nonce);
+
+    let bytes = string_to_be_encrypted.as_bytes().to_vec();
+
+    let ciphertext = cipher
+        .encrypt(&nonce.into(), &*bytes)
+        .map_err(|_| Error::msg("Invalid key, couldnt encrypt the specified item."))?;
+
+    let ciphertext = hex::encode([ciphertext, nonce.to_vec()].concat());
+
+    Ok(ciphertext)
+}
+
+/// The provided byte array is encrypted with aes256 with the given key
+pub fn encrypt_aes256_bytes(bytes: &[u8], key: &[u8]) -> anyhow::Result<Vec<u8>>
+{
+    ensure!(key.len() == 32);
+
+    let key = Key::<Aes256Gcm>::from_slice(key);
+
+    let cipher = Aes256Gcm::new(key);
+
+    let mut nonce = [0; 12];
+
+    rand::thread_rng().fill(&mut nonce);
+
+    let encrypted_bytes = cipher
+        .encrypt(&nonce.into(), bytes)
+        .map_err(|_| Error::msg("Invalid key, couldnt encrypt the specified item."))?;
+
+    Ok([encrypted_bytes, nonce.to_vec()].concat())
+}
+
+#[inline]
+/// This function decrypts a provided array of ```Bytes```, with the provided key using ```Aes-256```
+pub fn decrypt_aes256_bytes(bytes_to_be_decrypted: &[u8], key: &[u8]) -> anyhow::Result<Vec<u8>>
+{
+    //The nonce is appended to the end of the ciphertext
+    ensure!(
+        bytes_to_be_decrypted.len() >= 12,
+        "The encrypted bytes are too short!"
+    );
+
+    let mut bytes: Vec<u8> = bytes_to_be_decrypted.to_vec();
+
+    let key = Key::<Aes256Gcm>::from_slice(key);
+
+    let cipher = Aes256Gcm::new(key);
+
+    let nonce: Vec<u8> = bytes.drain(bytes.len() - 12..).collect();
+
+    let decrypted_bytes = cipher
+        .decrypt(&GenericArray::clone_from_slice(&nonce), &*bytes)
+        .map_err(|_| Error::msg("Invalid password!"))?;
+
+    Ok(decrypted_bytes)
+}
+
+#[inline]
+/// Argon is used to encrypt this
+pub fn encrypt(string_to_be_encrypted: String) -> String
+{
+    let password = string_to_be_encrypted.trim().as_bytes();
+    let salt = b"c1eaa94ec38ab7aa16e9c41d029256d3e423f01defb0a2760b27117ad513ccd2";
+    let config = Config::owasp1();
+
+    argon2::hash_encoded(password, salt, &config).unwrap()
+}
+
+#[inline]
+pub fn pass_hash_match(to_be_verified: String, encoded: String) -> bool
+{
+    argon2::verify_encoded(&encoded, to_be_verified.as_bytes()).unwrap()
+}
+
+/// This function fetches the incoming full message's lenght (it reads the 4 bytes and creates an u32 number from them, which it returns)
+/// afaik this function blocks until it can read the first 4 bytes out of the ```reader```
+pub async fn fetch_incoming_message_length<T>(reader: &mut T) -> anyhow::Result<u32>
+where
+    T: AsyncReadExt + Unpin + AsyncRead,
+{
+    let mut buf: Vec<u8> = vec![0; 4];
+
+    reader.read_exact(&mut buf).await?;
+
+    Ok(u32::from_be_bytes(buf[..4].try_into()?))
+}
+
+///This struct contains all the reactions of one message
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct MessageReaction
+{
+    /// The list of reactions added to a message
+    pub message_reactions: Vec<Reaction>,
+}
+
+impl MessageReaction
+{
+    /// Adds the user's (identified by their uuid) reaction, a user can only react with the same emoji once
+    pub fn add(&mut self, emoji_name: &str, uuid: &str)
+    {
+        match self
+            .message_reactions
+            .iter_mut()
+            .find(|reaction| reaction.emoji_name == emoji_name)
+        {
+            Some(reaction) => {
+                if !reaction.authors.iter().any(|author| author == uuid) {
+                    reaction.authors.push(uuid.to_string());
+                }
+            },
+            None => {
+                self.message_reactions.push(Reaction {
+                    emoji_name: emoji_name.to_string(),
+                    authors: vec![uuid.to_string()],
+                });
+            },
+        }
+    }
+
+    /// Removes the user's (identified by their uuid) reaction, if nobody has reacted with the emoji anymore the whole emoji entry is removed
+    pub fn remove(&mut self, emoji_name: &str, uuid: &str)
+    {
+        for reaction in self
+            .message_reactions
+            .iter_mut()
+            .filter(|reaction| reaction.emoji_name == emoji_name)
+        {
+            match reaction.authors.iter().position(|author| author == uuid) {
+                Some(idx) => {
+                    reaction.authors.remove(idx);
+                },
+                None => {
+                    tracing::error!("Tried to remove a non-author from the authors list.");
+                },
+            }
+        }
+
+        self.message_reactions
+            .retain(|reaction| !reaction.authors.is_empty());
+    }
+}
+
+/// This struct contains the what the people have reacted with.
+/// This struct contains the one emoji's name and the users' uuid who have sent this.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub struct Reaction
+{
+    /// The emoji send by the ```authors```
+    pub emoji_name: String,
+
+    /// Author's uuid list
+    pub authors: Vec<String>,
+}
+
+/// This header struct contains the uuid of the author who has sent this header and the image parts the server would receive
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct ImageHeader
+{
+    /// The author of this header aka who constructed it
+    pub uuid: String,
+
+    /// This entry contains the image parts in a list.
+    /// The keys are the bytes hashsed with ```Sha256```.
+    pub image_parts_hash: Vec<String>,
+
+    /// Custom identifier of the ```ImageHeader``` the server uses this to know where the image part goes
+    pub identificator: String,
+}
+
+impl ImageHeader
+{
+    /// Construct a new ```ImageHeader``` instance
+    pub fn new(uuid: String, image_parts_hash: Vec<String>, identificator: String) -> Self
+    {
+        Self {
+            uuid,
+            image_parts_hash,
+            identificator,
+        }
+    }
+}
+
+/// This function fetches the image header from the decrypted bytes.
+/// It inserts the image header into the ```ImageBuffer``` provided in the arguments.
+pub fn get_image_header(decrypted_bytes: &[u8], image_buffer: &ImageBuffer) -> anyhow::Result<()>
+{
+    //Get actual message, we ignore the message type
+    let message_bytes = decrypted_bytes.to_vec();
+
+    //Get string from bytes
+    let message_as_string = String::from_utf8(message_bytes)?;
+
+    //```Deserialize``` string into ```ImageHeader``` struct
+    let image_header = serde_json::from_str::<ImageHeader>(&message_as_string)?;
+
+    //Try getting the uuid's ImageHeaders
+    //Insert IndexMap into the ```image_buffer```
+    if let Some(mut image_headers) = image_buffer.get_mut(&image_header.uuid) {
+        image_headers.value_mut().insert(
+            image_header.identificator.clone(),
+            HashMap::from_iter(
+                image_header
+                    .image_parts_hash
+                    .iter()
+                    .map(|hash| (hash.clone(), None)),
+            ),
+        );
+    }
+
+    Ok(())
+}
+
+/// Returns the position of the message (identified by its id) in the list, the list has to be ordered by the ids
+pub fn message_position(messages: &[ServerOutput], id: MessageId) -> Option<usize>
+{
+    messages
+        .binary_search_by_key(&id, |message| message.id)
+        .ok()
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::server::server_output;
+
+    fn broadcast(sequence: u64) -> ServerOutput
+    {
+        let mut message = server_output(
+            sequence,
+            ServerMessageType::Deleted,
+            DEFAULT_CHANNEL_ID.into(),
+        );
+
+        message.sequence = sequence;
+
+        message
+    }
+
+    /// A message which is only sent to one client, it carries the sequence of the last broadcast
+    fn reply(sequence: u64) -> ServerOutput
+    {
+        let mut message = server_output(
+            0,
+            ServerMessageType::RequestError(RequestError::Muted(Utc::now())),
+            DEFAULT_CHANNEL_ID.into(),
+        );
+
+        message.sequence = sequence;
+
+        message
+    }
+
+    #[test]
+    fn broadcasts_advance_the_sequence_in_order()
+    {
+        let mut master = ServerMaster {
+            sequence: 5,
+            ..Default::default()
+        };
+
+        assert_eq!(master.check_sequence(&broadcast(6)), SequenceCheck::InOrder);
+        assert_eq!(master.check_sequence(&broadcast(7)), SequenceCheck::InOrder);
+        assert_eq!(master.sequence, 7);
+    }
+
+    #[test]
+    fn received_broadcasts_are_duplicates()
+    {
+        let mut master = ServerMaster {
+            sequence: 5,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            master.check_sequence(&broadcast(5)),
+            SequenceCheck::Duplicate
+        );
+        assert_eq!(
+            master.check_sequence(&broadcast(2)),
+            SequenceCheck::Duplicate
+        );
+        assert_eq!(master.sequence, 5);
+    }
+
+    #[test]
+    fn skipped_broadcasts_are_gaps()
+    {
+        let mut master = ServerMaster {
+            sequence: 5,
+            ..Default::default()
+        };
+
+        assert_eq!(master.check_sequence(&broadcast(7)), SequenceCheck::Gap);
+        //The sequence isnt advanced, so the missed messages are resynced
+        assert_eq!(master.sequence, 5);
+    }
+
+    #[test]
+    fn replies_never_advance_the_sequence()
+    {
+        let mut master = ServerMaster {
+            sequence: 5,
+            ..Default::default()
+        };
+
+        assert_eq!(master.check_sequence(&reply(5)), SequenceCheck::InOrder);
+        //A reply sent before the broadcasts the client has already received is still in order
+        assert_eq!(master.check_sequence(&reply(3)), SequenceCheck::InOrder);
+        //A reply sent after a broadcast the client hasnt received reveals the gap
+        assert_eq!(master.check_sequence(&reply(6)), SequenceCheck::Gap);
+        assert_eq!(master.sequence, 5);
+    }
+}
@@ -0,0 +1,302 @@
+use chrono::{DateTime, Utc};
+
+use crate::{
+    blob_store::{blob_reference, BlobStore},
+    protocol::{MediaType, QuotaExceeded, ServerMessageType, ServerOutput},
+    validation::{MAX_MEDIA_SIZE, MAX_UPLOAD_SIZE},
+};
+
+/// The default maximum size of an image in bytes
+pub const DEFAULT_MAX_IMAGE_SIZE: u64 = 20 * 1024 * 1024;
+
+/// The default maximum size of an audio file in bytes
+pub const DEFAULT_MAX_AUDIO_SIZE: u64 = 50 * 1024 * 1024;
+
+/// The limits of the server's storage, the sizes are in bytes
+/// A file which is already stored doesnt take up any more space when its uploaded again, so it only has to fit into the maximum size of its media type
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(default)]
+pub struct QuotaConfig
+{
+    /// The space the files uploaded by a single user can take up, if this is ```None``` there is no limit
+    pub user_quota: Option<u64>,
+
+    /// The space all of the uploaded files can take up, if this is ```None``` there is no limit
+    pub total_quota: Option<u64>,
+
+    /// The maximum size of an image
+    pub max_image_size: u64,
+
+    /// The maximum size of an audio file
+    pub max_audio_size: u64,
+
+    /// The maximum size of every other file, this cannot be larger than ```MAX_UPLOAD_SIZE```
+    pub max_file_size: u64,
+
+    /// How long the messages are kept for, if this is ```None``` the messages are kept forever
+    pub retention: Option<RetentionPolicy>,
+}
+
+impl Default for QuotaConfig
+{
+    fn default() -> Self
+    {
+        Self {
+            user_quota: None,
+            total_quota: None,
+            max_image_size: DEFAULT_MAX_IMAGE_SIZE,
+            max_audio_size: DEFAULT_MAX_AUDIO_SIZE,
+            max_file_size: MAX_UPLOAD_SIZE as u64,
+            retention: None,
+        }
+    }
+}
+
+impl QuotaConfig
+{
+    /// Returns the maximum size of the media type, the images and audio files cannot be larger than ```MAX_MEDIA_SIZE``` since they are served in a single reply
+    pub fn max_size(&self, media_type: MediaType) -> u64
+    {
+        match media_type {
+            MediaType::Image => self.max_image_size.min(MAX_MEDIA_SIZE as u64),
+            MediaType::Audio => self.max_audio_size.min(MAX_MEDIA_SIZE as u64),
+            MediaType::File => self.max_file_size,
+        }
+        .min(MAX_UPLOAD_SIZE as u64)
+    }
+
+    /// Checks if the user can upload the file, the current usage is read from the blob store
+    /// The space reserved for the uploads which havent been stored yet counts towards the usage
+    pub fn check_upload(
+        &self,
+        blobs: &BlobStore,
+        uploader: &str,
+        hash: &str,
+        media_type: MediaType,
+        size: u64,
+    ) -> Result<(), QuotaExceeded>
+    {
+        let max = self.max_size(media_type);
+
+        if size > max {
+            return Err(QuotaExceeded::FileSize { media_type, max });
+        }
+
+        //The file is already stored, so it doesnt take up any more space
+        if blobs.get(hash).is_some() {
+            return Ok(());
+        }
+
+        if let Some(quota) = self.user_quota {
+            let used = blobs.user_usage(uploader) + blobs.user_reserved(uploader);
+
+            if used + size > quota {
+                return Err(QuotaExceeded::UserQuota { used, quota });
+            }
+        }
+
+        if let Some(quota) = self.total_quota {
+            let used = blobs.usage().1 + blobs.reserved();
+
+            if used + size > quota {
+                return Err(QuotaExceeded::TotalQuota { quota });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks if the user can upload the file, and reserves the space for it if they can
+    /// The check and the reservation happen while the blob store is borrowed, so that no other upload can take up the space in between
+    /// The reservation is released when the file is stored (```BlobStore::insert```)
+    pub fn reserve_upload(
+        &self,
+        blobs: &mut BlobStore,
+        uploader: &str,
+        hash: &str,
+        media_type: MediaType,
+        size: u64,
+    ) -> Result<(), QuotaExceeded>
+    {
+        //If the user retries the upload, their earlier reservation is replaced
+        blobs.release(uploader, hash);
+
+        self.check_upload(blobs, uploader, hash, media_type, size)?;
+
+        //The file which is already stored doesnt take up any more space
+        if blobs.get(hash).is_none() {
+            blobs.reserve(uploader, hash, size);
+        }
+
+        Ok(())
+    }
+}
+
+/// Which messages are removed once they have become older than the maximum age
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RetentionTarget
+{
+    /// Every message is removed
+    Messages,
+
+    /// Only the uploaded files, images and audio files are removed, the rest of the messages are kept
+    Attachments,
+}
+
+/// Removes the old messages, the removed messages are set to ```Deleted``` (They are kept in the list, so that the messages referring to them by their ```MessageId``` still find them)
+/// The files the removed messages have referred to are collected by the blob store once nothing refers to them anymore
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct RetentionPolicy
+{
+    /// The age (in days) after which the messages are removed
+    pub max_age_days: u32,
+
+    /// Which messages are removed
+    pub target: RetentionTarget,
+}
+
+impl RetentionPolicy
+{
+    /// Returns whether the message should be removed, the messages without a timestamp (whose date couldnt be parsed when they were migrated) are always kept
+    pub fn is_expired(&self, message: &ServerOutput, now: DateTime<Utc>) -> bool
+    {
+        if matches!(message.message_type, ServerMessageType::Deleted) {
+            return false;
+        }
+
+        if self.target == RetentionTarget::Attachments
+            && blob_reference(&message.message_type).is_none()
+        {
+            return false;
+        }
+
+        message.timestamp != DateTime::<Utc>::default()
+            && now - message.timestamp > chrono::Duration::days(self.max_age_days as i64)
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::blob_store::Blob;
+
+    fn config() -> QuotaConfig
+    {
+        QuotaConfig {
+            user_quota: Some(100),
+            total_quota: Some(150),
+            max_image_size: 10,
+            ..Default::default()
+        }
+    }
+
+    fn store_blob(blobs: &mut BlobStore, hash: &str, uploader: &str, size: u64)
+    {
+        blobs.blobs.insert(
+            hash.to_string(),
+            Blob {
+                size,
+                uploader: Some(uploader.to_string()),
+                references: 1,
+                ..Default::default()
+            },
+        );
+    }
+
+    #[test]
+    fn rejects_files_larger_than_their_media_type_allows()
+    {
+        let blobs = BlobStore::default();
+
+        assert_eq!(
+            config().check_upload(&blobs, "user", "hash", MediaType::Image, 11),
+            Err(QuotaExceeded::FileSize {
+                media_type: MediaType::Image,
+                max: 10
+            })
+        );
+        assert_eq!(
+            config().check_upload(&blobs, "user", "hash", MediaType::File, 11),
+            Ok(())
+        );
+
+        //The images and audio files are served in a single reply, so they cannot be larger than the media limit
+        let config = QuotaConfig {
+            max_audio_size: MAX_UPLOAD_SIZE as u64,
+            ..config()
+        };
+
+        assert_eq!(config.max_size(MediaType::Audio), MAX_MEDIA_SIZE as u64);
+    }
+
+    #[test]
+    fn checks_the_user_and_the_total_quota()
+    {
+        let mut blobs = BlobStore::default();
+
+        store_blob(&mut blobs, "first", "user", 60);
+        store_blob(&mut blobs, "second", "other", 60);
+
+        assert_eq!(
+            config().check_upload(&blobs, "user", "new", MediaType::File, 41),
+            Err(QuotaExceeded::UserQuota {
+                used: 60,
+                quota: 100
+            })
+        );
+        assert_eq!(
+            config().check_upload(&blobs, "user", "new", MediaType::File, 30),
+            Ok(())
+        );
+        assert_eq!(
+            config().check_upload(&blobs, "third user", "new", MediaType::File, 31),
+            Err(QuotaExceeded::TotalQuota { quota: 150 })
+        );
+
+        //A file which is already stored doesnt take up any more space
+        assert_eq!(
+            config().check_upload(&blobs, "user", "second", MediaType::File, 60),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn reserved_space_counts_towards_the_quotas()
+    {
+        let mut blobs = BlobStore::default();
+
+        assert_eq!(
+            config().reserve_upload(&mut blobs, "user", "first", MediaType::File, 60),
+            Ok(())
+        );
+
+        //The second upload would fit on its own, but not together with the first one
+        assert_eq!(
+            config().reserve_upload(&mut blobs, "user", "second", MediaType::File, 60),
+            Err(QuotaExceeded::UserQuota {
+                used: 60,
+                quota: 100
+            })
+        );
+        assert_eq!(
+            config().reserve_upload(&mut blobs, "other", "second", MediaType::File, 91),
+            Err(QuotaExceeded::TotalQuota { quota: 150 })
+        );
+
+        //Retrying the same upload replaces the earlier reservation instead of adding to it
+        assert_eq!(
+            config().reserve_upload(&mut blobs, "user", "first", MediaType::File, 60),
+            Ok(())
+        );
+        assert_eq!(blobs.user_reserved("user"), 60);
+
+        blobs.release("user", "first");
+
+        assert_eq!(blobs.reserved(), 0);
+        assert_eq!(
+            config().reserve_upload(&mut blobs, "user", "second", MediaType::File, 60),
+            Ok(())
+        );
+    }
+}
@@ -0,0 +1,716 @@
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use dashmap::DashMap;
+
+use crate::protocol::RateLimitedAction;
+
+/// The per address limits are this many times higher than the per user limits, since multiple users can connect from the same address (For example from behind a NAT)
+pub const ADDRESS_LIMIT_MULTIPLIER: u32 = 4;
+
+/// The violations of a user are forgotten if they havent violated a limit for this long
+const VIOLATION_RESET_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The longest a user can be disconnected for because of violating the limits, in minutes
+const MAX_DISCONNECT_MINUTES: u32 = 60 * 24;
+
+/// The number of bytes in a megabyte
+const MEGABYTE: u64 = 1024 * 1024;
+
+/// The settings of a token bucket
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimit
+{
+    /// The maximum number of requests which can be sent in a burst
+    pub capacity: u32,
+
+    /// The number of requests which can be sent per minute, the bucket is refilled continuously
+    pub per_minute: u32,
+}
+
+impl RateLimit
+{
+    /// Creates a limit which allows a quarter of the requests of a minute to be sent in a burst
+    pub fn per_minute(per_minute: u32) -> Self
+    {
+        Self {
+            capacity: (per_minute / 4).max(1),
+            per_minute,
+        }
+    }
+
+    /// Returns the limit multiplied by the provided value
+    pub fn scaled(&self, multiplier: u32) -> Self
+    {
+        Self {
+            capacity: self.capacity.saturating_mul(multiplier),
+            per_minute: self.per_minute.saturating_mul(multiplier),
+        }
+    }
+}
+
+/// The limits of every kind of request which is rate limited
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ActionLimits
+{
+    /// Normal messages, private messages and edits
+    pub messages: RateLimit,
+
+    /// File, image and audio uploads
+    pub uploads: RateLimit,
+
+    /// Adding and removing reactions
+    pub reactions: RateLimit,
+
+    /// The number of bytes of the uploaded chunks and the requested files which can be transferred per second
+    /// A second's worth of bytes can be transferred in a burst, the transfers are slowed down instead of being rejected
+    pub transfer_per_second: u64,
+}
+
+impl ActionLimits
+{
+    /// Returns the limit of the action
+    pub fn limit(&self, action: RateLimitedAction) -> RateLimit
+    {
+        match action {
+            RateLimitedAction::Message => self.messages,
+            RateLimitedAction::Upload => self.uploads,
+            RateLimitedAction::Reaction => self.reactions,
+        }
+    }
+
+    /// Returns the limits multiplied by the provided value
+    pub fn scaled(&self, multiplier: u32) -> Self
+    {
+        Self {
+            messages: self.messages.scaled(multiplier),
+            uploads: self.uploads.scaled(multiplier),
+            reactions: self.reactions.scaled(multiplier),
+            transfer_per_second: self.transfer_per_second.saturating_mul(multiplier as u64),
+        }
+    }
+}
+
+impl Default for ActionLimits
+{
+    fn default() -> Self
+    {
+        Self {
+            messages: RateLimit::per_minute(60),
+            uploads: RateLimit::per_minute(10),
+            reactions: RateLimit::per_minute(120),
+            transfer_per_second: 8 * MEGABYTE,
+        }
+    }
+}
+
+/// The settings of the server's rate limiting
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimitConfig
+{
+    /// The limits of a single user
+    pub per_user: ActionLimits,
+
+    /// The limits of all the users connecting from the same address together
+    pub per_address: ActionLimits,
+
+    /// The maximum number of connections which can be open from the same address at the same time
+    pub max_connections_per_address: usize,
+
+    /// The number of rejected requests after which the user gets disconnected
+    pub violations_before_disconnect: u32,
+
+    /// How long the user is disconnected for the first time in minutes, this doubles every time the user gets disconnected again
+    pub disconnect_minutes: u32,
+}
+
+impl RateLimitConfig
+{
+    /// Creates the settings from the per user limits, the per address limits are derived from them
+    pub fn new(per_user: ActionLimits, max_connections_per_address: usize) -> Self
+    {
+        Self {
+            per_user,
+            per_address: per_user.scaled(ADDRESS_LIMIT_MULTIPLIER),
+            max_connections_per_address,
+            ..Default::default()
+        }
+    }
+}
+
+impl Default for RateLimitConfig
+{
+    fn default() -> Self
+    {
+        let per_user = ActionLimits::default();
+
+        Self {
+            per_user,
+            per_address: per_user.scaled(ADDRESS_LIMIT_MULTIPLIER),
+            max_connections_per_address: 8,
+            violations_before_disconnect: 10,
+            disconnect_minutes: 1,
+        }
+    }
+}
+
+/// A bucket which is refilled continuously, every request takes a token out of it
+/// If the bucket is empty the request has to be rejected
+#[derive(Debug, Clone)]
+struct TokenBucket
+{
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket
+{
+    /// Creates a full bucket
+    fn new(capacity: f64, now: Instant) -> Self
+    {
+        Self {
+            tokens: capacity,
+            last_refill: now,
+        }
+    }
+
+    /// Refills the bucket with the tokens generated since the last refill
+    fn refill(&mut self, capacity: f64, per_second: f64, now: Instant)
+    {
+        let refilled_tokens = now.duration_since(self.last_refill).as_secs_f64() * per_second;
+
+        self.tokens = (self.tokens + refilled_tokens).min(capacity);
+        self.last_refill = now;
+    }
+
+    /// Refills the bucket, then takes a token if there is one
+    /// Returns whether a token could be taken
+    fn try_take(&mut self, limit: RateLimit, now: Instant) -> bool
+    {
+        self.refill(limit.capacity as f64, limit.per_minute as f64 / 60., now);
+
+        if self.tokens >= 1. {
+            self.tokens -= 1.;
+
+            true
+        }
+        else {
+            false
+        }
+    }
+
+    /// Refills the bucket, then takes the amount of tokens even if there arent enough, the bucket can go into debt
+    /// Returns how long it takes to pay the debt back, the transfer should wait this long
+    fn take_debt(&mut self, amount: u64, per_second: u64, now: Instant) -> Duration
+    {
+        self.refill(per_second as f64, per_second as f64, now);
+
+        self.tokens -= amount as f64;
+
+        if self.tokens < 0. {
+            Duration::from_secs_f64(-self.tokens / per_second as f64)
+        }
+        else {
+            Duration::ZERO
+        }
+    }
+
+    /// Returns whether the bucket would be full if it was refilled, a full bucket is the same as a new one so it can be forgotten
+    fn is_full(&self, capacity: f64, per_second: f64, now: Instant) -> bool
+    {
+        self.tokens + now.duration_since(self.last_refill).as_secs_f64() * per_second >= capacity
+    }
+}
+
+/// The violations of a single user
+#[derive(Debug, Clone)]
+struct Violations
+{
+    /// The number of rejected requests since the user has last been disconnected
+    count: u32,
+
+    /// When the user has last violated a limit
+    last_violation: Instant,
+
+    /// The number of times the user has been disconnected, this is used to escalate the duration of the disconnects
+    disconnects: u32,
+}
+
+/// What should happen with a request after it has been checked
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RateLimitVerdict
+{
+    /// The request can be processed
+    Allowed,
+
+    /// The request has to be rejected
+    Rejected,
+
+    /// The request has to be rejected, and the user has to be disconnected for the provided amount of minutes
+    Disconnect(u32),
+}
+
+/// Keeps track of the token buckets of every user and address
+/// The full buckets and the old violations are forgotten periodically, so that the maps dont grow forever
+#[derive(Debug, Default)]
+pub struct RateLimiter
+{
+    config: RateLimitConfig,
+
+    user_buckets: HashMap<(String, RateLimitedAction), TokenBucket>,
+
+    address_buckets: HashMap<(IpAddr, RateLimitedAction), TokenBucket>,
+
+    /// The buckets of the transferred bytes, these are kept separately since they are measured in bytes instead of requests
+    user_transfer_buckets: HashMap<String, TokenBucket>,
+
+    address_transfer_buckets: HashMap<IpAddr, TokenBucket>,
+
+    violations: HashMap<String, Violations>,
+
+    /// When the buckets and the violations were last pruned
+    last_prune: Option<Instant>,
+}
+
+impl RateLimiter
+{
+    pub fn new(config: RateLimitConfig) -> Self
+    {
+        Self {
+            config,
+            ..Default::default()
+        }
+    }
+
+    /// Takes a token from the user's (identified by their uuid) and the address' bucket of the action
+    /// If any of the buckets is empty the request is rejected, if the user has violated the limits too many times they have to be disconnected
+    pub fn check(
+        &mut self,
+        uuid: &str,
+        address: IpAddr,
+        action: RateLimitedAction,
+    ) -> RateLimitVerdict
+    {
+        self.check_at(uuid, address, action, Instant::now())
+    }
+
+    fn check_at(
+        &mut self,
+        uuid: &str,
+        address: IpAddr,
+        action: RateLimitedAction,
+        now: Instant,
+    ) -> RateLimitVerdict
+    {
+        self.prune_if_due(now);
+
+        let user_limit = self.config.per_user.limit(action);
+        let address_limit = self.config.per_address.limit(action);
+
+        let user_allowed = self
+            .user_buckets
+            .entry((uuid.to_string(), action))
+            .or_insert_with(|| TokenBucket::new(user_limit.capacity as f64, now))
+            .try_take(user_limit, now);
+
+        //The address' bucket is only used if the user's bucket allowed the request, so that a single user cannot use up the bucket of the whole address
+        let allowed = user_allowed
+            && self
+                .address_buckets
+                .entry((address, action))
+                .or_insert_with(|| TokenBucket::new(address_limit.capacity as f64, now))
+                .try_take(address_limit, now);
+
+        if allowed {
+            return RateLimitVerdict::Allowed;
+        }
+
+        let violations = self.violations.entry(uuid.to_string()).or_insert_with(|| {
+            Violations {
+                count: 0,
+                last_violation: now,
+                disconnects: 0,
+            }
+        });
+
+        if now.duration_since(violations.last_violation) > VIOLATION_RESET_INTERVAL {
+            violations.count = 0;
+        }
+
+        violations.count += 1;
+        violations.last_violation = now;
+
+        if violations.count < self.config.violations_before_disconnect {
+            return RateLimitVerdict::Rejected;
+        }
+
+        //Every disconnect lasts twice as long as the last one
+        let disconnect_minutes = self
+            .config
+            .disconnect_minutes
+            .saturating_mul(2_u32.saturating_pow(violations.disconnects))
+            .min(MAX_DISCONNECT_MINUTES);
+
+        violations.count = 0;
+        violations.disconnects += 1;
+
+        RateLimitVerdict::Disconnect(disconnect_minutes)
+    }
+
+    /// Takes the transferred bytes from the user's (identified by their uuid) and the address' transfer bucket
+    /// The transfers are never rejected, instead this returns how long the transfer should wait so that the user stays within the limits
+    pub fn throttle_transfer(&mut self, uuid: &str, address: IpAddr, bytes: u64) -> Duration
+    {
+        self.throttle_transfer_at(uuid, address, bytes, Instant::now())
+    }
+
+    fn throttle_transfer_at(
+        &mut self,
+        uuid: &str,
+        address: IpAddr,
+        bytes: u64,
+        now: Instant,
+    ) -> Duration
+    {
+        self.prune_if_due(now);
+
+        let user_limit = self.config.per_user.transfer_per_second;
+        let address_limit = self.config.per_address.transfer_per_second;
+
+        let user_delay = self
+            .user_transfer_buckets
+            .entry(uuid.to_string())
+            .or_insert_with(|| TokenBucket::new(user_limit as f64, now))
+            .take_debt(bytes, user_limit, now);
+
+        let address_delay = self
+            .address_transfer_buckets
+            .entry(address)
+            .or_insert_with(|| TokenBucket::new(address_limit as f64, now))
+            .take_debt(bytes, address_limit, now);
+
+        user_delay.max(address_delay)
+    }
+
+    /// Forgets the full buckets and the old violations, if they havent been pruned for ```VIOLATION_RESET_INTERVAL```
+    fn prune_if_due(&mut self, now: Instant)
+    {
+        let Some(last_prune) = self.last_prune
+        else {
+            self.last_prune = Some(now);
+
+            return;
+        };
+
+        if now.duration_since(last_prune) < VIOLATION_RESET_INTERVAL {
+            return;
+        }
+
+        self.last_prune = Some(now);
+
+        let (per_user, per_address) = (self.config.per_user, self.config.per_address);
+
+        self.user_buckets.retain(|(_, action), bucket| {
+            let limit = per_user.limit(*action);
+
+            !bucket.is_full(limit.capacity as f64, limit.per_minute as f64 / 60., now)
+        });
+
+        self.address_buckets.retain(|(_, action), bucket| {
+            let limit = per_address.limit(*action);
+
+            !bucket.is_full(limit.capacity as f64, limit.per_minute as f64 / 60., now)
+        });
+
+        let user_transfer_limit = per_user.transfer_per_second as f64;
+
+        self.user_transfer_buckets
+            .retain(|_, bucket| !bucket.is_full(user_transfer_limit, user_transfer_limit, now));
+
+        let address_transfer_limit = per_address.transfer_per_second as f64;
+
+        self.address_transfer_buckets.retain(|_, bucket| {
+            !bucket.is_full(address_transfer_limit, address_transfer_limit, now)
+        });
+
+        //The disconnects are remembered for longer, so that the duration of the next disconnect can still be escalated
+        self.violations.retain(|_, violations| {
+            let idle = now.duration_since(violations.last_violation);
+
+            if violations.disconnects == 0 {
+                idle <= VIOLATION_RESET_INTERVAL
+            }
+            else {
+                idle <= Duration::from_secs(MAX_DISCONNECT_MINUTES as u64 * 60)
+            }
+        });
+    }
+}
+
+/// Keeps track of the number of connections open from every address
+#[derive(Debug, Clone, Default)]
+pub struct ConnectionLimiter
+{
+    connections: Arc<DashMap<IpAddr, usize>>,
+
+    max_connections_per_address: usize,
+}
+
+impl ConnectionLimiter
+{
+    pub fn new(max_connections_per_address: usize) -> Self
+    {
+        Self {
+            connections: Arc::new(DashMap::new()),
+            max_connections_per_address,
+        }
+    }
+
+    /// Registers a new connection from the address, returns ```None``` if there are too many connections open from the address already
+    /// The connection is counted until the returned guard is dropped
+    pub fn try_acquire(&self, address: IpAddr) -> Option<ConnectionGuard>
+    {
+        let mut connections = self.connections.entry(address).or_insert(0);
+
+        if *connections >= self.max_connections_per_address {
+            return None;
+        }
+
+        *connections += 1;
+
+        Some(ConnectionGuard {
+            connections: self.connections.clone(),
+            address,
+        })
+    }
+}
+
+/// A connection counted by the ```ConnectionLimiter```, the connection is no longer counted after this is dropped
+#[derive(Debug)]
+pub struct ConnectionGuard
+{
+    connections: Arc<DashMap<IpAddr, usize>>,
+
+    address: IpAddr,
+}
+
+impl Drop for ConnectionGuard
+{
+    fn drop(&mut self)
+    {
+        if let Some(mut connections) = self.connections.get_mut(&self.address) {
+            *connections = connections.saturating_sub(1);
+        }
+
+        //Forget the addresses without any connections so that the map doesnt grow forever
+        self.connections
+            .remove_if(&self.address, |_, connections| *connections == 0);
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use std::net::Ipv4Addr;
+
+    use super::*;
+
+    const ADDRESS: IpAddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+
+    fn limiter(messages: RateLimit) -> RateLimiter
+    {
+        RateLimiter::new(RateLimitConfig::new(
+            ActionLimits {
+                messages,
+                ..Default::default()
+            },
+            8,
+        ))
+    }
+
+    #[test]
+    fn token_bucket_refills_over_time()
+    {
+        let limit = RateLimit {
+            capacity: 2,
+            per_minute: 60,
+        };
+
+        let now = Instant::now();
+        let mut bucket = TokenBucket::new(limit.capacity as f64, now);
+
+        assert!(bucket.try_take(limit, now));
+        assert!(bucket.try_take(limit, now));
+        assert!(!bucket.try_take(limit, now));
+
+        //A token is generated every second
+        assert!(bucket.try_take(limit, now + Duration::from_secs(1)));
+        assert!(!bucket.try_take(limit, now + Duration::from_secs(1)));
+
+        //The bucket never holds more than its capacity
+        let later = now + Duration::from_secs(60);
+
+        assert!(bucket.is_full(limit.capacity as f64, 1., later));
+        assert!(bucket.try_take(limit, later));
+        assert!(bucket.try_take(limit, later));
+        assert!(!bucket.try_take(limit, later));
+    }
+
+    #[test]
+    fn token_bucket_debt_delays_transfers()
+    {
+        let now = Instant::now();
+        let mut bucket = TokenBucket::new(100., now);
+
+        assert_eq!(bucket.take_debt(100, 100, now), Duration::ZERO);
+        assert_eq!(bucket.take_debt(50, 100, now), Duration::from_millis(500));
+
+        //The debt is paid back before the next transfer can go through without waiting
+        assert_eq!(
+            bucket.take_debt(50, 100, now + Duration::from_millis(500)),
+            Duration::from_millis(500)
+        );
+        assert_eq!(
+            bucket.take_debt(0, 100, now + Duration::from_secs(1)),
+            Duration::ZERO
+        );
+    }
+
+    #[test]
+    fn rejects_after_burst_and_disconnects_repeat_violators()
+    {
+        let mut limiter = limiter(RateLimit {
+            capacity: 1,
+            per_minute: 1,
+        });
+
+        let now = Instant::now();
+
+        assert_eq!(
+            limiter.check_at("user", ADDRESS, RateLimitedAction::Message, now),
+            RateLimitVerdict::Allowed
+        );
+
+        let config = RateLimitConfig::default();
+
+        for _ in 1..config.violations_before_disconnect {
+            assert_eq!(
+                limiter.check_at("user", ADDRESS, RateLimitedAction::Message, now),
+                RateLimitVerdict::Rejected
+            );
+        }
+
+        assert_eq!(
+            limiter.check_at("user", ADDRESS, RateLimitedAction::Message, now),
+            RateLimitVerdict::Disconnect(config.disconnect_minutes)
+        );
+
+        //The next disconnect lasts twice as long
+        for _ in 1..config.violations_before_disconnect {
+            limiter.check_at("user", ADDRESS, RateLimitedAction::Message, now);
+        }
+
+        assert_eq!(
+            limiter.check_at("user", ADDRESS, RateLimitedAction::Message, now),
+            RateLimitVerdict::Disconnect(config.disconnect_minutes * 2)
+        );
+    }
+
+    #[test]
+    fn address_limit_applies_to_every_user_of_the_address()
+    {
+        let mut limiter = limiter(RateLimit {
+            capacity: 1,
+            per_minute: 1,
+        });
+
+        let now = Instant::now();
+
+        //Every user has a bucket of their own, but the address' bucket is shared
+        for user in 0..ADDRESS_LIMIT_MULTIPLIER {
+            assert_eq!(
+                limiter.check_at(&user.to_string(), ADDRESS, RateLimitedAction::Message, now),
+                RateLimitVerdict::Allowed
+            );
+        }
+
+        assert_eq!(
+            limiter.check_at("another user", ADDRESS, RateLimitedAction::Message, now),
+            RateLimitVerdict::Rejected
+        );
+
+        //The other actions have buckets of their own
+        assert_eq!(
+            limiter.check_at("another user", ADDRESS, RateLimitedAction::Reaction, now),
+            RateLimitVerdict::Allowed
+        );
+    }
+
+    #[test]
+    fn prunes_full_buckets_and_old_violations()
+    {
+        let mut limiter = limiter(RateLimit {
+            capacity: 1,
+            per_minute: 60,
+        });
+
+        let now = Instant::now();
+
+        limiter.check_at("user", ADDRESS, RateLimitedAction::Message, now);
+        limiter.check_at("user", ADDRESS, RateLimitedAction::Message, now);
+        limiter.throttle_transfer_at("user", ADDRESS, 1, now);
+
+        assert_eq!(limiter.user_buckets.len(), 1);
+        assert_eq!(limiter.address_buckets.len(), 1);
+        assert_eq!(limiter.user_transfer_buckets.len(), 1);
+        assert_eq!(limiter.violations.len(), 1);
+
+        //The buckets have been refilled and the violation has been forgotten by the time they are pruned
+        limiter.check_at(
+            "other user",
+            ADDRESS,
+            RateLimitedAction::Reaction,
+            now + VIOLATION_RESET_INTERVAL * 2,
+        );
+
+        assert!(limiter
+            .user_buckets
+            .keys()
+            .all(|(uuid, _)| uuid == "other user"));
+        assert!(limiter
+            .address_buckets
+            .keys()
+            .all(|(_, action)| *action == RateLimitedAction::Reaction));
+        assert!(limiter.user_transfer_buckets.is_empty());
+        assert!(limiter.address_transfer_buckets.is_empty());
+        assert!(limiter.violations.is_empty());
+    }
+
+    #[test]
+    fn remembers_disconnected_users_for_longer()
+    {
+        let mut limiter = limiter(RateLimit {
+            capacity: 1,
+            per_minute: 1,
+        });
+
+        let now = Instant::now();
+
+        for _ in 0..=RateLimitConfig::default().violations_before_disconnect {
+            limiter.check_at("user", ADDRESS, RateLimitedAction::Message, now);
+        }
+
+        limiter.check_at(
+            "other user",
+            ADDRESS,
+            RateLimitedAction::Reaction,
+            now + VIOLATION_RESET_INTERVAL * 2,
+        );
+
+        assert!(limiter.violations.contains_key("user"));
+    }
+}
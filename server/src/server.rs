@@ -0,0 +1,4099 @@
+pub const SERVER_UUID: &str = "00000000-0000-0000-0000-000000000000";
+pub const SERVER_AUTHOR: &str = "Server";
+
+/// The name of the folder the chunks of the uploads are written to until they are committed (Inside the server's storage folder)
+const UPLOADS_FOLDER_NAME: &str = "uploads";
+
+/// How long an upload can go without receiving a chunk before the received chunks are discarded
+const UPLOAD_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+
+/// The number of handled requests remembered per user (```ClientMessage::request_id```), the clients only send again the requests which havent been acknowledged yet
+const HANDLED_REQUEST_HISTORY: usize = 256;
+
+use std::{
+    collections::{hash_map::Entry, HashMap, HashSet, VecDeque},
+    fs,
+    io::{self, Read, Seek, SeekFrom},
+    net::SocketAddr,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use crate::protocol::{HASH_BYTE_OFFSET, IDENTIFICATOR_BYTE_OFFSET, UUID_BYTE_OFFSET};
+
+use anyhow::{bail, ensure, Error, Result};
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use indexmap::IndexMap;
+use tokio_util::sync::CancellationToken;
+
+use crate::protocol::{
+    decode_message, encrypt, fetch_incoming_message_length, Capability, Channel,
+    ClientChannelRequest, ClientDirectMessage, ClientFileTransfer, ClientHandshake,
+    ClientLastSeenMessage, ClientMessageEdit, ClientMessagePin, ClientMessageType,
+    ClientModerationRequest, ClientPong, ClientProfile, ClientRoleRequest, ClientSyncMessage,
+    ClientVoipRequest, ConnectedClient, ConnectionType, FrameCompression, MediaType, MessageId,
+    MessageReaction, Permission, QuotaExceeded, RateLimitedAction, ReactionType, RequestError,
+    Role, ServerChannelUpdate, ServerClientReply, ServerControlReply, ServerFrame,
+    ServerHistoryPage, ServerMessage, ServerMessageEdit, ServerMessageType, ServerPinUpdate,
+    ServerPing, ServerReplyType, ServerRoleUpdate, ServerSync, ServerUploadReply, ServerVoip,
+    ServerVoipReply, ServerVoipState, ValidationError, DEFAULT_CHANNEL_ID, HISTORY_PAGE_SIZE,
+    MESSAGE_DATE_FORMAT, MIN_PROTOCOL_VERSION, PROTOCOL_VERSION, TRANSFER_CHUNK_SIZE,
+};
+
+use crate::protocol::{
+    decrypt_aes256_bytes, encrypt_aes256_bytes, get_image_header, message_position,
+    ClientFileRequestType as ClientRequestTypeStruct, ClientFileUpload as ClientFileUploadStruct,
+    ClientMessage,
+    ClientMessageType::{
+        ChannelRequest, DirectMessage, FileRequestType, FileTransfer, FileUpload, HistoryRequest,
+        MessageEdit, MessagePin, ModerationRequest, NormalMessage, Pong,
+        Reaction as ClientReaction, RoleRequest, SyncMessage, VoipConnection,
+    },
+    ClientThreadChannels, ImageBuffer, ImageHeader, ServerFileReply, ServerImageReply,
+    ServerMaster, UdpMessageType,
+};
+
+use tokio::{
+    io::AsyncWrite,
+    net::{tcp::OwnedReadHalf, UdpSocket},
+    select,
+    sync::{
+        mpsc::{self, Receiver},
+        Notify,
+    },
+    task::JoinHandle,
+};
+
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net,
+};
+
+use crate::protocol::{ServerAudioReply, ServerOutput};
+
+use crate::{
+    ban::{BanRecord, Subnet},
+    blob_store::{
+        blob_reference, format_size, Blob, BlobStore, StorageReport, BLOB_COLLECTION_INTERVAL,
+    },
+    client_writer::{ClientWriter, OutboundQueueConfig},
+    heartbeat::HeartbeatConfig,
+    quota::QuotaConfig,
+    rate_limit::{
+        ConnectionGuard, ConnectionLimiter, RateLimitConfig, RateLimitVerdict, RateLimiter,
+    },
+    storage::{ServerState, StateChange, StateJournal, StateMetadata},
+    validation::{
+        check_frame_size, validate_request, MAX_CONNECTION_FRAME_SIZE, MAX_FRAME_SIZE,
+        MAX_HANDSHAKE_FRAME_SIZE, MAX_MEDIA_SIZE,
+    },
+};
+
+/// The state of the server, the requests of different clients are handled in parallel
+/// Every field has a lock of its own, when multiple locks have to be held at once they are locked in this order:
+/// ```connected_clients```, ```voip```, ```channels```, ```blobs```, ```messages```, ```direct_messages```, and the rest of the fields
+/// Nothing should be written to the disk while any of these locks are held
+#[derive(Debug, Default)]
+pub struct MessageService
+{
+    /// Contains all the messages
+    pub messages: Arc<tokio::sync::Mutex<Vec<ServerOutput>>>,
+
+    /// Contains all the channels of the server, every message belongs to one of these
+    pub channels: Arc<tokio::sync::Mutex<Vec<Channel>>>,
+
+    /// Contains all the private messages, these are stored separately from the public messages so that they never get synced with other clients
+    /// The key is the id of the conversation (```direct_conversation_id```), the value is the list of messages sent in the conversation
+    pub direct_messages: Arc<tokio::sync::Mutex<HashMap<String, Vec<ServerOutput>>>>,
+
+    /// Contains the ids of the pinned messages
+    pub pinned_messages: Arc<tokio::sync::Mutex<Vec<MessageId>>>,
+
+    /// Contains the roles assigned to the users, the key is the user's uuid
+    /// Users who havent been assigned a role have the default role
+    pub roles: Arc<tokio::sync::Mutex<HashMap<String, Role>>>,
+
+    /// Contains the users who cannot send messages or upload files, the key is the user's uuid and the value is the date when the mute expires
+    pub mutes: Arc<tokio::sync::Mutex<HashMap<String, DateTime<Utc>>>>,
+
+    /// Contains the users who cannot connect to the server, the key is the user's uuid and the value is the date when the timeout expires
+    pub timeouts: Arc<tokio::sync::Mutex<HashMap<String, DateTime<Utc>>>>,
+
+    /// Keeps track of how fast the clients are sending their requests
+    pub rate_limiter: Arc<tokio::sync::Mutex<RateLimiter>>,
+
+    /// The limits of how much space the uploads can take up, and how long the messages are kept for
+    pub quota_config: QuotaConfig,
+
+    /// How often the clients are pinged, and how long they can stay silent before they get disconnected
+    pub heartbeat_config: HeartbeatConfig,
+
+    /// The id the next message will be assigned, every message the server sends has a unique id
+    pub next_message_id: Arc<AtomicU64>,
+
+    /// The sequence of the last message sent to every client (```ServerOutput::sequence```)
+    pub sequence: Arc<AtomicU64>,
+
+    /// The revision of the last change made to the messages (```ServerOutput::revision```)
+    pub revision: Arc<AtomicU64>,
+
+    /// This is the required password by the server this password is hashed with argon2, and is compared with the hashed client password
+    pub passw: String,
+
+    /// The uploaded files, images and audio files, stored by their hash
+    /// A file which has been uploaded multiple times is only stored once
+    pub blobs: Arc<tokio::sync::Mutex<BlobStore>>,
+
+    /// Contains the uploads which have been started but havent been committed yet
+    /// The key is the path of the partial file the chunks are written to
+    pub pending_uploads: Arc<DashMap<PathBuf, PendingUpload>>,
+
+    ///connected clients
+    pub connected_clients: Arc<tokio::sync::Mutex<Vec<ConnectedClient>>>,
+
+    /// Client secret
+    pub decryption_key: [u8; 32],
+
+    /// Client last seen message
+    pub clients_last_seen_index: Arc<tokio::sync::Mutex<Vec<ClientLastSeenMessage>>>,
+
+    /// This hashmap contains the connected clients' profiles
+    /// In this hashmap the key is the connecting client's uuid, and the value is the ClientProfile struct (which will later get converted to string with serde_json)
+    pub connected_clients_profile: Arc<tokio::sync::Mutex<HashMap<String, ClientProfile>>>,
+
+    /// This field contains all the shared fields, these fields are shared with the frontend
+    pub shared_fields: Arc<tokio::sync::Mutex<SharedFields>>,
+
+    /// The ongoing voip call, if there is one
+    pub voip: Arc<tokio::sync::Mutex<Option<ServerVoip>>>,
+
+    /// The ids of the last requests handled for every user (```ClientMessage::request_id```), the key is the user's uuid
+    /// A request which was sent again after a reconnect is only acknowledged if its id is in here
+    handled_requests: Arc<tokio::sync::Mutex<HashMap<String, VecDeque<String>>>>,
+
+    /// This is notified whenever the state has changed, the state is saved by a thread of its own so that the requests dont have to wait for the disk
+    state_changed: Arc<Notify>,
+
+    opened_on_port: String,
+
+    /// The folder where the server stores the uploaded files
+    storage_path: PathBuf,
+}
+
+/// An upload which has been started but hasnt been committed yet
+/// The upload is discarded if it doesnt receive a chunk for ```UPLOAD_TIMEOUT```, or if its uploader is disconnected by the server
+#[derive(Debug, Clone)]
+pub struct PendingUpload
+{
+    /// The declared size of the file
+    pub size: u64,
+
+    /// The uuid of the user who has started the upload
+    pub uploader: String,
+
+    /// When the upload was started or last received a chunk
+    pub last_activity: Instant,
+}
+
+/// This struct has fields which are exposed to the Ui / Main thread, so they can freely modified via the channel system
+#[derive(Debug, Clone, Default)]
+pub struct SharedFields
+{
+    /// This list contains the bans, expired bans are removed automatically
+    /// The list is saved to the disk whenever it changes
+    pub bans: Arc<tokio::sync::Mutex<Vec<BanRecord>>>,
+
+    /// The usage of the blob store, and how much space the collector has reclaimed
+    /// This is updated every time the collector runs
+    pub storage_report: Arc<tokio::sync::Mutex<StorageReport>>,
+}
+
+/// This struct contains the handles needed to keep a frontend in sync with the server
+/// When running headless (no Ui attached) this is not needed, thus it is passed in as an ```Option<ServerUiSync>```
+#[derive(Clone)]
+pub struct ServerUiSync
+{
+    /// The connected clients' profiles get copied into this list every sync
+    pub connected_clients_profile: Arc<DashMap<String, ClientProfile>>,
+
+    /// This gets called every sync, so the frontend can request a repaint
+    pub request_repaint: Arc<dyn Fn() + Send + std::marker::Sync>,
+}
+
+/// The handles of a started server, returned by ```server_main```
+pub struct ServerHandle
+{
+    /// The fields which can be modified by the Ui / Main thread
+    pub shared_fields: Arc<tokio::sync::Mutex<SharedFields>>,
+
+    /// The thread saving the state, it saves the last changes and stops once the server has been shut down
+    /// This should be awaited before exiting, otherwise the last changes might not be saved
+    pub saver: JoinHandle<()>,
+}
+
+/// Starts the server, the server is shut down with the ```cancellation_token``` (The client readers are stopped by its child tokens)
+#[allow(clippy::too_many_arguments)]
+pub async fn server_main(
+    port: String,
+    password: String,
+    //This is the folder the server will store the uploaded files in
+    storage_path: PathBuf,
+    //The uuid of the user who owns the server, this user is always assigned the owner role
+    owner_uuid: Option<String>,
+    //The limits of how fast the clients can send requests, and how many connections can be open from the same address
+    rate_limit_config: RateLimitConfig,
+    //The limits of how much space the uploads can take up, and how long the messages are kept for
+    quota_config: QuotaConfig,
+    //The size of the queues the frames sent to the clients wait in, and what happens when one of them is full
+    outbound_queue_config: OutboundQueueConfig,
+    //How often the clients are pinged, and how long they can stay silent before they get disconnected
+    heartbeat_config: HeartbeatConfig,
+    //This signals all the client receivers to be shut down
+    cancellation_token: CancellationToken,
+    //If there is no Ui attached to the server this should be None
+    ui_sync: Option<ServerUiSync>,
+) -> anyhow::Result<ServerHandle>
+{
+    //Bind to ipv6 ip address
+    let tcp_listener_ipv6 = match net::TcpListener::bind(format!("[::]:{}", port)).await {
+        Ok(tcp_listener) => tcp_listener,
+        Err(err_v6) => {
+            bail!("\nCould not bind to IPv6: {err_v6}")
+        },
+    };
+
+    //Bind to ipv4 ip address
+    //On some platforms (like linux) the ipv6 listener is dual-stack by default, so it already accepts ipv4 connections
+    let tcp_listener_ipv4 = match net::TcpListener::bind(format!("0.0.0.0:{}", port)).await {
+        Ok(tcp_listener) => Some(tcp_listener),
+        Err(err_v4) => {
+            tracing::warn!("Could not bind to IPv4, relying on the IPv6 listener: {err_v4}");
+
+            None
+        },
+    };
+
+    //Create the storage folder if it doesnt exist yet
+    tokio::fs::create_dir_all(&storage_path).await?;
+
+    //Load the messages, uploads, etc. saved by the last session
+    let mut server_state = ServerState::load(&storage_path).await?;
+
+    let journal = StateJournal::new(&storage_path, &server_state)?;
+
+    if let Some(owner_uuid) = owner_uuid {
+        server_state.metadata.roles.insert(owner_uuid, Role::Owner);
+    }
+
+    //Server default information
+    let msg_service = Arc::new(MessageService {
+        messages: Arc::new(tokio::sync::Mutex::new(server_state.messages)),
+        channels: Arc::new(tokio::sync::Mutex::new(server_state.metadata.channels)),
+        direct_messages: Arc::new(tokio::sync::Mutex::new(server_state.direct_messages)),
+        pinned_messages: Arc::new(tokio::sync::Mutex::new(
+            server_state.metadata.pinned_messages,
+        )),
+        roles: Arc::new(tokio::sync::Mutex::new(server_state.metadata.roles)),
+        mutes: Arc::new(tokio::sync::Mutex::new(server_state.metadata.mutes)),
+        timeouts: Arc::new(tokio::sync::Mutex::new(server_state.metadata.timeouts)),
+        rate_limiter: Arc::new(tokio::sync::Mutex::new(RateLimiter::new(rate_limit_config))),
+        quota_config,
+        heartbeat_config,
+        next_message_id: Arc::new(AtomicU64::new(server_state.metadata.next_message_id)),
+        sequence: Arc::new(AtomicU64::new(server_state.metadata.sequence)),
+        revision: Arc::new(AtomicU64::new(server_state.metadata.revision)),
+        blobs: Arc::new(tokio::sync::Mutex::new(server_state.blobs)),
+        clients_last_seen_index: Arc::new(tokio::sync::Mutex::new(
+            server_state.metadata.clients_last_seen_index,
+        )),
+        passw: encrypt(password),
+        decryption_key: rand::random::<[u8; 32]>(),
+        shared_fields: Arc::new(tokio::sync::Mutex::new(SharedFields {
+            bans: Arc::new(tokio::sync::Mutex::new(server_state.metadata.bans)),
+            ..Default::default()
+        })),
+        opened_on_port: port,
+        storage_path,
+        ..Default::default()
+    });
+
+    //This is used to shutdown the main server thread
+    let cancellation_child = cancellation_token.child_token();
+
+    //This is used to shutdown the Ui-Server sync thread
+    let cancellation_child_clone = cancellation_child.clone();
+
+    //This is used to shutdown the blob collector thread
+    let cancellation_collector = cancellation_child.clone();
+
+    //This is used to shutdown the thread saving the state
+    let cancellation_saver = cancellation_child.clone();
+
+    //We have to clone here to be able to move this into the thread
+    let msg_service_clone = msg_service.clone();
+
+    //Counts the connections open from every address
+    let connection_limiter = ConnectionLimiter::new(rate_limit_config.max_connections_per_address);
+
+    //Server thread
+    let server_thread: tokio::task::JoinHandle<anyhow::Result<()>> = tokio::spawn(async move {
+        loop {
+            //Wait for incoming connections or wait till the server gets shut down
+            let (stream, socket_addr) = select! {
+                _ = cancellation_child.cancelled() => {
+                    //shutdown server
+                    break;
+                }
+
+                //Listen on incoming ipv6 packets
+                connection = tcp_listener_ipv6.accept() => {
+                    connection?
+                }
+
+                //Listen on incoming ipv4 packets
+                connection = async {
+                    match &tcp_listener_ipv4 {
+                        Some(tcp_listener) => tcp_listener.accept().await,
+                        None => std::future::pending().await,
+                    }
+                } => {
+                    connection?
+                }
+            };
+
+            //Refuse the connection if there are too many connections open from the address already
+            let Some(connection_guard) = connection_limiter.try_acquire(socket_addr.ip())
+            else {
+                tracing::warn!("Refused connection from {socket_addr}, too many connections are open from the address");
+
+                //Dont block accepting the other connections while the reply is being sent
+                tokio::spawn(async move {
+                    let mut stream = stream;
+
+                    send_control_reply(&mut stream, ServerControlReply::TooManyConnections).await
+                });
+
+                continue;
+            };
+
+            //split client stream, so we will be able to store these separately
+            let (reader, writer) = stream.into_split();
+
+            //The frames are written by a task of their own, so that the server never waits for a client
+            let writer = ClientWriter::spawn(writer, socket_addr, outbound_queue_config);
+
+            //We need to clone here too, to pass it into the listener thread
+            let message_service_clone = msg_service_clone.clone();
+
+            //Listen for future client messages (IF the client stays connected)
+            spawn_client_reader(
+                Arc::new(tokio::sync::Mutex::new(reader)),
+                writer,
+                message_service_clone,
+                cancellation_token.child_token(),
+                socket_addr,
+                connection_guard,
+            );
+        }
+        Ok(())
+    });
+
+    //The server thread is detached, it stops once the server is shut down
+    drop(server_thread);
+
+    //We have to clone here to be able to move it into the thread
+    let message_service_clone = msg_service.clone();
+
+    //This thread enforces the bans, and keeps in sync with the ui (if there is one), so the user can interact with the servers settings
+    let ban_enforcer: JoinHandle<anyhow::Result<()>> = tokio::spawn(async move {
+        //The ban list as it was last saved, the Ui can modify the list at any time so we have to check for changes
+        let mut saved_bans = message_service_clone
+            .shared_fields
+            .lock()
+            .await
+            .bans
+            .lock()
+            .await
+            .clone();
+
+        loop {
+            select! {
+                //We should only init a sync 3 secs
+                _ = tokio::time::sleep(Duration::from_secs(3)) => {
+                    let message_service = &message_service_clone;
+
+                    //Only sync the profiles if there is a Ui attached
+                    if let Some(ui_sync) = &ui_sync {
+                        (ui_sync.request_repaint)();
+
+                        //The original client list contained by the server
+                        let connected_clients_server = message_service.connected_clients_profile.lock().await.clone();
+
+                        ui_sync.connected_clients_profile.clear();
+
+                        //Since we cant just rewrite the connected_clients we clear and then insert every
+                        for (key, value) in connected_clients_server.into_iter() {
+                            ui_sync.connected_clients_profile.insert(key.clone(), value);
+                        }
+                    }
+
+                    //Remove the expired bans, and take a copy so that we dont hold the lock while disconnecting the clients
+                    let bans = {
+                        let shared_fields = message_service.shared_fields.lock().await;
+
+                        let mut bans = shared_fields.bans.lock().await;
+
+                        bans.retain(|ban| !ban.is_expired());
+
+                        bans.clone()
+                    };
+
+                    let mut clients = message_service.connected_clients.lock().await;
+
+                    //The clients who are banned by uuid or by their address, and the ban which applies to them
+                    let banned_clients: Vec<(ConnectedClient, BanRecord)> = clients
+                        .iter()
+                        .filter_map(|client| {
+                            bans.iter()
+                                .find(|ban| ban.applies_to(&client.uuid, client.address))
+                                .map(|ban| (client.clone(), ban.clone()))
+                        })
+                        .collect();
+
+                    let mut announcements = Vec::new();
+
+                    for (client, ban) in &banned_clients {
+                        //Look up the index every time, since the list shrinks with every ban
+                        if let Some(idx) = clients.iter().position(|connected_client| connected_client.uuid == client.uuid) {
+                            announcements.push(message_service.handle_server_ban(client, ban, &mut clients, idx).await?);
+                        }
+                    }
+
+                    //The announcements are sent to all the clients, so we cant hold the lock
+                    drop(clients);
+
+                    for (client, _) in &banned_clients {
+                        message_service.discard_uploads(&client.uuid).await;
+                    }
+
+                    for announcement in announcements {
+                        message_service.announce(announcement).await?;
+                    }
+
+                    let voip = message_service.voip.lock().await.clone();
+                    if let Some(voip) = voip {
+                        for banned_uuid in banned_clients.iter().map(|(client, _)| &client.uuid).chain(bans.iter().filter_map(|ban| ban.uuid.as_ref())) {
+                            voip.disconnect(banned_uuid.to_string()).unwrap_or_default();
+                        }
+                    }
+
+                    //Save the ban list if it has been modified (by the Ui or by a user)
+                    if bans != saved_bans {
+                        message_service.save_state();
+
+                        saved_bans = bans;
+                    }
+                },
+
+                _ = cancellation_child_clone.cancelled() => {
+                    //shutdown sync thread
+                    break;
+                },
+            }
+        }
+        Ok(())
+    });
+
+    //The thread is detached, it stops once the server is shut down
+    drop(ban_enforcer);
+
+    //We have to clone here to be able to move it into the thread
+    let message_service_clone = msg_service.clone();
+
+    //This thread removes the messages which are older than the retention policy allows, and deletes the uploaded files which arent referred to by any message anymore
+    let blob_collector: JoinHandle<anyhow::Result<()>> = tokio::spawn(async move {
+        let mut collection_interval = tokio::time::interval(BLOB_COLLECTION_INTERVAL);
+
+        loop {
+            select! {
+                _ = collection_interval.tick() => {
+                    let message_service = &message_service_clone;
+
+                    if let Err(err) = message_service.apply_retention().await {
+                        tracing::error!("Failed to apply the retention policy: {err}");
+                    }
+
+                    message_service.collect_blobs().await;
+
+                    if let Err(err) = message_service.expire_uploads().await {
+                        tracing::error!("Failed to remove the expired uploads: {err}");
+                    }
+                },
+
+                _ = cancellation_collector.cancelled() => {
+                    //shutdown collector thread
+                    break;
+                },
+            }
+        }
+        Ok(())
+    });
+
+    //The collector is detached, it stops once the server is shut down
+    drop(blob_collector);
+
+    //This thread saves the state whenever it changes, so that writing the file never holds up the requests
+    let saver = tokio::spawn({
+        let message_service = msg_service.clone();
+
+        async move {
+            //The saver is the only one writing to the storage folder, so it keeps track of what has already been written
+            let mut journal = journal;
+
+            loop {
+                select! {
+                    _ = message_service.state_changed.notified() => {
+                        message_service.write_state(&mut journal).await;
+                    },
+
+                    _ = cancellation_saver.cancelled() => {
+                        //The last changes are saved before the thread shuts down
+                        message_service.write_state(&mut journal).await;
+
+                        break;
+                    },
+                }
+            }
+        }
+    });
+
+    //We return the fields which can be modified by the Ui, and the saver which has to be awaited before exiting
+    Ok(ServerHandle {
+        shared_fields: msg_service.shared_fields.clone(),
+        saver,
+    })
+}
+
+/// This function does not need to be async since it spawn an async thread anyway
+/// Spawn reader thread, this will constantly listen to the client which was connected, this thread will only finish if the client disconnects
+#[inline]
+fn spawn_client_reader(
+    reader: Arc<tokio::sync::Mutex<OwnedReadHalf>>,
+    writer: ClientWriter,
+    msg_service: Arc<MessageService>,
+    cancellation_token: CancellationToken,
+    socket_addr: SocketAddr,
+    connection_guard: ConnectionGuard,
+)
+{
+    let client_reader: tokio::task::JoinHandle<anyhow::Result<()>> = tokio::spawn(async move {
+        //The connection is counted until the reader stops
+        let _connection_guard = connection_guard;
+
+        //The first frame is the handshake, the client and the server agree on the protocol version and the optional features
+        let capabilities = select! {
+            _ = cancellation_token.cancelled() => {
+                return Ok(());
+            }
+
+            capabilities = handshake(reader.clone(), writer.clone()) => {
+                match capabilities {
+                    Ok(capabilities) => capabilities,
+                    Err(err) => {
+                        tracing::warn!("Refused connection from {socket_addr}, the handshake has failed: {err}");
+
+                        return Ok(());
+                    },
+                }
+            }
+        };
+
+        //The frames sent after the handshake are compressed if both sides support it
+        let compression = FrameCompression::negotiate(&capabilities);
+
+        //The next frame is the connection request, which cannot be as large as the rest of the requests
+        //A frame which has been partially read cannot be read again, so the frame is only replaced once it has been received
+        let incoming_frame =
+            receive_message(reader.clone(), MAX_CONNECTION_FRAME_SIZE, compression);
+
+        tokio::pin!(incoming_frame);
+
+        //Only the clients which answer the pings are expected to send something periodically
+        let heartbeat_enabled = capabilities.contains(&Capability::Heartbeat);
+
+        let heartbeat_config = msg_service.heartbeat_config;
+
+        let mut ping_interval = tokio::time::interval(heartbeat_config.interval);
+
+        ping_interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+        //This is reset every time the client sends a frame
+        let silence_timeout = tokio::time::sleep(heartbeat_config.timeout);
+
+        tokio::pin!(silence_timeout);
+
+        loop {
+            //Wait until client sends a message or thread gets cancelled
+            let incoming_message = select! {
+                //Check if the thread needs to be shut down
+                _ = cancellation_token.cancelled() => {
+                    //Let the client know that the server is shutting down, the client might have disconnected already
+                    if let Err(err) = writer.send_control_reply(ServerControlReply::Shutdown) {
+                        tracing::warn!("Failed to notify {socket_addr} about the shutdown: {err}");
+                    }
+
+                    //The other clients arent notified about the disconnection, since they are disconnected too
+                    return Ok(());
+                }
+
+                _ = ping_interval.tick(), if heartbeat_enabled => {
+                    if let Err(err) = msg_service.ping_client(&writer, compression).await {
+                        tracing::warn!("Listener on {socket_addr} shutting down, failed to ping the client: {err}");
+
+                        break;
+                    }
+
+                    continue;
+                }
+
+                //The connection has been lost without being closed, the client might not be reachable at all
+                _ = &mut silence_timeout, if heartbeat_enabled => {
+                    tracing::warn!("Listener on {socket_addr} shutting down, the client hasnt sent anything for {:?}", heartbeat_config.timeout);
+
+                    //There is no point in sending the queued frames to a client which isnt reading them
+                    writer.close();
+
+                    break;
+                }
+
+                //The connection has been closed by the writer, because the client couldnt keep up with the frames sent to it
+                _ = writer.closed() => {
+                    tracing::warn!("Listener on {socket_addr} shutting down, the connection has been closed");
+
+                    break;
+                }
+
+                msg = &mut incoming_frame => {
+                    match msg {
+                        Ok(msg) => msg,
+                        Err(err) => {
+                            tracing::error!("Listener on {socket_addr} shutting down, error receiving a message: {err}");
+
+                            break;
+                        },
+                    }
+                }
+            };
+
+            incoming_frame.set(receive_message(reader.clone(), MAX_FRAME_SIZE, compression));
+
+            silence_timeout
+                .as_mut()
+                .reset(tokio::time::Instant::now() + heartbeat_config.timeout);
+
+            //The requests of the client are handled in the order they were sent, the other clients' requests are handled meanwhile
+            match msg_service
+                .message_main(incoming_message, writer.clone(), socket_addr, &capabilities)
+                .await
+            {
+                Ok(_) => {},
+                Err(err) => {
+                    tracing::error!("Listener on {socket_addr} shutting down, error processing a message: {err}");
+
+                    break;
+                },
+            }
+        }
+
+        //The client is removed from the connected clients, so that it doesnt stay there after its connection has been lost
+        if let Err(err) = msg_service.remove_disconnected_client(&writer).await {
+            tracing::error!("Failed to remove the client connected from {socket_addr}: {err}");
+        }
+
+        Ok(())
+    });
+
+    //The reader is detached, it stops once the client disconnects or the server is shut down
+    drop(client_reader);
+}
+
+/// Receives the client's handshake and replies with the protocol version and the capabilities both sides support
+/// Returns an error if the server cannot talk to the client, the client is notified about the reason before that
+async fn handshake(
+    reader: Arc<tokio::sync::Mutex<OwnedReadHalf>>,
+    writer: ClientWriter,
+) -> Result<Vec<Capability>>
+{
+    //The handshake is never compressed, since the compression hasnt been agreed on yet
+    let message =
+        receive_message(reader, MAX_HANDSHAKE_FRAME_SIZE, FrameCompression::Disabled).await?;
+
+    //Clients older than the handshake send the connection request right away
+    let handshake = serde_json::from_slice::<ClientHandshake>(&message).ok();
+
+    match handshake {
+        Some(handshake) if handshake.is_supported() => {
+            let capabilities = handshake.negotiate_capabilities();
+
+            writer.send_control_reply(ServerControlReply::Handshake {
+                protocol_version: handshake.negotiate_version(),
+                capabilities: capabilities.clone(),
+            })?;
+
+            Ok(capabilities)
+        },
+        _ => {
+            let reply = ServerControlReply::UnsupportedProtocol {
+                client_version: handshake.map(|handshake| handshake.protocol_version),
+                min_version: MIN_PROTOCOL_VERSION,
+                max_version: PROTOCOL_VERSION,
+            };
+
+            writer.send_control_reply(reply.clone())?;
+
+            bail!(reply)
+        },
+    }
+}
+
+#[inline]
+async fn receive_message(
+    reader: Arc<tokio::sync::Mutex<OwnedReadHalf>>,
+    max_frame_size: u32,
+    compression: FrameCompression,
+) -> Result<Vec<u8>>
+{
+    let mut reader = reader.lock().await;
+
+    let incoming_message_len = fetch_incoming_message_length(&mut *reader).await?;
+
+    //Check the length before allocating the buffer, so that a client cannot make the server allocate any amount of memory
+    check_frame_size(incoming_message_len, max_frame_size)?;
+
+    let mut message_buffer: Vec<u8> = vec![0; incoming_message_len as usize];
+
+    //Wait until the client sends the main message
+    reader.read_exact(&mut message_buffer).await?;
+
+    //The decompressed frame has the same size limit as the frame itself
+    compression.decompress(&message_buffer, max_frame_size as usize)
+}
+
+/// Separates the two participants' uuids in the id of a private conversation
+const DIRECT_CONVERSATION_ID_SEPARATOR: char = ':';
+
+/// Returns the id of the private conversation between the two users, the order of the uuids doesnt matter
+fn direct_conversation_id(uuid: &str, other_uuid: &str) -> String
+{
+    if uuid < other_uuid {
+        format!("{uuid}{DIRECT_CONVERSATION_ID_SEPARATOR}{other_uuid}")
+    }
+    else {
+        format!("{other_uuid}{DIRECT_CONVERSATION_ID_SEPARATOR}{uuid}")
+    }
+}
+
+/// Returns the date when the user's (identified by their uuid) restriction (a mute or a timeout) expires, returns ```None``` if the user isnt restricted
+/// Expired restrictions are removed from the list
+fn active_restriction(
+    restrictions: &mut HashMap<String, DateTime<Utc>>,
+    uuid: &str,
+) -> Option<DateTime<Utc>>
+{
+    restrictions.retain(|_, until| *until > Utc::now());
+
+    restrictions.get(uuid).copied()
+}
+
+/// Reads at most ```TRANSFER_CHUNK_SIZE``` bytes of the file starting at the offset, returns the chunk and the size of the whole file
+fn read_file_chunk(path: &PathBuf, offset: u64) -> std::io::Result<(Vec<u8>, u64)>
+{
+    let mut file = fs::File::open(path)?;
+
+    let total_size = file.metadata()?.len();
+
+    let mut bytes = Vec::new();
+
+    file.seek(SeekFrom::Start(offset))?;
+    file.take(TRANSFER_CHUNK_SIZE as u64)
+        .read_to_end(&mut bytes)?;
+
+    Ok((bytes, total_size))
+}
+
+/// Creates a message sent by the server, dated with the current time
+pub(crate) fn server_output(
+    id: MessageId,
+    message_type: ServerMessageType,
+    channel_id: String,
+) -> ServerOutput
+{
+    let timestamp = Utc::now();
+
+    ServerOutput {
+        id,
+        replying_to: None,
+        message_type,
+        author: SERVER_AUTHOR.to_string(),
+        message_date: timestamp.format(MESSAGE_DATE_FORMAT).to_string(),
+        timestamp,
+        sequence: 0,
+        revision: 0,
+        uuid: SERVER_UUID.to_string(),
+        channel_id,
+        reactions: MessageReaction::default(),
+    }
+}
+
+/// Sends a control reply to the client, these are not encrypted since the client might not have received the key yet
+pub async fn send_control_reply<T>(writer: T, reply: ServerControlReply) -> anyhow::Result<()>
+where
+    T: AsyncWriteExt + Unpin + AsyncWrite,
+{
+    send_message_to_client(writer, reply.struct_into_string().as_bytes()).await
+}
+
+pub async fn send_message_to_client<T>(mut writer: T, message_bytes: &[u8]) -> anyhow::Result<()>
+where
+    T: AsyncWriteExt + Unpin + AsyncWrite,
+{
+    //Send message length
+    writer
+        .write_all(&(message_bytes.len() as u32).to_be_bytes())
+        .await?;
+
+    //Send message
+    writer.write_all(message_bytes).await?;
+
+    Ok(())
+}
+
+/// This function will create a management thread, but only if the ```voip.threads``` field is None (Preventing spawning multiple threads)
+pub fn create_client_voip_manager(
+    voip: ServerVoip,
+    shutdown_token: CancellationToken,
+    key: [u8; 32],
+    mut receiver: Receiver<Vec<u8>>,
+    #[allow(unused_variables)] listening_to: SocketAddr,
+    uuid: String,
+)
+{
+    let message_buffer = voip.image_buffer.clone();
+
+    //Spawn client management thread
+    tokio::spawn(async move {
+        loop {
+            let socket_v6 = voip.socket_v6.clone();
+
+            let socket_v4 = voip.socket_v4.clone();
+
+            //Clone so we can move the value
+            let voip_connected_clients = voip.connected_clients.clone();
+
+            let image_buffer: ImageBuffer = message_buffer.clone();
+
+            select! {
+                _ = shutdown_token.cancelled() => {
+                    //Shutdown thread by exiting the loop
+                    break;
+                },
+
+                //receive_message length by reading its first 4 bytes
+                received_bytes = receiver.recv() => {
+                    if let Some(received_bytes) = received_bytes {
+                        //Decrypt message
+                    // [. . . . . .4][4 . . . . len - 4][len - 4..]
+                    //  PACKET LENGTH       MESSAGE      MSG TYPE
+                    //Packets which cannot be decrypted or are too short to contain the message type are ignored
+                    let Ok(mut decrypted_bytes) = decrypt_aes256_bytes(&received_bytes, &key) else {
+                        tracing::warn!("Received an invalid voip packet from {uuid}");
+
+                        continue;
+                    };
+
+                    if decrypted_bytes.len() < 4 {
+                        tracing::warn!("Received a voip packet without a message type from {uuid}");
+
+                        continue;
+                    }
+
+                    let message_type_bytes: [u8; 4] = decrypted_bytes[decrypted_bytes.len() - 4..].try_into().unwrap_or_default();
+
+                    decrypted_bytes.truncate(decrypted_bytes.len() - 4);
+
+                    //Get message type by reading last 4 bytes
+                    let Some(message_type) = UdpMessageType::try_from_number(u32::from_be_bytes(message_type_bytes)) else {
+                        tracing::warn!("Received a voip packet with an unknown message type from {uuid}");
+
+                        continue;
+                    };
+
+                    match message_type {
+                        UdpMessageType::Voice => {
+                            //Spawn relay thread
+                            tokio::spawn(async move {
+                                //Relay message to all of the clients
+                                for connected_socket_addr in voip_connected_clients.iter().filter(|entry| {
+                                    #[allow(unused_variables)]
+                                    let socket_addr = entry.value();
+
+                                    //We dont send the user's voice to them in release builds
+                                    #[cfg(not(debug_assertions))]
+                                    {
+                                        *socket_addr != listening_to
+                                    }
+
+                                    //We allow voice loopback in debug builds
+                                    #[cfg(debug_assertions)]
+                                    {
+                                        true
+                                    }
+                                }).map(|entry| *entry.value()) {
+                                    let mut decrypted_bytes = decrypted_bytes.clone();
+
+                                    //Append message type, this will be encrypted
+                                    decrypted_bytes.append(&mut (message_type.clone() as u32).to_be_bytes().to_vec());
+
+                                    //Encrypt packet
+                                    let mut encrypted_packet = encrypt_aes256_bytes(&decrypted_bytes, &key).unwrap();
+
+                                    //Get encrypted packet size
+                                    let mut message_length_header = (encrypted_packet.len() as u32).to_be_bytes().to_vec();
+
+                                    //Append message to header
+                                    message_length_header.append(&mut encrypted_packet);
+
+                                    //Math ip type
+                                    match connected_socket_addr.is_ipv6() {
+                                        //The connected client's ip has ipv6 protocol
+                                        true => {
+                                            if let Some(ref socket_v6) = socket_v6 {
+                                                //Send the header indicating message lenght and send the whole message appended to it
+                                                socket_v6.send_to(&message_length_header, connected_socket_addr).await.unwrap();
+                                            }
+                                        },
+                                        //The connected client's ip has ipv4 protocol
+                                        false => {
+                                            //Send the header indicating message lenght and send the whole message appended to it
+                                            socket_v4.send_to(&message_length_header, connected_socket_addr).await.unwrap();
+                                        },
+                                    }
+                                }
+                            });
+                        }
+                        UdpMessageType::ImageHeader => {
+                            if let Err(err) = get_image_header(&decrypted_bytes, &image_buffer) {
+                                tracing::warn!("Received an invalid image header from {uuid}: {err}");
+                            }
+                        }
+                        UdpMessageType::Image => {
+                            // [. . . . . . . . . . . len - 164][len - 164 . . . . . len - 100][len - 100. . . . . len - 64][len - 64 . . . .]
+                            //      IMAGE                           HASH                            UUID                      IDENTIFICATOR
+                            let message_bytes = decrypted_bytes.to_vec();
+
+                            //The image part has to be at least as long as its trailer
+                            if message_bytes.len() < HASH_BYTE_OFFSET {
+                                tracing::warn!("Received an image part without a trailer from {uuid}");
+
+                                continue;
+                            }
+
+                            //Get the identificator of the image part in bytes
+                            let indetificator_bytes = message_bytes[message_bytes.len() - IDENTIFICATOR_BYTE_OFFSET..].to_vec();
+
+                            //Get the identificator of the image part in bytes
+                            let hash_bytes = message_bytes[message_bytes.len() - HASH_BYTE_OFFSET..message_bytes.len() - UUID_BYTE_OFFSET].to_vec();
+
+                            //Get the image part bytes
+                            //We subtract 164 bytes to only get the image part
+                            let image = message_bytes[..message_bytes.len() - HASH_BYTE_OFFSET].to_vec();
+
+                            //THIS IS UNUSED AND SHOULD BE REMOVED
+                            let _uuid_bytes = message_bytes[message_bytes.len() - UUID_BYTE_OFFSET..message_bytes.len() - IDENTIFICATOR_BYTE_OFFSET].to_vec();
+
+                            let (Ok(identificator), Ok(hash), Ok(author_uuid)) = (String::from_utf8(indetificator_bytes), String::from_utf8(hash_bytes), String::from_utf8(_uuid_bytes)) else {
+                                tracing::warn!("Received an image part with an invalid trailer from {uuid}");
+
+                                continue;
+                            };
+
+                            if let Some(mut image_header) = image_buffer.get_mut(&uuid) {
+                                if let Some((index, _, contents)) = image_header.get_full_mut(&identificator) {
+
+                                    if let Some(byte_pair) = contents.get_mut(&hash) {
+                                        *byte_pair = Some(image);
+                                    }
+                                    else {
+                                        tracing::error!("Image part hash not found in the image header: {hash}");
+                                    }
+
+                                    //If all the parts of the image header had arrived send the image to all the clients
+                                    if contents.iter().all(|(_, value)| value.is_some()) {
+                                        let contents_clone = contents.clone();
+                                        tokio::spawn(async move {
+
+
+                                        //Combine the image part bytes
+                                        let image_bytes: Vec<u8> = contents_clone.values().flat_map(|value| {
+                                            <std::option::Option<std::vec::Vec<u8>> as Clone>::clone(value).unwrap()
+                                        }).collect();
+
+                                        //Create image parts by splitting it every 60000 bytes
+                                        let image_parts_tuple: Vec<(String, &[u8])> = image_bytes
+                                            .chunks(60000)
+                                            .map(|image_part| (sha256::digest(image_part), image_part))
+                                            .collect();
+
+                                        let image_parts = Vec::from_iter(image_parts_tuple.iter().map(|part| part.0.clone()));
+
+                                        let identificator = sha256::digest(
+                                            image_parts
+                                                .iter()
+                                                .flat_map(|hash| hash.as_bytes().to_vec())
+                                                .collect::<Vec<u8>>(),
+                                        );
+
+                                        for connected_client in voip_connected_clients.iter() {
+                                            let socket_addr = connected_client.value();
+
+                                            //Create header message
+                                            let header_message =
+                                                ImageHeader::new(author_uuid.clone(), image_parts.clone(), identificator.clone());
+
+                                            match socket_addr.is_ipv6() {
+                                                true => {
+                                                    // Send image header
+                                                    send_bytes(
+                                                        serde_json::to_string(&header_message).unwrap().as_bytes().to_vec(),
+                                                        &key,
+                                                        UdpMessageType::ImageHeader,
+                                                        socket_v6.clone().unwrap(),
+                                                        *socket_addr,
+                                                    )
+                                                    .await.unwrap();
+
+                                                    //Send image parts
+                                                    //We have already sent the image header
+                                                    send_image_parts(image_parts_tuple.clone(), author_uuid.clone(), &key, identificator.clone(), socket_v6.clone().unwrap(), *socket_addr)
+                                                        .await.unwrap();
+                                                },
+                                                false => {
+                                                    // Send image header
+                                                    send_bytes(
+                                                        serde_json::to_string(&header_message).unwrap().as_bytes().to_vec(),
+                                                        &key,
+                                                        UdpMessageType::ImageHeader,
+                                                        socket_v4.clone(),
+                                                        *socket_addr,
+                                                    )
+                                                    .await.unwrap();
+
+                                                    //Send image parts
+                                                    //We have already sent the image header
+                                                    send_image_parts(image_parts_tuple.clone(), author_uuid.clone(), &key, identificator.clone(), socket_v4.clone(), *socket_addr)
+                                                        .await.unwrap();
+                                                },
+                                            }
+
+                                        }
+                                        });
+
+                                        //Drain earlier ImageHeaders (and the current one), because a new one has arrived
+                                        image_header.drain(index..=index);
+                                    };
+                                }
+                                else {
+                                    tracing::error!("Image header not found: {identificator}");
+                                }
+                            }
+                            else {
+                                tracing::error!("User not found in the image header list: {uuid}");
+                            };
+                        }
+                    }
+                    }
+                },
+            }
+        }
+    });
+}
+
+async fn send_bytes(
+    mut bytes: Vec<u8>,
+    encryption_key: &[u8],
+    message_type: UdpMessageType,
+    socket: Arc<UdpSocket>,
+    send_to: SocketAddr,
+) -> anyhow::Result<()>
+{
+    //Append message flag bytes
+    bytes.append(&mut (message_type as u32).to_be_bytes().to_vec());
+
+    //Encrypt message
+    let mut encrypted_message = encrypt_aes256_bytes(&bytes, encryption_key)?;
+
+    //Get message length
+    let mut message_length_in_bytes = (encrypted_message.len() as u32).to_be_bytes().to_vec();
+
+    //Append message to message length
+    message_length_in_bytes.append(&mut encrypted_message);
+
+    //Check for packet length overflow
+    let bytes_length = message_length_in_bytes.len();
+
+    if bytes_length > 65536 {
+        bail!(format!(
+            "Udp packet length overflow, with length of {bytes_length}"
+        ))
+    }
+
+    //Send bytes
+    socket.send_to(&message_length_in_bytes, send_to).await?;
+
+    Ok(())
+}
+
+/// Send the images specified in the ```image_parts_tuple``` argument
+/// __Image message contents:__
+/// - ```[len - 64 - 64 - 36..len - 64 - 36]``` = Contains the hash (sha256 hash) of the image part we are sending
+/// - ```[len - 64 - 36.. len - 64]``` = Contains the UUID of the author who has sent the message
+/// - ```[..len - 64 - 64 - 36]``` = Contains the image part we are sending (JPEG image)
+/// - ```[len - 64..]``` = Contains the identificator of the part we are sending
+/// - **The hash length is 64 bytes.**
+/// - **The identificator is 64 bytes.**
+/// - **The uuid is 36 bytes.**
+async fn send_image_parts(
+    image_parts_tuple: Vec<(String, &[u8])>,
+    uuid: String,
+    encryption_key: &[u8],
+    identificator: String,
+    socket: Arc<UdpSocket>,
+    send_to: SocketAddr,
+) -> Result<(), Error>
+{
+    for (hash, bytes) in image_parts_tuple {
+        //Hash as bytes
+        let mut hash = hash.as_bytes().to_vec();
+
+        //Append the hash to the bytes
+        let mut bytes = bytes.to_vec();
+
+        //Append hash
+        bytes.append(&mut hash);
+
+        //Append uuid to the message
+        bytes.append(&mut uuid.as_bytes().to_vec());
+
+        //Append identificator
+        bytes.append(&mut identificator.as_bytes().to_vec());
+
+        //Send bytes
+        send_bytes(
+            bytes,
+            encryption_key,
+            UdpMessageType::Image,
+            socket.clone(),
+            send_to,
+        )
+        .await?;
+    }
+
+    Ok(())
+}
+
+impl MessageService
+{
+    /// The result returned by this function may be a real error, or an error constructed on purpose so that the thread call this function gets shut down.
+    /// When experiencing errors, make sure to check the error message as it may be on purpose
+    #[inline]
+    async fn message_main(
+        &self,
+        message: Vec<u8>,
+        client_handle: ClientWriter,
+        socket_addr: SocketAddr,
+        capabilities: &[Capability],
+    ) -> Result<()>
+    {
+        //A request which cannot be parsed cannot be answered either, since we dont know who has sent it
+        let req: ClientMessage =
+            decode_message(&message).map_err(|_| ValidationError::MalformedRequest)?;
+
+        let uuid = req.uuid.clone();
+        let request_id = req.request_id.clone();
+
+        self.handle_client_request(req, client_handle.clone(), socket_addr, capabilities)
+            .await?;
+
+        //The requests sent from the outbox of the client are acknowledged once they have been handled (applied or rejected)
+        if let Some(request_id) = request_id {
+            self.acknowledge_request(&uuid, request_id, &client_handle, capabilities)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Checks and applies the request of the client, the requests which are rejected are answered with a ```RequestError```
+    async fn handle_client_request(
+        &self,
+        req: ClientMessage,
+        client_handle: ClientWriter,
+        socket_addr: SocketAddr,
+        capabilities: &[Capability],
+    ) -> Result<()>
+    {
+        let is_connection_request = matches!(
+            &req.message_type,
+            SyncMessage(ClientSyncMessage {
+                sync_attribute: Some(ConnectionType::Connect(_)),
+                ..
+            })
+        );
+
+        //Every request (except the connection request) has to be sent through the connection of the user it was sent in the name of
+        //Otherwise anyone could send requests in the name of other users, or without connecting first
+        if !is_connection_request && !self.is_connected_through(&req.uuid, &client_handle).await {
+            client_handle.send_control_reply(ServerControlReply::ProtocolError(
+                ValidationError::UuidMismatch,
+            ))?;
+
+            return Err(ValidationError::UuidMismatch.into());
+        }
+
+        //Check if user has been banned, before anything is done for the request (The connection requests are checked when the client is connected)
+        if !is_connection_request {
+            self.handle_banned_client(&req, &client_handle, socket_addr)
+                .await?;
+        }
+
+        //The request has been sent again after a reconnect, but it has already been handled
+        if let Some(request_id) = &req.request_id {
+            if self
+                .handled_requests
+                .lock()
+                .await
+                .get(&req.uuid)
+                .is_some_and(|request_ids| request_ids.contains(request_id))
+            {
+                return Ok(());
+            }
+        }
+
+        //Check the limits of the request, this has to happen before anything is allocated for the message
+        if let Err(err) = validate_request(&req) {
+            tracing::warn!(
+                "Client {} ({socket_addr}) sent an invalid request: {err}",
+                req.uuid
+            );
+
+            //The client hasnt connected yet, so we can only reply through the connection
+            if is_connection_request {
+                client_handle.send_control_reply(ServerControlReply::ProtocolError(err.clone()))?;
+
+                return Err(err.into());
+            }
+
+            self.send_request_error(&req.uuid, RequestError::Invalid(err))
+                .await?;
+
+            return Ok(());
+        }
+
+        //The optional features can only be used if they were agreed on in the handshake
+        if let Some(capability) = req.required_capability() {
+            if !capabilities.contains(&capability) {
+                tracing::warn!(
+                    "Client {} ({socket_addr}) used a feature it hasnt agreed on: {capability:?}",
+                    req.uuid
+                );
+
+                self.send_request_error(
+                    &req.uuid,
+                    RequestError::Invalid(ValidationError::UnsupportedCapability(capability)),
+                )
+                .await?;
+
+                return Ok(());
+            }
+        }
+
+        //The pongs are only used to measure the latency of the connection
+        if let Pong(pong) = &req.message_type {
+            self.record_latency(&client_handle, pong).await;
+
+            return Ok(());
+        }
+
+        //Messages sent to a channel which doesnt exist (anymore) are rejected
+        if matches!(&req.message_type, NormalMessage(_) | FileUpload(_))
+            && !self
+                .channels
+                .lock()
+                .await
+                .iter()
+                .any(|channel| channel.id == req.channel_id)
+        {
+            tracing::warn!(
+                "Client {} sent a message to a non-existent channel: {}",
+                req.uuid,
+                req.channel_id
+            );
+
+            self.send_request_error(
+                &req.uuid,
+                RequestError::Invalid(ValidationError::ChannelNotFound(req.channel_id.clone())),
+            )
+            .await?;
+
+            return Ok(());
+        }
+
+        //Check if the messages the request refers to exist, so that the request can be applied to them
+        if let Err(err) = self.validate_references(&req).await {
+            tracing::warn!(
+                "Client {} ({socket_addr}) sent an invalid request: {err}",
+                req.uuid
+            );
+
+            self.send_request_error(&req.uuid, RequestError::Invalid(err))
+                .await?;
+
+            return Ok(());
+        }
+
+        //Check if the client is sending requests too fast, this has to happen before anything is allocated for the message
+        if let Some(action) = RateLimitedAction::of_request(&req.message_type) {
+            if !self
+                .check_rate_limit(&req.uuid, socket_addr, action)
+                .await?
+            {
+                return Ok(());
+            }
+        }
+
+        //Check if the client is allowed to make this request, this has to happen before anything is allocated for the message
+        if let Some(permission) = self.required_permission(&req.message_type).await {
+            if !self.check_permission(&req.uuid, permission).await? {
+                return Ok(());
+            }
+        }
+
+        //Muted users cannot send messages, upload files, react or edit their messages until their mute expires (They can still delete them)
+        if matches!(
+            &req.message_type,
+            NormalMessage(_)
+                | DirectMessage(_)
+                | FileUpload(_)
+                | FileTransfer(_)
+                | ClientReaction(_)
+                | MessageEdit(ClientMessageEdit {
+                    new_message: Some(_),
+                    ..
+                })
+        ) {
+            if let Err(err) = self.check_mute(&req.uuid).await {
+                tracing::warn!("Rejected the request of client {}: {err}", req.uuid);
+
+                self.send_request_error(&req.uuid, err).await?;
+
+                return Ok(());
+            }
+        }
+
+        //The chunks of the uploads are written to disk as they arrive, they dont have a message of their own
+        if let FileTransfer(transfer) = &req.message_type {
+            if let ClientFileTransfer::UploadChunk { bytes, .. } = transfer {
+                self.throttle_transfer(&req.uuid, socket_addr, bytes.len() as u64)
+                    .await;
+            }
+
+            match self.handle_file_transfer(&req.uuid, transfer).await {
+                Ok(Some(reply)) => {
+                    client_handle.send(ServerFrame::Reply(reply).encrypt(
+                        &self.decryption_key,
+                        FrameCompression::negotiate(capabilities),
+                    )?)?;
+                },
+                Ok(None) => {},
+                Err(err) => self.reply_transfer_error(&req.uuid, err).await?,
+            }
+
+            return Ok(());
+        }
+
+        //The older pages of the message history are only sent to the client who has requested them
+        if let HistoryRequest(history_request) = &req.message_type {
+            let page = self
+                .history_page(history_request.before_id, &req.channel_id)
+                .await;
+
+            client_handle.send(ServerFrame::Reply(ServerReplyType::History(page)).encrypt(
+                &self.decryption_key,
+                FrameCompression::negotiate(capabilities),
+            )?)?;
+
+            return Ok(());
+        }
+
+        //The upload has to be complete before its message is created
+        if let FileUpload(upload) = &req.message_type {
+            if let Err(err) = self.finish_upload(&req.uuid, upload).await {
+                self.reply_transfer_error(&req.uuid, err).await?;
+
+                return Ok(());
+            }
+        }
+
+        if let ClientMessageType::SyncMessage(sync_msg) = &req.message_type {
+            if sync_msg.password == self.passw.trim() {
+                //Handle incoming connections and disconnections, if sync_attr is a None then its just a message for syncing
+                if let Some(sync_attr) = &sync_msg.sync_attribute {
+                    match sync_attr {
+                        ConnectionType::Connect(profile) => {
+                            //Check if user has been timed out
+                            if let Some(until) =
+                                active_restriction(&mut *self.timeouts.lock().await, &req.uuid)
+                            {
+                                client_handle
+                                    .send_control_reply(ServerControlReply::TimedOut(until))?;
+
+                                return Err(Error::msg(format!(
+                                    "Client has been timed out until {until}!"
+                                )));
+                            }
+
+                            //Check if user has been banned
+                            if let Some(ban) = self.find_ban(&req.uuid, Some(socket_addr)).await {
+                                client_handle.send_control_reply(ServerControlReply::Banned {
+                                    reason: ban.reason.clone(),
+                                    expires_at: ban.expires_at,
+                                })?;
+
+                                return Err(Error::msg(format!(
+                                    "Client has been banned: {}",
+                                    ban.reason
+                                )));
+                            }
+                            else {
+                                let mut clients = self.connected_clients.lock().await;
+
+                                //The token the client has to send if it reconnects while the server still considers this connection open
+                                let reconnect_token = hex::encode(rand::random::<[u8; 32]>());
+
+                                //Check if the client has already been connected once
+                                if let Some(client) =
+                                    clients.iter_mut().find(|client| client.uuid == req.uuid)
+                                {
+                                    //This can only happen if the connection has been lost, and the server hasnt noticed it yet
+                                    //Unless the old connection has already been closed, the new one has to prove that it belongs to the same client, otherwise anyone knowing the uuid could take over the session
+                                    let old_connection_is_open = client
+                                        .handle
+                                        .as_ref()
+                                        .is_some_and(|old_handle| !old_handle.is_closed());
+
+                                    if old_connection_is_open
+                                        && sync_msg.reconnect_token.as_deref()
+                                            != Some(client.reconnect_token.as_str())
+                                    {
+                                        client_handle.send_control_reply(
+                                            ServerControlReply::AlreadyConnected,
+                                        )?;
+
+                                        return Err(Error::msg(format!(
+                                            "Client {} is already connected",
+                                            req.uuid
+                                        )));
+                                    }
+
+                                    //The old connection is closed, the client's requests are going to be sent through the new connection from now on
+                                    if let Some(old_handle) =
+                                        client.handle.replace(client_handle.clone())
+                                    {
+                                        old_handle.close();
+                                    }
+
+                                    client.username = profile.username.clone();
+                                    client.address = Some(socket_addr);
+                                    client.capabilities = capabilities.to_vec();
+                                    client.latency = None;
+                                    client.reconnect_token = reconnect_token.clone();
+                                }
+                                else {
+                                    //The id is assigned when the message is stored
+                                    let server_msg = server_output(
+                                        0,
+                                        ServerMessageType::Server(
+                                            crate::protocol::ServerMessage::Connect(
+                                                profile.clone(),
+                                            ),
+                                        ),
+                                        DEFAULT_CHANNEL_ID.to_string(),
+                                    );
+
+                                    let server_msg = self.store_message(server_msg).await;
+
+                                    self.save_state();
+
+                                    //We should sync the connection message with all the clients except the connecting one, therefor we only pus hback the connected client after we have syncted this message with all the clients
+                                    self.sync_message_with_clients(
+                                        Arc::new(tokio::sync::Mutex::new(clients.clone())),
+                                        server_msg,
+                                    )
+                                    .await?;
+
+                                    //If the ip is not found then add it to connected clients
+                                    clients.push(ConnectedClient::new(
+                                        req.uuid.clone(),
+                                        profile.username.clone(),
+                                        client_handle.clone(),
+                                        socket_addr,
+                                        capabilities.to_vec(),
+                                        reconnect_token.clone(),
+                                    ));
+                                }
+
+                                //Store connected client's profile
+                                self.connected_clients_profile
+                                    .lock()
+                                    .await
+                                    .insert(req.uuid.clone(), profile.clone());
+
+                                //Return custom key which the server's text will be encrypted with
+                                client_handle.send_control_reply(ServerControlReply::Accepted(
+                                    hex::encode(self.decryption_key),
+                                ))?;
+
+                                //Sync all messages, send all of the messages to the client, because we have already provided the decryption key
+                                //A client which has reconnected only receives the messages it has missed
+                                client_handle.send(
+                                    self.full_sync_client(
+                                        &req.uuid,
+                                        FrameCompression::negotiate(capabilities),
+                                        sync_msg.last_message_id,
+                                        sync_msg.last_revision,
+                                        Some(reconnect_token),
+                                    )
+                                    .await?,
+                                )?;
+                                return Ok(());
+                            }
+                        },
+                        //Handle disconnections
+                        ConnectionType::Disconnect => {
+                            let mut clients = self.connected_clients.lock().await;
+                            //Search for connected ip in all connected ips
+                            for (index, client) in clients.clone().iter().enumerate() {
+                                //If found, then disconnect the client
+                                if client.uuid == req.uuid {
+                                    let server_msg = self
+                                        .handle_server_disconnect(client, &mut clients, index)
+                                        .await?;
+
+                                    self.save_state();
+
+                                    self.sync_message_with_clients(
+                                        Arc::new(tokio::sync::Mutex::new(clients.clone())),
+                                        server_msg,
+                                    )
+                                    .await?;
+
+                                    drop(clients);
+
+                                    self.discard_uploads(&req.uuid).await;
+
+                                    return Err(Error::msg("Client disconnected!"));
+                                }
+                            }
+                        },
+                    }
+                }
+            }
+            else {
+                client_handle.send_control_reply(ServerControlReply::InvalidPassword)?;
+
+                //return an error so the client listener thread stops
+                return Err(Error::msg("Invalid password entered by client!"));
+            }
+        }
+
+        //if the client is not found in the list means we have not established a connection, thus an invalid packet (if the user enters a false password then this will return false because it didnt get added in the first part of this function)
+        if self //Check if we have already established a connection with the client, if yes then it doesnt matter what password the user has entered
+            .connected_clients
+            .lock()
+            .await
+            .iter()
+            .any(|client| client.uuid == req.uuid)
+        //Search through the list
+        {
+            //The message the request has created, if it has created one which has to be stored
+            let mut stored_message: Option<ServerOutput> = None;
+
+            match &req.message_type {
+                VoipConnection(request) => {
+                    //The call is kept locked while the request is applied, so that the clients connecting at the same time join the same call
+                    let mut voip_guard = self.voip.lock().await;
+
+                    match request {
+                        crate::protocol::ClientVoipRequest::Connect(port) => {
+                            let socket_addr = SocketAddr::new(socket_addr.ip(), *port);
+
+                            //Send important info to client (Session ID, etc)
+                            client_handle.send(
+                                ServerFrame::Voip(ServerVoipReply::Success).encrypt(
+                                    &self.decryption_key,
+                                    FrameCompression::negotiate(capabilities),
+                                )?,
+                            )?;
+
+                            if let Some(ongoing_call) = &*voip_guard {
+                                ongoing_call.connect(req.uuid.clone(), socket_addr)?;
+                            }
+                            // If there is no ongoing call, we should create it
+                            else {
+                                let voip_server_instance =
+                                    self.create_voip_server(self.opened_on_port.clone()).await?;
+
+                                //Immediately connect the user who has requested the voip call
+                                voip_server_instance.connect(req.uuid.clone(), socket_addr)?;
+
+                                //Set voip server
+                                *voip_guard = Some(voip_server_instance);
+                            }
+
+                            //We can safely assume its Some(_) here
+                            if let Some(voip) = voip_guard.as_mut() {
+                                //Create handler thread
+                                voip.threads.get_or_insert_with(|| {
+                                    //Clone so we can move it into the thread
+                                    let socket_v6 = voip.socket_v6.clone();
+                                    let socket_v4 = voip.socket_v4.clone();
+                                    let connected_clients = voip.connected_client_thread_channels.clone();
+                                    let cancellation_token = voip.thread_cancellation_token.clone();
+
+                                    //Spawn manager thread
+                                    tokio::spawn(async move {
+                                        loop {
+                                            //Create buffer for header, this is the size of the maximum udp packet so no error will appear
+                                            let mut header_buf_v6 = vec![0; 65536];
+                                            let mut header_buf_v4 = vec![0; 65536];
+
+                                            let socket_v6 = socket_v6.clone().unwrap();
+
+                                            //Wait until we get a new message or until the thread token gets cancelled
+                                            select! {
+                                                //Wait until we recive a new message from the ipv6 binding
+                                                _ = socket_v6.peek_from(&mut header_buf_v6) => {
+                                                    send_message_to_connected_client(socket_v6.clone(), connected_clients.clone(), header_buf_v6).await;
+                                                }
+
+                                                //Wait until we recive a new message from the ipv6 binding
+                                                _ = socket_v4.peek_from(&mut header_buf_v4) => {
+                                                    send_message_to_connected_client(socket_v4.clone(), connected_clients.clone(), header_buf_v4).await;
+                                                }
+
+                                                //Wait until the token gets cancelled
+                                                _ = cancellation_token.cancelled() => {
+                                                    //End loop once the token gets cancelled
+                                                    break;
+                                                }
+                                            }
+                                        }
+                                    });
+                                });
+
+                                //Search if there is a channel for the handler thread of this connecting SocketAddr
+                                if voip
+                                    .connected_client_thread_channels
+                                    .get(&socket_addr)
+                                    .is_none()
+                                {
+                                    let (sender, receiver) = mpsc::channel::<Vec<u8>>(255);
+
+                                    //Create cancellation token for client
+                                    let client_manager_cancellation_token =
+                                        CancellationToken::new();
+
+                                    //Create voip manager for client
+                                    create_client_voip_manager(
+                                        voip.clone(),
+                                        client_manager_cancellation_token.clone(),
+                                        self.decryption_key,
+                                        receiver,
+                                        socket_addr,
+                                        req.uuid.clone(),
+                                    );
+
+                                    voip.connected_client_thread_channels.insert(
+                                        socket_addr,
+                                        (Arc::new(sender), client_manager_cancellation_token),
+                                    );
+                                }
+                            }
+
+                            let connected_clients = voip_guard
+                                .as_ref()
+                                .ok_or_else(|| Error::msg("The voip server has not been created"))?
+                                .connected_clients
+                                .iter()
+                                .map(|f| f.key().clone())
+                                .collect();
+
+                            //The call cannot be locked while the message is sent, since the clients are locked before the call
+                            drop(voip_guard);
+
+                            //Sync connected users with all users
+                            self.sync_message_with_clients(
+                                self.connected_clients.clone(),
+                                ServerOutput {
+                                    uuid: req.uuid.clone(),
+                                    author: self.client_profile(&req.uuid).await?.username,
+                                    ..server_output(
+                                        self.next_message_id(),
+                                        ServerMessageType::VoipState(ServerVoipState {
+                                            connected_clients: Some(connected_clients),
+                                        }),
+                                        DEFAULT_CHANNEL_ID.to_string(),
+                                    )
+                                },
+                            )
+                            .await?;
+                        },
+                        crate::protocol::ClientVoipRequest::Disconnect => {
+                            self.leave_voip_call(&req.uuid, voip_guard).await?;
+                        },
+                        crate::protocol::ClientVoipRequest::ImageConnected => {
+                            if let Some(voip) = &mut *voip_guard {
+                                voip.image_buffer.insert(req.uuid.clone(), IndexMap::new());
+                            }
+                            else {
+                                tracing::error!("Voip image connected to an offline server");
+                            }
+                        },
+                        crate::protocol::ClientVoipRequest::ImageDisconnected => {
+                            if let Some(voip) = &mut *voip_guard {
+                                voip.image_buffer.remove(&req.uuid);
+                            }
+                            else {
+                                tracing::error!("Voip image connected to an offline server");
+                            }
+                        },
+                    }
+                },
+
+                NormalMessage(_msg) => stored_message = Some(self.normal_message(&req).await?),
+
+                SyncMessage(sync_msg) => {
+                    self.sync_message(&req).await;
+
+                    //The client has missed some messages, so it is sent the messages it doesnt have
+                    if sync_msg.resync {
+                        //The clients are kept locked, so that no message is sent while the sync is created
+                        let _connected_clients = self.connected_clients.lock().await;
+
+                        client_handle.send(
+                            self.full_sync_client(
+                                &req.uuid,
+                                FrameCompression::negotiate(capabilities),
+                                sync_msg.last_message_id,
+                                sync_msg.last_revision,
+                                None,
+                            )
+                            .await?,
+                        )?;
+
+                        return Ok(());
+                    }
+                },
+
+                FileRequestType(request_type) => {
+                    let reply = match self.handle_request(request_type).await {
+                        Ok(reply) => reply,
+                        Err(err) => {
+                            tracing::warn!("Client {} requested a missing file: {err}", req.uuid);
+
+                            self.send_request_error(&req.uuid, RequestError::Invalid(err))
+                                .await?;
+
+                            return Ok(());
+                        },
+                    };
+
+                    //The requested files count towards the client's transfer rate too
+                    if let ServerReplyType::File(ServerFileReply { bytes, .. })
+                    | ServerReplyType::Image(ServerImageReply { bytes, .. })
+                    | ServerReplyType::Audio(ServerAudioReply { bytes, .. }) = &reply
+                    {
+                        self.throttle_transfer(&req.uuid, socket_addr, bytes.len() as u64)
+                            .await;
+                    }
+
+                    client_handle.send(
+                        //Encrypt the request reply
+                        ServerFrame::Reply(reply).encrypt(
+                            &self.decryption_key,
+                            FrameCompression::negotiate(capabilities),
+                        )?,
+                    )?;
+
+                    return Ok(());
+                },
+
+                FileUpload(upload_type) => {
+                    match self.handle_upload(req.clone(), upload_type).await? {
+                        Some(server_msg) => stored_message = Some(server_msg),
+                        //The upload couldnt be stored, so there is nothing to send
+                        None => return Ok(()),
+                    }
+                },
+
+                //File transfers, history requests and pongs are answered before any message is allocated, they dont create a message
+                FileTransfer(_) | HistoryRequest(_) | Pong(_) => {},
+
+                ChannelRequest(channel_request) => {
+                    //An invalid channel request shouldnt disconnect the client
+                    let channels = match self.handle_channel_request(channel_request).await {
+                        Ok(channels) => channels,
+                        Err(err) => {
+                            tracing::error!("Failed to handle channel request: {err}");
+
+                            return Ok(());
+                        },
+                    };
+
+                    self.save_state();
+
+                    //Send the updated channel list to all the clients
+                    self.sync_message_with_clients(
+                        self.connected_clients.clone(),
+                        server_output(
+                            self.next_message_id(),
+                            ServerMessageType::ChannelUpdate(ServerChannelUpdate { channels }),
+                            DEFAULT_CHANNEL_ID.to_string(),
+                        ),
+                    )
+                    .await?;
+
+                    return Ok(());
+                },
+
+                DirectMessage(direct_message) => {
+                    //An invalid private message shouldnt disconnect the client
+                    let server_msg = match self.handle_direct_message(&req, direct_message).await {
+                        Ok(server_msg) => server_msg,
+                        Err(err) => {
+                            tracing::error!("Failed to handle private message: {err}");
+
+                            return Ok(());
+                        },
+                    };
+
+                    self.save_state();
+
+                    //The clients are kept locked while the message is sent, so that its sequence cannot advance
+                    let connected_clients = self.connected_clients.lock().await;
+
+                    //Only the sender and the recipient should receive the message
+                    let participants: Vec<ConnectedClient> = connected_clients
+                        .iter()
+                        .filter(|client| {
+                            client.uuid == req.uuid || client.uuid == direct_message.recipient
+                        })
+                        .cloned()
+                        .collect();
+
+                    self.sync_message_with_clients(
+                        Arc::new(tokio::sync::Mutex::new(participants)),
+                        server_msg,
+                    )
+                    .await?;
+
+                    return Ok(());
+                },
+
+                MessagePin(pin) => {
+                    let pinned_messages = match self.handle_message_pin(pin).await {
+                        Ok(pinned_messages) => pinned_messages,
+                        Err(err) => {
+                            tracing::error!("Failed to handle message pin: {err}");
+
+                            return Ok(());
+                        },
+                    };
+
+                    self.save_state();
+
+                    //Send the updated list of the pinned messages to all the clients
+                    self.sync_message_with_clients(
+                        self.connected_clients.clone(),
+                        server_output(
+                            self.next_message_id(),
+                            ServerMessageType::PinUpdate(ServerPinUpdate { pinned_messages }),
+                            DEFAULT_CHANNEL_ID.to_string(),
+                        ),
+                    )
+                    .await?;
+
+                    return Ok(());
+                },
+
+                RoleRequest(role_request) => {
+                    let roles = match self.handle_role_request(&req.uuid, role_request).await {
+                        Ok(roles) => roles,
+                        Err(err) => {
+                            tracing::error!("Failed to handle role request: {err}");
+
+                            return Ok(());
+                        },
+                    };
+
+                    self.save_state();
+
+                    //Send the updated roles to all the clients
+                    self.sync_message_with_clients(
+                        self.connected_clients.clone(),
+                        server_output(
+                            self.next_message_id(),
+                            ServerMessageType::RoleUpdate(ServerRoleUpdate { roles }),
+                            DEFAULT_CHANNEL_ID.to_string(),
+                        ),
+                    )
+                    .await?;
+
+                    return Ok(());
+                },
+
+                ModerationRequest(moderation_request) => {
+                    match self
+                        .handle_moderation_request(&req.uuid, moderation_request)
+                        .await
+                    {
+                        Ok(Some(announcement)) => {
+                            self.announce(announcement).await?;
+                        },
+                        Ok(None) => (),
+                        Err(err) => {
+                            //The requester is told if the user they have targeted doesnt exist or isnt connected
+                            match err.downcast::<ValidationError>() {
+                                Ok(validation_error) => {
+                                    self.send_request_error(
+                                        &req.uuid,
+                                        RequestError::Invalid(validation_error),
+                                    )
+                                    .await?;
+                                },
+                                Err(err) => {
+                                    tracing::error!("Failed to handle moderation request: {err}");
+                                },
+                            }
+                        },
+                    }
+
+                    return Ok(());
+                },
+
+                ClientReaction(reaction) => {
+                    if let Err(err) = self.handle_reaction(reaction, &req).await {
+                        self.send_request_error(&req.uuid, RequestError::Invalid(err))
+                            .await?;
+
+                        return Ok(());
+                    }
+                },
+
+                MessageEdit(edit) => {
+                    if let Err(err) = self.handle_message_edit(edit, &req).await {
+                        self.send_request_error(&req.uuid, RequestError::Invalid(err))
+                            .await?;
+
+                        return Ok(());
+                    }
+                },
+            };
+
+            let server_msg = match stored_message {
+                Some(stored_message) => stored_message,
+                None => {
+                    let server_msg = match ServerOutput::convert_clientmsg_to_servermsg(
+                        req.clone(),
+                        req.uuid.clone(),
+                        self.client_profile(&req.uuid).await?.username,
+                    ) {
+                        Ok(server_msg) => server_msg,
+                        //The request doesnt create a message, it should have been answered above
+                        Err(err) => {
+                            tracing::error!(
+                                "The request of client {} has no message: {err}",
+                                req.uuid
+                            );
+
+                            self.send_request_error(&req.uuid, RequestError::Invalid(err))
+                                .await?;
+
+                            return Ok(());
+                        },
+                    };
+
+                    match &req.message_type {
+                        //The voip events are displayed in the message list, so they are stored like the messages
+                        VoipConnection(
+                            ClientVoipRequest::Connect(_) | ClientVoipRequest::Disconnect,
+                        ) => self.store_message(server_msg).await,
+                        //The rest of the requests only carry their side effects to the clients, they arent stored
+                        _ => {
+                            ServerOutput {
+                                id: self.next_message_id(),
+                                ..server_msg
+                            }
+                        },
+                    }
+                },
+            };
+
+            //Save the side effects of the client's request
+            self.save_state();
+
+            //We return the syncing function because after we have handled the request we return back the updated messages, which already contain the "side effects" of the client request
+            //Please rework this, we should always be sending the latest message to all the clients so we are kept in sync, we only send all of them when we are connecting
+            //We should send the incoming message to all of the clients, we are already storing the messages in self.messages
+            self.sync_message_with_clients(self.connected_clients.clone(), server_msg)
+                .await?;
+
+            Ok(())
+        }
+        else {
+            client_handle.send_control_reply(ServerControlReply::InvalidPassword)?;
+
+            Err(Error::msg("Invalid password entered by client!"))
+        }
+    }
+
+    async fn create_voip_server(&self, port: String) -> anyhow::Result<ServerVoip>
+    {
+        // Create sockets
+        let socket_v4 = UdpSocket::bind(format!("0.0.0.0:{port}")).await?;
+        let socket_v6: Option<UdpSocket> = UdpSocket::bind(format!("[::]:{port}")).await.ok();
+
+        //Return ServerVoip
+        Ok(ServerVoip {
+            connected_clients: Arc::new(DashMap::new()),
+            _established_since: Utc::now(),
+            socket_v6: { socket_v6.map(Arc::new) },
+            socket_v4: Arc::new(socket_v4),
+            thread_cancellation_token: CancellationToken::new(),
+            threads: None,
+            connected_client_thread_channels: Arc::new(DashMap::new()),
+            image_buffer: Arc::new(DashMap::new()),
+        })
+    }
+
+    async fn handle_server_disconnect(
+        &self,
+        client: &ConnectedClient,
+        clients: &mut tokio::sync::MutexGuard<'_, Vec<ConnectedClient>>,
+        index: usize,
+    ) -> Result<ServerOutput, Error>
+    {
+        if let Some(client_handle) = &client.handle {
+            client_handle.send_control_reply(ServerControlReply::Disconnected)?;
+        }
+
+        clients.remove(index);
+
+        //The id is assigned when the message is stored
+        let server_msg = server_output(
+            0,
+            ServerMessageType::Server(crate::protocol::ServerMessage::Disconnect(
+                self.connected_clients_profile
+                    .lock()
+                    .await
+                    .get(&client.uuid)
+                    .cloned()
+                    .unwrap_or_default(),
+            )),
+            DEFAULT_CHANNEL_ID.to_string(),
+        );
+
+        Ok(self.store_message(server_msg).await)
+    }
+
+    /// Disconnects the banned client, returns the announcement of the ban
+    async fn handle_server_ban(
+        &self,
+        client: &ConnectedClient,
+        ban: &BanRecord,
+        clients: &mut tokio::sync::MutexGuard<'_, Vec<ConnectedClient>>,
+        index: usize,
+    ) -> Result<ServerMessage, Error>
+    {
+        //Remove client
+        clients.remove(index);
+
+        if let Some(client_handle) = &client.handle {
+            //Send ban message to client, this also signals the disconnection
+            client_handle.send_control_reply(ServerControlReply::Banned {
+                reason: ban.reason.clone(),
+                expires_at: ban.expires_at,
+            })?;
+
+            //Shutdown client connection
+            client_handle.shutdown();
+        }
+
+        Ok(ServerMessage::Ban(self.client_profile(&client.uuid).await?))
+    }
+
+    async fn handle_banned_client(
+        &self,
+        req: &ClientMessage,
+        client_handle: &ClientWriter,
+        socket_addr: SocketAddr,
+    ) -> Result<(), Error>
+    {
+        if let Some(ban) = self.find_ban(&req.uuid, Some(socket_addr)).await {
+            self.connected_clients
+                .lock()
+                .await
+                .retain(|client| client.uuid != req.uuid);
+
+            //Send ban message to client, this also signals the disconnection
+            client_handle.send_control_reply(ServerControlReply::Banned {
+                reason: ban.reason,
+                expires_at: ban.expires_at,
+            })?;
+
+            self.discard_uploads(&req.uuid).await;
+
+            return Err(Error::msg("Client has been banned!"));
+        };
+        Ok(())
+    }
+
+    /// Creates, renames or deletes a channel, returns the updated list of channels
+    /// When a channel is deleted all of its messages are set to ```Deleted```, they are kept in the list so that the messages referring to them (by their ```MessageId```) still find them
+    async fn handle_channel_request(
+        &self,
+        channel_request: &ClientChannelRequest,
+    ) -> anyhow::Result<Vec<Channel>>
+    {
+        let mut channels = self.channels.lock().await;
+
+        match channel_request {
+            ClientChannelRequest::Create(name) => {
+                ensure!(!name.trim().is_empty(), "Channel name cannot be empty!");
+
+                channels.push(Channel::new(name.trim().to_string()));
+            },
+            ClientChannelRequest::Rename { channel_id, name } => {
+                ensure!(!name.trim().is_empty(), "Channel name cannot be empty!");
+
+                let Some(channel) = channels
+                    .iter_mut()
+                    .find(|channel| channel.id == *channel_id)
+                else {
+                    bail!("Channel not found: {channel_id}");
+                };
+
+                channel.name = name.trim().to_string();
+            },
+            ClientChannelRequest::Delete(channel_id) => {
+                ensure!(
+                    channel_id != DEFAULT_CHANNEL_ID,
+                    "The default channel cannot be deleted!"
+                );
+
+                let Some(channel_idx) = channels
+                    .iter()
+                    .position(|channel| channel.id == *channel_id)
+                else {
+                    bail!("Channel not found: {channel_id}");
+                };
+
+                channels.remove(channel_idx);
+
+                let mut blobs = self.blobs.lock().await;
+
+                //Delete the messages of the channel
+                for message in self
+                    .messages
+                    .lock()
+                    .await
+                    .iter_mut()
+                    .filter(|message| message.channel_id == *channel_id)
+                {
+                    if let Some(hash) = blob_reference(&message.message_type) {
+                        blobs.remove_reference(hash);
+                    }
+
+                    message.message_type = ServerMessageType::Deleted;
+                    message.revision = self.next_revision();
+                }
+
+                drop(blobs);
+
+                //Remove the last seen indexes of the channel
+                self.clients_last_seen_index
+                    .lock()
+                    .await
+                    .retain(|last_seen| last_seen.channel_id != *channel_id);
+            },
+        }
+
+        Ok(channels.clone())
+    }
+
+    /// Stores a private message in the conversation of the sender and the recipient, returns the message which should be sent to them
+    async fn handle_direct_message(
+        &self,
+        req: &ClientMessage,
+        direct_message: &ClientDirectMessage,
+    ) -> anyhow::Result<ServerOutput>
+    {
+        ensure!(
+            !direct_message.message.trim().is_empty(),
+            "Private message cannot be empty!"
+        );
+
+        ensure!(
+            direct_message.recipient != req.uuid,
+            "Private messages cannot be sent to ourselves!"
+        );
+
+        let connected_clients_profile = self.connected_clients_profile.lock().await;
+
+        //The recipient must have connected to the server at least once
+        ensure!(
+            connected_clients_profile.contains_key(&direct_message.recipient),
+            "Recipient not found: {}",
+            direct_message.recipient
+        );
+
+        let Some(sender_profile) = connected_clients_profile.get(&req.uuid)
+        else {
+            bail!("Sender not found: {}", req.uuid);
+        };
+
+        let mut server_msg = ServerOutput {
+            id: self.next_message_id(),
+            ..ServerOutput::convert_clientmsg_to_servermsg(
+                req.clone(),
+                req.uuid.clone(),
+                sender_profile.username.clone(),
+            )?
+        };
+
+        drop(connected_clients_profile);
+
+        let mut direct_messages = self.direct_messages.lock().await;
+
+        server_msg.revision = self.next_revision();
+
+        direct_messages
+            .entry(direct_conversation_id(&req.uuid, &direct_message.recipient))
+            .or_default()
+            .push(server_msg.clone());
+
+        Ok(server_msg)
+    }
+
+    /// Returns the permission the client needs to have for the request to be applied, returns ```None``` if the request doesnt require any
+    async fn required_permission(&self, message_type: &ClientMessageType) -> Option<Permission>
+    {
+        match message_type {
+            //Anyone can request files and sync their last seen messages
+            FileRequestType(_) | SyncMessage(_) | HistoryRequest(_) | Pong(_) => None,
+            NormalMessage(_) | DirectMessage(_) => Some(Permission::SendMessages),
+            FileUpload(_) | FileTransfer(_) => Some(Permission::UploadFiles),
+            ClientReaction(_) => Some(Permission::React),
+            //Editing and deleting messages is checked when the edit is applied, since it depends on who has sent the message
+            MessageEdit(_) => None,
+            //Only starting a call requires a permission, anyone can join an ongoing call
+            VoipConnection(ClientVoipRequest::Connect(_)) if self.voip.lock().await.is_none() => {
+                Some(Permission::StartCalls)
+            },
+            VoipConnection(_) => None,
+            ChannelRequest(_) => Some(Permission::ManageChannels),
+            MessagePin(_) => Some(Permission::PinMessages),
+            RoleRequest(_) => Some(Permission::ManageRoles),
+            ModerationRequest(ClientModerationRequest::Ban { .. }) => Some(Permission::Ban),
+            ModerationRequest(
+                ClientModerationRequest::Kick(_) | ClientModerationRequest::Timeout { .. },
+            ) => Some(Permission::Kick),
+            ModerationRequest(ClientModerationRequest::Mute { .. }) => Some(Permission::Mute),
+        }
+    }
+
+    /// Returns whether the user (identified by their uuid) has the permission, the user is notified if their request has been denied
+    async fn check_permission(&self, uuid: &str, permission: Permission) -> anyhow::Result<bool>
+    {
+        let role = Role::of_user(&*self.roles.lock().await, uuid);
+
+        if !role.has_permission(permission) {
+            tracing::warn!("Client {uuid} ({role}) doesnt have the permission: {permission:?}");
+
+            self.send_request_error(uuid, RequestError::PermissionDenied(permission))
+                .await?;
+
+            return Ok(false);
+        }
+
+        Ok(true)
+    }
+
+    /// Pins or unpins a message, returns the updated list of the pinned messages
+    async fn handle_message_pin(&self, pin: &ClientMessagePin) -> anyhow::Result<Vec<MessageId>>
+    {
+        ensure!(
+            message_position(&self.messages.lock().await, pin.message_id).is_some(),
+            "Message not found: {}",
+            pin.message_id
+        );
+
+        let mut pinned_messages = self.pinned_messages.lock().await;
+
+        if pin.pinned {
+            if !pinned_messages.contains(&pin.message_id) {
+                pinned_messages.push(pin.message_id);
+            }
+        }
+        else {
+            pinned_messages.retain(|message_id| *message_id != pin.message_id);
+        }
+
+        Ok(pinned_messages.clone())
+    }
+
+    /// Assigns a role to a user, returns the updated roles
+    /// Users can only assign roles to users who have a lower role than them, and they cannot assign a role higher than their own
+    async fn handle_role_request(
+        &self,
+        sender_uuid: &str,
+        role_request: &ClientRoleRequest,
+    ) -> anyhow::Result<HashMap<String, Role>>
+    {
+        let mut roles = self.roles.lock().await;
+
+        let sender_role = Role::of_user(&roles, sender_uuid);
+
+        ensure!(
+            role_request.uuid != sender_uuid,
+            "Users cannot change their own role!"
+        );
+
+        ensure!(
+            Role::of_user(&roles, &role_request.uuid) < sender_role,
+            "Users can only change the role of users with a lower role!"
+        );
+
+        ensure!(
+            role_request.role <= sender_role,
+            "Users cannot assign a role higher than their own!"
+        );
+
+        roles.insert(role_request.uuid.clone(), role_request.role);
+
+        Ok(roles.clone())
+    }
+
+    /// Applies a moderation request (for example a ban) sent by a user, returns the announcement of the action if it should be made
+    /// Users can only take actions against users who have a lower role than them
+    async fn handle_moderation_request(
+        &self,
+        sender_uuid: &str,
+        moderation_request: &ClientModerationRequest,
+    ) -> anyhow::Result<Option<ServerMessage>>
+    {
+        let (ClientModerationRequest::Ban {
+            uuid: target_uuid, ..
+        }
+        | ClientModerationRequest::Kick(target_uuid)
+        | ClientModerationRequest::Mute {
+            uuid: target_uuid, ..
+        }
+        | ClientModerationRequest::Timeout {
+            uuid: target_uuid, ..
+        }) = moderation_request;
+
+        {
+            let roles = self.roles.lock().await;
+
+            ensure!(
+                Role::of_user(&roles, target_uuid) < Role::of_user(&roles, sender_uuid),
+                "Users can only take actions against users with a lower role!"
+            );
+        }
+
+        match moderation_request {
+            ClientModerationRequest::Ban {
+                uuid,
+                reason,
+                minutes,
+                include_address,
+            } => {
+                ensure!(minutes != &Some(0), "A ban must last at least a minute!");
+
+                //Ban the address the user is connected from, if they are connected
+                let subnet = if *include_address {
+                    let clients = self.connected_clients.lock().await;
+
+                    let Some(address) = clients
+                        .iter()
+                        .find(|client| client.uuid == *uuid)
+                        .and_then(|client| client.address)
+                    else {
+                        bail!("The address of the user is unknown, they are not connected: {uuid}");
+                    };
+
+                    Some(Subnet::single(address.ip()))
+                }
+                else {
+                    None
+                };
+
+                let shared_fields = self.shared_fields.lock().await;
+
+                let mut bans = shared_fields.bans.lock().await;
+
+                //A user can only have one ban, the new one replaces the old one
+                bans.retain(|ban| ban.uuid.as_ref() != Some(uuid));
+
+                //The client is disconnected and the ban is announced by the thread enforcing the bans
+                bans.push(BanRecord::new(
+                    Some(uuid.clone()),
+                    subnet,
+                    reason.clone(),
+                    Some(sender_uuid.to_string()),
+                    minutes.map(|minutes| chrono::Duration::minutes(minutes as i64)),
+                ));
+
+                Ok(None)
+            },
+            ClientModerationRequest::Kick(uuid) => {
+                let profile = self.client_profile(uuid).await?;
+
+                //The kick is only announced if there was anyone to kick
+                if !self
+                    .disconnect_client(uuid, ServerControlReply::Kicked)
+                    .await?
+                {
+                    return Err(ValidationError::UserNotConnected(uuid.clone()).into());
+                }
+
+                Ok(Some(ServerMessage::Kick(profile)))
+            },
+            ClientModerationRequest::Mute { uuid, minutes } => {
+                let profile = self.client_profile(uuid).await?;
+
+                ensure!(*minutes > 0, "A mute must last at least a minute!");
+
+                let until = Utc::now() + chrono::Duration::minutes(*minutes as i64);
+
+                self.mutes.lock().await.insert(uuid.clone(), until);
+
+                Ok(Some(ServerMessage::Mute(profile, until)))
+            },
+            ClientModerationRequest::Timeout { uuid, minutes } => {
+                let profile = self.client_profile(uuid).await?;
+
+                ensure!(*minutes > 0, "A timeout must last at least a minute!");
+
+                let until = Utc::now() + chrono::Duration::minutes(*minutes as i64);
+
+                self.timeouts.lock().await.insert(uuid.clone(), until);
+
+                self.disconnect_client(uuid, ServerControlReply::TimedOut(until))
+                    .await?;
+
+                Ok(Some(ServerMessage::Timeout(profile, until)))
+            },
+        }
+    }
+
+    /// Returns the ban which applies to the user (identified by their uuid) connecting from the address, returns ```None``` if the user isnt banned
+    async fn find_ban(&self, uuid: &str, address: Option<SocketAddr>) -> Option<BanRecord>
+    {
+        self.shared_fields
+            .lock()
+            .await
+            .bans
+            .lock()
+            .await
+            .iter()
+            .find(|ban| ban.applies_to(uuid, address))
+            .cloned()
+    }
+
+    /// Returns the profile of the user (identified by their uuid), the user must have connected to the server at least once
+    async fn client_profile(&self, uuid: &str) -> anyhow::Result<ClientProfile>
+    {
+        self.connected_clients_profile
+            .lock()
+            .await
+            .get(uuid)
+            .cloned()
+            .ok_or_else(|| ValidationError::UserNotFound(uuid.to_string()).into())
+    }
+
+    /// Returns whether the client (identified by their uuid) is connected through the connection the handle belongs to
+    async fn is_connected_through(&self, uuid: &str, handle: &ClientWriter) -> bool
+    {
+        self.connected_clients
+            .lock()
+            .await
+            .iter()
+            .any(|client| client.uuid == uuid && client.is_connected_through(handle))
+    }
+
+    /// Checks whether the messages the request refers to exist
+    async fn validate_references(&self, req: &ClientMessage) -> Result<(), ValidationError>
+    {
+        let messages = self.messages.lock().await;
+
+        let check_id = |message_id: MessageId| {
+            if message_position(&messages, message_id).is_some() {
+                Ok(())
+            }
+            else {
+                Err(ValidationError::MessageNotFound(message_id))
+            }
+        };
+
+        match &req.message_type {
+            NormalMessage(_) | FileUpload(_) => req.replying_to.map_or(Ok(()), check_id),
+            MessageEdit(edit) => check_id(edit.message_id),
+            MessagePin(pin) => check_id(pin.message_id),
+            SyncMessage(sync_message) => sync_message.last_seen_message_id.map_or(Ok(()), check_id),
+            ClientReaction(ReactionType::Add(reaction) | ReactionType::Remove(reaction)) => {
+                check_id(reaction.message_id)
+            },
+            _ => Ok(()),
+        }
+    }
+
+    /// Disconnects the client (identified by their uuid) if its connected, the reason is sent to the client before disconnecting
+    /// The client is also removed from the voip call, if there is one
+    /// Returns whether the client was connected
+    async fn disconnect_client(
+        &self,
+        uuid: &str,
+        reason: ServerControlReply,
+    ) -> anyhow::Result<bool>
+    {
+        if let Some(voip) = &*self.voip.lock().await {
+            voip.disconnect(uuid.to_string()).unwrap_or_default();
+        }
+
+        let client = {
+            let mut clients = self.connected_clients.lock().await;
+
+            let Some(index) = clients.iter().position(|client| client.uuid == uuid)
+            else {
+                return Ok(false);
+            };
+
+            clients.remove(index)
+        };
+
+        if let Some(client_handle) = client.handle {
+            //The reason also signals the disconnection
+            client_handle.send_control_reply(reason)?;
+
+            client_handle.shutdown();
+        }
+
+        self.discard_uploads(uuid).await;
+
+        Ok(true)
+    }
+
+    /// Removes the client (identified by their uuid) from the voip call, the call is shut down if it was the last client in it
+    /// The call has to be locked by the caller, since the lock is released before the clients are notified about the new state of the call
+    async fn leave_voip_call(
+        &self,
+        uuid: &str,
+        mut voip_guard: tokio::sync::MutexGuard<'_, Option<ServerVoip>>,
+    ) -> anyhow::Result<()>
+    {
+        if let Some(ongoing_voip) = voip_guard.clone() {
+            //Get who disconnected
+            let connected_client = ongoing_voip
+                .connected_clients
+                .get(uuid)
+                .ok_or_else(|| Error::msg("Connected client not found based on UUID"))?;
+
+            let socket_addr = connected_client.value();
+
+            let client_manager_thread = ongoing_voip
+                .connected_client_thread_channels
+                .get(socket_addr)
+                .ok_or_else(|| {
+                    Error::msg("Client not found in connected client list based on SocketAddr")
+                })?;
+
+            //Cancel client manager thread
+            client_manager_thread.1.cancel();
+
+            //Make sure to drop the reference so we will not deadlock upon calling ```voip.disconnect```
+            drop(connected_client);
+            drop(client_manager_thread);
+
+            //Blocks here
+            ongoing_voip.disconnect(uuid.to_string())?;
+
+            if ongoing_voip.connected_clients.is_empty() {
+                //If the voip has no connected clients we can shut down the whole service
+                ongoing_voip.thread_cancellation_token.cancel();
+
+                //Reset voip's state
+                *voip_guard = None;
+            }
+
+            //Match server Voip state
+            let connected_clients = voip_guard.as_ref().map(|server_voip| {
+                server_voip
+                    .connected_clients
+                    .iter()
+                    .map(|entry| entry.key().clone())
+                    .collect()
+            });
+
+            //The call cannot be locked while the message is sent, since the clients are locked before the call
+            drop(voip_guard);
+
+            self.sync_message_with_clients(
+                self.connected_clients.clone(),
+                ServerOutput {
+                    uuid: uuid.to_string(),
+                    author: String::new(),
+                    ..server_output(
+                        self.next_message_id(),
+                        ServerMessageType::VoipState(ServerVoipState { connected_clients }),
+                        DEFAULT_CHANNEL_ID.to_string(),
+                    )
+                },
+            )
+            .await?;
+        }
+        else {
+            tracing::error!("Voip disconnected from an offline server")
+        }
+
+        Ok(())
+    }
+
+    /// Removes the client connected through the connection the handle belongs to, this is called when the connection has been closed or lost
+    /// The client is also removed from the voip call, and the rest of the clients are notified about the disconnection
+    async fn remove_disconnected_client(&self, handle: &ClientWriter) -> anyhow::Result<()>
+    {
+        let client = {
+            let mut clients = self.connected_clients.lock().await;
+
+            //The client might have reconnected through another connection, or it might have been disconnected by the server already
+            let Some(index) = clients
+                .iter()
+                .position(|client| client.is_connected_through(handle))
+            else {
+                return Ok(());
+            };
+
+            clients.remove(index)
+        };
+
+        tracing::info!(
+            "Client {} has been removed, their connection has been lost",
+            client.uuid
+        );
+
+        let voip_guard = self.voip.lock().await;
+
+        if voip_guard
+            .as_ref()
+            .is_some_and(|voip| voip.connected_clients.contains_key(&client.uuid))
+        {
+            self.leave_voip_call(&client.uuid, voip_guard).await?;
+        }
+        else {
+            //The call cannot be locked while the message is sent, since the clients are locked before the call
+            drop(voip_guard);
+        }
+
+        let profile = self
+            .connected_clients_profile
+            .lock()
+            .await
+            .get(&client.uuid)
+            .cloned()
+            .unwrap_or_default();
+
+        self.announce(ServerMessage::Disconnect(profile)).await
+    }
+
+    /// Pings the client connected through the connection the handle belongs to, the ping also carries the latency measured by the previous one
+    /// Nothing is sent if the client hasnt connected yet, since it couldnt decrypt the ping
+    async fn ping_client(
+        &self,
+        handle: &ClientWriter,
+        compression: FrameCompression,
+    ) -> anyhow::Result<()>
+    {
+        let Some(latency) = self
+            .connected_clients
+            .lock()
+            .await
+            .iter()
+            .find(|client| client.is_connected_through(handle))
+            .map(|client| client.latency)
+        else {
+            return Ok(());
+        };
+
+        handle.send(
+            ServerFrame::Ping(ServerPing {
+                sent_at: Utc::now(),
+                latency,
+            })
+            .encrypt(&self.decryption_key, compression)?,
+        )
+    }
+
+    /// Stores the round trip time of the ping the client has answered, this is sent to the client with the next ping
+    async fn record_latency(&self, handle: &ClientWriter, pong: &ClientPong)
+    {
+        //The date is sent back by the client, so it could be anything
+        let Ok(latency) = (Utc::now() - pong.sent_at).to_std()
+        else {
+            return;
+        };
+
+        if let Some(client) = self
+            .connected_clients
+            .lock()
+            .await
+            .iter_mut()
+            .find(|client| client.is_connected_through(handle))
+        {
+            client.latency = Some(latency);
+        }
+    }
+
+    /// Returns whether the request can be processed, rejected requests are answered with an error
+    /// If the client has sent too many requests too fast they get disconnected for a while, and they cannot reconnect until then
+    async fn check_rate_limit(
+        &self,
+        uuid: &str,
+        socket_addr: SocketAddr,
+        action: RateLimitedAction,
+    ) -> anyhow::Result<bool>
+    {
+        let verdict = self
+            .rate_limiter
+            .lock()
+            .await
+            .check(uuid, socket_addr.ip(), action);
+
+        match verdict {
+            RateLimitVerdict::Allowed => return Ok(true),
+            RateLimitVerdict::Rejected => {
+                tracing::warn!(
+                    "Client {uuid} ({socket_addr}) is sending {action:?} requests too fast"
+                );
+
+                self.send_request_error(uuid, RequestError::RateLimited(action))
+                    .await?;
+            },
+            RateLimitVerdict::Disconnect(minutes) => {
+                tracing::warn!("Client {uuid} ({socket_addr}) has violated the rate limits too many times, disconnecting them for {minutes} minutes");
+
+                self.send_request_error(uuid, RequestError::RateLimited(action))
+                    .await?;
+
+                let until = Utc::now() + chrono::Duration::minutes(minutes as i64);
+
+                self.timeouts.lock().await.insert(uuid.to_string(), until);
+
+                self.save_state();
+
+                self.disconnect_client(uuid, ServerControlReply::TimedOut(until))
+                    .await?;
+            },
+        }
+
+        Ok(false)
+    }
+
+    /// Waits until the client's transfers are within the transfer rate limit again, the transfers are slowed down instead of being rejected
+    /// Only the client's own requests wait, since every client's requests are handled by a thread of their own
+    async fn throttle_transfer(&self, uuid: &str, socket_addr: SocketAddr, bytes: u64)
+    {
+        let delay = self
+            .rate_limiter
+            .lock()
+            .await
+            .throttle_transfer(uuid, socket_addr.ip(), bytes);
+
+        if !delay.is_zero() {
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// Returns an error if the user (identified by their uuid) is muted
+    async fn check_mute(&self, uuid: &str) -> Result<(), RequestError>
+    {
+        match active_restriction(&mut *self.mutes.lock().await, uuid) {
+            Some(until) => Err(RequestError::Muted(until)),
+            None => Ok(()),
+        }
+    }
+
+    /// Remembers that the request (identified by its id) has been handled, and lets the client know so it can remove the request from its outbox
+    async fn acknowledge_request(
+        &self,
+        uuid: &str,
+        request_id: String,
+        client_handle: &ClientWriter,
+        capabilities: &[Capability],
+    ) -> anyhow::Result<()>
+    {
+        {
+            let mut handled_requests = self.handled_requests.lock().await;
+
+            let request_ids = handled_requests.entry(uuid.to_string()).or_default();
+
+            if !request_ids.contains(&request_id) {
+                request_ids.push_back(request_id.clone());
+
+                if request_ids.len() > HANDLED_REQUEST_HISTORY {
+                    request_ids.pop_front();
+                }
+            }
+        }
+
+        client_handle.send(
+            ServerFrame::Reply(ServerReplyType::Acknowledged(request_id)).encrypt(
+                &self.decryption_key,
+                FrameCompression::negotiate(capabilities),
+            )?,
+        )?;
+
+        Ok(())
+    }
+
+    /// Sends the error to the client (identified by their uuid) whose request has been rejected
+    async fn send_request_error(
+        &self,
+        uuid: &str,
+        request_error: RequestError,
+    ) -> anyhow::Result<()>
+    {
+        //The clients are kept locked while the message is sent, so that its sequence cannot advance
+        let connected_clients = self.connected_clients.lock().await;
+
+        let client: Vec<ConnectedClient> = connected_clients
+            .iter()
+            .filter(|client| client.uuid == uuid)
+            .cloned()
+            .collect();
+
+        self.sync_message_with_clients(
+            Arc::new(tokio::sync::Mutex::new(client)),
+            server_output(
+                self.next_message_id(),
+                ServerMessageType::RequestError(request_error),
+                DEFAULT_CHANNEL_ID.to_string(),
+            ),
+        )
+        .await
+    }
+
+    /// Returns a new unique message id
+    fn next_message_id(&self) -> MessageId
+    {
+        self.next_message_id.fetch_add(1, Ordering::Relaxed)
+    }
+
+    /// Returns a new revision, this must be called while the list of the changed message is locked (```ServerOutput::revision```)
+    fn next_revision(&self) -> u64
+    {
+        self.revision.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    /// Assigns an id to the message and adds it to the message list, returns the stored message
+    /// The id is assigned while the list is locked, so that the list stays ordered by the ids
+    async fn store_message(&self, mut message: ServerOutput) -> ServerOutput
+    {
+        let mut messages = self.messages.lock().await;
+
+        message.id = self.next_message_id();
+        message.revision = self.next_revision();
+
+        messages.push(message.clone());
+
+        message
+    }
+
+    /// This function iterates over all the connected clients, and sends the message to their designated ```OwnedWriteHalf``` (All of the users see all of the messages)
+    /// The message's sequence is assigned here, if the message is only sent to some of the clients the caller has to keep all of the connected clients locked, so that the sequence cannot advance while its being sent
+    /// If the message has been stored, its stored copy is updated with the assigned sequence
+    async fn sync_message_with_clients(
+        &self,
+        //The clients the message is sent to
+        connected_clients: Arc<tokio::sync::Mutex<Vec<ConnectedClient>>>,
+        //The message sent by the owner
+        //This struct contains the owner of this message (by name & uuid)
+        mut message: ServerOutput,
+    ) -> anyhow::Result<()>
+    {
+        let connected_clients_locked = connected_clients.lock().await;
+
+        let is_broadcast = message.is_broadcast();
+
+        //The sequence is assigned while the clients are locked, so that the clients receive the messages in the order of their sequences
+        if is_broadcast {
+            message.sequence = self.sequence.fetch_add(1, Ordering::Relaxed) + 1;
+
+            //The stored copy of the message keeps its sequence, so that the syncs and the history pages carry it too
+            let mut messages = self.messages.lock().await;
+
+            if let Some(position) = message_position(&messages, message.id) {
+                message.revision = self.next_revision();
+
+                messages[position].sequence = message.sequence;
+                messages[position].revision = message.revision;
+
+                self.save_state();
+            }
+        }
+        else {
+            message.sequence = self.sequence.load(Ordering::Relaxed);
+        }
+
+        let server_master = ServerSync {
+            message,
+            user_seen_list: self.clients_last_seen_index.lock().await.to_vec(),
+        };
+
+        let frame = ServerFrame::Sync(server_master);
+
+        //The frame is only encoded and encrypted once for every kind of compression the clients use
+        let mut encrypted_frames: HashMap<FrameCompression, Arc<[u8]>> = HashMap::new();
+
+        for client in connected_clients_locked.iter() {
+            if let Some(client_handle) = &client.handle {
+                //The connection has already been closed, so there is no one to send the message to
+                if client_handle.is_closed() {
+                    continue;
+                }
+
+                let compression = client.compression();
+
+                let encrypted_bytes = match encrypted_frames.entry(compression) {
+                    Entry::Occupied(entry) => entry.into_mut(),
+                    Entry::Vacant(entry) => {
+                        entry.insert(frame.encrypt(&self.decryption_key, compression)?.into())
+                    },
+                };
+
+                //The frame is only queued, a client which cannot keep up shouldnt stop the others from receiving it
+                //Only the messages sent to every client can be dropped, since the clients resync them by their sequences
+                let result = match is_broadcast {
+                    true => client_handle.send_broadcast(encrypted_bytes.clone()),
+                    false => client_handle.send(encrypted_bytes.clone()),
+                };
+
+                if let Err(err) = result {
+                    tracing::warn!("Failed to send a message to {}: {err}", client.uuid);
+                }
+            };
+        }
+
+        Ok(())
+    }
+
+    /// Adds the server's announcement to the message list, and sends it to all the clients
+    async fn announce(&self, announcement: ServerMessage) -> anyhow::Result<()>
+    {
+        //The id is assigned when the message is stored
+        let server_msg = server_output(
+            0,
+            ServerMessageType::Server(announcement),
+            DEFAULT_CHANNEL_ID.to_string(),
+        );
+
+        let server_msg = self.store_message(server_msg).await;
+
+        self.save_state();
+
+        self.sync_message_with_clients(self.connected_clients.clone(), server_msg)
+            .await
+    }
+
+    /// Saves the messages, reactions, uploads, etc. to the storage folder, so that they can be loaded after a restart
+    /// This should be called after every change made to them, the state is saved by a thread of its own (```MessageService::write_state```)
+    fn save_state(&self)
+    {
+        self.state_changed.notify_one();
+    }
+
+    /// Writes the changes made since the last save into the storage folder, if saving fails the error is only logged
+    /// The changes are appended to the journal, once the journal has grown too large a new snapshot of the whole state is written instead
+    /// The state is copied while its locked, but the file is written after the locks have been released
+    /// Every part of the state is locked until the copy is complete, so that the saved state is consistent (a deleted channel's messages are deleted too, etc.)
+    /// The locks are taken in the same order as everywhere else (channels, blobs, messages, private messages, then the rest), so that they cannot deadlock
+    async fn write_state(&self, journal: &mut StateJournal)
+    {
+        let channels = self.channels.lock().await;
+        let mut blob_store = self.blobs.lock().await;
+        let messages = self.messages.lock().await;
+        let direct_messages = self.direct_messages.lock().await;
+        let pinned_messages = self.pinned_messages.lock().await;
+        let roles = self.roles.lock().await;
+        let mutes = self.mutes.lock().await;
+        let timeouts = self.timeouts.lock().await;
+        let clients_last_seen_index = self.clients_last_seen_index.lock().await;
+        let shared_fields = self.shared_fields.lock().await;
+        let bans = shared_fields.bans.lock().await;
+
+        let blob_changes = blob_store.take_changes();
+
+        //Compacting needs the whole store, appending only needs the blobs which have changed
+        let blobs = journal.needs_compaction().then(|| blob_store.clone());
+
+        let metadata = StateMetadata {
+            channels: channels.clone(),
+            pinned_messages: pinned_messages.clone(),
+            roles: roles.clone(),
+            mutes: mutes.clone(),
+            timeouts: timeouts.clone(),
+            bans: bans.clone(),
+            next_message_id: self.next_message_id.load(Ordering::Relaxed),
+            sequence: self.sequence.load(Ordering::Relaxed),
+            revision: self.revision.load(Ordering::Relaxed),
+            clients_last_seen_index: clients_last_seen_index.clone(),
+        };
+
+        drop(bans);
+        drop(shared_fields);
+        drop(clients_last_seen_index);
+        drop(timeouts);
+        drop(mutes);
+        drop(roles);
+        drop(pinned_messages);
+        drop(blob_store);
+        drop(channels);
+
+        let result = if let Some(blobs) = blobs {
+            let server_state = ServerState {
+                messages: messages.clone(),
+                direct_messages: direct_messages.clone(),
+                metadata,
+                blobs,
+                ..Default::default()
+            };
+
+            drop(direct_messages);
+            drop(messages);
+
+            journal.compact(&self.storage_path, server_state).await
+        }
+        else {
+            let saved_revision = journal.saved_revision();
+
+            let mut changes: Vec<StateChange> = messages
+                .iter()
+                .filter(|message| message.revision > saved_revision)
+                .cloned()
+                .map(StateChange::Message)
+                .collect();
+
+            for (conversation_id, conversation) in direct_messages.iter() {
+                changes.extend(
+                    conversation
+                        .iter()
+                        .filter(|message| message.revision > saved_revision)
+                        .map(|message| {
+                            StateChange::DirectMessage {
+                                conversation_id: conversation_id.clone(),
+                                message: message.clone(),
+                            }
+                        }),
+                );
+            }
+
+            drop(direct_messages);
+            drop(messages);
+
+            changes.extend(
+                blob_changes
+                    .into_iter()
+                    .map(|(hash, blob)| StateChange::Blob { hash, blob }),
+            );
+
+            journal.append(&self.storage_path, changes, metadata).await
+        };
+
+        if let Err(err) = result {
+            tracing::error!("Failed to save the server's state: {err}");
+        }
+    }
+
+    /// Deletes the blobs which havent been referred to for longer than the grace period, and updates the storage report shown in the Ui
+    async fn collect_blobs(&self)
+    {
+        let collected = self.blobs.lock().await.take_collectable();
+
+        let hashes: Vec<String> = collected.iter().map(|(hash, _)| hash.clone()).collect();
+
+        //The files are deleted without holding the blob store, so that the disk doesnt hold up the uploads and downloads
+        //The collected blobs cannot be uploaded again until the collection is finished
+        let failed = match hashes.is_empty() {
+            true => HashSet::new(),
+            false => {
+                let storage_path = self.storage_path.clone();
+
+                tokio::task::spawn_blocking(move || BlobStore::delete_files(&storage_path, &hashes))
+                    .await
+                    .unwrap_or_else(|err| {
+                        tracing::error!("Failed to delete the collected blobs: {err}");
+
+                        collected.iter().map(|(hash, _)| hash.clone()).collect()
+                    })
+            },
+        };
+
+        let (collected_count, reclaimed_bytes) = self
+            .blobs
+            .lock()
+            .await
+            .finish_collection(collected, &failed);
+
+        let shared_fields = self.shared_fields.lock().await;
+        let mut storage_report = shared_fields.storage_report.lock().await;
+
+        storage_report.collected_count += collected_count;
+        storage_report.reclaimed_bytes += reclaimed_bytes;
+        storage_report.last_collection = Some(Utc::now());
+
+        drop(storage_report);
+        drop(shared_fields);
+
+        self.update_storage_report().await;
+
+        if collected_count > 0 {
+            tracing::info!(
+                "Collected {collected_count} unreferenced file(s), reclaimed {}",
+                format_size(reclaimed_bytes)
+            );
+
+            self.save_state();
+        }
+    }
+
+    /// Copies the current usage of the blob store into the storage report shown in the Ui
+    async fn update_storage_report(&self)
+    {
+        let blobs = self.blobs.lock().await;
+
+        let (blob_count, stored_bytes) = blobs.usage();
+        let usage_by_user = blobs.usage_by_user();
+
+        drop(blobs);
+
+        let shared_fields = self.shared_fields.lock().await;
+        let mut storage_report = shared_fields.storage_report.lock().await;
+
+        storage_report.blob_count = blob_count;
+        storage_report.stored_bytes = stored_bytes;
+        storage_report.usage_by_user = usage_by_user;
+    }
+
+    /// Removes the messages which are older than the retention policy allows, and lets the clients know about the removed messages
+    /// The private messages are removed too, the clients receive the changes the next time they connect
+    async fn apply_retention(&self) -> anyhow::Result<()>
+    {
+        let Some(retention) = self.quota_config.retention
+        else {
+            return Ok(());
+        };
+
+        let now = Utc::now();
+
+        //The id and the channel of the removed messages
+        let mut expired_messages: Vec<(MessageId, String)> = Vec::new();
+        let mut expired_count = 0;
+
+        let mut blobs = self.blobs.lock().await;
+
+        for message in self.messages.lock().await.iter_mut() {
+            if retention.is_expired(message, now) {
+                if let Some(hash) = blob_reference(&message.message_type) {
+                    blobs.remove_reference(hash);
+                }
+
+                message.message_type = ServerMessageType::Deleted;
+                message.revision = self.next_revision();
+
+                expired_messages.push((message.id, message.channel_id.clone()));
+            }
+        }
+
+        for message in self.direct_messages.lock().await.values_mut().flatten() {
+            if retention.is_expired(message, now) {
+                if let Some(hash) = blob_reference(&message.message_type) {
+                    blobs.remove_reference(hash);
+                }
+
+                message.message_type = ServerMessageType::Deleted;
+                message.revision = self.next_revision();
+
+                expired_count += 1;
+            }
+        }
+
+        drop(blobs);
+
+        expired_count += expired_messages.len();
+
+        if expired_count == 0 {
+            return Ok(());
+        }
+
+        tracing::info!(
+            "Removed {expired_count} message(s) older than {} days",
+            retention.max_age_days
+        );
+
+        self.shared_fields
+            .lock()
+            .await
+            .storage_report
+            .lock()
+            .await
+            .expired_count += expired_count;
+
+        self.save_state();
+
+        for (message_id, channel_id) in expired_messages {
+            self.sync_message_with_clients(
+                self.connected_clients.clone(),
+                server_output(
+                    self.next_message_id(),
+                    ServerMessageType::Edit(ServerMessageEdit {
+                        message_id,
+                        new_message: None,
+                    }),
+                    channel_id,
+                ),
+            )
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    /// all the functions the server can do
+    /// Returns the stored message
+    async fn normal_message(&self, req: &ClientMessage) -> anyhow::Result<ServerOutput>
+    {
+        let author = self.client_profile(&req.uuid).await?.username;
+
+        Ok(self
+            .store_message(ServerOutput::convert_clientmsg_to_servermsg(
+                req.clone(),
+                req.uuid.clone(),
+                author,
+            )?)
+            .await)
+    }
+
+    /// This function returns a message containing a full sync (all the messages etc)
+    /// Only the private messages of the client (identified by the uuid) are included
+    /// It returns a ```ServerMaster``` converted to an encrypted string
+    /// The caller has to hold the lock of the connected clients, so that no broadcast is sent between reading the sequence and sending the sync
+    /// If the client has sent the revision of its last sync, the messages it already has which have been changed since are included too
+    /// The ```reconnect_token``` is only sent when the client connects, the resyncs dont carry it
+    async fn full_sync_client(
+        &self,
+        uuid: &str,
+        compression: FrameCompression,
+        last_message_id: Option<MessageId>,
+        last_revision: Option<u64>,
+        reconnect_token: Option<String>,
+    ) -> anyhow::Result<Vec<u8>>
+    {
+        //Every field is copied in a statement of its own, so that only one of the locks is held at a time
+        let ongoing_voip_call = ServerVoipState {
+            connected_clients: self.voip.lock().await.as_ref().map(|voip| {
+                voip.connected_clients
+                    .iter()
+                    .map(|entry| entry.key().clone())
+                    .collect()
+            }),
+        };
+
+        let channels = self.channels.lock().await.clone();
+
+        let messages = self.messages.lock().await;
+
+        //Every change of the messages bumps the revision while holding the lock, so the revision matches the messages read here
+        let revision = self.revision.load(Ordering::Relaxed);
+
+        //Only send the messages the client is missing, if its missing more than a page it only receives the most recent page of every channel
+        //The changes of the messages the client has can only be sent if the client knows which revision it has
+        let continued_position = last_message_id
+            .filter(|_| last_revision.is_some())
+            .and_then(|last_message_id| message_position(&messages, last_message_id))
+            .map(|position| position + 1)
+            .filter(|position| messages.len() - position <= HISTORY_PAGE_SIZE);
+
+        let mut channels_with_older_messages = HashSet::new();
+
+        let (message_list, changed_messages) = match (continued_position, last_revision) {
+            (Some(position), Some(last_revision)) => {
+                (
+                    messages[position..].to_vec(),
+                    messages[..position]
+                        .iter()
+                        .filter(|message| message.revision > last_revision)
+                        .cloned()
+                        .collect(),
+                )
+            },
+            _ => {
+                //Walk the messages from the newest one, and take the last page of every channel
+                let mut channel_counts: HashMap<&str, usize> = HashMap::new();
+                let mut message_list = Vec::new();
+
+                for message in messages.iter().rev() {
+                    let count = channel_counts.entry(&message.channel_id).or_default();
+
+                    if *count < HISTORY_PAGE_SIZE {
+                        *count += 1;
+                        message_list.push(message.clone());
+                    }
+                    else {
+                        channels_with_older_messages.insert(message.channel_id.clone());
+                    }
+                }
+
+                message_list.reverse();
+
+                (message_list, Vec::new())
+            },
+        };
+
+        drop(messages);
+
+        let direct_messages = self
+            .direct_messages
+            .lock()
+            .await
+            .iter()
+            .filter(|(conversation_id, _)| {
+                conversation_id
+                    .split(DIRECT_CONVERSATION_ID_SEPARATOR)
+                    .any(|participant| participant == uuid)
+            })
+            .flat_map(|(_, messages)| messages.clone())
+            .collect();
+
+        let user_seen_list = self.clients_last_seen_index.lock().await.clone();
+        let connected_clients_profile = self.connected_clients_profile.lock().await.clone();
+        let pinned_messages = self.pinned_messages.lock().await.clone();
+        let roles = self.roles.lock().await.clone();
+
+        //Construct reply
+        let server_master = ServerMaster {
+            message_list,
+            continues_from: continued_position.and(last_message_id),
+            channels_with_older_messages,
+            revision,
+            changed_messages,
+            sequence: self.sequence.load(Ordering::Relaxed),
+            user_seen_list,
+            connected_clients_profile,
+            channels,
+            direct_messages,
+            pinned_messages,
+            roles,
+            ongoing_voip_call,
+            reconnect_token,
+        };
+
+        //Reply with the encrypted frame
+        ServerFrame::Master(server_master).encrypt(&self.decryption_key, compression)
+    }
+
+    /// Returns the page of the channel's message history which ends before the message (identified by its id)
+    async fn history_page(&self, before_id: MessageId, channel_id: &str) -> ServerHistoryPage
+    {
+        let messages = self.messages.lock().await;
+
+        //The list is ordered by the ids, so the page ends where the ids reach the requested one
+        let end = messages.partition_point(|message| message.id < before_id);
+
+        let mut channel_messages = messages[..end]
+            .iter()
+            .rev()
+            .filter(|message| message.channel_id == channel_id);
+
+        let mut message_list: Vec<ServerOutput> = channel_messages
+            .by_ref()
+            .take(HISTORY_PAGE_SIZE)
+            .cloned()
+            .collect();
+
+        message_list.reverse();
+
+        ServerHistoryPage {
+            channel_id: channel_id.to_string(),
+            message_list,
+            has_older_messages: channel_messages.next().is_some(),
+        }
+    }
+
+    /// This function has a side effect on the user_seen_list, modifying it according to the client
+    async fn sync_message(&self, req: &ClientMessage)
+    {
+        //Dont ask me why I did it this way
+        if let SyncMessage(inner) = &req.message_type {
+            //if its Some(_) then modify the list, the whole updated list will get sent back to the client regardless
+            if let Some(last_seen_message_id) = inner.last_seen_message_id {
+                let mut client_vec = self.clients_last_seen_index.lock().await;
+
+                //Iter over the whole list so we can update the user's last seen message in the channel if there is one
+                if let Some(client_index_pos) = client_vec.iter().position(|client| {
+                    client.uuid == req.uuid && client.channel_id == req.channel_id
+                }) {
+                    //Update the last seen message
+                    client_vec[client_index_pos].message_id = last_seen_message_id;
+                }
+                else {
+                    client_vec.push(ClientLastSeenMessage::new(
+                        last_seen_message_id,
+                        req.uuid.clone(),
+                        req.channel_id.clone(),
+                    ));
+                }
+            }
+        };
+    }
+    /// Returns the path of the partial file the chunks of an upload are written to
+    /// The uploader's uuid is part of the path, so that clients uploading the same file dont write into each other's upload
+    fn upload_part_path(&self, uuid: &str, hash: &str) -> PathBuf
+    {
+        self.storage_path
+            .join(UPLOADS_FOLDER_NAME)
+            .join(format!("{}.part", sha256::digest(format!("{uuid}{hash}"))))
+    }
+
+    /// Starts, resumes and cancels uploads, and appends the received chunks to the upload's partial file
+    /// Returns the reply which has to be sent to the client, if the request has one
+    async fn handle_file_transfer(
+        &self,
+        uuid: &str,
+        transfer: &ClientFileTransfer,
+    ) -> anyhow::Result<Option<ServerReplyType>>
+    {
+        match transfer {
+            ClientFileTransfer::StartUpload {
+                hash,
+                size,
+                extension,
+            } => {
+                //The limits are checked before any chunks are received, they are checked again when the upload is committed
+                //The whole file has to be uploaded even if it is already stored, so that nobody can get a reference to a file by only knowing its hash
+                self.quota_config.check_upload(
+                    &*self.blobs.lock().await,
+                    uuid,
+                    hash,
+                    MediaType::from_extension(extension.as_deref()),
+                    *size,
+                )?;
+
+                let part_path = self.upload_part_path(uuid, hash);
+
+                tokio::fs::create_dir_all(self.storage_path.join(UPLOADS_FOLDER_NAME)).await?;
+
+                //If some of the chunks have already been received (the connection has dropped) the upload continues from the end of the partial file
+                let mut offset = tokio::fs::metadata(&part_path)
+                    .await
+                    .map(|metadata| metadata.len())
+                    .unwrap_or_default();
+
+                if offset == 0 || offset > *size {
+                    tokio::fs::File::create(&part_path).await?;
+
+                    offset = 0;
+                }
+
+                self.pending_uploads.insert(
+                    part_path,
+                    PendingUpload {
+                        size: *size,
+                        uploader: uuid.to_string(),
+                        last_activity: Instant::now(),
+                    },
+                );
+
+                Ok(Some(ServerReplyType::Upload(ServerUploadReply {
+                    hash: hash.clone(),
+                    offset,
+                })))
+            },
+            ClientFileTransfer::UploadChunk {
+                hash,
+                offset,
+                bytes,
+            } => {
+                let part_path = self.upload_part_path(uuid, hash);
+
+                let size = {
+                    let mut upload = self
+                        .pending_uploads
+                        .get_mut(&part_path)
+                        .ok_or_else(|| ValidationError::UploadNotStarted(hash.clone()))?;
+
+                    upload.last_activity = Instant::now();
+
+                    upload.size
+                };
+
+                let received = tokio::fs::metadata(&part_path).await?.len();
+
+                if *offset != received {
+                    bail!(ValidationError::UploadOffsetMismatch {
+                        offset: *offset,
+                        expected: received,
+                    });
+                }
+
+                if received + bytes.len() as u64 > size {
+                    bail!(ValidationError::ChunkTooLarge {
+                        max: (size - received) as usize,
+                    });
+                }
+
+                tokio::fs::OpenOptions::new()
+                    .append(true)
+                    .open(&part_path)
+                    .await?
+                    .write_all(bytes)
+                    .await?;
+
+                Ok(None)
+            },
+            ClientFileTransfer::CancelUpload { hash } => {
+                let part_path = self.upload_part_path(uuid, hash);
+
+                if self.pending_uploads.remove(&part_path).is_some() {
+                    tokio::fs::remove_file(&part_path).await?;
+                }
+
+                Ok(None)
+            },
+        }
+    }
+
+    /// Checks if all the chunks of the upload have been received, and if the received file matches its hash
+    /// If the file doesnt match its hash the received chunks are discarded, since there is no way to tell which chunk was corrupted
+    async fn finish_upload(&self, uuid: &str, upload: &ClientFileUploadStruct)
+        -> anyhow::Result<()>
+    {
+        let part_path = self.upload_part_path(uuid, &upload.hash);
+
+        //If the file is already stored the received chunks are discarded when storing the file, but they still had to be uploaded
+        let size = self
+            .pending_uploads
+            .get(&part_path)
+            .map(|pending_upload| pending_upload.size)
+            .ok_or_else(|| ValidationError::UploadNotStarted(upload.hash.clone()))?;
+
+        let received = tokio::fs::metadata(&part_path).await?.len();
+
+        if received != size || size != upload.size {
+            bail!(ValidationError::UploadIncomplete {
+                received,
+                size: upload.size,
+            });
+        }
+
+        //Hashing a large file takes a while, so it shouldnt block the other clients
+        let file_hash = tokio::task::spawn_blocking({
+            let part_path = part_path.clone();
+
+            move || sha256::try_digest(part_path.as_path())
+        })
+        .await??;
+
+        if file_hash != upload.hash {
+            self.pending_uploads.remove(&part_path);
+
+            tokio::fs::remove_file(&part_path).await?;
+
+            bail!(ValidationError::UploadHashMismatch(upload.hash.clone()));
+        }
+
+        //Other uploads might have been committed since this one has been started, so the limits have to be checked again
+        //The space is reserved right away, so that the uploads committed at the same time cannot exceed the limits together
+        let quota_check = self.quota_config.reserve_upload(
+            &mut *self.blobs.lock().await,
+            uuid,
+            &upload.hash,
+            MediaType::from_extension(upload.extension.as_deref()),
+            upload.size,
+        );
+
+        if let Err(err) = quota_check {
+            if self.pending_uploads.remove(&part_path).is_some() {
+                tokio::fs::remove_file(&part_path).await?;
+            }
+
+            bail!(err);
+        }
+
+        self.pending_uploads.remove(&part_path);
+
+        Ok(())
+    }
+
+    /// Discards the uploads which havent received a chunk for longer than ```UPLOAD_TIMEOUT```
+    /// The partial files which dont belong to any upload (For example the ones left behind by a restart) are deleted once they havent been written to for as long
+    async fn expire_uploads(&self) -> anyhow::Result<()>
+    {
+        let now = Instant::now();
+
+        self.pending_uploads
+            .retain(|_, upload| now.duration_since(upload.last_activity) < UPLOAD_TIMEOUT);
+
+        let Ok(mut entries) =
+            tokio::fs::read_dir(self.storage_path.join(UPLOADS_FOLDER_NAME)).await
+        else {
+            return Ok(());
+        };
+
+        while let Some(entry) = entries.next_entry().await? {
+            let part_path = entry.path();
+
+            if self.pending_uploads.contains_key(&part_path) {
+                continue;
+            }
+
+            let last_modified = entry.metadata().await?.modified()?;
+
+            if last_modified
+                .elapsed()
+                .is_ok_and(|elapsed| elapsed >= UPLOAD_TIMEOUT)
+            {
+                tokio::fs::remove_file(&part_path).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Discards the uploads of the user (identified by their uuid), this is done when the server disconnects the user
+    /// The uploads of the users whose connection has been lost are kept until they expire, so that they can be resumed after reconnecting
+    async fn discard_uploads(&self, uuid: &str)
+    {
+        let part_paths: Vec<PathBuf> = self
+            .pending_uploads
+            .iter()
+            .filter(|upload| upload.uploader == uuid)
+            .map(|upload| upload.key().clone())
+            .collect();
+
+        for part_path in part_paths {
+            if self.pending_uploads.remove(&part_path).is_some() {
+                if let Err(err) = tokio::fs::remove_file(&part_path).await {
+                    tracing::error!("Failed to delete upload {}: {err}", part_path.display());
+                }
+            }
+        }
+    }
+
+    /// Answers the client if the file transfer was invalid, other errors (like failing to write the file) are only logged
+    async fn reply_transfer_error(&self, uuid: &str, err: anyhow::Error) -> anyhow::Result<()>
+    {
+        let err = match err.downcast::<QuotaExceeded>() {
+            Ok(err) => {
+                tracing::warn!("Client {uuid} has exceeded a storage limit: {err}");
+
+                return self
+                    .send_request_error(uuid, RequestError::QuotaExceeded(err))
+                    .await;
+            },
+            Err(err) => err,
+        };
+
+        match err.downcast::<ValidationError>() {
+            Ok(err) => {
+                tracing::warn!("Client {uuid} sent an invalid file transfer: {err}");
+
+                self.send_request_error(uuid, RequestError::Invalid(err))
+                    .await?;
+            },
+            Err(err) => {
+                tracing::error!("Failed to handle the file transfer of client {uuid}: {err}");
+            },
+        }
+
+        Ok(())
+    }
+
+    /// Returns the stored blob and its path, if the file (identified by its signature) has been uploaded
+    async fn stored_blob(&self, signature: &str) -> Result<(Blob, PathBuf), ValidationError>
+    {
+        let blob = self
+            .blobs
+            .lock()
+            .await
+            .get(signature)
+            .cloned()
+            .ok_or_else(|| ValidationError::FileNotFound(signature.to_string()))?;
+
+        Ok((blob, BlobStore::path(&self.storage_path, signature)))
+    }
+    /// Reads a chunk of the file starting at the offset, so that the whole file never has to be held in memory
+    async fn serve_file(
+        &self,
+        signature: String,
+        offset: u64,
+    ) -> Result<ServerFileReply, ValidationError>
+    {
+        let (blob, path) = self.stored_blob(&signature).await?;
+
+        //Reading the file shouldnt block the other clients
+        let chunk = tokio::task::spawn_blocking({
+            let path = path.clone();
+
+            move || read_file_chunk(&path, offset)
+        })
+        .await
+        .map_err(|err| err.to_string())
+        .and_then(|chunk| chunk.map_err(|err| err.to_string()));
+
+        let (bytes, total_size) = chunk.map_err(|err| {
+            tracing::error!("Failed to read file {}: {err}", path.display());
+
+            ValidationError::FileNotFound(signature.clone())
+        })?;
+
+        if offset > total_size {
+            return Err(ValidationError::OffsetOutOfRange {
+                offset,
+                size: total_size,
+            });
+        }
+
+        Ok(ServerFileReply {
+            bytes,
+            file_name: blob.file_name(),
+            signature,
+            offset,
+            total_size,
+        })
+    }
+    /// Reads the whole image or audio file, these are sent to the client in a single reply so their size is limited (```MAX_MEDIA_SIZE```)
+    async fn serve_media(&self, signature: &str) -> Result<(Blob, Vec<u8>), ValidationError>
+    {
+        let (blob, path) = self.stored_blob(signature).await?;
+
+        //The file might have been uploaded as something else, or before the limit was introduced
+        if blob.size > MAX_MEDIA_SIZE as u64 {
+            return Err(ValidationError::FileTooLarge {
+                max: MAX_MEDIA_SIZE,
+            });
+        }
+
+        let bytes = tokio::fs::read(&path).await.map_err(|err| {
+            tracing::error!("Failed to read file {}: {err}", path.display());
+
+            ValidationError::FileNotFound(signature.to_string())
+        })?;
+
+        Ok((blob, bytes))
+    }
+    async fn serve_image(&self, signature: String) -> Result<Vec<u8>, ValidationError>
+    {
+        let (_, bytes) = self.serve_media(&signature).await?;
+
+        Ok(bytes)
+    }
+    async fn serve_audio(
+        &self,
+        signature: String,
+    ) -> Result<(Vec<u8>, Option<String>), ValidationError>
+    {
+        let (blob, bytes) = self.serve_media(&signature).await?;
+
+        Ok((bytes, blob.name))
+    }
+
+    /// used to handle all the requests, route the user's request
+    #[inline]
+    pub async fn handle_request(
+        &self,
+        request_type: &ClientRequestTypeStruct,
+    ) -> Result<ServerReplyType, ValidationError>
+    {
+        let reply = match request_type {
+            ClientRequestTypeStruct::ImageRequest(img_request) => {
+                let read_file = self.serve_image(img_request.signature.clone()).await?;
+
+                ServerReplyType::Image(ServerImageReply {
+                    bytes: read_file,
+                    signature: img_request.signature.clone(),
+                })
+            },
+            ClientRequestTypeStruct::FileRequest(file_request) => {
+                ServerReplyType::File(
+                    self.serve_file(file_request.signature.clone(), file_request.offset)
+                        .await?,
+                )
+            },
+            ClientRequestTypeStruct::AudioRequest(audio_request) => {
+                let (file_bytes, file_name) =
+                    self.serve_audio(audio_request.signature.clone()).await?;
+
+                ServerReplyType::Audio(ServerAudioReply {
+                    bytes: file_bytes,
+                    signature: audio_request.signature.clone(),
+                    file_name: file_name.unwrap_or_default(),
+                    message_id: audio_request.message_id,
+                })
+            },
+            ClientRequestTypeStruct::ClientRequest(client_request_uuid) => {
+                let connected_clients = self.connected_clients_profile.lock().await;
+
+                let client = connected_clients
+                    .get(client_request_uuid)
+                    .ok_or_else(|| ValidationError::UserNotFound(client_request_uuid.clone()))?;
+
+                ServerReplyType::Client(ServerClientReply {
+                    uuid: client_request_uuid.to_string(),
+                    profile: client.clone(),
+                })
+            },
+        };
+
+        Ok(reply)
+    }
+
+    /// handle all the file uploads, the upload has already been completed and verified (```finish_upload```)
+    /// Returns the stored message, returns ```None``` if the upload couldnt be stored
+    pub async fn handle_upload(
+        &self,
+        req: ClientMessage,
+        upload_type: &ClientFileUploadStruct,
+    ) -> anyhow::Result<Option<ServerOutput>>
+    {
+        //We should retrieve the username of the cient who has sent this, we clone it so that the mutex is dropped, thus allowing other threads to lock it
+        let file_author = match self.client_profile(&req.uuid).await {
+            Ok(profile) => profile.username,
+            Err(err) => {
+                self.blobs
+                    .lock()
+                    .await
+                    .release(&req.uuid, &upload_type.hash);
+
+                return Err(err);
+            },
+        };
+
+        let part_path = self.upload_part_path(&req.uuid, &upload_type.hash);
+
+        //Move the completed upload into the blob store, if the same file has already been uploaded it is only referred to again
+        //The file is moved without holding the blob store, so that the disk doesnt hold up the other requests
+        let stored_size = if self.blobs.lock().await.begin_insert(&upload_type.hash) {
+            let storage_path = self.storage_path.clone();
+            let hash = upload_type.hash.clone();
+
+            let stored_size = tokio::task::spawn_blocking(move || {
+                BlobStore::store_file(&storage_path, &hash, &part_path)
+            })
+            .await
+            .unwrap_or_else(|err| Err(io::Error::new(io::ErrorKind::Other, err)));
+
+            let mut blobs = self.blobs.lock().await;
+
+            match stored_size {
+                Ok(size) => {
+                    blobs.insert(
+                        &upload_type.hash,
+                        size,
+                        &req.uuid,
+                        upload_type.name.clone(),
+                        upload_type.extension.clone(),
+                    );
+                },
+                Err(_) => blobs.end_insert(&req.uuid, &upload_type.hash),
+            }
+
+            stored_size
+        }
+        else {
+            self.blobs
+                .lock()
+                .await
+                .release(&req.uuid, &upload_type.hash);
+
+            Err(io::Error::new(
+                io::ErrorKind::Other,
+                "the stored file is being deleted",
+            ))
+        };
+
+        if let Err(err) = stored_size {
+            tracing::error!(
+                "Failed to store the upload ({}) of client {}: {err}",
+                upload_type.hash,
+                req.uuid
+            );
+
+            self.send_request_error(
+                &req.uuid,
+                RequestError::UploadFailed(upload_type.hash.clone()),
+            )
+            .await?;
+
+            return Ok(None);
+        }
+
+        let server_msg = self
+            .store_message(ServerOutput::convert_clientmsg_to_servermsg(
+                req.clone(),
+                req.uuid.clone(),
+                file_author,
+            )?)
+            .await;
+
+        //The usage shown in the Ui has changed
+        self.update_storage_report().await;
+
+        Ok(Some(server_msg))
+    }
+
+    /// Edits or deletes the message, only the author of the message can edit it
+    /// Users with the permission can delete other users' messages, but they cannot edit them
+    pub async fn handle_message_edit(
+        &self,
+        edit: &ClientMessageEdit,
+        req: &ClientMessage,
+    ) -> Result<(), ValidationError>
+    {
+        let can_delete_others_messages = edit.new_message.is_none()
+            && Role::of_user(&*self.roles.lock().await, &req.uuid)
+                .has_permission(Permission::DeleteOthersMessages);
+
+        //The blobs are locked before the messages, in case the deleted message refers to an uploaded file
+        let mut blobs = self.blobs.lock().await;
+        let mut messages = self.messages.lock().await;
+
+        let edited_message = message_position(&messages, edit.message_id)
+            .map(|position| &mut messages[position])
+            .ok_or(ValidationError::MessageNotFound(edit.message_id))?;
+
+        //Server-side uuid check
+        if edited_message.uuid != req.uuid && !can_delete_others_messages {
+            //Nice try :)
+            tracing::warn!(
+                "Client {} tried to edit the message of another user: {}",
+                req.uuid,
+                edit.message_id
+            );
+
+            return Err(ValidationError::NotMessageAuthor(edit.message_id));
+        }
+
+        //If its () then we can check for the index, because you can delete all messages, rest is ignored
+        if edit.new_message.is_none() {
+            //The deleted message doesnt refer to its uploaded file anymore
+            if let Some(hash) = blob_reference(&edited_message.message_type) {
+                blobs.remove_reference(hash);
+            }
+
+            //Set as `Deleted`
+            edited_message.message_type = ServerMessageType::Deleted;
+        }
+
+        if let ServerMessageType::Normal(inner_msg) = &mut edited_message.message_type {
+            if let Some(new_msg) = edit.new_message.clone() {
+                inner_msg.message = new_msg;
+
+                inner_msg.has_been_edited = true;
+            }
+        }
+
+        edited_message.revision = self.next_revision();
+
+        Ok(())
+    }
+
+    /// handle reaction requests
+    pub async fn handle_reaction(
+        &self,
+        reaction: &ReactionType,
+        req: &ClientMessage,
+    ) -> Result<(), ValidationError>
+    {
+        let (ReactionType::Add(client_reaction) | ReactionType::Remove(client_reaction)) = reaction;
+
+        let mut messages = self.messages.lock().await;
+
+        //The reactions are stored on the message itself
+        let position = message_position(&messages, client_reaction.message_id)
+            .ok_or(ValidationError::MessageNotFound(client_reaction.message_id))?;
+
+        messages[position].revision = self.next_revision();
+
+        let message_reactions = &mut messages[position].reactions;
+
+        match reaction {
+            ReactionType::Add(reaction) => message_reactions.add(&reaction.emoji_name, &req.uuid),
+            ReactionType::Remove(reaction) => {
+                message_reactions.remove(&reaction.emoji_name, &req.uuid)
+            },
+        }
+
+        Ok(())
+    }
+}
+
+/// This function adds a connecting client to the ```connected_clients``` list
+/// A header_buf must be provided, so that the function can fetsh the entire length of the message
+async fn send_message_to_connected_client(
+    socket: Arc<UdpSocket>,
+    connected_clients: ClientThreadChannels,
+    header_buf: Vec<u8>,
+)
+{
+    //Get message lenght
+    let header_lenght = u32::from_be_bytes(header_buf[..4].try_into().unwrap());
+
+    //Create body according to message size indicated by the header, make sure to add 4 to the byte lenght because we peeked the header thus we didnt remove the bytes from the buffer
+    //The length is limited to the size of the header buffer, since a udp packet cannot be larger than that
+    let mut body_buf = vec![0; (header_lenght as usize + 4).min(header_buf.len())];
+
+    match socket.recv_from(&mut body_buf).await {
+        Ok((_, socket_addr)) => {
+            match connected_clients.get(&socket_addr) {
+                Some(client) => {
+                    //We send everythin from the 4th byte since that is the part of the header
+                    //We dont care about the result since it will panic when the thread is shut down
+                    let _ = client.0.send(body_buf[4..].to_vec()).await;
+                },
+                None => {
+                    tracing::error!("Client hasnt been added to the client connected list");
+                },
+            };
+        },
+
+        Err(err) => {
+            tracing::error!("{err}");
+        },
+    }
+}
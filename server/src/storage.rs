@@ -0,0 +1,836 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+use tokio::io::AsyncWriteExt;
+
+use crate::{
+    ban::BanRecord,
+    blob_store::{Blob, BlobStore, BLOBS_FOLDER_NAME},
+    protocol::{
+        Channel, ClientLastSeenMessage, MessageId, MessageReaction, Role, ServerOutput,
+        DEFAULT_CHANNEL_ID, MESSAGE_DATE_FORMAT,
+    },
+};
+
+/// The name of the file the server's state is saved into (Inside the server's storage folder)
+/// This is a snapshot of the whole state, the changes made since it has been written are appended to its journal (```journal_path```)
+pub const SERVER_STATE_FILE_NAME: &str = "server_state.json";
+
+/// The extension of the journals, a journal is named after the generation of the snapshot it continues
+const JOURNAL_EXTENSION: &str = "journal";
+
+/// The journal is compacted into a new snapshot once it has grown larger than the snapshot, or larger than this if the snapshot is smaller
+const MIN_COMPACTION_SIZE: u64 = 1024 * 1024;
+
+/// The parts of the server's state which are small enough to be written as a whole whenever they change
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct StateMetadata
+{
+    /// All the channels of the server, the default channel is always present
+    pub channels: Vec<Channel>,
+
+    /// The ids of the pinned messages
+    pub pinned_messages: Vec<MessageId>,
+
+    /// The roles assigned to the users, the key is the user's uuid
+    pub roles: HashMap<String, Role>,
+
+    /// The dates when the users' mutes expire, the key is the user's uuid
+    pub mutes: HashMap<String, DateTime<Utc>>,
+
+    /// The dates when the users' timeouts expire, the key is the user's uuid
+    pub timeouts: HashMap<String, DateTime<Utc>>,
+
+    /// The bans issued from the server's settings or by the users
+    pub bans: Vec<BanRecord>,
+
+    /// The id the next message will be assigned
+    /// This is 0 in the states saved before the messages had ids, the ids are assigned when loading
+    pub next_message_id: MessageId,
+
+    /// The sequence of the last message sent to every client, so that the sequences keep increasing after a restart
+    pub sequence: u64,
+
+    /// The revision of the last change made to the messages (```ServerOutput::revision```)
+    pub revision: u64,
+
+    /// The last seen message index of every client
+    pub clients_last_seen_index: Vec<ClientLastSeenMessage>,
+}
+
+/// This struct contains everything the server needs to save to the disk, so that the chat history survives a restart
+/// The uploaded files themselves are stored in the blobs folder next to this file, this only contains their metadata
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub struct ServerState
+{
+    /// All the messages sent to the server
+    pub messages: Vec<ServerOutput>,
+
+    /// All the private messages, the key is the id of the conversation
+    pub direct_messages: HashMap<String, Vec<ServerOutput>>,
+
+    /// The channels, roles, bans, etc. they are saved next to the messages
+    #[serde(flatten)]
+    pub metadata: StateMetadata,
+
+    /// The generation of the snapshot, it is increased every time a new snapshot is written so that a journal is never applied to a newer snapshot
+    pub generation: u64,
+
+    /// The reactions of the messages, the indexes match the ones of ```messages```
+    /// This is only present in the states saved before the messages had ids, the reactions are moved onto the messages when loading
+    #[serde(skip_serializing)]
+    pub reactions: Vec<MessageReaction>,
+
+    /// The uploaded files, images and audio files
+    pub blobs: BlobStore,
+
+    /// The paths to the uploaded files, the key is the file's signature
+    /// This is only present in the states saved before the blob store, the files are moved into the blob store when loading
+    #[serde(skip_serializing)]
+    pub file_list: HashMap<String, PathBuf>,
+
+    /// The paths to the uploaded images, the key is the image's signature
+    /// This is only present in the states saved before the blob store, the images are moved into the blob store when loading
+    #[serde(skip_serializing)]
+    pub image_list: HashMap<String, PathBuf>,
+
+    /// The paths to the uploaded audio files, the key is the audio's signature
+    /// This is only present in the states saved before the blob store, the audio files are moved into the blob store when loading
+    #[serde(skip_serializing)]
+    pub audio_list: HashMap<String, PathBuf>,
+
+    /// The names of the uploaded audio files, the key is the audio's signature
+    /// This is only present in the states saved before the blob store
+    #[serde(skip_serializing)]
+    pub audio_names: HashMap<String, Option<String>>,
+}
+
+/// A change of the server's state, the changes are appended to the journal one per line
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub enum StateChange
+{
+    /// A message has been stored or changed, it replaces the stored message with the same id
+    Message(ServerOutput),
+
+    /// A private message has been stored or changed, it replaces the stored message with the same id in the conversation
+    DirectMessage
+    {
+        conversation_id: String,
+        message: ServerOutput,
+    },
+
+    /// A blob has been stored or changed, if its ```None``` the blob has been collected
+    Blob
+    {
+        hash: String, blob: Option<Blob>
+    },
+
+    /// The channels, roles, bans, etc. have changed, they replace the saved ones as a whole
+    /// The counters (```next_message_id```, ```sequence```, ```revision```) are saved by ```StateChange::Counters```, since they change with every message
+    Metadata(StateMetadata),
+
+    /// The counters have changed
+    Counters
+    {
+        next_message_id: MessageId,
+        sequence: u64,
+        revision: u64,
+    },
+}
+
+impl ServerState
+{
+    /// Loads the server's state from the storage folder
+    /// If there is no saved state (the server has never been started with this storage folder) a default state is returned
+    pub async fn load(storage_path: &Path) -> anyhow::Result<Self>
+    {
+        let state_path = storage_path.join(SERVER_STATE_FILE_NAME);
+
+        let mut server_state: Self = if state_path.exists() {
+            serde_json::from_slice(&tokio::fs::read(&state_path).await?)?
+        }
+        else {
+            Self::default()
+        };
+
+        //Apply the changes made since the snapshot has been written
+        let replayed_journal_path = journal_path(storage_path, server_state.generation);
+
+        let replayed = replayed_journal_path.exists();
+
+        if replayed {
+            server_state.replay_journal(&tokio::fs::read(&replayed_journal_path).await?);
+        }
+
+        //Make sure the default channel always exists
+        if !server_state
+            .metadata
+            .channels
+            .iter()
+            .any(|channel| channel.id == DEFAULT_CHANNEL_ID)
+        {
+            server_state.metadata.channels.insert(0, Channel::default());
+        }
+
+        let migrated = server_state.migrate_uploads(storage_path)?
+            | server_state.migrate_message_ids()
+            | server_state.migrate_timestamps();
+
+        //The references are counted from the messages, so that they always match the loaded history
+        server_state.blobs.recount(
+            server_state
+                .messages
+                .iter()
+                .chain(server_state.direct_messages.values().flatten()),
+        );
+
+        //Save the migrated state right away, so that the moved files (and the messages) are never referred to by their old paths
+        //The replayed journal is compacted into the new snapshot too, so that it doesnt have to be replayed again
+        if migrated || replayed {
+            server_state.save(storage_path).await?;
+        }
+
+        //Remove the journals left behind by a crash while compacting, they only contain changes which are already in the snapshot
+        let current_journal_path = journal_path(storage_path, server_state.generation);
+
+        let mut entries = tokio::fs::read_dir(storage_path).await?;
+
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+
+            if path
+                .extension()
+                .is_some_and(|extension| extension == JOURNAL_EXTENSION)
+                && path != current_journal_path
+            {
+                tokio::fs::remove_file(&path).await?;
+            }
+        }
+
+        Ok(server_state)
+    }
+
+    /// Applies the changes of the journal to the state, the changes are applied in the order they were appended
+    /// The journal is read until the first line which cannot be parsed, that line has been cut off by a crash while it was being appended
+    fn replay_journal(&mut self, journal: &[u8])
+    {
+        for (index, line) in journal
+            .split(|byte| *byte == b'\n')
+            .filter(|line| !line.is_empty())
+            .enumerate()
+        {
+            match serde_json::from_slice::<StateChange>(line) {
+                Ok(change) => self.apply_change(change),
+                Err(err) => {
+                    tracing::warn!(
+                        "The journal has been cut off at line {}, the rest of it is ignored: {err}",
+                        index + 1
+                    );
+
+                    break;
+                },
+            }
+        }
+    }
+
+    /// Applies a change read from the journal to the state
+    fn apply_change(&mut self, change: StateChange)
+    {
+        match change {
+            StateChange::Message(message) => {
+                //The messages are sorted by their ids, since the ids are assigned in the order the messages are stored
+                let index = self
+                    .messages
+                    .partition_point(|stored_message| stored_message.id < message.id);
+
+                if self
+                    .messages
+                    .get(index)
+                    .is_some_and(|stored_message| stored_message.id == message.id)
+                {
+                    self.messages[index] = message;
+                }
+                else {
+                    self.messages.insert(index, message);
+                }
+            },
+            StateChange::DirectMessage {
+                conversation_id,
+                message,
+            } => {
+                let conversation = self.direct_messages.entry(conversation_id).or_default();
+
+                if let Some(stored_message) = conversation
+                    .iter_mut()
+                    .find(|stored_message| stored_message.id == message.id)
+                {
+                    *stored_message = message;
+                }
+                else {
+                    conversation.push(message);
+                }
+            },
+            StateChange::Blob { hash, blob } => {
+                match blob {
+                    Some(blob) => {
+                        self.blobs.blobs.insert(hash, blob);
+                    },
+                    None => {
+                        self.blobs.blobs.remove(&hash);
+                    },
+                }
+            },
+            StateChange::Metadata(metadata) => {
+                self.metadata = StateMetadata {
+                    next_message_id: self.metadata.next_message_id,
+                    sequence: self.metadata.sequence,
+                    revision: self.metadata.revision,
+                    ..metadata
+                };
+            },
+            StateChange::Counters {
+                next_message_id,
+                sequence,
+                revision,
+            } => {
+                self.metadata.next_message_id = next_message_id;
+                self.metadata.sequence = sequence;
+                self.metadata.revision = revision;
+            },
+        }
+    }
+
+    /// Moves the uploads saved before the blob store into the blob store, returns whether there was anything to move
+    fn migrate_uploads(&mut self, storage_path: &Path) -> anyhow::Result<bool>
+    {
+        let legacy_uploads: Vec<(String, PathBuf)> = self
+            .file_list
+            .drain()
+            .chain(self.image_list.drain())
+            .chain(self.audio_list.drain())
+            .collect();
+
+        if legacy_uploads.is_empty() {
+            return Ok(false);
+        }
+
+        fs::create_dir_all(storage_path.join(BLOBS_FOLDER_NAME))?;
+
+        for (hash, path) in legacy_uploads {
+            let blob_path = BlobStore::path(storage_path, &hash);
+
+            if let Err(err) = fs::rename(&path, &blob_path) {
+                tracing::error!(
+                    "Failed to move upload {} into the blob store: {err}",
+                    path.display()
+                );
+
+                continue;
+            }
+
+            self.blobs.blobs.insert(
+                hash.clone(),
+                Blob {
+                    name: self.audio_names.remove(&hash).flatten().or_else(|| {
+                        path.file_stem()
+                            .map(|file_stem| file_stem.to_string_lossy().to_string())
+                    }),
+                    extension: path
+                        .extension()
+                        .map(|extension| extension.to_string_lossy().to_string()),
+                    size: fs::metadata(&blob_path)?.len(),
+                    ..Default::default()
+                },
+            );
+        }
+
+        self.audio_names.clear();
+
+        Ok(true)
+    }
+
+    /// Assigns ids to the messages saved before the messages had ids, returns whether there was anything to migrate
+    /// The messages used to be referred to by their indexes, so every index is turned into the id of the message it pointed at
+    fn migrate_message_ids(&mut self) -> bool
+    {
+        if self.metadata.next_message_id != 0 {
+            return false;
+        }
+
+        //The ids start from 1, so that they cannot be confused with the messages whose id hasnt been assigned
+        let index_to_id = |index: usize| index as MessageId + 1;
+
+        let mut reactions = self.reactions.drain(..);
+
+        for (index, message) in self.messages.iter_mut().enumerate() {
+            message.id = index_to_id(index);
+            message.replying_to = message.replying_to.map(|index| index_to_id(index as usize));
+            message.reactions = reactions.next().unwrap_or_default();
+        }
+
+        drop(reactions);
+
+        for pinned_message in self.metadata.pinned_messages.iter_mut() {
+            *pinned_message = index_to_id(*pinned_message as usize);
+        }
+
+        for last_seen in self.metadata.clients_last_seen_index.iter_mut() {
+            last_seen.message_id = index_to_id(last_seen.message_id as usize);
+        }
+
+        self.metadata.next_message_id = index_to_id(self.messages.len());
+
+        for message in self.direct_messages.values_mut().flatten() {
+            message.id = self.metadata.next_message_id;
+
+            self.metadata.next_message_id += 1;
+        }
+
+        true
+    }
+
+    /// Sets the timestamps of the messages saved before the messages had timestamps, returns whether there was anything to migrate
+    /// The timestamps are parsed from the dates of the messages, the messages whose date cannot be parsed are left without one
+    fn migrate_timestamps(&mut self) -> bool
+    {
+        let mut migrated = false;
+
+        for message in self
+            .messages
+            .iter_mut()
+            .chain(self.direct_messages.values_mut().flatten())
+            .filter(|message| message.timestamp == DateTime::<Utc>::default())
+        {
+            if let Ok(message_date) =
+                NaiveDateTime::parse_from_str(&message.message_date, MESSAGE_DATE_FORMAT)
+            {
+                message.timestamp = message_date.and_utc();
+
+                migrated = true;
+            }
+        }
+
+        migrated
+    }
+
+    /// Saves a new snapshot of the server's state into the storage folder, this also removes the journal of the previous snapshot
+    /// The state is first written to a temporary file which is then renamed, so that a crash while writing cannot corrupt the saved history
+    /// Returns the size of the snapshot in bytes
+    pub async fn save(&mut self, storage_path: &Path) -> anyhow::Result<u64>
+    {
+        let state_path = storage_path.join(SERVER_STATE_FILE_NAME);
+        let temp_path = state_path.with_extension("json.tmp");
+        let previous_journal_path = journal_path(storage_path, self.generation);
+
+        self.generation += 1;
+
+        let snapshot = serde_json::to_vec(self)?;
+
+        tokio::fs::write(&temp_path, &snapshot).await?;
+
+        tokio::fs::rename(&temp_path, &state_path).await?;
+
+        //The journal is only removed once the new snapshot is in place, if the server crashes before that the journal is removed when loading
+        if previous_journal_path.exists() {
+            tokio::fs::remove_file(&previous_journal_path).await?;
+        }
+
+        Ok(snapshot.len() as u64)
+    }
+}
+
+/// Returns the path of the journal which continues the snapshot of the generation
+fn journal_path(storage_path: &Path, generation: u64) -> PathBuf
+{
+    storage_path
+        .join(SERVER_STATE_FILE_NAME)
+        .with_extension(format!("{generation}.{JOURNAL_EXTENSION}"))
+}
+
+/// Returns the counters of the metadata (```StateChange::Counters```)
+fn counters(metadata: &StateMetadata) -> (MessageId, u64, u64)
+{
+    (
+        metadata.next_message_id,
+        metadata.sequence,
+        metadata.revision,
+    )
+}
+
+/// Serializes the metadata without its counters, so that it can be compared with the last written metadata
+fn serialize_without_counters(metadata: &StateMetadata) -> anyhow::Result<Vec<u8>>
+{
+    let mut value = serde_json::to_value(metadata)?;
+
+    if let Some(fields) = value.as_object_mut() {
+        for counter in ["next_message_id", "sequence", "revision"] {
+            fields.remove(counter);
+        }
+    }
+
+    Ok(serde_json::to_vec(&value)?)
+}
+
+/// Keeps track of what has already been written to the disk, so that only the changes have to be appended to the journal
+/// The journal is compacted into a new snapshot once it has grown too large
+#[derive(Debug, Default)]
+pub struct StateJournal
+{
+    /// The generation of the last snapshot, the changes are appended to its journal
+    generation: u64,
+
+    /// The revision of the last change written to the disk, the messages with a higher revision have to be appended
+    saved_revision: u64,
+
+    /// The last written metadata without its counters, serialized, the metadata is only appended if it has changed
+    saved_metadata: Vec<u8>,
+
+    /// The last written counters (```StateChange::Counters```)
+    saved_counters: (MessageId, u64, u64),
+
+    /// The size of the last snapshot in bytes
+    snapshot_size: u64,
+
+    /// The size of the journal in bytes
+    journal_size: u64,
+
+    /// Whether appending to the journal has failed, the changes which couldnt be appended can only be saved by a new snapshot
+    broken: bool,
+}
+
+impl StateJournal
+{
+    /// Creates the journal of the loaded state, the loaded state has either just been compacted or its journal is empty
+    pub fn new(storage_path: &Path, server_state: &ServerState) -> anyhow::Result<Self>
+    {
+        Ok(Self {
+            generation: server_state.generation,
+            saved_revision: server_state.metadata.revision,
+            saved_metadata: serialize_without_counters(&server_state.metadata)?,
+            saved_counters: counters(&server_state.metadata),
+            snapshot_size: fs::metadata(storage_path.join(SERVER_STATE_FILE_NAME))
+                .map(|metadata| metadata.len())
+                .unwrap_or_default(),
+            journal_size: 0,
+            broken: false,
+        })
+    }
+
+    /// Returns whether the next save should write a new snapshot instead of appending to the journal
+    pub fn needs_compaction(&self) -> bool
+    {
+        self.broken || self.journal_size > self.snapshot_size.max(MIN_COMPACTION_SIZE)
+    }
+
+    /// Returns the revision of the last change written to the disk
+    pub fn saved_revision(&self) -> u64
+    {
+        self.saved_revision
+    }
+
+    /// Returns the changes of the metadata since it was last written
+    fn metadata_changes(
+        &self,
+        metadata: StateMetadata,
+    ) -> anyhow::Result<(Vec<StateChange>, Vec<u8>)>
+    {
+        let serialized_metadata = serialize_without_counters(&metadata)?;
+
+        let mut changes = Vec::new();
+
+        if self.saved_counters != counters(&metadata) {
+            changes.push(StateChange::Counters {
+                next_message_id: metadata.next_message_id,
+                sequence: metadata.sequence,
+                revision: metadata.revision,
+            });
+        }
+
+        if self.saved_metadata != serialized_metadata {
+            changes.push(StateChange::Metadata(metadata));
+        }
+
+        Ok((changes, serialized_metadata))
+    }
+
+    /// Writes a new snapshot of the state, the journal starts over after it
+    pub async fn compact(
+        &mut self,
+        storage_path: &Path,
+        mut server_state: ServerState,
+    ) -> anyhow::Result<()>
+    {
+        server_state.generation = self.generation;
+
+        let saved_metadata = serialize_without_counters(&server_state.metadata)?;
+
+        self.snapshot_size = server_state.save(storage_path).await?;
+
+        self.generation = server_state.generation;
+        self.saved_revision = server_state.metadata.revision;
+        self.saved_metadata = saved_metadata;
+        self.saved_counters = counters(&server_state.metadata);
+        self.journal_size = 0;
+        self.broken = false;
+
+        Ok(())
+    }
+
+    /// Appends the changes to the journal, followed by the changes of the metadata the changes have been collected with
+    /// If the changes cannot be appended the next save writes a new snapshot instead
+    pub async fn append(
+        &mut self,
+        storage_path: &Path,
+        mut changes: Vec<StateChange>,
+        metadata: StateMetadata,
+    ) -> anyhow::Result<()>
+    {
+        let revision = metadata.revision;
+        let saved_counters = counters(&metadata);
+
+        let (metadata_changes, saved_metadata) = self.metadata_changes(metadata)?;
+
+        changes.extend(metadata_changes);
+
+        if changes.is_empty() {
+            return Ok(());
+        }
+
+        let mut lines = Vec::new();
+
+        for change in &changes {
+            serde_json::to_writer(&mut lines, change)?;
+
+            lines.push(b'\n');
+        }
+
+        if let Err(err) = self.append_lines(storage_path, &lines).await {
+            self.broken = true;
+
+            return Err(err);
+        }
+
+        self.saved_revision = revision;
+        self.saved_metadata = saved_metadata;
+        self.saved_counters = saved_counters;
+        self.journal_size += lines.len() as u64;
+
+        Ok(())
+    }
+
+    /// Appends the lines to the journal and waits until they have been written to the disk
+    async fn append_lines(&self, storage_path: &Path, lines: &[u8]) -> anyhow::Result<()>
+    {
+        let mut journal = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(journal_path(storage_path, self.generation))
+            .await?;
+
+        journal.write_all(lines).await?;
+
+        journal.sync_data().await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::{
+        protocol::{Reaction, ServerMessageType},
+        server::server_output,
+        test_util::TestStorage,
+    };
+
+    /// A message saved before the messages had ids or timestamps
+    fn legacy_message(message_date: &str) -> ServerOutput
+    {
+        let mut message = server_output(0, ServerMessageType::Deleted, DEFAULT_CHANNEL_ID.into());
+
+        message.message_date = message_date.to_string();
+        message.timestamp = DateTime::<Utc>::default();
+
+        message
+    }
+
+    fn reaction(emoji_name: &str) -> MessageReaction
+    {
+        MessageReaction {
+            message_reactions: vec![Reaction {
+                emoji_name: emoji_name.to_string(),
+                authors: vec!["author".to_string()],
+            }],
+        }
+    }
+
+    fn parse_date(message_date: &str) -> DateTime<Utc>
+    {
+        NaiveDateTime::parse_from_str(message_date, MESSAGE_DATE_FORMAT)
+            .unwrap()
+            .and_utc()
+    }
+
+    #[test]
+    fn message_indexes_are_turned_into_ids()
+    {
+        let mut reply = legacy_message("");
+
+        reply.replying_to = Some(0);
+
+        let mut state = ServerState {
+            messages: vec![legacy_message(""), legacy_message(""), reply],
+            direct_messages: HashMap::from([(
+                "conversation".to_string(),
+                vec![legacy_message(""), legacy_message("")],
+            )]),
+            metadata: StateMetadata {
+                pinned_messages: vec![1],
+                clients_last_seen_index: vec![ClientLastSeenMessage::new(
+                    2,
+                    "uuid".into(),
+                    DEFAULT_CHANNEL_ID.into(),
+                )],
+                ..Default::default()
+            },
+            reactions: vec![reaction("first"), reaction("second")],
+            ..Default::default()
+        };
+
+        assert!(state.migrate_message_ids());
+
+        let ids: Vec<MessageId> = state.messages.iter().map(|message| message.id).collect();
+
+        assert_eq!(ids, vec![1, 2, 3]);
+        assert_eq!(state.messages[2].replying_to, Some(1));
+        assert_eq!(
+            state.messages[1].reactions.message_reactions[0].emoji_name,
+            "second"
+        );
+        assert!(state.messages[2].reactions.message_reactions.is_empty());
+        assert!(state.reactions.is_empty());
+        assert_eq!(state.metadata.pinned_messages, vec![2]);
+        assert_eq!(state.metadata.clients_last_seen_index[0].message_id, 3);
+
+        //The private messages get the ids after the messages
+        let direct_message_ids: Vec<MessageId> = state.direct_messages["conversation"]
+            .iter()
+            .map(|message| message.id)
+            .collect();
+
+        assert_eq!(direct_message_ids, vec![4, 5]);
+        assert_eq!(state.metadata.next_message_id, 6);
+    }
+
+    #[test]
+    fn message_ids_are_only_migrated_once()
+    {
+        let mut state = ServerState {
+            messages: vec![legacy_message("")],
+            ..Default::default()
+        };
+
+        assert!(state.migrate_message_ids());
+
+        state.messages[0].id = 10;
+        state.metadata.pinned_messages = vec![10];
+
+        assert!(!state.migrate_message_ids());
+        assert_eq!(state.messages[0].id, 10);
+        assert_eq!(state.metadata.pinned_messages, vec![10]);
+    }
+
+    #[test]
+    fn timestamps_are_parsed_from_the_dates()
+    {
+        let mut stamped_message = legacy_message("2020.01.01. 00:00");
+        let timestamp = Utc::now();
+
+        stamped_message.timestamp = timestamp;
+
+        let mut state = ServerState {
+            messages: vec![
+                legacy_message("2024.03.15. 12:30"),
+                legacy_message("not a date"),
+                stamped_message,
+            ],
+            direct_messages: HashMap::from([(
+                "conversation".to_string(),
+                vec![legacy_message("2023.12.31. 23:59")],
+            )]),
+            ..Default::default()
+        };
+
+        assert!(state.migrate_timestamps());
+
+        assert_eq!(state.messages[0].timestamp, parse_date("2024.03.15. 12:30"));
+        //The messages whose date cannot be parsed are left without a timestamp
+        assert_eq!(state.messages[1].timestamp, DateTime::<Utc>::default());
+        //The messages which already have a timestamp are left untouched
+        assert_eq!(state.messages[2].timestamp, timestamp);
+        assert_eq!(
+            state.direct_messages["conversation"][0].timestamp,
+            parse_date("2023.12.31. 23:59")
+        );
+
+        //Only the messages which couldnt be parsed are left, so there is nothing more to migrate
+        assert!(!state.migrate_timestamps());
+    }
+
+    #[test]
+    fn uploads_are_moved_into_the_blob_store()
+    {
+        let storage = TestStorage::new();
+
+        let file_path = storage.0.join("report.pdf");
+        let audio_path = storage.0.join("recording.wav");
+
+        fs::write(&file_path, b"file").unwrap();
+        fs::write(&audio_path, b"audio!").unwrap();
+
+        let mut state = ServerState {
+            file_list: HashMap::from([("file_hash".to_string(), file_path.clone())]),
+            audio_list: HashMap::from([("audio_hash".to_string(), audio_path.clone())]),
+            audio_names: HashMap::from([(
+                "audio_hash".to_string(),
+                Some("Voice message".to_string()),
+            )]),
+            //The upload whose file is missing is skipped
+            image_list: HashMap::from([("image_hash".to_string(), storage.0.join("missing.png"))]),
+            ..Default::default()
+        };
+
+        assert!(state.migrate_uploads(&storage.0).unwrap());
+
+        assert!(!file_path.exists());
+        assert!(!audio_path.exists());
+        assert!(BlobStore::path(&storage.0, "file_hash").exists());
+        assert!(BlobStore::path(&storage.0, "audio_hash").exists());
+
+        let file = state.blobs.get("file_hash").unwrap();
+
+        assert_eq!(file.name.as_deref(), Some("report"));
+        assert_eq!(file.extension.as_deref(), Some("pdf"));
+        assert_eq!(file.size, 4);
+
+        let audio = state.blobs.get("audio_hash").unwrap();
+
+        assert_eq!(audio.name.as_deref(), Some("Voice message"));
+        assert_eq!(audio.extension.as_deref(), Some("wav"));
+        assert_eq!(audio.size, 6);
+
+        assert!(state.blobs.get("image_hash").is_none());
+        assert!(state.audio_names.is_empty());
+
+        //The legacy lists have been emptied, so there is nothing more to migrate
+        assert!(!state.migrate_uploads(&storage.0).unwrap());
+    }
+}
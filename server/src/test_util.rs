@@ -0,0 +1,24 @@
+use std::{fs, path::PathBuf};
+
+/// A storage folder of its own for every test, it is removed when the test ends
+pub struct TestStorage(pub PathBuf);
+
+impl TestStorage
+{
+    pub fn new() -> Self
+    {
+        let path = std::env::temp_dir().join(format!("matthias-test-{}", uuid::Uuid::new_v4()));
+
+        fs::create_dir_all(&path).unwrap();
+
+        Self(path)
+    }
+}
+
+impl Drop for TestStorage
+{
+    fn drop(&mut self)
+    {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
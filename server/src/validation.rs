@@ -0,0 +1,335 @@
+use crate::protocol::{
+    ClientChannelRequest, ClientFileTransfer, ClientMessage, ClientMessageType,
+    ClientModerationRequest, ClientProfile, ConnectionType, ReactionType, ValidationError,
+    TRANSFER_CHUNK_SIZE,
+};
+
+/// The maximum size of an uploaded file in bytes
+pub const MAX_UPLOAD_SIZE: usize = 500_000_000;
+
+/// The maximum size of an image or an audio file in bytes, these are sent to the clients in a single reply (unlike the other files, which are sent in chunks)
+pub const MAX_MEDIA_SIZE: usize = 64 * 1024 * 1024;
+
+/// The maximum size of a profile picture in bytes, the clients send 256x256 pngs which are way smaller than this
+pub const MAX_PROFILE_PICTURE_SIZE: usize = 512 * 1024;
+
+/// The maximum length of a message in characters
+pub const MAX_MESSAGE_LENGTH: usize = 4000;
+
+/// The maximum length of a name (username, channel name, file name, emoji name) or a moderation reason in characters
+pub const MAX_NAME_LENGTH: usize = 256;
+
+/// The maximum size of the first frame a client sends (the handshake) in bytes
+pub const MAX_HANDSHAKE_FRAME_SIZE: u32 = 16 * 1024;
+
+/// The maximum size of the frame a client sends after the handshake (the connection request) in bytes, this contains the client's profile pictures
+/// The frames are binary, so the pictures take up as many bytes as they are large (The rest of the request fits into the extra megabyte)
+pub const MAX_CONNECTION_FRAME_SIZE: u32 = (2 * MAX_PROFILE_PICTURE_SIZE + 1024 * 1024) as u32;
+
+/// The maximum size of a frame in bytes, the largest requests are the chunks of the file uploads
+/// The rest of the request fits into the extra 64 kilobytes
+pub const MAX_FRAME_SIZE: u32 = (TRANSFER_CHUNK_SIZE + 64 * 1024) as u32;
+
+/// Checks the size of a frame before anything is allocated for it
+pub fn check_frame_size(size: u32, max: u32) -> Result<(), ValidationError>
+{
+    if size > max {
+        return Err(ValidationError::FrameTooLarge { size, max });
+    }
+
+    Ok(())
+}
+
+/// Checks the limits of the request which dont depend on the server's state
+/// The messages the request refers to are checked by the server, since it depends on the stored messages
+pub fn validate_request(req: &ClientMessage) -> Result<(), ValidationError>
+{
+    match &req.message_type {
+        ClientMessageType::NormalMessage(message) => {
+            check_text(&message.message, MAX_MESSAGE_LENGTH)
+        },
+        ClientMessageType::DirectMessage(message) => {
+            check_text(&message.message, MAX_MESSAGE_LENGTH)
+        },
+        ClientMessageType::MessageEdit(edit) => {
+            match &edit.new_message {
+                Some(new_message) => check_text(new_message, MAX_MESSAGE_LENGTH),
+                None => Ok(()),
+            }
+        },
+        ClientMessageType::FileUpload(upload) => {
+            check_upload(&upload.hash, upload.size)?;
+
+            check_text(upload.name.as_deref().unwrap_or_default(), MAX_NAME_LENGTH)?;
+            check_text(
+                upload.extension.as_deref().unwrap_or_default(),
+                MAX_NAME_LENGTH,
+            )
+        },
+        ClientMessageType::FileTransfer(transfer) => {
+            match transfer {
+                ClientFileTransfer::StartUpload {
+                    hash,
+                    size,
+                    extension,
+                } => {
+                    check_upload(hash, *size)?;
+
+                    check_text(extension.as_deref().unwrap_or_default(), MAX_NAME_LENGTH)
+                },
+                ClientFileTransfer::UploadChunk { hash, bytes, .. } => {
+                    if bytes.len() > TRANSFER_CHUNK_SIZE {
+                        return Err(ValidationError::ChunkTooLarge {
+                            max: TRANSFER_CHUNK_SIZE,
+                        });
+                    }
+
+                    check_hash(hash)
+                },
+                ClientFileTransfer::CancelUpload { hash } => check_hash(hash),
+            }
+        },
+        ClientMessageType::SyncMessage(sync_message) => {
+            check_text(&sync_message.username, MAX_NAME_LENGTH)?;
+
+            match &sync_message.sync_attribute {
+                Some(ConnectionType::Connect(profile)) => validate_profile(profile),
+                _ => Ok(()),
+            }
+        },
+        ClientMessageType::Reaction(
+            ReactionType::Add(reaction) | ReactionType::Remove(reaction),
+        ) => check_text(&reaction.emoji_name, MAX_NAME_LENGTH),
+        ClientMessageType::ChannelRequest(
+            ClientChannelRequest::Create(name) | ClientChannelRequest::Rename { name, .. },
+        ) => check_text(name, MAX_NAME_LENGTH),
+        ClientMessageType::ModerationRequest(ClientModerationRequest::Ban { reason, .. }) => {
+            check_text(reason, MAX_NAME_LENGTH)
+        },
+        _ => Ok(()),
+    }
+}
+
+/// Checks the limits of the profile a client connects with
+pub fn validate_profile(profile: &ClientProfile) -> Result<(), ValidationError>
+{
+    check_text(&profile.username, MAX_NAME_LENGTH)?;
+    check_text(&profile.full_name, MAX_NAME_LENGTH)?;
+
+    if profile.small_profile_picture.len() > MAX_PROFILE_PICTURE_SIZE
+        || profile.normal_profile_picture.len() > MAX_PROFILE_PICTURE_SIZE
+    {
+        return Err(ValidationError::ProfilePictureTooLarge {
+            max: MAX_PROFILE_PICTURE_SIZE,
+        });
+    }
+
+    Ok(())
+}
+
+/// Checks the declared size and the hash of an upload
+fn check_upload(hash: &str, size: u64) -> Result<(), ValidationError>
+{
+    if size > MAX_UPLOAD_SIZE as u64 {
+        return Err(ValidationError::FileTooLarge {
+            max: MAX_UPLOAD_SIZE,
+        });
+    }
+
+    check_hash(hash)
+}
+
+/// Checks if the hash is a lowercase hex encoded sha256 hash, the hashes are used in the names of the stored files (on the server, and in the clients' caches)
+/// Uppercase digits are rejected, otherwise the same file could be stored under two names
+pub fn check_hash(hash: &str) -> Result<(), ValidationError>
+{
+    if hash.len() != 64
+        || !hash
+            .chars()
+            .all(|char| char.is_ascii_digit() || ('a'..='f').contains(&char))
+    {
+        return Err(ValidationError::MalformedRequest);
+    }
+
+    Ok(())
+}
+
+/// Checks the length of the text in characters
+fn check_text(text: &str, max: usize) -> Result<(), ValidationError>
+{
+    if text.chars().count() > max {
+        return Err(ValidationError::TextTooLong { max });
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests
+{
+    use std::path::Path;
+
+    use super::*;
+
+    const HASH: &str = "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08";
+
+    fn upload_chunk(hash: &str, len: usize) -> ClientMessage
+    {
+        ClientMessage::construct_file_transfer_msg(
+            ClientFileTransfer::UploadChunk {
+                hash: hash.to_string(),
+                offset: 0,
+                bytes: vec![0; len],
+            },
+            "uuid",
+        )
+    }
+
+    #[test]
+    fn frame_size_is_limited()
+    {
+        assert!(check_frame_size(MAX_FRAME_SIZE, MAX_FRAME_SIZE).is_ok());
+
+        assert_eq!(
+            check_frame_size(MAX_FRAME_SIZE + 1, MAX_FRAME_SIZE),
+            Err(ValidationError::FrameTooLarge {
+                size: MAX_FRAME_SIZE + 1,
+                max: MAX_FRAME_SIZE
+            })
+        );
+    }
+
+    #[test]
+    fn text_is_limited_in_characters()
+    {
+        //Multi byte characters count as one
+        let message = "é".repeat(MAX_MESSAGE_LENGTH);
+
+        assert!(validate_request(&ClientMessage::construct_normal_msg(
+            &message,
+            "uuid",
+            None,
+            String::new()
+        ))
+        .is_ok());
+
+        assert_eq!(
+            validate_request(&ClientMessage::construct_normal_msg(
+                &format!("{message}é"),
+                "uuid",
+                None,
+                String::new()
+            )),
+            Err(ValidationError::TextTooLong {
+                max: MAX_MESSAGE_LENGTH
+            })
+        );
+
+        assert_eq!(
+            validate_request(&ClientMessage::construct_direct_msg(
+                &"a".repeat(MAX_MESSAGE_LENGTH + 1),
+                "uuid",
+                String::from("recipient")
+            )),
+            Err(ValidationError::TextTooLong {
+                max: MAX_MESSAGE_LENGTH
+            })
+        );
+    }
+
+    #[test]
+    fn profile_pictures_are_limited()
+    {
+        let profile = ClientProfile {
+            small_profile_picture: vec![0; MAX_PROFILE_PICTURE_SIZE],
+            normal_profile_picture: vec![0; MAX_PROFILE_PICTURE_SIZE],
+            ..Default::default()
+        };
+
+        assert!(validate_profile(&profile).is_ok());
+
+        let profile = ClientProfile {
+            normal_profile_picture: vec![0; MAX_PROFILE_PICTURE_SIZE + 1],
+            ..profile
+        };
+
+        assert_eq!(
+            validate_profile(&profile),
+            Err(ValidationError::ProfilePictureTooLarge {
+                max: MAX_PROFILE_PICTURE_SIZE
+            })
+        );
+
+        let profile = ClientProfile {
+            username: "a".repeat(MAX_NAME_LENGTH + 1),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            validate_profile(&profile),
+            Err(ValidationError::TextTooLong {
+                max: MAX_NAME_LENGTH
+            })
+        );
+    }
+
+    #[test]
+    fn uploads_are_limited()
+    {
+        let upload = |size: u64| {
+            ClientMessage::construct_file_msg(
+                Path::new("file.txt"),
+                HASH.to_string(),
+                size,
+                "uuid",
+                None,
+                String::new(),
+            )
+        };
+
+        assert!(validate_request(&upload(MAX_UPLOAD_SIZE as u64)).is_ok());
+
+        assert_eq!(
+            validate_request(&upload(MAX_UPLOAD_SIZE as u64 + 1)),
+            Err(ValidationError::FileTooLarge {
+                max: MAX_UPLOAD_SIZE
+            })
+        );
+
+        assert!(validate_request(&upload_chunk(HASH, TRANSFER_CHUNK_SIZE)).is_ok());
+
+        assert_eq!(
+            validate_request(&upload_chunk(HASH, TRANSFER_CHUNK_SIZE + 1)),
+            Err(ValidationError::ChunkTooLarge {
+                max: TRANSFER_CHUNK_SIZE
+            })
+        );
+    }
+
+    #[test]
+    fn malformed_hashes_are_rejected()
+    {
+        assert!(check_hash(HASH).is_ok());
+
+        for hash in [
+            //Too short and too long
+            &HASH[..63],
+            &format!("{HASH}0"),
+            //Uppercase digits would name the same file differently
+            &HASH.to_uppercase(),
+            //Not hex
+            &HASH.replace('a', "g"),
+            //The hashes are used as file names
+            &format!("../../{}", &HASH[6..]),
+            "",
+        ] {
+            assert_eq!(check_hash(hash), Err(ValidationError::MalformedRequest));
+        }
+
+        //The hashes of the transfers are checked too
+        assert_eq!(
+            validate_request(&upload_chunk(&HASH.to_uppercase(), 1)),
+            Err(ValidationError::MalformedRequest)
+        );
+    }
+}